@@ -0,0 +1,24 @@
+/* ~~/build.rs */
+
+// standard crates
+use std::path::Path;
+use std::process::Command;
+
+fn main() {
+  // Record the commit for `get_build_info`; builds outside a checkout get none
+  let hash = Command::new("git")
+    .args(["rev-parse", "--short=12", "HEAD"])
+    .output()
+    .ok()
+    .filter(|output| output.status.success())
+    .and_then(|output| String::from_utf8(output.stdout).ok())
+    .unwrap_or_default();
+  println!("cargo:rustc-env=SHOYU_GIT_HASH={}", hash.trim());
+
+  println!("cargo:rerun-if-changed=build.rs");
+  for path in [".git/HEAD", ".git/refs", ".git/packed-refs"] {
+    if Path::new(path).exists() {
+      println!("cargo:rerun-if-changed={}", path);
+    }
+  }
+}
@@ -14,207 +14,1493 @@ ffi.cdef([[
     const char* theme,
     const char* config_json
   );
+  char* generate_snippet_image_with_metadata(
+    const char* code,
+    const char* language,
+    const char* theme,
+    const char* config_json
+  );
+  char* generate_terminal_preview(
+    const char* code,
+    const char* language,
+    const char* theme,
+    const char* config_json,
+    const char* protocol
+  );
+  char* generate_image_from_tokens(
+    const char* tokens_json,
+    const char* theme,
+    const char* config_json
+  );
+  char* generate_backdrop(
+    const char* theme,
+    const char* config_json,
+    unsigned int width,
+    unsigned int height
+  );
   void free_string(char* s);
+  char* render_with_profile(
+    const char* code,
+    const char* language,
+    const char* theme,
+    const char* profile,
+    const char* overrides_json
+  );
+  int set_profile(const char* name, const char* config_json);
+  char* get_profiles(void);
+  char* generate_snippet_carousel(
+    const char* code,
+    const char* language,
+    const char* theme,
+    const char* config_json
+  );
+  char* generate_tabbed_image(const char* files_json, const char* theme, const char* config_json);
+  char* export_snippet_pdf(
+    const char* code,
+    const char* language,
+    const char* theme,
+    const char* config_json,
+    const char* output_path
+  );
+  char* export_scrolling_video(
+    const char* code,
+    const char* language,
+    const char* theme,
+    const char* config_json,
+    const char* output_path
+  );
+  char* export_animation_frames(
+    const char* code,
+    const char* language,
+    const char* theme,
+    const char* config_json,
+    const char* spec_json,
+    const char* output_dir
+  );
+  char* export_typing_animation(
+    const char* code,
+    const char* language,
+    const char* theme,
+    const char* config_json,
+    const char* spec_json,
+    const char* output_path
+  );
+  char* export_theme_transition(
+    const char* code,
+    const char* language,
+    const char* from_theme,
+    const char* to_theme,
+    const char* config_json,
+    const char* output_path
+  );
+  char* theme_from_highlights(const char* groups_json, const char* name);
+  char* import_vscode_theme(const char* path, const char* name);
+  char* import_base16_scheme(const char* path, const char* name);
+  char* reload_themes(const char* dir);
   char* get_available_themes(void);
+  char* get_theme_details(const char* name);
+  char* validate_theme(const char* name, const char* background);
+  char* get_available_presets(void);
+  char* get_gradient_presets(void);
+  char* get_build_info(void);
+  int add_syntax_directory(const char* path);
   int is_language_supported(const char* language);
+  void set_resource_limits(
+    unsigned int max_concurrent,
+    uint64_t max_pixels,
+    unsigned int max_cache_mb
+  );
+  char* get_resource_usage(void);
+  int set_temp_policy(const char* dir, unsigned int cleanup_older_than);
+  char* purge_cache(void);
+  char* get_warnings(void);
+  char* measure_snippet(const char* code, const char* language, const char* config_json);
+  char* highlight_code(const char* code, const char* language, const char* theme);
+  char* dump_tokens(
+    const char* code,
+    const char* language,
+    const char* theme,
+    const char* config_json
+  );
+  unsigned int open_highlight_stream(
+    const char* code,
+    const char* language,
+    const char* theme,
+    const char* config_json
+  );
+  int edit_highlight_stream(
+    unsigned int handle,
+    unsigned int start,
+    unsigned int end,
+    const char* text
+  );
+  char* poll_highlight_stream(unsigned int handle, unsigned int max_lines);
+  int close_highlight_stream(unsigned int handle);
 ]])
 
 local defaults = {
+  theme = 'dracula',
+  output_dir = vim.fn.expand('~/Pictures/shoyu'),
+  auto_open = true,
+  preset = nil, -- 'carbon', 'rayso', 'minimal', or 'social'
+  profiles = nil, -- { name = { ...render options } }, stored in the library by setup
+  profile = nil, -- Profile used by :Shoyu; render options above are ignored when set
+  syntax_dirs = nil, -- { '~/.config/shoyu/syntaxes' }, folders of .sublime-syntax files
+  themes_dir = nil, -- '~/.config/shoyu/themes', JSON and YAML themes named after their files
+  editor_highlights = false, -- Render :Shoyu with the colors Neovim draws instead of a theme's
+  confirm_title = false, -- Prompt to accept or edit the title inferred with `infer_title = true`
+  resource_limits = nil, -- { max_concurrent = 0, max_pixels = 0, max_cache_mb = 0 }, 0 = unlimited
+  temp = nil, -- { dir = nil, cleanup_older_than = 86400 }, where exports are staged and for how long
+  server = false, -- Render in a separate `shoyu --rpc` process rather than inside Neovim
+}
+-- Render settings sent when no preset is chosen; a preset supplies its own
+local render_defaults = {
   width = 1200,
   height = nil,
   padding = 64,
   line_height = 0.8,
   font_size = 18,
   font_family = 'Fira Code',
-  background_color = '#1e1e1e',
+  panel_color = nil,
   window_controls = true,
   window_title = nil,
   line_numbers = false,
   drop_shadow = true,
   border_radius = 8,
   export_size = 2.0,
-  theme = 'dracula',
-  output_dir = vim.fn.expand('~/Pictures/shoyu'),
-  auto_open = true,
   panel_padding = 80,
   gradient_backdrop = true,
   noise_effect = true,
 }
 local config = {}
 
+-- Short age such as `3d` or `2y` of a Unix timestamp
+local function relative_age(timestamp)
+  local age = math.max(os.time() - timestamp, 0)
+  local units = {
+    { 'y', 31536000 },
+    { 'mo', 2592000 },
+    { 'w', 604800 },
+    { 'd', 86400 },
+    { 'h', 3600 },
+  }
+  for _, unit in ipairs(units) do
+    if age >= unit[2] then
+      return math.floor(age / unit[2]) .. unit[1]
+    end
+  end
+  return math.floor(age / 60) .. 'm'
+end
+
+-- `author age` per line from `first` to `last` of the current buffer, from git blame of
+-- its unsaved contents against the file at `path`, empty when it isn't tracked
+local function blame_lines(path, first, last)
+  if path == '' then
+    return {}
+  end
+  local output = vim.fn.systemlist({
+    'git',
+    '-C',
+    vim.fn.fnamemodify(path, ':h'),
+    'blame',
+    '--line-porcelain',
+    '-L',
+    first .. ',' .. last,
+    '--contents',
+    '-',
+    '--',
+    path,
+  }, vim.api.nvim_buf_get_lines(0, 0, -1, false))
+  if vim.v.shell_error ~= 0 then
+    return {}
+  end
+  local lines, author, time = {}, '', 0
+  for _, row in ipairs(output) do
+    if row:sub(1, 1) == '\t' then
+      local committed = author ~= 'Not Committed Yet'
+      table.insert(lines, committed and author .. ' ' .. relative_age(time) or 'uncommitted')
+    elseif row:find('^author ') then
+      author = row:sub(8)
+    elseif row:find('^author%-time ') then
+      time = tonumber(row:sub(13)) or 0
+    end
+  end
+  return lines
+end
+
+-- Merge per-call options over the configuration that gets sent to the library, for a
+-- snippet of buffer lines `first` to `last`, the whole buffer by default
+local function build_render_config(opts, first, last)
+  first = first or 1
+  last = last or vim.api.nvim_buf_line_count(0)
+  local merged = vim.tbl_deep_extend('force', config, opts or {})
+  -- Match the buffer's tab stops unless configured
+  if merged.tab_width == nil then
+    merged.tab_width = vim.bo.tabstop
+  end
+  -- `text_width = 'buffer'` marks where this buffer's 'textwidth' hard-wraps
+  if merged.text_width == 'buffer' then
+    merged.text_width = vim.bo.textwidth
+  end
+  -- `blame = true` notes who last changed each line and when
+  if merged.blame == true then
+    local lines = blame_lines(vim.api.nvim_buf_get_name(0), first, last)
+    -- An empty Lua table would encode as a JSON object
+    merged.blame = #lines > 0 and { lines = lines } or nil
+  end
+  -- `diagnostics = true` shows the snippet's diagnostics as the editor does
+  if merged.diagnostics == true then
+    local severities = { 'error', 'warning', 'info', 'hint' }
+    local diagnostics = {}
+    for _, diagnostic in ipairs(vim.diagnostic.get(0)) do
+      local row = diagnostic.lnum + 1
+      -- Only those on the snippet's lines, numbered from its first
+      if row >= first and row <= last then
+        local last_col = math.max(vim.fn.col({ row, '$' }) - 1, 1)
+        -- Spans running onto later lines are cut at the end of the first
+        local end_col = diagnostic.end_lnum == diagnostic.lnum and diagnostic.end_col or last_col
+        local first_col = math.min(diagnostic.col + 1, last_col)
+        table.insert(diagnostics, {
+          line = row - first + 1,
+          col_start = vim.fn.virtcol({ row, first_col }, 1)[1],
+          col_end = vim.fn.virtcol({ row, math.min(math.max(end_col, first_col), last_col) }, 1)[2],
+          severity = severities[diagnostic.severity],
+          message = (diagnostic.message:gsub('\n.*', '')),
+        })
+      end
+    end
+    -- An empty Lua table would encode as a JSON object
+    merged.diagnostics = #diagnostics > 0 and diagnostics or nil
+  end
+  -- `cursor = true` draws a block where the window's cursor sits, when that is in the snippet
+  if merged.cursor == true then
+    local row, col = unpack(vim.api.nvim_win_get_cursor(0))
+    local column = vim.fn.virtcol({ row, col + 1 }, 1)[1]
+    local inside = row >= first and row <= last
+    merged.cursor = inside and { line = row - first + 1, column = column, style = 'block' } or nil
+  end
+  -- `theme = 'colorscheme'` matches whatever colorscheme is running
+  if merged.theme == 'colorscheme' then
+    merged.theme = M.register_colorscheme() or 'dracula'
+  end
+  if merged.backdrop_image then
+    merged.backdrop_image = vim.fn.expand(merged.backdrop_image)
+  end
+  if merged.theme_file then
+    merged.theme_file = vim.fn.expand(merged.theme_file)
+  end
+  if merged.themes_dir then
+    merged.themes_dir = vim.fn.expand(merged.themes_dir)
+  end
+  if merged.syntax_dirs then
+    merged.syntax_dirs = vim.tbl_map(vim.fn.expand, merged.syntax_dirs)
+  end
+  -- `project_root = 'auto'` uses the git repository around the buffer
+  if merged.project_root == 'auto' then
+    local git = vim.fs.find('.git', { upward = true, path = vim.fn.expand('%:p:h') })[1]
+    merged.project_root = git and vim.fn.fnamemodify(git, ':h') or nil
+  elseif merged.project_root then
+    merged.project_root = vim.fn.expand(merged.project_root)
+  end
+  -- Titled with the buffer's path, which the library shows relative to the root
+  if merged.project_root and merged.window_title == nil then
+    local path = vim.api.nvim_buf_get_name(0)
+    merged.window_title = path ~= '' and path or nil
+  end
+  -- Header and footer placeholders such as {path} and {branch} describe the buffer
+  if (merged.header or merged.footer) and merged.source_path == nil then
+    local path = vim.api.nvim_buf_get_name(0)
+    merged.source_path = path ~= '' and path or nil
+  end
+  if merged.preset then
+    return merged
+  end
+  return vim.tbl_deep_extend('force', render_defaults, merged)
+end
+
 -- Get the plugin directory path
 local function get_plugin_dir()
   local source = debug.getinfo(1, 'S').source
   if source:sub(1, 1) == '@' then
     source = source:sub(2)
   end
-  -- Go up two levels: from lua/shoyu/init.lua to plugin root
-  return vim.fn.fnamemodify(source, ':h:h:h')
+  -- Go up two levels: from lua/shoyu/init.lua to plugin root
+  return vim.fn.fnamemodify(source, ':h:h:h')
+end
+
+-- Channel of the `shoyu --rpc` process, started on first use and again after it exits
+local server_channel = nil
+
+-- Call `method` on the render server, raising its error message on failure
+local function server_request(method, ...)
+  if not server_channel then
+    local binary = get_plugin_dir() .. '/target/release/shoyu'
+    if vim.fn.has('win32') == 1 then
+      binary = binary .. '.exe'
+    end
+    local channel = vim.fn.jobstart({ binary, '--rpc' }, {
+      rpc = true,
+      on_exit = function()
+        server_channel = nil
+      end,
+    })
+    if channel <= 0 then
+      error(
+        'Could not start the shoyu render server: '
+          .. binary
+          .. '\nBuild it with: cargo build --release --features cli'
+      )
+    end
+    server_channel = channel
+  end
+  return vim.rpcrequest(server_channel, method, ...)
+end
+
+-- Show warnings the library queued during the last call, e.g. deprecated options
+local function notify_warnings()
+  local warnings = config.server and server_request('warnings') or M.get_warnings()
+  for _, warning in ipairs(warnings) do
+    vim.notify('shoyu: ' .. warning, vim.log.levels.WARN)
+  end
+end
+
+-- Highlight groups drawn at a position, lowest priority first
+local function groups_at(bufnr, row, col)
+  local items = vim.inspect_pos(bufnr, row, col)
+  local groups = {}
+  for _, item in ipairs(items.syntax) do
+    table.insert(groups, item.hl_group)
+  end
+  for _, item in ipairs(items.treesitter) do
+    table.insert(groups, item.hl_group)
+  end
+  for _, item in ipairs(items.semantic_tokens) do
+    table.insert(groups, item.opts.hl_group)
+  end
+  for _, item in ipairs(items.extmarks) do
+    if item.opts.hl_group then
+      table.insert(groups, item.opts.hl_group)
+    end
+  end
+  return groups
+end
+
+-- Tokens and colors exactly as Neovim draws rows `start_row` to `end_row` (0-based, exclusive)
+function M.capture_highlights(bufnr, start_row, end_row)
+  bufnr = bufnr or 0
+  local lines = vim.api.nvim_buf_get_lines(bufnr, start_row or 0, end_row or -1, false)
+  local captured = {}
+  for index, line in ipairs(lines) do
+    local row = (start_row or 0) + index - 1
+    local tokens = {}
+    local starts = vim.str_utf_pos(line)
+    for i, start in ipairs(starts) do
+      local finish = (starts[i + 1] or #line + 1) - 1
+      local token =
+        { text = line:sub(start, finish), bold = false, italic = false, underline = false }
+      -- Later groups draw over earlier ones one attribute at a time
+      for _, group in ipairs(groups_at(bufnr, row, start - 1)) do
+        local hl = vim.api.nvim_get_hl(0, { name = group, link = false })
+        if hl.fg then
+          token.fg = string.format('#%06x', hl.fg)
+          token.group = group
+        end
+        token.bold = hl.bold or token.bold
+        token.italic = hl.italic or token.italic
+        token.underline = hl.underline or hl.undercurl or token.underline
+      end
+      local last = tokens[#tokens]
+      if
+        last
+        and last.fg == token.fg
+        and last.bold == token.bold
+        and last.italic == token.italic
+        and last.underline == token.underline
+      then
+        last.text = last.text .. token.text
+      else
+        table.insert(tokens, token)
+      end
+    end
+    -- An empty Lua table would encode as a JSON object, so blank lines get an empty token
+    if #tokens == 0 then
+      tokens = { { text = '' } }
+    end
+    table.insert(captured, tokens)
+  end
+  return captured
+end
+
+-- Load the shared library
+function M.load_library()
+  if M.lib then
+    return M.lib
+  end
+  local file_names = {
+    'libshoyu.so', -- Linux
+    'shoyu.dll', -- Windows
+    'libshoyu.dylib', -- macOS
+  }
+  local plugin_dir = get_plugin_dir()
+  for _, shared_object in ipairs(file_names) do
+    local lib_path = plugin_dir .. '/target/release/' .. shared_object
+    if vim.fn.filereadable(lib_path) == 1 then
+      M.lib = ffi.load(lib_path)
+      return M.lib
+    end
+  end
+  vim.schedule(function()
+    vim.notify(
+      string.format(
+        'Could not load shoyu shared library from plugin directory: %s\n'
+          .. 'Make sure to build the plugin with: cargo build --release\n'
+          .. 'Searched paths:\n'
+          .. '  - %s/target/release/\n',
+        plugin_dir,
+        plugin_dir
+      ),
+      vim.log.levels.ERROR
+    )
+  end)
+end
+
+-- Generate image output of buffer lines `first` to `last`, the whole buffer by default
+function M.generate_image(opts, first, last)
+  opts = opts or {}
+  if not config.server and not M.lib then
+    M.load_library()
+  end
+
+  -- Get current buffer content
+  local lines = vim.api.nvim_buf_get_lines(0, (first or 1) - 1, last or -1, false)
+  local code = table.concat(lines, '\n')
+  if code == '' then
+    vim.notify('Buffer is empty', vim.log.levels.WARN)
+    return
+  end
+
+  -- Determine language
+  local filetype = opts.language or vim.bo.filetype
+  if filetype == '' then
+    filetype = 'auto'
+  end
+
+  -- Check if language is supported
+  local lang_cstr = ffi.new('char[?]', #filetype + 1, filetype)
+  if not config.server and M.lib.is_language_supported(lang_cstr) == 0 then
+    vim.notify(string.format("Language '%s' is not supported", filetype), vim.log.levels.WARN)
+    filetype = 'text'
+  end
+
+  -- Merge config with options
+  local render_config = build_render_config(opts, first, last)
+  if render_config.confirm_title and render_config.infer_title and not render_config.window_title then
+    local metrics = M.measure(code, filetype, opts) or {}
+    local title = vim.fn.input('Title: ', metrics.window_title or '')
+    render_config.window_title = title ~= '' and title or nil
+    render_config.infer_title = false
+  end
+  local config_json = vim.json.encode(render_config)
+
+  -- Generate image
+  local code_cstr = ffi.new('char[?]', #code + 1, code)
+  local lang_cstr = ffi.new('char[?]', #filetype + 1, filetype)
+  local theme_cstr = ffi.new('char[?]', #render_config.theme + 1, render_config.theme)
+  local config_cstr = ffi.new('char[?]', #config_json + 1, config_json)
+  local result
+  local profile = opts.profile or config.profile
+  if render_config.editor_highlights and not profile then
+    -- Sit the editor's colors on the editor's own background unless one is configured
+    local normal = vim.api.nvim_get_hl(0, { name = 'Normal', link = false })
+    if render_config.panel_color == nil and normal.bg then
+      render_config.panel_color = string.format('#%06x', normal.bg)
+      config_json = vim.json.encode(render_config)
+      config_cstr = ffi.new('char[?]', #config_json + 1, config_json)
+    end
+    M.load_library()
+    local tokens_json = vim.json.encode(M.capture_highlights(0, (first or 1) - 1, last))
+    local tokens_cstr = ffi.new('char[?]', #tokens_json + 1, tokens_json)
+    result = M.lib.generate_image_from_tokens(tokens_cstr, theme_cstr, config_cstr)
+  elseif profile then
+    -- The library deep-merges `opts.overrides` over the stored profile
+    M.load_library()
+    local overrides_json = vim.json.encode(opts.overrides or vim.empty_dict())
+    local profile_cstr = ffi.new('char[?]', #profile + 1, profile)
+    local overrides_cstr = ffi.new('char[?]', #overrides_json + 1, overrides_json)
+    result =
+      M.lib.render_with_profile(code_cstr, lang_cstr, theme_cstr, profile_cstr, overrides_cstr)
+  elseif config.server then
+    local ok, output =
+      pcall(server_request, 'render', code, filetype, render_config.theme, config_json)
+    result = ok and output.image or nil
+  else
+    result = M.lib.generate_snippet_image(code_cstr, lang_cstr, theme_cstr, config_cstr)
+  end
+  notify_warnings()
+  if result == nil then
+    vim.notify('Failed to generate image', vim.log.levels.ERROR)
+    return
+  end
+
+  local image_data = result
+  if type(result) ~= 'string' then
+    image_data = ffi.string(result)
+    M.lib.free_string(result)
+  end
+
+  -- Save image
+  local filename = opts.filename or M.generate_filename(filetype)
+  local filepath = config.output_dir .. '/' .. filename
+  M.save_image_data(image_data, filepath)
+  vim.notify(string.format('Image saved to: %s', filepath), vim.log.levels.INFO)
+  if config.auto_open then
+    M.open_image(filepath)
+  end
+  return filepath
+end
+
+-- Generate a carousel of page images from the current buffer
+function M.generate_carousel(opts)
+  opts = opts or {}
+  if not M.lib then
+    M.load_library()
+  end
+
+  local lines = vim.api.nvim_buf_get_lines(0, 0, -1, false)
+  local code = table.concat(lines, '\n')
+  if code == '' then
+    vim.notify('Buffer is empty', vim.log.levels.WARN)
+    return
+  end
+  local filetype = opts.language or vim.bo.filetype
+  if filetype == '' then
+    filetype = 'auto'
+  end
+
+  -- Pages share the buffer's file name as their header unless a title is given
+  local render_config = build_render_config(opts)
+  if render_config.window_title == nil then
+    local file_name = vim.fn.expand('%:t')
+    render_config.window_title = file_name ~= '' and file_name or nil
+  end
+  local config_json = vim.json.encode(render_config)
+
+  local code_cstr = ffi.new('char[?]', #code + 1, code)
+  local lang_cstr = ffi.new('char[?]', #filetype + 1, filetype)
+  local theme_cstr = ffi.new('char[?]', #render_config.theme + 1, render_config.theme)
+  local config_cstr = ffi.new('char[?]', #config_json + 1, config_json)
+  local result = M.lib.generate_snippet_carousel(code_cstr, lang_cstr, theme_cstr, config_cstr)
+  notify_warnings()
+  if result == nil then
+    vim.notify('Failed to generate carousel', vim.log.levels.ERROR)
+    return
+  end
+  local pages = vim.json.decode(ffi.string(result))
+  M.lib.free_string(result)
+
+  local base = M.generate_filename(filetype):gsub('%.png$', '')
+  local filepaths = {}
+  for index, image_data in ipairs(pages) do
+    local filepath = string.format('%s/%s_%02d.png', config.output_dir, base, index)
+    M.save_image_data(image_data, filepath)
+    table.insert(filepaths, filepath)
+  end
+  vim.notify(
+    string.format('Saved %d images to: %s', #filepaths, config.output_dir),
+    vim.log.levels.INFO
+  )
+  return filepaths
+end
+
+-- Generate one image of several files as tabs, by default the buffers shown in
+-- this tab page with the current one in front; `opts.files` takes
+-- { title, code, language } entries instead
+function M.generate_tabs(opts)
+  opts = opts or {}
+  if not M.lib then
+    M.load_library()
+  end
+
+  local files = opts.files
+  local render_config = build_render_config(opts)
+  render_config.files = nil
+  if not files then
+    files = {}
+    local current = vim.api.nvim_get_current_buf()
+    local seen = {}
+    for _, win in ipairs(vim.api.nvim_tabpage_list_wins(0)) do
+      local buf = vim.api.nvim_win_get_buf(win)
+      local name = vim.api.nvim_buf_get_name(buf)
+      if not seen[buf] and vim.bo[buf].buftype == '' and name ~= '' then
+        seen[buf] = true
+        if buf == current then
+          render_config.tabs = vim.tbl_extend('force', render_config.tabs or {}, { active = #files })
+        end
+        table.insert(files, {
+          title = vim.fn.fnamemodify(name, ':t'),
+          code = table.concat(vim.api.nvim_buf_get_lines(buf, 0, -1, false), '\n'),
+          language = vim.bo[buf].filetype,
+        })
+      end
+    end
+  end
+  if #files == 0 then
+    vim.notify('No files to show as tabs', vim.log.levels.WARN)
+    return
+  end
+
+  local files_json = vim.json.encode(files)
+  local config_json = vim.json.encode(render_config)
+  local files_cstr = ffi.new('char[?]', #files_json + 1, files_json)
+  local theme_cstr = ffi.new('char[?]', #render_config.theme + 1, render_config.theme)
+  local config_cstr = ffi.new('char[?]', #config_json + 1, config_json)
+  local result = M.lib.generate_tabbed_image(files_cstr, theme_cstr, config_cstr)
+  notify_warnings()
+  if result == nil then
+    vim.notify('Failed to generate tabbed image', vim.log.levels.ERROR)
+    return
+  end
+  local image_data = ffi.string(result)
+  M.lib.free_string(result)
+
+  local filename = opts.filename or M.generate_filename('tabs')
+  local filepath = config.output_dir .. '/' .. filename
+  M.save_image_data(image_data, filepath)
+  vim.notify(string.format('Image saved to: %s', filepath), vim.log.levels.INFO)
+  if config.auto_open then
+    M.open_image(filepath)
+  end
+  return filepath
+end
+
+-- Export lines `first` to `last` of the current buffer, all of it by default, as a one-page
+-- PDF for print and LaTeX documents
+function M.export_pdf(opts, first, last)
+  opts = opts or {}
+  if not M.lib then
+    M.load_library()
+  end
+
+  local lines = vim.api.nvim_buf_get_lines(0, (first or 1) - 1, last or -1, false)
+  local code = table.concat(lines, '\n')
+  if code == '' then
+    vim.notify('Buffer is empty', vim.log.levels.WARN)
+    return
+  end
+  local filetype = opts.language or vim.bo.filetype
+  if filetype == '' then
+    filetype = 'auto'
+  end
+
+  local render_config = build_render_config(opts, first, last)
+  local config_json = vim.json.encode(render_config)
+  local filename = M.generate_filename(filetype):gsub('%.png$', '.pdf')
+  local filepath = config.output_dir .. '/' .. filename
+
+  local code_cstr = ffi.new('char[?]', #code + 1, code)
+  local lang_cstr = ffi.new('char[?]', #filetype + 1, filetype)
+  local theme_cstr = ffi.new('char[?]', #render_config.theme + 1, render_config.theme)
+  local config_cstr = ffi.new('char[?]', #config_json + 1, config_json)
+  local path_cstr = ffi.new('char[?]', #filepath + 1, filepath)
+  local result = M.lib.export_snippet_pdf(code_cstr, lang_cstr, theme_cstr, config_cstr, path_cstr)
+  notify_warnings()
+  if result == nil then
+    vim.notify('Failed to export PDF', vim.log.levels.ERROR)
+    return
+  end
+  M.lib.free_string(result)
+  vim.notify(string.format('PDF saved to: %s', filepath), vim.log.levels.INFO)
+  return filepath
+end
+
+-- Export a video that scrolls through the current buffer (requires ffmpeg)
+function M.export_video(opts)
+  opts = opts or {}
+  if not M.lib then
+    M.load_library()
+  end
+
+  local lines = vim.api.nvim_buf_get_lines(0, 0, -1, false)
+  local code = table.concat(lines, '\n')
+  if code == '' then
+    vim.notify('Buffer is empty', vim.log.levels.WARN)
+    return
+  end
+  local filetype = opts.language or vim.bo.filetype
+  if filetype == '' then
+    filetype = 'auto'
+  end
+
+  local render_config = build_render_config(opts)
+  local config_json = vim.json.encode(render_config)
+  local extension = (render_config.scroll or {}).format or 'mp4'
+  local filename = M.generate_filename(filetype):gsub('%.png$', '.' .. extension)
+  local filepath = config.output_dir .. '/' .. filename
+
+  local code_cstr = ffi.new('char[?]', #code + 1, code)
+  local lang_cstr = ffi.new('char[?]', #filetype + 1, filetype)
+  local theme_cstr = ffi.new('char[?]', #render_config.theme + 1, render_config.theme)
+  local config_cstr = ffi.new('char[?]', #config_json + 1, config_json)
+  local path_cstr = ffi.new('char[?]', #filepath + 1, filepath)
+  local result =
+    M.lib.export_scrolling_video(code_cstr, lang_cstr, theme_cstr, config_cstr, path_cstr)
+  notify_warnings()
+  if result == nil then
+    vim.notify('Failed to export video (is ffmpeg installed?)', vim.log.levels.ERROR)
+    return
+  end
+  M.lib.free_string(result)
+  vim.notify(string.format('Video saved to: %s', filepath), vim.log.levels.INFO)
+  return filepath
+end
+
+-- Export a GIF of the current buffer morphing from the configured theme into `to_theme`
+function M.export_transition(to_theme, opts)
+  opts = opts or {}
+  if not M.lib then
+    M.load_library()
+  end
+  if not vim.tbl_contains(M.get_build_info().image_formats or {}, 'gif') then
+    vim.notify('This build of shoyu cannot encode GIFs', vim.log.levels.ERROR)
+    return
+  end
+
+  local lines = vim.api.nvim_buf_get_lines(0, 0, -1, false)
+  local code = table.concat(lines, '\n')
+  if code == '' then
+    vim.notify('Buffer is empty', vim.log.levels.WARN)
+    return
+  end
+  local filetype = opts.language or vim.bo.filetype
+  if filetype == '' then
+    filetype = 'auto'
+  end
+
+  local render_config = build_render_config(opts)
+  local config_json = vim.json.encode(render_config)
+  local filename = M.generate_filename(filetype):gsub('%.png$', '.gif')
+  local filepath = config.output_dir .. '/' .. filename
+
+  local code_cstr = ffi.new('char[?]', #code + 1, code)
+  local lang_cstr = ffi.new('char[?]', #filetype + 1, filetype)
+  local from_cstr = ffi.new('char[?]', #render_config.theme + 1, render_config.theme)
+  local to_cstr = ffi.new('char[?]', #to_theme + 1, to_theme)
+  local config_cstr = ffi.new('char[?]', #config_json + 1, config_json)
+  local path_cstr = ffi.new('char[?]', #filepath + 1, filepath)
+  local result = M.lib.export_theme_transition(
+    code_cstr,
+    lang_cstr,
+    from_cstr,
+    to_cstr,
+    config_cstr,
+    path_cstr
+  )
+  notify_warnings()
+  if result == nil then
+    vim.notify('Failed to export theme transition', vim.log.levels.ERROR)
+    return
+  end
+  M.lib.free_string(result)
+  vim.notify(string.format('Transition saved to: %s', filepath), vim.log.levels.INFO)
+  return filepath
+end
+
+-- Export a GIF or APNG of the current buffer being typed out; `format` is 'gif' or 'apng'
+-- `opts.typing` is a spec such as { unit = 'token', units_per_frame = 2, hold_frames = 45 }
+function M.export_typing(format, opts)
+  opts = opts or {}
+  format = format or 'gif'
+  if not M.lib then
+    M.load_library()
+  end
+  if format == 'gif' and not vim.tbl_contains(M.get_build_info().image_formats or {}, 'gif') then
+    vim.notify('This build of shoyu cannot encode GIFs', vim.log.levels.ERROR)
+    return
+  end
+
+  local lines = vim.api.nvim_buf_get_lines(0, 0, -1, false)
+  local code = table.concat(lines, '\n')
+  if code == '' then
+    vim.notify('Buffer is empty', vim.log.levels.WARN)
+    return
+  end
+  local filetype = opts.language or vim.bo.filetype
+  if filetype == '' then
+    filetype = 'auto'
+  end
+
+  local render_config = build_render_config(opts)
+  local config_json = vim.json.encode(render_config)
+  local spec_json = vim.json.encode(render_config.typing or vim.empty_dict())
+  -- APNGs keep the .png extension so viewers without APNG support show the first frame
+  local extension = format == 'gif' and '.gif' or '.png'
+  local filename = M.generate_filename(filetype):gsub('%.png$', '_typing' .. extension)
+  local filepath = config.output_dir .. '/' .. filename
+
+  local code_cstr = ffi.new('char[?]', #code + 1, code)
+  local lang_cstr = ffi.new('char[?]', #filetype + 1, filetype)
+  local theme_cstr = ffi.new('char[?]', #render_config.theme + 1, render_config.theme)
+  local config_cstr = ffi.new('char[?]', #config_json + 1, config_json)
+  local spec_cstr = ffi.new('char[?]', #spec_json + 1, spec_json)
+  local path_cstr = ffi.new('char[?]', #filepath + 1, filepath)
+  local result = M.lib.export_typing_animation(
+    code_cstr,
+    lang_cstr,
+    theme_cstr,
+    config_cstr,
+    spec_cstr,
+    path_cstr
+  )
+  notify_warnings()
+  if result == nil then
+    vim.notify('Failed to export typing animation', vim.log.levels.ERROR)
+    return
+  end
+  M.lib.free_string(result)
+  vim.notify(string.format('Typing animation saved to: %s', filepath), vim.log.levels.INFO)
+  return filepath
+end
+
+-- Write numbered PNG frames of an animation for an external encoder
+-- `opts.animation` is a spec such as { kind = 'typing', unit = 'token' }
+function M.export_frames(opts)
+  opts = opts or {}
+  if not M.lib then
+    M.load_library()
+  end
+
+  local lines = vim.api.nvim_buf_get_lines(0, 0, -1, false)
+  local code = table.concat(lines, '\n')
+  if code == '' then
+    vim.notify('Buffer is empty', vim.log.levels.WARN)
+    return
+  end
+  local filetype = opts.language or vim.bo.filetype
+  if filetype == '' then
+    filetype = 'auto'
+  end
+
+  local render_config = build_render_config(opts)
+  local config_json = vim.json.encode(render_config)
+  local spec_json = vim.json.encode(opts.animation or { kind = 'typing' })
+  local dirname = M.generate_filename(filetype):gsub('%.png$', '_frames')
+  local dirpath = config.output_dir .. '/' .. dirname
+
+  local code_cstr = ffi.new('char[?]', #code + 1, code)
+  local lang_cstr = ffi.new('char[?]', #filetype + 1, filetype)
+  local theme_cstr = ffi.new('char[?]', #render_config.theme + 1, render_config.theme)
+  local config_cstr = ffi.new('char[?]', #config_json + 1, config_json)
+  local spec_cstr = ffi.new('char[?]', #spec_json + 1, spec_json)
+  local dir_cstr = ffi.new('char[?]', #dirpath + 1, dirpath)
+  local result = M.lib.export_animation_frames(
+    code_cstr,
+    lang_cstr,
+    theme_cstr,
+    config_cstr,
+    spec_cstr,
+    dir_cstr
+  )
+  notify_warnings()
+  if result == nil then
+    vim.notify('Failed to export animation frames', vim.log.levels.ERROR)
+    return
+  end
+  local sequence = vim.json.decode(ffi.string(result))
+  M.lib.free_string(result)
+  vim.notify(
+    string.format('Saved %d frames to: %s', sequence.frame_count, sequence.pattern),
+    vim.log.levels.INFO
+  )
+  return sequence
+end
+
+-- Check if there's an active visual selection
+function M.has_visual_selection()
+  local start_pos = vim.fn.getpos("'<")
+  local end_pos = vim.fn.getpos("'>")
+
+  -- Check if marks exist and are valid
+  if start_pos[2] == 0 or end_pos[2] == 0 then
+    return false
+  end
+
+  -- Check if selection is meaningful (not just a cursor position)
+  if start_pos[2] == end_pos[2] and start_pos[3] == end_pos[3] then
+    return false
+  end
+  return true
+end
+
+-- Generate image from line range
+function M.generate_from_range(start_line, end_line, opts)
+  opts = opts or {}
+
+  -- Get lines from range (0-based line numbers)
+  local lines = vim.api.nvim_buf_get_lines(0, start_line - 1, end_line, false)
+  if #lines == 0 then
+    vim.notify('No lines in range', vim.log.levels.WARN)
+    return
+  end
+  opts.line_number_start = opts.line_number_start or start_line
+  return M.generate_image(opts, start_line, end_line)
+end
+
+-- Generate image from visual selection
+function M.generate_from_selection(opts)
+  opts = opts or {}
+  -- Get visual selection
+  local start_pos = vim.fn.getpos("'<")
+  local end_pos = vim.fn.getpos("'>")
+  -- Convert to line numbers (1-based)
+  local start_line = start_pos[2]
+  local end_line = end_pos[2]
+  return M.generate_from_range(start_line, end_line, opts)
+end
+
+-- The last visual selection as a `selection` config, in display columns
+function M.visual_selection()
+  local start_pos = vim.fn.getpos("'<")
+  local end_pos = vim.fn.getpos("'>")
+  if start_pos[2] == 0 or end_pos[2] == 0 then
+    return nil
+  end
+  local modes = { v = 'char', V = 'line', ['\22'] = 'block' }
+  local mode = modes[vim.fn.visualmode()] or 'char'
+  -- Tabs and wide characters span several columns; the start takes the
+  -- first, the end the last
+  local last = vim.fn.col({ end_pos[2], '$' }) - 1
+  local start_col = vim.fn.virtcol({ start_pos[2], start_pos[3] }, 1)[1]
+  local end_col = vim.fn.virtcol({ end_pos[2], math.min(end_pos[3], math.max(last, 1)) }, 1)[2]
+  if end_pos[3] > last then
+    -- Past the end with `$` or on an empty line, also selecting the line break
+    end_col = end_col + 1
+  end
+  return {
+    start = { line = start_pos[2], column = start_col },
+    ['end'] = { line = end_pos[2], column = end_col },
+    mode = mode,
+  }
+end
+
+-- Generate image of the whole buffer with the last visual selection painted in
+function M.generate_with_selection(opts)
+  opts = opts or {}
+  opts.selection = opts.selection or M.visual_selection()
+  return M.generate_image(opts)
+end
+
+-- Generate image intelligently: use selection if available, otherwise full buffer
+function M.generate_smart(opts)
+  opts = opts or {}
+  if M.has_visual_selection() then
+    return M.generate_from_selection(opts)
+  else
+    return M.generate_image(opts)
+  end
+end
+
+-- Get available themes
+function M.get_themes()
+  if config.server then
+    return server_request('themes')
+  end
+  if not M.lib then
+    M.load_library()
+  end
+  local result = M.lib.get_available_themes()
+  if result == nil then
+    return {}
+  end
+  local themes_json = ffi.string(result)
+  M.lib.free_string(result)
+  return vim.json.decode(themes_json)
+end
+
+-- Get every color of a theme, e.g. for swatches in a picker:
+-- { name, background = { hex, rgb }, foreground, comment, ..., scope_styles, chrome }
+function M.get_theme(name)
+  if config.server then
+    return server_request('theme', name)
+  end
+  if not M.lib then
+    M.load_library()
+  end
+  local name_cstr = ffi.new('char[?]', #name + 1, name)
+  local result = M.lib.get_theme_details(name_cstr)
+  notify_warnings()
+  if result == nil then
+    return nil
+  end
+  local theme_json = ffi.string(result)
+  M.lib.free_string(result)
+  return vim.json.decode(theme_json)
+end
+
+-- Check a theme's text colors against WCAG contrast on `background`, a hex color, or the
+-- theme's own background: { theme, background, passes_aa, min_ratio, colors = {...} }
+function M.validate_theme(name, background)
+  if not M.lib then
+    M.load_library()
+  end
+  local name_cstr = ffi.new('char[?]', #name + 1, name)
+  local background_cstr = background and ffi.new('char[?]', #background + 1, background) or nil
+  local result = M.lib.validate_theme(name_cstr, background_cstr)
+  notify_warnings()
+  if result == nil then
+    return nil
+  end
+  local report_json = ffi.string(result)
+  M.lib.free_string(result)
+  return vim.json.decode(report_json)
+end
+
+-- Highlight groups a theme is built from, treesitter captures alongside the legacy groups
+local THEME_GROUPS = {
+  'Normal',
+  'Comment',
+  '@comment',
+  'Keyword',
+  '@keyword',
+  'Statement',
+  'String',
+  '@string',
+  'Number',
+  '@number',
+  'Constant',
+  '@constant',
+  'Function',
+  '@function',
+  'Identifier',
+  'Type',
+  '@type',
+  '@type.definition',
+  'Structure',
+  '@variable',
+  'Operator',
+  '@operator',
+  'Delimiter',
+  '@punctuation.delimiter',
+  'StatusLine',
+  'LineNr',
+  'WinSeparator',
+}
+
+-- Build a theme from the running colorscheme's highlight groups and register it as `name`,
+-- `nvim-<colorscheme>` by default so it never collides with a built-in; returns the name
+function M.register_colorscheme(name)
+  if not M.lib then
+    M.load_library()
+  end
+  name = name or ('nvim-' .. (vim.g.colors_name or 'default'))
+  local groups = {}
+  for _, group in ipairs(THEME_GROUPS) do
+    local hl = vim.api.nvim_get_hl(0, { name = group, link = false })
+    if hl.fg or hl.bg then
+      groups[group] = {
+        fg = hl.fg and string.format('#%06x', hl.fg),
+        bg = hl.bg and string.format('#%06x', hl.bg),
+        bold = hl.bold or false,
+        italic = hl.italic or false,
+      }
+    end
+  end
+
+  local groups_json = vim.json.encode(groups)
+  local groups_cstr = ffi.new('char[?]', #groups_json + 1, groups_json)
+  local name_cstr = ffi.new('char[?]', #name + 1, name)
+  local result = M.lib.theme_from_highlights(groups_cstr, name_cstr)
+  notify_warnings()
+  if result == nil then
+    vim.notify('Failed to build a theme from the colorscheme', vim.log.levels.ERROR)
+    return
+  end
+  M.lib.free_string(result)
+  return name
+end
+
+-- Load a VS Code color theme JSON, or a base16 or base24 scheme for `.yaml` and `.yml`,
+-- registering it as `name` when given; returns the theme as M.get_theme does
+function M.import_theme(path, name)
+  if not M.lib then
+    M.load_library()
+  end
+  path = vim.fn.expand(path)
+  local path_cstr = ffi.new('char[?]', #path + 1, path)
+  local name_cstr = name and ffi.new('char[?]', #name + 1, name) or nil
+  local extension = vim.fn.fnamemodify(path, ':e'):lower()
+  local import = (extension == 'yaml' or extension == 'yml') and M.lib.import_base16_scheme
+    or M.lib.import_vscode_theme
+  local result = import(path_cstr, name_cstr)
+  notify_warnings()
+  if result == nil then
+    vim.notify('Failed to import theme from ' .. path, vim.log.levels.ERROR)
+    return nil
+  end
+  local theme_json = ffi.string(result)
+  M.lib.free_string(result)
+  return vim.json.decode(theme_json)
+end
+
+-- Get the languages grammars are bundled or loaded for, through the render server
+function M.get_languages()
+  return server_request('languages')
+end
+
+-- Get built-in config presets
+function M.get_presets()
+  if not M.lib then
+    M.load_library()
+  end
+  local result = M.lib.get_available_presets()
+  if result == nil then
+    return {}
+  end
+  local presets_json = ffi.string(result)
+  M.lib.free_string(result)
+  return vim.json.decode(presets_json)
+end
+
+-- Get named backdrop gradients as { name, colors, direction } tables
+function M.get_gradient_presets()
+  if not M.lib then
+    M.load_library()
+  end
+  local result = M.lib.get_gradient_presets()
+  if result == nil then
+    return {}
+  end
+  local presets_json = ffi.string(result)
+  M.lib.free_string(result)
+  return vim.json.decode(presets_json)
+end
+
+-- Version, commit, enabled features, and output formats of the loaded library
+function M.get_build_info()
+  if not M.lib then
+    M.load_library()
+  end
+  local result = M.lib.get_build_info()
+  if result == nil then
+    return {}
+  end
+  local info_json = ffi.string(result)
+  M.lib.free_string(result)
+  return vim.json.decode(info_json)
 end
 
--- Load the shared library
-function M.load_library()
-  if M.lib then
-    return M.lib
-  end
-  local file_names = {
-    'libshoyu.so', -- Linux
-    'shoyu.dll', -- Windows
-    'libshoyu.dylib', -- macOS
-  }
-  local plugin_dir = get_plugin_dir()
-  for _, shared_object in ipairs(file_names) do
-    local lib_path = plugin_dir .. '/target/release/' .. shared_object
-    if vim.fn.filereadable(lib_path) == 1 then
-      M.lib = ffi.load(lib_path)
-      return M.lib
-    end
+-- Store a named base config that renders can select with `profile`
+function M.set_profile(name, profile_config)
+  if not M.lib then
+    M.load_library()
   end
-  vim.schedule(function()
-    vim.notify(
-      string.format(
-        'Could not load shoyu shared library from plugin directory: %s\n'
-          .. 'Make sure to build the plugin with: cargo build --release\n'
-          .. 'Searched paths:\n'
-          .. '  - %s/target/release/\n',
-        plugin_dir,
-        plugin_dir
-      ),
-      vim.log.levels.ERROR
-    )
-  end)
+  local config_json = vim.json.encode(profile_config)
+  local name_cstr = ffi.new('char[?]', #name + 1, name)
+  local config_cstr = ffi.new('char[?]', #config_json + 1, config_json)
+  local stored = M.lib.set_profile(name_cstr, config_cstr) == 1
+  notify_warnings()
+  return stored
 end
 
--- Generate image output
-function M.generate_image(opts)
-  opts = opts or {}
+-- Load .sublime-syntax files from a directory for languages syntect doesn't bundle
+function M.add_syntax_directory(path)
   if not M.lib then
     M.load_library()
   end
+  path = vim.fn.expand(path)
+  local path_cstr = ffi.new('char[?]', #path + 1, path)
+  local added = M.lib.add_syntax_directory(path_cstr) == 1
+  notify_warnings()
+  return added
+end
 
-  -- Get current buffer content
-  local lines = vim.api.nvim_buf_get_lines(0, 0, -1, false)
-  local code = table.concat(lines, '\n')
-  if code == '' then
-    vim.notify('Buffer is empty', vim.log.levels.WARN)
-    return
+-- Load the theme files in `themes_dir` again after editing them; returns the names found
+function M.reload_themes()
+  if not M.lib then
+    M.load_library()
   end
+  local dir = config.themes_dir and vim.fn.expand(config.themes_dir)
+  local dir_cstr = dir and ffi.new('char[?]', #dir + 1, dir) or nil
+  local result = M.lib.reload_themes(dir_cstr)
+  notify_warnings()
+  if result == nil then
+    vim.notify('Failed to load themes from ' .. tostring(dir), vim.log.levels.ERROR)
+    return {}
+  end
+  local names_json = ffi.string(result)
+  M.lib.free_string(result)
+  return vim.json.decode(names_json)
+end
 
-  -- Determine language
-  local filetype = opts.language or vim.bo.filetype
-  if filetype == '' then
-    filetype = 'text'
+-- Get the names of stored profiles
+function M.get_profiles()
+  if not M.lib then
+    M.load_library()
+  end
+  local result = M.lib.get_profiles()
+  if result == nil then
+    return {}
   end
+  local profiles_json = ffi.string(result)
+  M.lib.free_string(result)
+  return vim.json.decode(profiles_json)
+end
 
-  -- Check if language is supported
-  local lang_cstr = ffi.new('char[?]', #filetype + 1, filetype)
-  if M.lib.is_language_supported(lang_cstr) == 0 then
-    vim.notify(string.format("Language '%s' is not supported", filetype), vim.log.levels.WARN)
-    filetype = 'text'
+-- Take warnings queued by the library since the last call
+function M.get_warnings()
+  if not M.lib then
+    M.load_library()
+  end
+  local result = M.lib.get_warnings()
+  if result == nil then
+    return {}
   end
+  local warnings_json = ffi.string(result)
+  M.lib.free_string(result)
+  return vim.json.decode(warnings_json)
+end
 
-  -- Merge config with options
-  local render_config = vim.tbl_deep_extend('force', config, opts)
+-- Render code without saving it, returning { image, width, height, line_count,
+-- render_ms, theme, truncated } where image is a PNG data URL, for placing the
+-- image with image.nvim or reporting progress
+function M.render(code, language, opts)
+  local render_config = build_render_config(opts)
   local config_json = vim.json.encode(render_config)
+  if config.server then
+    local ok, output =
+      pcall(server_request, 'render', code, language, render_config.theme, config_json)
+    notify_warnings()
+    return ok and output or nil
+  end
+  if not M.lib then
+    M.load_library()
+  end
+  local code_cstr = ffi.new('char[?]', #code + 1, code)
+  local lang_cstr = ffi.new('char[?]', #language + 1, language)
+  local theme_cstr = ffi.new('char[?]', #render_config.theme + 1, render_config.theme)
+  local config_cstr = ffi.new('char[?]', #config_json + 1, config_json)
+  local result =
+    M.lib.generate_snippet_image_with_metadata(code_cstr, lang_cstr, theme_cstr, config_cstr)
+  notify_warnings()
+  if result == nil then
+    return nil
+  end
+  local output_json = ffi.string(result)
+  M.lib.free_string(result)
+  return vim.json.decode(output_json)
+end
 
-  -- Generate image
+-- Render code to the escape sequence that draws it inline in the terminal;
+-- protocol is 'kitty', 'iterm2', 'sixel', or nil to detect it from the
+-- environment
+function M.preview_sequence(code, language, protocol, opts)
+  if not M.lib then
+    M.load_library()
+  end
+  local render_config = build_render_config(opts)
+  local config_json = vim.json.encode(render_config)
+  protocol = protocol or 'auto'
   local code_cstr = ffi.new('char[?]', #code + 1, code)
-  local lang_cstr = ffi.new('char[?]', #filetype + 1, filetype)
+  local lang_cstr = ffi.new('char[?]', #language + 1, language)
   local theme_cstr = ffi.new('char[?]', #render_config.theme + 1, render_config.theme)
   local config_cstr = ffi.new('char[?]', #config_json + 1, config_json)
-  local result = M.lib.generate_snippet_image(code_cstr, lang_cstr, theme_cstr, config_cstr)
+  local protocol_cstr = ffi.new('char[?]', #protocol + 1, protocol)
+  local result =
+    M.lib.generate_terminal_preview(code_cstr, lang_cstr, theme_cstr, config_cstr, protocol_cstr)
+  notify_warnings()
   if result == nil then
-    vim.notify('Failed to generate image', vim.log.levels.ERROR)
-    return
+    return nil
   end
+  local sequence = ffi.string(result)
+  M.lib.free_string(result)
+  return sequence
+end
 
-  local image_data = ffi.string(result)
+-- Measure output size and line metrics for code without rendering it
+function M.measure(code, language, opts)
+  if not M.lib then
+    M.load_library()
+  end
+  local render_config = build_render_config(opts)
+  local config_json = vim.json.encode(render_config)
+  local code_cstr = ffi.new('char[?]', #code + 1, code)
+  local lang_cstr = ffi.new('char[?]', #language + 1, language)
+  local config_cstr = ffi.new('char[?]', #config_json + 1, config_json)
+  local result = M.lib.measure_snippet(code_cstr, lang_cstr, config_cstr)
+  notify_warnings()
+  if result == nil then
+    return nil
+  end
+  local metrics_json = ffi.string(result)
   M.lib.free_string(result)
+  return vim.json.decode(metrics_json)
+end
 
-  -- Save image
-  local filename = opts.filename or M.generate_filename(filetype)
-  local filepath = config.output_dir .. '/' .. filename
-  M.save_image_data(image_data, filepath)
-  vim.notify(string.format('Image saved to: %s', filepath), vim.log.levels.INFO)
-  if config.auto_open then
-    M.open_image(filepath)
+-- Highlight code without rendering it, returning one list of tokens per line,
+-- each { text, fg, bold, italic, underline, group, start_byte, end_byte }
+function M.highlight(code, language, theme)
+  if not M.lib then
+    M.load_library()
   end
-  return filepath
+  theme = theme or config.theme
+  local code_cstr = ffi.new('char[?]', #code + 1, code)
+  local lang_cstr = ffi.new('char[?]', #language + 1, language)
+  local theme_cstr = ffi.new('char[?]', #theme + 1, theme)
+  local result = M.lib.highlight_code(code_cstr, lang_cstr, theme_cstr)
+  notify_warnings()
+  if result == nil then
+    return nil
+  end
+  local lines_json = ffi.string(result)
+  M.lib.free_string(result)
+  return vim.json.decode(lines_json)
 end
 
--- Check if there's an active visual selection
-function M.has_visual_selection()
-  local start_pos = vim.fn.getpos("'<")
-  local end_pos = vim.fn.getpos("'>")
+-- Highlighted tokens of code with their scope, matching palette rule, and
+-- color, to see why a token got its color
+function M.dump_tokens(code, language, opts)
+  if not M.lib then
+    M.load_library()
+  end
+  local render_config = build_render_config(opts)
+  local config_json = vim.json.encode(render_config)
+  local code_cstr = ffi.new('char[?]', #code + 1, code)
+  local lang_cstr = ffi.new('char[?]', #language + 1, language)
+  local theme_cstr = ffi.new('char[?]', #render_config.theme + 1, render_config.theme)
+  local config_cstr = ffi.new('char[?]', #config_json + 1, config_json)
+  local result = M.lib.dump_tokens(code_cstr, lang_cstr, theme_cstr, config_cstr)
+  notify_warnings()
+  if result == nil then
+    return nil
+  end
+  local dump_json = ffi.string(result)
+  M.lib.free_string(result)
+  return vim.json.decode(dump_json)
+end
 
-  -- Check if marks exist and are valid
-  if start_pos[2] == 0 or end_pos[2] == 0 then
-    return false
+-- Highlight code incrementally as it changes, without rendering. Feed edits
+-- with `stream:edit(start, end, lines)` (0-based, end exclusive, as in
+-- nvim_buf_set_lines) and collect re-highlighted lines with
+-- `stream:poll(max_lines)`; edits made between polls are coalesced, and a
+-- budget leaves the rest for the next poll with `pending = true`
+function M.highlight_stream(code, language, opts)
+  if not M.lib then
+    M.load_library()
+  end
+  local render_config = build_render_config(opts)
+  local config_json = vim.json.encode(render_config)
+  local code_cstr = ffi.new('char[?]', #code + 1, code)
+  local lang_cstr = ffi.new('char[?]', #language + 1, language)
+  local theme_cstr = ffi.new('char[?]', #render_config.theme + 1, render_config.theme)
+  local config_cstr = ffi.new('char[?]', #config_json + 1, config_json)
+  local handle = M.lib.open_highlight_stream(code_cstr, lang_cstr, theme_cstr, config_cstr)
+  notify_warnings()
+  if handle == 0 then
+    return nil
   end
 
-  -- Check if selection is meaningful (not just a cursor position)
-  if start_pos[2] == end_pos[2] and start_pos[3] == end_pos[3] then
-    return false
+  local stream = {}
+  function stream:edit(start_line, end_line, lines)
+    local text = lines and #lines > 0 and table.concat(lines, '\n') or nil
+    local text_cstr = text and ffi.new('char[?]', #text + 1, text) or nil
+    local ok = M.lib.edit_highlight_stream(handle, start_line, end_line, text_cstr) == 1
+    notify_warnings()
+    return ok
   end
-  return true
+  function stream:poll(max_lines)
+    local result = M.lib.poll_highlight_stream(handle, max_lines or 0)
+    if result == nil then
+      return nil
+    end
+    local update_json = ffi.string(result)
+    M.lib.free_string(result)
+    return vim.json.decode(update_json)
+  end
+  function stream:close()
+    return M.lib.close_highlight_stream(handle) == 1
+  end
+  return stream
 end
 
--- Generate image from line range
-function M.generate_from_range(start_line, end_line, opts)
+-- Render only the backdrop at width x height pixels, to composite behind a
+-- panel rendered with `layer = 'panel'`
+function M.generate_backdrop(width, height, opts)
   opts = opts or {}
-
-  -- Get lines from range (0-based line numbers)
-  local lines = vim.api.nvim_buf_get_lines(0, start_line - 1, end_line, false)
-  if #lines == 0 then
-    vim.notify('No lines in range', vim.log.levels.WARN)
+  if not M.lib then
+    M.load_library()
+  end
+  local render_config = build_render_config(opts)
+  local config_json = vim.json.encode(render_config)
+  local theme_cstr = ffi.new('char[?]', #render_config.theme + 1, render_config.theme)
+  local config_cstr = ffi.new('char[?]', #config_json + 1, config_json)
+  local result = M.lib.generate_backdrop(theme_cstr, config_cstr, width, height)
+  notify_warnings()
+  if result == nil then
+    vim.notify('Failed to generate backdrop', vim.log.levels.ERROR)
     return
   end
-  local code = table.concat(lines, '\n')
 
-  -- Temporarily replace buffer content for generation
-  local original_lines = vim.api.nvim_buf_get_lines(0, 0, -1, false)
-  vim.api.nvim_buf_set_lines(0, 0, -1, false, lines)
-  local result = M.generate_image(opts)
+  local image_data = ffi.string(result)
+  M.lib.free_string(result)
 
-  -- Restore original content
-  vim.api.nvim_buf_set_lines(0, 0, -1, false, original_lines)
-  return result
+  local filename = opts.filename or M.generate_filename('backdrop')
+  local filepath = config.output_dir .. '/' .. filename
+  M.save_image_data(image_data, filepath)
+  vim.notify(string.format('Backdrop saved to: %s', filepath), vim.log.levels.INFO)
+  return filepath
 end
 
--- Generate image from visual selection
-function M.generate_from_selection(opts)
-  opts = opts or {}
-  -- Get visual selection
-  local start_pos = vim.fn.getpos("'<")
-  local end_pos = vim.fn.getpos("'>")
-  -- Convert to line numbers (1-based)
-  local start_line = start_pos[2]
-  local end_line = end_pos[2]
-  return M.generate_from_range(start_line, end_line, opts)
+-- Stage exports in a `shoyu` directory under `temp.dir` (the system temp
+-- directory when nil) and remove staged files older than
+-- `temp.cleanup_older_than` seconds (0 keeps them)
+function M.set_temp_policy(temp)
+  if not M.lib then
+    M.load_library()
+  end
+  temp = temp or {}
+  local dir = temp.dir and vim.fn.expand(temp.dir) or nil
+  local dir_cstr = dir and ffi.new('char[?]', #dir + 1, dir) or nil
+  return M.lib.set_temp_policy(dir_cstr, temp.cleanup_older_than or 86400) == 1
 end
 
--- Generate image intelligently: use selection if available, otherwise full buffer
-function M.generate_smart(opts)
-  opts = opts or {}
-  if M.has_visual_selection() then
-    return M.generate_from_selection(opts)
-  else
-    return M.generate_image(opts)
+-- Delete staged temp files, returning how many files and bytes were removed
+function M.purge_cache()
+  if not M.lib then
+    M.load_library()
+  end
+  local result = M.lib.purge_cache()
+  if result == nil then
+    return nil
   end
+  local summary_json = ffi.string(result)
+  M.lib.free_string(result)
+  return vim.json.decode(summary_json)
 end
 
--- Get available themes
-function M.get_themes()
+-- Cap concurrent renders, canvas size, and cache size (0 disables a limit)
+function M.set_resource_limits(limits)
   if not M.lib then
     M.load_library()
   end
-  local result = M.lib.get_available_themes()
+  limits = limits or {}
+  M.lib.set_resource_limits(
+    limits.max_concurrent or 0,
+    limits.max_pixels or 0,
+    limits.max_cache_mb or 0
+  )
+end
+
+-- Get configured limits and render counters
+function M.get_resource_usage()
+  if not M.lib then
+    M.load_library()
+  end
+  local result = M.lib.get_resource_usage()
   if result == nil then
     return {}
   end
-  local themes_json = ffi.string(result)
+  local usage_json = ffi.string(result)
   M.lib.free_string(result)
-  return vim.json.decode(themes_json)
+  return vim.json.decode(usage_json)
 end
 
 -- Generate filename
@@ -313,6 +1599,21 @@ function M.setup(_, opts)
   config = vim.tbl_deep_extend('force', defaults, opts or {})
   vim.fn.mkdir(config.output_dir, 'p')
   M.load_library()
+  if config.resource_limits then
+    M.set_resource_limits(config.resource_limits)
+  end
+  if config.temp then
+    M.set_temp_policy(config.temp)
+  end
+  for name, profile_config in pairs(config.profiles or {}) do
+    M.set_profile(name, profile_config)
+  end
+  for _, dir in ipairs(config.syntax_dirs or {}) do
+    M.add_syntax_directory(dir)
+  end
+  if config.themes_dir then
+    M.reload_themes()
+  end
   vim.api.nvim_create_user_command('Shoyu', function(args)
     if args.args ~= '' then
       opts.theme = args.args
@@ -330,6 +1631,125 @@ function M.setup(_, opts)
     end,
     desc = 'Generate code snippet image (uses range/selection if available, otherwise full buffer)',
   })
+  vim.api.nvim_create_user_command('ShoyuSelection', function()
+    M.generate_with_selection(vim.deepcopy(opts))
+  end, {
+    range = true,
+    desc = 'Generate image of the whole buffer showing the last visual selection',
+  })
+  vim.api.nvim_create_user_command('ShoyuCarousel', function()
+    M.generate_carousel(opts)
+  end, {
+    desc = 'Generate a series of page images from the whole buffer',
+  })
+  vim.api.nvim_create_user_command('ShoyuTabs', function(args)
+    local tabs = args.args ~= '' and { layout = args.args } or nil
+    M.generate_tabs(vim.tbl_extend('force', opts, { tabs = tabs }))
+  end, {
+    nargs = '?',
+    complete = function()
+      return { 'active', 'stacked' }
+    end,
+    desc = 'Generate one image of the files in this tab page as tabs',
+  })
+  vim.api.nvim_create_user_command('ShoyuPdf', function(args)
+    M.export_pdf(opts, args.line1, args.line2)
+  end, {
+    range = '%',
+    desc = 'Export the range, or the whole buffer, as a one-page PDF',
+  })
+  vim.api.nvim_create_user_command('ShoyuVideo', function()
+    M.export_video(opts)
+  end, {
+    desc = 'Export a scrolling video of the whole buffer (requires ffmpeg)',
+  })
+  vim.api.nvim_create_user_command('ShoyuTransition', function(args)
+    M.export_transition(args.args, opts)
+  end, {
+    nargs = 1,
+    complete = function()
+      return M.get_themes()
+    end,
+    desc = 'Export a GIF morphing the whole buffer into another theme',
+  })
+  vim.api.nvim_create_user_command('ShoyuTyping', function(args)
+    M.export_typing(args.args ~= '' and args.args or 'gif', opts)
+  end, {
+    nargs = '?',
+    complete = function()
+      return { 'gif', 'apng' }
+    end,
+    desc = 'Export a GIF or APNG of the whole buffer being typed out',
+  })
+  vim.api.nvim_create_user_command('ShoyuFrames', function(args)
+    local kind = args.args ~= '' and args.args or 'typing'
+    M.export_frames(vim.tbl_extend('force', opts, { animation = { kind = kind } }))
+  end, {
+    nargs = '?',
+    complete = function()
+      return { 'typing', 'scroll' }
+    end,
+    desc = 'Write numbered PNG frames of a typing or scrolling animation',
+  })
+  vim.api.nvim_create_user_command('ShoyuTokens', function(args)
+    local lines = vim.api.nvim_buf_get_lines(0, args.line1 - 1, args.line2, false)
+    local filetype = vim.bo.filetype ~= '' and vim.bo.filetype or 'auto'
+    local dump = M.dump_tokens(table.concat(lines, '\n'), filetype, opts)
+    if dump == nil then
+      vim.notify('Failed to highlight tokens', vim.log.levels.ERROR)
+      return
+    end
+    local output = {
+      string.format('-- %s via %s, theme %s', dump.language, dump.highlighter, dump.theme),
+    }
+    for _, token in ipairs(dump.tokens) do
+      table.insert(
+        output,
+        string.format(
+          '%d:%d-%d %s %q %s <- %s',
+          token.line + args.line1 - 1,
+          token.start_column,
+          token.end_column,
+          token.color,
+          token.text,
+          token.scope,
+          token.rule == vim.NIL and 'foreground' or token.rule
+        )
+      )
+    end
+    vim.cmd('new')
+    vim.bo.buftype = 'nofile'
+    vim.bo.bufhidden = 'wipe'
+    vim.api.nvim_buf_set_lines(0, 0, -1, false, output)
+  end, {
+    range = '%',
+    desc = 'List highlighted tokens with their scopes and colors in a scratch buffer',
+  })
+  vim.api.nvim_create_user_command('ShoyuPurgeCache', function()
+    local summary = M.purge_cache()
+    if summary then
+      vim.notify(string.format('Removed %d temp files (%d bytes)', summary.files, summary.bytes))
+    end
+  end, {
+    desc = 'Delete temp files staged by exports',
+  })
+  vim.api.nvim_create_user_command('ShoyuReloadThemes', function()
+    local themes = M.reload_themes()
+    vim.notify('Loaded themes: ' .. table.concat(themes, ', '))
+  end, {
+    desc = 'Load the theme files in themes_dir again',
+  })
+  vim.api.nvim_create_user_command('ShoyuImportTheme', function(args)
+    local path = args.fargs[1]
+    local name = args.fargs[2] or vim.fn.fnamemodify(path, ':t:r')
+    if M.import_theme(path, name) then
+      vim.notify('Imported theme: ' .. name)
+    end
+  end, {
+    nargs = '+',
+    complete = 'file',
+    desc = 'Register a theme file under a name, its file name by default',
+  })
   vim.api.nvim_create_user_command('ShoyuThemes', function()
     local themes = M.get_themes()
     vim.notify('Available themes: ' .. table.concat(themes, ', '))
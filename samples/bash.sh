@@ -0,0 +1,9 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+# Back up every config file with a timestamp
+backup_dir="${HOME}/backups/$(date +%Y%m%d)"
+mkdir -p "$backup_dir"
+for file in ~/.config/*.conf; do
+  cp "$file" "$backup_dir/" && echo "saved $file"
+done
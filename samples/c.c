@@ -0,0 +1,15 @@
+#include <stdio.h>
+
+/* Sum the first n integers */
+static long sum(int n) {
+    long total = 0;
+    for (int i = 1; i <= n; i++) {
+        total += i;
+    }
+    return total;
+}
+
+int main(void) {
+    printf("%ld\n", sum(100));
+    return 0;
+}
@@ -0,0 +1,10 @@
+(ns shoyu.core
+  (:require [clojure.string :as str]))
+
+;; Title-case every word in a sentence
+(defn title-case [sentence]
+  (->> (str/split sentence #"\s+")
+       (map str/capitalize)
+       (str/join " ")))
+
+(println (title-case "beautiful code snippets"))
@@ -0,0 +1,15 @@
+#include <iostream>
+#include <vector>
+
+template <typename T>
+T max_of(const std::vector<T>& values) {
+    T best = values.front();
+    for (const auto& value : values) {
+        if (value > best) best = value;
+    }
+    return best;
+}
+
+int main() {
+    std::cout << max_of<int>({3, 9, 4}) << std::endl;
+}
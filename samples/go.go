@@ -0,0 +1,10 @@
+package main
+
+import "fmt"
+
+func main() {
+	numbers := []int{1, 2, 3}
+	for i, n := range numbers {
+		fmt.Printf("%d: %d\n", i, n*n) // squares
+	}
+}
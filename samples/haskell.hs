@@ -0,0 +1,10 @@
+module Main where
+
+-- | Fibonacci numbers as an infinite list
+fibs :: [Integer]
+fibs = 0 : 1 : zipWith (+) fibs (tail fibs)
+
+main :: IO ()
+main = do
+  let firstTen = take 10 fibs
+  print firstTen
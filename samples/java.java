@@ -0,0 +1,12 @@
+public class Greeter {
+    private final String greeting;
+
+    public Greeter(String greeting) {
+        this.greeting = greeting;
+    }
+
+    /** Say hello to someone */
+    public String greet(String name) {
+        return String.format("%s, %s!", greeting, name);
+    }
+}
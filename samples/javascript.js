@@ -0,0 +1,8 @@
+// Debounce a function so bursts of calls collapse into one
+export function debounce(fn, wait = 200) {
+  let timer = null;
+  return (...args) => {
+    clearTimeout(timer);
+    timer = setTimeout(() => fn.apply(this, args), wait);
+  };
+}
@@ -0,0 +1,11 @@
+data class Book(val title: String, val year: Int)
+
+// Books published after a given year, newest first
+fun recent(books: List<Book>, after: Int): List<Book> =
+    books.filter { it.year > after }
+        .sortedByDescending { it.year }
+
+fun main() {
+    val books = listOf(Book("Dune", 1965), Book("Anathem", 2008))
+    println(recent(books, 2000))
+}
@@ -0,0 +1,15 @@
+local M = {}
+
+-- Merge two tables, preferring values from `b`
+function M.merge(a, b)
+  local out = {}
+  for k, v in pairs(a) do
+    out[k] = v
+  end
+  for k, v in pairs(b or {}) do
+    out[k] = v
+  end
+  return out
+end
+
+return M
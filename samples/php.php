@@ -0,0 +1,13 @@
+<?php
+
+namespace App;
+
+final class Slug
+{
+    // Turn a title into a URL slug
+    public static function make(string $title): string
+    {
+        $slug = strtolower(trim($title));
+        return preg_replace('/[^a-z0-9]+/', '-', $slug);
+    }
+}
@@ -0,0 +1,11 @@
+from dataclasses import dataclass
+
+
+@dataclass
+class Point:
+    x: float
+    y: float
+
+    def distance(self, other: "Point") -> float:
+        # Euclidean distance
+        return ((self.x - other.x) ** 2 + (self.y - other.y) ** 2) ** 0.5
@@ -0,0 +1,15 @@
+# A tiny stack
+class Stack
+  attr_reader :items
+
+  def initialize
+    @items = []
+  end
+
+  def push(item)
+    @items << item
+    self
+  end
+
+  def pop = @items.pop
+end
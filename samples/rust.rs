@@ -0,0 +1,10 @@
+use std::collections::HashMap;
+
+/// Count how often each word appears
+fn word_counts(text: &str) -> HashMap<&str, usize> {
+  let mut counts = HashMap::new();
+  for word in text.split_whitespace() {
+    *counts.entry(word).or_insert(0) += 1;
+  }
+  counts
+}
@@ -0,0 +1,11 @@
+sealed trait Shape
+case class Circle(radius: Double) extends Shape
+case class Rect(width: Double, height: Double) extends Shape
+
+object Geometry {
+  // Area of any shape
+  def area(shape: Shape): Double = shape match {
+    case Circle(r)  => math.Pi * r * r
+    case Rect(w, h) => w * h
+  }
+}
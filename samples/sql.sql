@@ -0,0 +1,9 @@
+-- Top customers by revenue
+SELECT c.name, SUM(o.total) AS revenue
+FROM customers AS c
+JOIN orders AS o ON o.customer_id = c.id
+WHERE o.created_at >= '2024-01-01'
+GROUP BY c.name
+HAVING SUM(o.total) > 1000
+ORDER BY revenue DESC
+LIMIT 10;
@@ -0,0 +1,13 @@
+import Foundation
+
+struct Temperature {
+    let celsius: Double
+
+    // Convert to Fahrenheit
+    var fahrenheit: Double {
+        celsius * 9 / 5 + 32
+    }
+}
+
+let today = Temperature(celsius: 21.5)
+print("It is \(today.fahrenheit)°F")
@@ -0,0 +1,10 @@
+interface User {
+  id: number;
+  name: string;
+  email?: string;
+}
+
+export async function fetchUser(id: number): Promise<User> {
+  const response = await fetch(`/api/users/${id}`);
+  return (await response.json()) as User;
+}
@@ -0,0 +1,12 @@
+" Highlight trailing whitespace
+highlight TrailingSpace ctermbg=red guibg=#ff5555
+augroup trailing_space
+  autocmd!
+  autocmd BufWinEnter * match TrailingSpace /\s\+$/
+augroup END
+
+function! s:Strip() abort
+  let l:view = winsaveview()
+  %s/\s\+$//e
+  call winrestview(l:view)
+endfunction
@@ -0,0 +1,43 @@
+/* ~~/src/animation/apng.rs */
+
+// standard crates
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+// third-party crates
+use anyhow::{Result, anyhow};
+
+// local modules
+use crate::animation::frames::AnimationFrames;
+use crate::tempfiles::TempFile;
+
+/// Encode frames as a looping APNG, which keeps full color where a GIF
+/// would quantize and dither every frame
+pub fn write_apng(frames: AnimationFrames<'_>, output: &Path, fps: u32) -> Result<()> {
+  let png_error = |e: png::EncodingError| anyhow!("Failed to write APNG: {}", e);
+  // Staged in the temp directory so a failed export leaves no partial APNG
+  let staged = TempFile::create("png")?;
+  let file = File::create(staged.path())
+    .map_err(|e| anyhow!("Failed to create {}: {}", staged.path().display(), e))?;
+  let (width, height) = frames.dimensions();
+  let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+  encoder.set_color(png::ColorType::Rgba);
+  encoder.set_depth(png::BitDepth::Eight);
+  encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+  // 0 plays loops forever
+  encoder
+    .set_animated(frames.len() as u32, 0)
+    .map_err(png_error)?;
+  encoder
+    .set_frame_delay(1, fps.clamp(1, u16::MAX as u32) as u16)
+    .map_err(png_error)?;
+  let mut writer = encoder.write_header().map_err(png_error)?;
+  for frame in frames {
+    writer
+      .write_image_data(frame?.as_raw())
+      .map_err(png_error)?;
+  }
+  writer.finish().map_err(png_error)?;
+  staged.persist(output)
+}
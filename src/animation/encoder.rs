@@ -0,0 +1,134 @@
+/* ~~/src/animation/encoder.rs */
+
+// standard crates
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::thread::{self, JoinHandle};
+
+// third-party crates
+use anyhow::{Result, anyhow};
+use image::RgbaImage;
+
+// local modules
+use crate::config::VideoFormat;
+
+/// Streams raw RGBA frames into an ffmpeg child process
+pub struct VideoEncoder {
+  child: Child,
+  stdin: Option<ChildStdin>,
+  /// ffmpeg's messages, collected as they arrive
+  stderr: Option<JoinHandle<String>>,
+  width: u32,
+  height: u32,
+}
+
+impl VideoEncoder {
+  pub fn spawn(
+    ffmpeg_path: &str,
+    output: &Path,
+    format: VideoFormat,
+    width: u32,
+    height: u32,
+    fps: u32,
+  ) -> Result<Self> {
+    let mut command = Command::new(ffmpeg_path);
+    command
+      .args(["-y", "-loglevel", "error", "-nostats"])
+      .args(["-f", "rawvideo", "-pix_fmt", "rgba"])
+      .args(["-s", &format!("{}x{}", width, height)])
+      .args(["-r", &fps.max(1).to_string()])
+      .args(["-i", "-"])
+      // Chroma subsampled codecs need even dimensions
+      .args(["-vf", "pad=ceil(iw/2)*2:ceil(ih/2)*2"]);
+    match format {
+      VideoFormat::Mp4 => command.args([
+        "-c:v",
+        "libx264",
+        "-pix_fmt",
+        "yuv420p",
+        "-movflags",
+        "+faststart",
+      ]),
+      VideoFormat::Webm => command.args([
+        "-c:v",
+        "libvpx-vp9",
+        "-pix_fmt",
+        "yuv420p",
+        "-b:v",
+        "0",
+        "-crf",
+        "32",
+      ]),
+    };
+    let mut child = command
+      .arg(output)
+      .stdin(Stdio::piped())
+      .stdout(Stdio::null())
+      .stderr(Stdio::piped())
+      .spawn()
+      .map_err(|e| anyhow!("Failed to start {}: {}", ffmpeg_path, e))?;
+    let stdin = child.stdin.take();
+    // Drained from the start so a chatty ffmpeg never stalls on a full pipe
+    let stderr = child.stderr.take().map(|mut pipe| {
+      thread::spawn(move || {
+        let mut text = String::new();
+        let _ = pipe.read_to_string(&mut text);
+        text
+      })
+    });
+    Ok(Self {
+      child,
+      stdin,
+      stderr,
+      width,
+      height,
+    })
+  }
+
+  pub fn write_frame(&mut self, frame: &RgbaImage) -> Result<()> {
+    if frame.dimensions() != (self.width, self.height) {
+      return Err(anyhow!(
+        "Frame is {}x{} but the video is {}x{}",
+        frame.width(),
+        frame.height(),
+        self.width,
+        self.height
+      ));
+    }
+    let stdin = self
+      .stdin
+      .as_mut()
+      .ok_or_else(|| anyhow!("Encoder input already closed"))?;
+    stdin
+      .write_all(frame.as_raw())
+      .map_err(|e| anyhow!("ffmpeg stopped accepting frames: {}", e))
+  }
+
+  /// Close the input and wait for ffmpeg to finish writing the file
+  pub fn finish(mut self) -> Result<()> {
+    drop(self.stdin.take());
+    let status = self.child.wait()?;
+    if status.success() {
+      return Ok(());
+    }
+    let stderr = self
+      .stderr
+      .take()
+      .and_then(|reader| reader.join().ok())
+      .unwrap_or_default();
+    Err(anyhow!("ffmpeg exited with {}: {}", status, stderr.trim()))
+  }
+}
+
+impl Drop for VideoEncoder {
+  /// Stop an ffmpeg that was never `finish`ed, such as after a failed frame,
+  /// rather than leave it running
+  fn drop(&mut self) {
+    drop(self.stdin.take());
+    if let Ok(None) = self.child.try_wait() {
+      let _ = self.child.kill();
+      let _ = self.child.wait();
+    }
+  }
+}
@@ -0,0 +1,204 @@
+/* ~~/src/animation/frames.rs */
+
+// standard crates
+use std::fs;
+use std::path::Path;
+
+// third-party crates
+use anyhow::{Result, anyhow};
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+
+// local modules
+use crate::animation::scroll::ScrollPlan;
+use crate::animation::transition::{TransitionPlan, TransitionSpec};
+use crate::animation::typing::{TypingPlan, TypingSpec};
+use crate::config::RenderConfig;
+use crate::limits::RenderGuard;
+use crate::renderer::SnippetRenderer;
+use crate::themes::Theme;
+
+/// Which animation to render, e.g. `{"kind": "scroll"}` or
+/// `{"kind": "typing", "unit": "token"}`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum AnimationSpec {
+  /// Pan a viewport down the snippet using the `scroll` config
+  Scroll,
+  /// Reveal the snippet a piece at a time
+  Typing(TypingSpec),
+  /// Morph the snippet from the renderer's theme into another one
+  Transition(TransitionSpec),
+}
+
+enum FramePlan {
+  Scroll(ScrollPlan),
+  Typing(TypingPlan),
+  Transition(Box<TransitionPlan>),
+}
+
+/// Frames of an animation, rendered lazily one at a time. The renderer's
+/// config and theme are borrowed for the duration and restored when this is
+/// dropped.
+pub struct AnimationFrames<'a> {
+  renderer: &'a mut SnippetRenderer,
+  saved_config: RenderConfig,
+  saved_theme: Theme,
+  guard: Option<RenderGuard>,
+  plan: FramePlan,
+  index: usize,
+}
+
+/// Where `write_frames_to_dir` left the frames, for handing to an encoder
+#[derive(Debug, Serialize)]
+pub struct FrameSequence {
+  /// printf-style path, e.g. `out/frame_%05d.png` for ffmpeg's image2 demuxer
+  pub pattern: String,
+  pub frame_count: usize,
+  pub width: u32,
+  pub height: u32,
+}
+
+/// Plan an animation of `code` and return an iterator over its frames
+pub fn render_animation_frames<'a>(
+  renderer: &'a mut SnippetRenderer,
+  code: &str,
+  language: &str,
+  spec: &AnimationSpec,
+) -> Result<AnimationFrames<'a>> {
+  let guard = RenderGuard::acquire()?;
+  let highlighted_lines = renderer.highlight_snippet(code, language);
+  if highlighted_lines.is_empty() {
+    return Err(anyhow!("Nothing to animate"));
+  }
+  let saved_config = renderer.config().clone();
+  let saved_theme = renderer.theme().clone();
+
+  // Pin a random backdrop for the whole run so it doesn't change every frame
+  #[cfg(feature = "gradient")]
+  if renderer.config().gradient_seed.is_none() {
+    renderer.config_mut().gradient_seed = Some(rand::random());
+  }
+  let first_line = renderer.config().line_number_start;
+  renderer.reserve_gutter(first_line.saturating_add((highlighted_lines.len() as u32).max(1) - 1));
+  renderer.pin_width(&highlighted_lines);
+
+  let plan = match spec {
+    AnimationSpec::Scroll => {
+      ScrollPlan::new(renderer, &guard, &highlighted_lines).map(FramePlan::Scroll)
+    }
+    AnimationSpec::Typing(typing) => Ok(FramePlan::Typing(TypingPlan::new(
+      renderer,
+      highlighted_lines,
+      typing,
+    ))),
+    AnimationSpec::Transition(transition) => {
+      TransitionPlan::new(renderer, code, language, highlighted_lines, transition)
+        .map(|plan| FramePlan::Transition(Box::new(plan)))
+    }
+  };
+  let plan = match plan {
+    Ok(plan) => plan,
+    Err(error) => {
+      *renderer.config_mut() = saved_config;
+      renderer.set_theme(saved_theme);
+      renderer.reserve_gutter(0);
+      renderer.reserve_content(0, 0);
+      return Err(error);
+    }
+  };
+  Ok(AnimationFrames {
+    renderer,
+    saved_config,
+    saved_theme,
+    guard: Some(guard),
+    plan,
+    index: 0,
+  })
+}
+
+impl AnimationFrames<'_> {
+  /// Size of every frame in the sequence
+  pub fn dimensions(&self) -> (u32, u32) {
+    match &self.plan {
+      FramePlan::Scroll(plan) => plan.dimensions(),
+      FramePlan::Typing(plan) => plan.dimensions(),
+      FramePlan::Transition(plan) => plan.dimensions(),
+    }
+  }
+
+  /// Same frames, encoded as PNG files
+  pub fn into_png(self) -> impl Iterator<Item = Result<Vec<u8>>> {
+    self.map(|frame| SnippetRenderer::image_to_png_bytes(&frame?))
+  }
+
+  fn frame_count(&self) -> usize {
+    match &self.plan {
+      FramePlan::Scroll(plan) => plan.len(),
+      FramePlan::Typing(plan) => plan.len(),
+      FramePlan::Transition(plan) => plan.len(),
+    }
+  }
+}
+
+impl Iterator for AnimationFrames<'_> {
+  type Item = Result<RgbaImage>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.index >= self.frame_count() {
+      if let Some(guard) = self.guard.take() {
+        guard.complete();
+      }
+      return None;
+    }
+    let guard = self.guard.as_ref()?;
+    let frame = match &mut self.plan {
+      FramePlan::Scroll(plan) => Ok(plan.frame(self.index)),
+      FramePlan::Typing(plan) => plan.frame(self.renderer, guard, self.index),
+      FramePlan::Transition(plan) => plan.frame(self.renderer, guard, self.index),
+    };
+    self.index += 1;
+    if frame.is_err() {
+      // A failed frame ends the sequence
+      self.guard = None;
+    }
+    Some(frame)
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    let remaining = match self.guard {
+      Some(_) => self.frame_count() - self.index,
+      None => 0,
+    };
+    (remaining, Some(remaining))
+  }
+}
+
+impl ExactSizeIterator for AnimationFrames<'_> {}
+
+impl Drop for AnimationFrames<'_> {
+  fn drop(&mut self) {
+    *self.renderer.config_mut() = std::mem::take(&mut self.saved_config);
+    self.renderer.set_theme(self.saved_theme.clone());
+    self.renderer.reserve_gutter(0);
+    self.renderer.reserve_content(0, 0);
+  }
+}
+
+/// Write each frame as `frame_00001.png`, `frame_00002.png`, ... under `dir`
+pub fn write_frames_to_dir(frames: AnimationFrames<'_>, dir: &Path) -> Result<FrameSequence> {
+  fs::create_dir_all(dir).map_err(|e| anyhow!("Failed to create {}: {}", dir.display(), e))?;
+  let (width, height) = frames.dimensions();
+  let mut frame_count = 0;
+  for png in frames.into_png() {
+    frame_count += 1;
+    let path = dir.join(format!("frame_{:05}.png", frame_count));
+    fs::write(&path, png?).map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+  }
+  Ok(FrameSequence {
+    pattern: dir.join("frame_%05d.png").to_string_lossy().into_owned(),
+    frame_count,
+    width,
+    height,
+  })
+}
@@ -0,0 +1,32 @@
+/* ~~/src/animation/gif.rs */
+
+// standard crates
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+// third-party crates
+use anyhow::{Result, anyhow};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame};
+
+// local modules
+use crate::animation::frames::AnimationFrames;
+use crate::tempfiles::TempFile;
+
+/// Encode frames as a looping GIF. Each frame gets its own palette, so
+/// gradients band less than with one shared palette.
+pub fn write_gif(frames: AnimationFrames<'_>, output: &Path, fps: u32) -> Result<()> {
+  // Staged in the temp directory so a failed export leaves no partial GIF
+  let staged = TempFile::create("gif")?;
+  let file = File::create(staged.path())
+    .map_err(|e| anyhow!("Failed to create {}: {}", staged.path().display(), e))?;
+  let mut encoder = GifEncoder::new_with_speed(BufWriter::new(file), 10);
+  encoder.set_repeat(Repeat::Infinite)?;
+  let delay = Delay::from_numer_denom_ms(1000, fps.max(1));
+  for frame in frames {
+    encoder.encode_frame(Frame::from_parts(frame?, 0, 0, delay))?;
+  }
+  drop(encoder);
+  staged.persist(output)
+}
@@ -0,0 +1,47 @@
+/* ~~/src/animation/mod.rs */
+
+mod apng;
+mod encoder;
+mod frames;
+#[cfg(feature = "gif")]
+mod gif;
+mod scroll;
+mod transition;
+mod typing;
+
+pub use self::frames::{AnimationSpec, render_animation_frames, write_frames_to_dir};
+#[cfg(feature = "gif")]
+pub use self::gif::write_gif;
+pub use self::scroll::export_scrolling_video;
+pub use self::transition::TransitionSpec;
+pub use self::typing::TypingSpec;
+
+/// Encode frames as a GIF or an APNG, picked by the extension of `output`
+pub fn write_animated_image(
+  frames: frames::AnimationFrames<'_>,
+  output: &std::path::Path,
+  fps: u32,
+) -> anyhow::Result<()> {
+  let extension = output
+    .extension()
+    .map(|extension| extension.to_string_lossy().to_lowercase());
+  match extension.as_deref() {
+    Some("gif") => write_gif(frames, output, fps),
+    Some("png" | "apng") => apng::write_apng(frames, output, fps),
+    _ => Err(anyhow::anyhow!(
+      "Cannot tell the animation format of {}; use .gif or .png",
+      output.display()
+    )),
+  }
+}
+
+/// Stand-in for builds without the `gif` feature
+#[cfg(not(feature = "gif"))]
+pub fn write_gif(
+  _: frames::AnimationFrames<'_>,
+  _: &std::path::Path,
+  _: u32,
+) -> anyhow::Result<()> {
+  crate::warnings::push("Built without the gif feature; GIF export is unavailable");
+  Err(anyhow::anyhow!("GIF encoding is not compiled in"))
+}
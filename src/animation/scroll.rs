@@ -0,0 +1,207 @@
+/* ~~/src/animation/scroll.rs */
+
+// standard crates
+use std::path::Path;
+
+// third-party crates
+use anyhow::{Result, anyhow};
+use image::RgbaImage;
+
+// local modules
+use crate::animation::encoder::VideoEncoder;
+use crate::animation::frames::{AnimationSpec, render_animation_frames};
+use crate::config::{PanelSize, VideoFormat};
+use crate::limits::RenderGuard;
+use crate::renderer::SnippetRenderer;
+use crate::syntax::HighlightedLine;
+use crate::tempfiles::TempFile;
+
+/// Pan a fixed-height window down the whole snippet and encode it as video
+pub fn export_scrolling_video(
+  renderer: &mut SnippetRenderer,
+  code: &str,
+  language: &str,
+  output: &Path,
+) -> Result<()> {
+  let scroll = renderer.config().scroll.clone();
+  let frames = render_animation_frames(renderer, code, language, &AnimationSpec::Scroll)?;
+  let (width, height) = frames.dimensions();
+  // ffmpeg picks the container from the extension of the staged file
+  let extension = match scroll.format {
+    VideoFormat::Mp4 => "mp4",
+    VideoFormat::Webm => "webm",
+  };
+  let staged = TempFile::create(extension)?;
+  let mut encoder = VideoEncoder::spawn(
+    &scroll.ffmpeg_path,
+    staged.path(),
+    scroll.format,
+    width,
+    height,
+    scroll.fps,
+  )?;
+  for frame in frames {
+    encoder.write_frame(&frame?)?;
+  }
+  encoder.finish()?;
+  staged.persist(output)
+}
+
+/// Scroll frames copy a band of one tall render into a viewport-sized frame
+pub(super) struct ScrollPlan {
+  full: RgbaImage,
+  canvas: RgbaImage,
+  band_top: u32,
+  band_bottom: u32,
+  left: u32,
+  width: u32,
+  offsets: Vec<u32>,
+}
+
+impl ScrollPlan {
+  pub(super) fn new(
+    renderer: &mut SnippetRenderer,
+    guard: &RenderGuard,
+    highlighted_lines: &[HighlightedLine],
+  ) -> Result<Self> {
+    if highlighted_lines.is_empty() {
+      return Err(anyhow!("Nothing to scroll"));
+    }
+    let scroll = renderer.config().scroll.clone();
+    let line_count = highlighted_lines.len() as u32;
+    let viewport_lines = scroll.viewport_lines.clamp(1, line_count) as usize;
+
+    // One tall render of everything, and a viewport-sized frame around it
+    let config = renderer.config_mut();
+    config.height = PanelSize::Auto;
+    (config.min_height, config.max_height) = (0, None);
+    let full = renderer.rasterize(guard, highlighted_lines)?;
+    let full_metrics = renderer.compute_metrics(highlighted_lines);
+    let viewport_height = renderer.unscaled_panel_height(viewport_lines as u32);
+    renderer.config_mut().height = PanelSize::Fixed(viewport_height);
+    let canvas = renderer.rasterize(guard, &highlighted_lines[..viewport_lines])?;
+    let frame_metrics = renderer.compute_metrics(&highlighted_lines[..viewport_lines]);
+    let (top_chrome, bottom_chrome) = (
+      renderer.top_chrome_height(),
+      renderer.bottom_chrome_height(),
+    );
+    let border_radius = (renderer.config().border_radius * renderer.config().export_size) as u32;
+
+    // Content scrolls in the band between the title bar or header, the
+    // footer, and the rounded corners
+    let band_top = frame_metrics.panel_padding.top + top_chrome.max(border_radius);
+    let band_bottom = (frame_metrics.panel_padding.top + frame_metrics.panel_height)
+      .saturating_sub(bottom_chrome.max(border_radius))
+      .max(band_top);
+    let max_offset = full_metrics
+      .panel_height
+      .saturating_sub(frame_metrics.panel_height);
+
+    let fps = scroll.fps.max(1);
+    let pixels_per_frame =
+      (scroll.lines_per_second * full_metrics.line_height as f32 / fps as f32).max(1.0);
+    let hold_frames = (scroll.hold_seconds.max(0.0) * fps as f32).round() as usize;
+
+    let mut offsets = vec![0; hold_frames];
+    let mut offset = 0.0;
+    while offset < max_offset as f32 {
+      offsets.push(offset as u32);
+      offset += pixels_per_frame;
+    }
+    offsets.extend(std::iter::repeat_n(max_offset, hold_frames.max(1)));
+
+    Ok(Self {
+      full,
+      canvas,
+      band_top,
+      band_bottom,
+      left: frame_metrics.panel_padding.left,
+      width: frame_metrics.panel_width,
+      offsets,
+    })
+  }
+
+  pub(super) fn len(&self) -> usize {
+    self.offsets.len()
+  }
+
+  pub(super) fn dimensions(&self) -> (u32, u32) {
+    self.canvas.dimensions()
+  }
+
+  pub(super) fn frame(&mut self, index: usize) -> RgbaImage {
+    copy_band(
+      &self.full,
+      &mut self.canvas,
+      self.band_top,
+      self.band_bottom,
+      self.left,
+      self.width,
+      self.offsets[index],
+    );
+    self.canvas.clone()
+  }
+}
+
+/// Copy panel rows `top..bottom` from `source`, shifted down by `offset`
+fn copy_band(
+  source: &RgbaImage,
+  target: &mut RgbaImage,
+  top: u32,
+  bottom: u32,
+  left: u32,
+  width: u32,
+  offset: u32,
+) {
+  let right = (left + width).min(target.width()).min(source.width());
+  for y in top..bottom.min(target.height()) {
+    let source_y = y + offset;
+    if source_y >= source.height() {
+      break;
+    }
+    for x in left..right {
+      target.put_pixel(x, y, *source.get_pixel(x, source_y));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::{RenderConfig, ScrollConfig};
+
+  #[test]
+  fn test_scroll_plan() {
+    let config = RenderConfig {
+      export_size: 1.0,
+      scroll: ScrollConfig {
+        viewport_lines: 2,
+        hold_seconds: 0.0,
+        fps: 10,
+        ffmpeg_path: "shoyu-missing-ffmpeg".to_string(),
+        ..ScrollConfig::default()
+      },
+      ..RenderConfig::default()
+    };
+    let mut renderer = SnippetRenderer::new("dracula", config).unwrap();
+    let guard = RenderGuard::acquire().unwrap();
+
+    let lines = renderer.highlight_snippet("a\nb\nc\nd\ne\nf", "text");
+    let mut plan = ScrollPlan::new(&mut renderer, &guard, &lines).unwrap();
+    assert!(plan.len() > 2);
+    let first = plan.frame(0);
+    let last = plan.frame(plan.len() - 1);
+    assert_eq!(first.dimensions(), plan.dimensions());
+    assert_ne!(first, last);
+
+    // Nothing to scroll is an error rather than an empty viewport
+    assert!(ScrollPlan::new(&mut renderer, &guard, &[]).is_err());
+    drop(guard);
+
+    // A missing ffmpeg fails the export without leaving a file behind
+    let output = std::env::temp_dir().join(format!("shoyu-scroll-{}.mp4", std::process::id()));
+    assert!(export_scrolling_video(&mut renderer, "a\nb\nc", "text", &output).is_err());
+    assert!(export_scrolling_video(&mut renderer, "", "text", &output).is_err());
+    assert!(!output.exists());
+  }
+}
@@ -0,0 +1,127 @@
+/* ~~/src/animation/transition.rs */
+
+// third-party crates
+use anyhow::{Result, anyhow};
+use image::RgbaImage;
+use serde::Deserialize;
+
+// local modules
+use crate::limits::RenderGuard;
+use crate::renderer::SnippetRenderer;
+use crate::syntax::{HighlightedLine, HighlightedToken};
+use crate::themes::{Theme, get_theme};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TransitionSpec {
+  /// Theme to morph into from the renderer's current theme
+  pub to: String,
+  /// Frames spent blending between the two themes
+  pub frames: u32,
+  /// Still frames shown before and after the blend
+  pub hold_frames: u32,
+  /// Playback rate used by encoders such as GIF
+  pub fps: u32,
+}
+
+impl Default for TransitionSpec {
+  fn default() -> Self {
+    Self {
+      to: "github".to_string(),
+      frames: 30,
+      hold_frames: 15,
+      fps: 25,
+    }
+  }
+}
+
+/// Transition frames re-rasterize the snippet with a progressively blended theme
+pub(super) struct TransitionPlan {
+  from: Theme,
+  to: Theme,
+  from_lines: Vec<HighlightedLine>,
+  to_lines: Vec<HighlightedLine>,
+  frames: usize,
+  hold_frames: usize,
+  dimensions: (u32, u32),
+}
+
+impl TransitionPlan {
+  pub(super) fn new(
+    renderer: &mut SnippetRenderer,
+    code: &str,
+    language: &str,
+    from_lines: Vec<HighlightedLine>,
+    spec: &TransitionSpec,
+  ) -> Result<Self> {
+    let from = renderer.theme().clone();
+    let to = get_theme(&spec.to).ok_or_else(|| anyhow!("Unknown theme: {}", spec.to))?;
+    renderer.set_theme(to.clone());
+    let to_lines = renderer.highlight(code, language);
+    renderer.set_theme(from.clone());
+
+    let metrics = renderer.compute_metrics(&from_lines);
+    Ok(Self {
+      from,
+      to,
+      from_lines,
+      to_lines,
+      frames: spec.frames.max(2) as usize,
+      hold_frames: spec.hold_frames as usize,
+      dimensions: (metrics.width, metrics.height),
+    })
+  }
+
+  pub(super) fn len(&self) -> usize {
+    self.frames + self.hold_frames * 2
+  }
+
+  pub(super) fn dimensions(&self) -> (u32, u32) {
+    self.dimensions
+  }
+
+  pub(super) fn frame(
+    &self,
+    renderer: &mut SnippetRenderer,
+    guard: &RenderGuard,
+    index: usize,
+  ) -> Result<RgbaImage> {
+    let step = index.saturating_sub(self.hold_frames).min(self.frames - 1);
+    let progress = step as f32 / (self.frames - 1) as f32;
+    // Ease in and out so the morph doesn't start and stop abruptly
+    let t = progress * progress * (3.0 - 2.0 * progress);
+
+    renderer.set_theme(self.from.blend(&self.to, t));
+    renderer.rasterize(guard, &blend_lines(&self.from_lines, &self.to_lines, t))
+  }
+}
+
+/// Token colors from both highlights mixed by `t`. Lines tokenized differently
+/// under the two themes switch over at the midpoint instead.
+fn blend_lines(from: &[HighlightedLine], to: &[HighlightedLine], t: f32) -> Vec<HighlightedLine> {
+  from
+    .iter()
+    .zip(to)
+    .map(|(from_line, to_line)| {
+      let same_tokens = from_line.tokens.len() == to_line.tokens.len()
+        && from_line
+          .tokens
+          .iter()
+          .zip(&to_line.tokens)
+          .all(|(a, b)| a.text == b.text);
+      if !same_tokens {
+        return if t < 0.5 { from_line } else { to_line }.clone();
+      }
+      let tokens = from_line
+        .tokens
+        .iter()
+        .zip(&to_line.tokens)
+        .map(|(a, b)| HighlightedToken {
+          color: a.color.blend(&b.color, t),
+          ..a.clone()
+        })
+        .collect();
+      HighlightedLine { tokens }
+    })
+    .collect()
+}
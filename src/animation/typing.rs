@@ -0,0 +1,204 @@
+/* ~~/src/animation/typing.rs */
+
+// third-party crates
+use anyhow::Result;
+use image::RgbaImage;
+use serde::Deserialize;
+
+// local modules
+use crate::limits::RenderGuard;
+use crate::renderer::SnippetRenderer;
+use crate::syntax::{HighlightedLine, HighlightedToken};
+
+/// How much of the snippet each typing step reveals
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TypingUnit {
+  #[default]
+  Char,
+  Token,
+  Line,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct TypingSpec {
+  pub unit: TypingUnit,
+  pub units_per_frame: u32,
+  /// Extra frames of the finished snippet appended at the end
+  pub hold_frames: u32,
+  /// Playback rate used by encoders such as GIF and APNG
+  pub fps: u32,
+}
+
+impl Default for TypingSpec {
+  fn default() -> Self {
+    Self {
+      unit: TypingUnit::Char,
+      units_per_frame: 1,
+      hold_frames: 30,
+      fps: 30,
+    }
+  }
+}
+
+/// Typing frames re-rasterize a growing prefix of the snippet on a canvas
+/// sized for the finished result
+pub(super) struct TypingPlan {
+  lines: Vec<HighlightedLine>,
+  cutoffs: Vec<usize>,
+  hold_frames: usize,
+  dimensions: (u32, u32),
+}
+
+impl TypingPlan {
+  pub(super) fn new(
+    renderer: &mut SnippetRenderer,
+    lines: Vec<HighlightedLine>,
+    spec: &TypingSpec,
+  ) -> Self {
+    renderer.reserve_content(lines.len() as u32, renderer.widest_line(&lines));
+    let metrics = renderer.compute_metrics(&lines);
+    Self {
+      cutoffs: reveal_cutoffs(&lines, spec.unit, spec.units_per_frame),
+      lines,
+      hold_frames: spec.hold_frames as usize,
+      dimensions: (metrics.width, metrics.height),
+    }
+  }
+
+  pub(super) fn len(&self) -> usize {
+    self.cutoffs.len() + self.hold_frames
+  }
+
+  pub(super) fn dimensions(&self) -> (u32, u32) {
+    self.dimensions
+  }
+
+  pub(super) fn frame(
+    &self,
+    renderer: &mut SnippetRenderer,
+    guard: &RenderGuard,
+    index: usize,
+  ) -> Result<RgbaImage> {
+    let cutoff = self.cutoffs[index.min(self.cutoffs.len() - 1)];
+    renderer.rasterize(guard, &reveal(&self.lines, cutoff))
+  }
+}
+
+/// Character counts to show on each frame, starting from an empty panel and
+/// ending on the whole snippet. Whitespace never gets a frame of its own.
+fn reveal_cutoffs(lines: &[HighlightedLine], unit: TypingUnit, units_per_frame: u32) -> Vec<usize> {
+  let mut boundaries = Vec::new();
+  let mut position = 0;
+  for line in lines {
+    for token in &line.tokens {
+      match unit {
+        TypingUnit::Char => {
+          for ch in token.text.chars() {
+            position += 1;
+            if !ch.is_whitespace() {
+              boundaries.push(position);
+            }
+          }
+        }
+        TypingUnit::Token | TypingUnit::Line => {
+          position += token.text.chars().count();
+          if unit == TypingUnit::Token && !token.text.trim().is_empty() {
+            boundaries.push(position);
+          }
+        }
+      }
+    }
+    if unit == TypingUnit::Line {
+      boundaries.push(position);
+    }
+  }
+  boundaries.dedup();
+
+  let step = units_per_frame.max(1) as usize;
+  let mut cutoffs: Vec<usize> = std::iter::once(0)
+    .chain(boundaries.into_iter().skip(step - 1).step_by(step))
+    .collect();
+  if cutoffs.last() != Some(&position) {
+    cutoffs.push(position);
+  }
+  cutoffs.dedup();
+  cutoffs
+}
+
+/// The first `cutoff` characters of the snippet, split back into lines.
+/// A line appears once the one before it is complete.
+fn reveal(lines: &[HighlightedLine], cutoff: usize) -> Vec<HighlightedLine> {
+  let mut remaining = cutoff;
+  let mut revealed = Vec::new();
+  for (index, line) in lines.iter().enumerate() {
+    if index > 0 && remaining == 0 {
+      break;
+    }
+    let mut tokens = Vec::new();
+    for token in &line.tokens {
+      if remaining == 0 {
+        break;
+      }
+      let length = token.text.chars().count();
+      let text = match length <= remaining {
+        true => token.text.clone(),
+        false => token.text.chars().take(remaining).collect(),
+      };
+      remaining -= length.min(remaining);
+      tokens.push(HighlightedToken {
+        end_col: token.start_col.advance(&text),
+        text,
+        ..token.clone()
+      });
+    }
+    revealed.push(HighlightedLine { tokens });
+  }
+  revealed
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::syntax::{Column, TokenStyle};
+  use crate::themes::ThemeColor;
+
+  fn line(tokens: &[&str]) -> HighlightedLine {
+    HighlightedLine {
+      tokens: tokens
+        .iter()
+        .map(|text| HighlightedToken {
+          text: text.to_string(),
+          color: ThemeColor::new("#ffffff"),
+          background: None,
+          style: TokenStyle::REGULAR,
+          scope: String::new(),
+          start_col: Column::default(),
+          end_col: Column::default(),
+        })
+        .collect(),
+    }
+  }
+
+  #[test]
+  fn test_typing_reveal() {
+    let lines = vec![line(&["let", " ", "x"]), line(&[]), line(&["y"])];
+    assert_eq!(
+      reveal_cutoffs(&lines, TypingUnit::Char, 1),
+      vec![0, 1, 2, 3, 5, 6]
+    );
+    assert_eq!(
+      reveal_cutoffs(&lines, TypingUnit::Token, 1),
+      vec![0, 3, 5, 6]
+    );
+    assert_eq!(reveal_cutoffs(&lines, TypingUnit::Line, 1), vec![0, 5, 6]);
+    assert_eq!(reveal_cutoffs(&lines, TypingUnit::Char, 4), vec![0, 5, 6]);
+
+    let partial = reveal(&lines, 4);
+    assert_eq!(partial.len(), 1);
+    assert_eq!(partial[0].tokens.len(), 2);
+    assert_eq!(reveal(&lines, 6).len(), 3);
+    assert_eq!(reveal(&lines, 0).len(), 1);
+  }
+}
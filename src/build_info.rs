@@ -0,0 +1,76 @@
+/* ~~/src/build_info.rs */
+
+// standard crates
+use std::path::Path;
+
+// third-party crates
+use serde::Serialize;
+
+// local modules
+use crate::font::FONT_PATHS;
+
+/// What this copy of the library was built from and can do, for adapting the
+/// editor UI and for bug reports
+#[derive(Debug, Serialize)]
+pub struct BuildInfo {
+  pub version: &'static str,
+  /// Short commit hash, absent when built outside a git checkout
+  pub git_hash: Option<&'static str>,
+  /// `release` or `debug`
+  pub profile: &'static str,
+  /// Enabled cargo features
+  pub features: Vec<&'static str>,
+  /// Still image and animation formats the library encodes itself
+  pub image_formats: Vec<&'static str>,
+  /// Video formats encoded through ffmpeg, when it is installed
+  pub video_formats: Vec<&'static str>,
+  /// Formats accepted for `backdrop_image`
+  pub backdrop_formats: Vec<&'static str>,
+  /// Font files from the fallback chain present on this machine
+  pub fonts: Vec<&'static str>,
+}
+
+pub fn build_info() -> BuildInfo {
+  let features = [
+    ("bundled-syntaxes", cfg!(feature = "bundled-syntaxes")),
+    ("cli", cfg!(feature = "cli")),
+    ("complex-scripts", cfg!(feature = "complex-scripts")),
+    ("embedded-font", cfg!(feature = "embedded-font")),
+    ("gif", cfg!(feature = "gif")),
+    ("gradient", cfg!(feature = "gradient")),
+    ("jpeg", cfg!(feature = "jpeg")),
+    ("parallel", cfg!(feature = "parallel")),
+    ("qr", cfg!(feature = "qr")),
+    ("shaping", cfg!(feature = "shaping")),
+    ("tree-sitter", cfg!(feature = "tree-sitter")),
+    ("yaml", cfg!(feature = "yaml")),
+  ];
+  let enabled = |names: &[(&'static str, bool)]| -> Vec<&'static str> {
+    names
+      .iter()
+      .filter(|(_, on)| *on)
+      .map(|(name, _)| *name)
+      .collect()
+  };
+  BuildInfo {
+    version: env!("CARGO_PKG_VERSION"),
+    git_hash: Some(env!("SHOYU_GIT_HASH")).filter(|hash| !hash.is_empty()),
+    profile: if cfg!(debug_assertions) {
+      "debug"
+    } else {
+      "release"
+    },
+    features: enabled(&features),
+    image_formats: enabled(&[
+      ("png", true),
+      ("apng", true),
+      ("gif", cfg!(feature = "gif")),
+    ]),
+    video_formats: vec!["mp4", "webm"],
+    backdrop_formats: enabled(&[("png", true), ("jpeg", cfg!(feature = "jpeg"))]),
+    fonts: FONT_PATHS
+      .into_iter()
+      .filter(|path| Path::new(path).exists())
+      .collect(),
+  }
+}
@@ -0,0 +1,377 @@
+/* ~~/src/cli.rs */
+
+// standard crates
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+// third-party crates
+use anyhow::{Result, anyhow};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+
+// local modules
+use crate::config::RenderConfig;
+use crate::renderer::SnippetRenderer;
+use crate::rpc;
+use crate::terminal::{self, ImageProtocol};
+use crate::warnings;
+
+/// Clear the screen and home the cursor, so each preview in watch mode
+/// replaces the last
+const CLEAR_SCREEN: &str = "\x1b[H\x1b[2J";
+
+/// Quiet spell after a file event before watch mode renders, so a save
+/// written in several steps renders once
+const SETTLE: Duration = Duration::from_millis(50);
+
+const USAGE: &str = "Usage: shoyu [options] <file>
+       shoyu [options] --watch <file>
+       shoyu --rpc
+       shoyu --serve [--workers <count>]
+
+Render a code file to a PNG snippet.
+
+Options:
+  -o, --output <path>      PNG to write, or a PDF when it ends in .pdf,
+                           defaults to <file>.png
+  -t, --theme <name>       Color theme, defaults to dracula
+  -l, --language <name>    Language to highlight as, defaults to the file's
+  -c, --config <path>      JSON render config, the same one the plugin sends
+  -w, --watch <file>       Render again whenever the file or config changes
+  -p, --preview            Show the image in the terminal, writing a PNG only
+                           with --output
+      --protocol <name>    Image protocol for --preview: kitty, iterm2 or sixel,
+                           detected from the terminal by default
+      --rpc                Serve msgpack-RPC on stdin and stdout instead
+      --serve              Serve JSON-RPC 2.0 on stdin and stdout instead
+      --workers <count>    Requests --serve renders at once, 0 for one per core
+  -h, --help               Show this message";
+
+/// Parsed command line
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+  input: Option<PathBuf>,
+  output: Option<PathBuf>,
+  theme: Option<String>,
+  language: Option<String>,
+  config: Option<PathBuf>,
+  watch: bool,
+  preview: bool,
+  protocol: Option<ImageProtocol>,
+  rpc: bool,
+  serve: bool,
+  workers: usize,
+  help: bool,
+}
+
+impl Options {
+  fn parse(args: impl IntoIterator<Item = OsString>) -> Result<Self> {
+    let mut options = Self::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+      let mut value = |flag: &str| args.next().ok_or_else(|| anyhow!("{} needs a value", flag));
+      match arg.to_str() {
+        Some(flag @ ("-o" | "--output")) => options.output = Some(value(flag)?.into()),
+        Some(flag @ ("-t" | "--theme")) => options.theme = Some(utf8(value(flag)?)?),
+        Some(flag @ ("-l" | "--language")) => options.language = Some(utf8(value(flag)?)?),
+        Some(flag @ ("-c" | "--config")) => options.config = Some(value(flag)?.into()),
+        Some(flag @ ("-w" | "--watch")) => {
+          let path = value(flag)?;
+          options.set_input(path)?;
+          options.watch = true;
+        }
+        Some("-p" | "--preview") => options.preview = true,
+        Some(flag @ "--protocol") => {
+          options.protocol = Some(ImageProtocol::from_name(&utf8(value(flag)?)?)?);
+        }
+        Some("--rpc") => options.rpc = true,
+        Some("--serve") => options.serve = true,
+        Some(flag @ "--workers") => {
+          let count = utf8(value(flag)?)?;
+          options.workers = count
+            .parse()
+            .map_err(|_| anyhow!("{} takes a number, not {}", flag, count))?;
+        }
+        Some("-h" | "--help") => options.help = true,
+        Some(flag) if flag.starts_with('-') && flag.len() > 1 => {
+          return Err(anyhow!("Unknown option: {}", flag));
+        }
+        _ => options.set_input(arg)?,
+      }
+    }
+    Ok(options)
+  }
+
+  fn set_input(&mut self, path: OsString) -> Result<()> {
+    if self.input.is_some() {
+      return Err(anyhow!("Only one file can be rendered at a time"));
+    }
+    self.input = Some(PathBuf::from(path));
+    Ok(())
+  }
+
+  /// `--output`, or the input with `.png` appended so `main.rs` and
+  /// `main.py` don't overwrite each other
+  fn output_path(&self, input: &Path) -> PathBuf {
+    self.output.clone().unwrap_or_else(|| {
+      let mut name = input.as_os_str().to_owned();
+      name.push(".png");
+      PathBuf::from(name)
+    })
+  }
+}
+
+fn utf8(value: OsString) -> Result<String> {
+  value
+    .into_string()
+    .map_err(|value| anyhow!("Not valid UTF-8: {}", value.to_string_lossy()))
+}
+
+/// Entry point of the `shoyu` binary, taking the arguments after the
+/// program name
+pub fn run(args: impl IntoIterator<Item = OsString>) -> ExitCode {
+  let result = Options::parse(args).and_then(|options| {
+    if options.help {
+      println!("{}", USAGE);
+      return Ok(());
+    }
+    if options.rpc {
+      return rpc::serve_msgpack(io::stdin().lock(), io::stdout().lock());
+    }
+    if options.serve {
+      return rpc::serve_json(io::stdin().lock(), io::stdout(), options.workers);
+    }
+    let Some(input) = &options.input else {
+      return Err(anyhow!("No file given\n\n{}", USAGE));
+    };
+    match options.watch {
+      true => watch(&options, input),
+      false => render(&options, input),
+    }
+  });
+  match result {
+    Ok(()) => ExitCode::SUCCESS,
+    Err(e) => {
+      eprintln!("shoyu: {}", e);
+      ExitCode::FAILURE
+    }
+  }
+}
+
+/// Render `input` and replace the output image or show it in the terminal,
+/// reporting warnings and how long the render took on stderr
+fn render(options: &Options, input: &Path) -> Result<()> {
+  let started = Instant::now();
+  // Known before rendering, so a terminal without inline images fails fast
+  let protocol = match (options.preview, options.protocol) {
+    (false, _) => None,
+    (true, Some(protocol)) => Some(protocol),
+    (true, None) => Some(ImageProtocol::from_name("auto")?),
+  };
+  let code =
+    fs::read_to_string(input).map_err(|e| anyhow!("Failed to read {}: {}", input.display(), e))?;
+  let mut config = match &options.config {
+    Some(path) => {
+      let json = fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+      RenderConfig::from_json(&json)?
+    }
+    None => RenderConfig::default(),
+  };
+  // Header and footer placeholders describe the file being rendered
+  if config.source_path.is_none() {
+    config.source_path = Some(input.to_string_lossy().into_owned());
+  }
+  // A file name resolves to its language the same way an editor path does
+  let language = match &options.language {
+    Some(language) => language.clone(),
+    None => input
+      .file_name()
+      .map_or_else(String::new, |name| name.to_string_lossy().into_owned()),
+  };
+  let theme = options.theme.as_deref().unwrap_or("dracula");
+  let pdf = options.output.as_deref().is_some_and(|output| {
+    output
+      .extension()
+      .is_some_and(|extension| extension.eq_ignore_ascii_case("pdf"))
+  });
+  if pdf && protocol.is_some() {
+    return Err(anyhow!(
+      "--preview shows PNGs only; write the PDF without it"
+    ));
+  }
+
+  let mut renderer = SnippetRenderer::new(theme, config)?;
+  let image_data = match pdf {
+    true => renderer.render_snippet_pdf(&code, &language)?,
+    false => renderer.render_snippet_png(&code, &language)?,
+  };
+  let output = match (options.preview, &options.output) {
+    (true, None) => None,
+    _ => {
+      let output = options.output_path(input);
+      write_replacing(&output, &image_data)?;
+      Some(output)
+    }
+  };
+  if let Some(protocol) = protocol {
+    let mut stdout = io::BufWriter::new(io::stdout().lock());
+    let clear = if options.watch { CLEAR_SCREEN } else { "" };
+    stdout
+      .write_all(clear.as_bytes())
+      .map_err(|e| anyhow!("Failed to write the preview: {}", e))?;
+    terminal::write_inline_image(&mut stdout, &image_data, protocol)?;
+    writeln!(stdout)
+      .and_then(|_| stdout.flush())
+      .map_err(|e| anyhow!("Failed to write the preview: {}", e))?;
+  }
+
+  for warning in warnings::drain() {
+    eprintln!("shoyu: warning: {}", warning);
+  }
+  let rendered = output.map_or_else(|| "preview".to_string(), |path| path.display().to_string());
+  eprintln!(
+    "Rendered {} in {} ms",
+    rendered,
+    started.elapsed().as_millis()
+  );
+  Ok(())
+}
+
+/// Write next to `path` and rename over it, so a viewer reloading the image
+/// never sees it half written
+fn write_replacing(path: &Path, data: &[u8]) -> Result<()> {
+  let mut staged = path.as_os_str().to_owned();
+  staged.push(".tmp");
+  fs::write(&staged, data)
+    .and_then(|_| fs::rename(&staged, path))
+    .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Whether `event` writes one of the `watched` files. Opening or reading
+/// them, as each render does, is ignored.
+fn saves(event: &Event, watched: &[PathBuf]) -> bool {
+  matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+    && event.paths.iter().any(|path| watched.contains(path))
+}
+
+/// Render now and again every time the input or config changes, until
+/// interrupted. Each file's directory is watched rather than the file, which
+/// also follows editors that save by replacing the file.
+fn watch(options: &Options, input: &Path) -> Result<()> {
+  let watched = std::iter::once(input)
+    .chain(options.config.as_deref())
+    .map(|path| {
+      fs::canonicalize(path).map_err(|e| anyhow!("Cannot watch {}: {}", path.display(), e))
+    })
+    .collect::<Result<Vec<_>>>()?;
+  let (sender, events) = mpsc::channel();
+  let mut watcher = notify::recommended_watcher(sender)?;
+  let mut directories: Vec<&Path> = watched.iter().filter_map(|path| path.parent()).collect();
+  directories.dedup();
+  for directory in directories {
+    watcher.watch(directory, RecursiveMode::NonRecursive)?;
+  }
+  // A failed render keeps watching so the next save can fix it
+  let attempt = || {
+    if let Err(e) = render(options, input) {
+      eprintln!("shoyu: {}", e);
+    }
+  };
+  attempt();
+  eprintln!("Watching {}, press Ctrl-C to stop", input.display());
+  for event in &events {
+    match event {
+      Ok(event) if saves(&event, &watched) => {}
+      Ok(_) => continue,
+      Err(e) => {
+        eprintln!("shoyu: {}", e);
+        continue;
+      }
+    }
+    // One save is often several events; render once they stop
+    while events.recv_timeout(SETTLE).is_ok() {}
+    // Missing mid-save; wait for the editor to finish replacing it
+    if watched.iter().all(|path| path.exists()) {
+      attempt();
+    }
+  }
+  Err(anyhow!("Stopped watching {}", input.display()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse(args: &[&str]) -> Result<Options> {
+    Options::parse(args.iter().map(OsString::from))
+  }
+
+  #[test]
+  fn test_parses_arguments() {
+    let options = parse(&["-t", "nord", "--watch", "slide.rs", "-o", "out.png"]).unwrap();
+    assert_eq!(
+      options,
+      Options {
+        input: Some(PathBuf::from("slide.rs")),
+        output: Some(PathBuf::from("out.png")),
+        theme: Some("nord".to_string()),
+        watch: true,
+        ..Options::default()
+      }
+    );
+    let options = parse(&["src/main.rs"]).unwrap();
+    assert!(!options.watch);
+    assert_eq!(
+      options.output_path(Path::new("src/main.rs")),
+      PathBuf::from("src/main.rs.png")
+    );
+
+    assert!(parse(&["a.rs", "b.rs"]).is_err());
+    assert!(parse(&["a.rs", "--theme"]).is_err());
+    assert!(parse(&["--frobnicate", "a.rs"]).is_err());
+
+    let options = parse(&["-p", "--protocol", "iterm2", "a.rs"]).unwrap();
+    assert!(options.preview);
+    assert_eq!(options.protocol, Some(ImageProtocol::Iterm2));
+    assert!(parse(&["--protocol", "ascii", "a.rs"]).is_err());
+  }
+
+  #[test]
+  fn test_watches_writes_to_watched_files() {
+    use notify::event::{AccessKind, CreateKind, ModifyKind, RenameMode};
+
+    let watched = [PathBuf::from("/slides/greet.py")];
+    let event = |kind: EventKind, path: &str| Event::new(kind).add_path(PathBuf::from(path));
+    let written = EventKind::Modify(ModifyKind::Any);
+    assert!(saves(&event(written, "/slides/greet.py"), &watched));
+    // Editors that write a copy and rename it over the file
+    let renamed = EventKind::Modify(ModifyKind::Name(RenameMode::To));
+    assert!(saves(&event(renamed, "/slides/greet.py"), &watched));
+    let created = EventKind::Create(CreateKind::File);
+    assert!(saves(&event(created, "/slides/greet.py"), &watched));
+
+    assert!(!saves(&event(written, "/slides/greet.py.png"), &watched));
+    let read = EventKind::Access(AccessKind::Any);
+    assert!(!saves(&event(read, "/slides/greet.py"), &watched));
+  }
+
+  #[test]
+  fn test_renders_file_to_png() {
+    let dir = std::env::temp_dir().join(format!("shoyu-cli-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("greet.py");
+    fs::write(&input, "def greet():\n    print('hi')\n").unwrap();
+    let config = dir.join("config.json");
+    fs::write(&config, r#"{"export_size": 1, "gradient_backdrop": false}"#).unwrap();
+    let options = parse(&["-c", config.to_str().unwrap(), input.to_str().unwrap()]).unwrap();
+    render(&options, &input).unwrap();
+    let png_data = fs::read(dir.join("greet.py.png")).unwrap();
+    assert!(png_data.starts_with(b"\x89PNG"));
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
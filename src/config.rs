@@ -1,70 +1,1549 @@
 /* ~~/src/config.rs */
 
+// standard crates
+use std::collections::HashMap;
+use std::ops::Add;
+
 // third-party crates
+use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+// local modules
+use crate::themes::{ScopeFont, ThemeVariant};
+use crate::warnings;
+
+/// Current config schema, bumped whenever a field is renamed
+pub const CONFIG_VERSION: u32 = 2;
+
+/// Field renames as `(version that introduced the new name, old, new)`
+const RENAMED_FIELDS: &[(u32, &str, &str)] = &[
+  // "background" became ambiguous once backdrop images arrived
+  (2, "background_color", "panel_color"),
+];
+
+/// A 1-based inclusive line range, written as `12` or `"10-14"` in JSON
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "LineRangeSpec", into = "String")]
+pub struct LineRange {
+  pub start: u32,
+  pub end: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LineRangeSpec {
+  Single(u32),
+  Text(String),
+}
+
+impl TryFrom<LineRangeSpec> for LineRange {
+  type Error = String;
+
+  fn try_from(spec: LineRangeSpec) -> Result<Self, Self::Error> {
+    let (start, end) = match spec {
+      LineRangeSpec::Single(line) => (line, line),
+      LineRangeSpec::Text(text) => {
+        let parse = |s: &str| {
+          s.trim()
+            .parse::<u32>()
+            .map_err(|_| format!("Invalid line range: {}", text))
+        };
+        match text.split_once('-') {
+          Some((start, end)) => (parse(start)?, parse(end)?),
+          None => (parse(&text)?, parse(&text)?),
+        }
+      }
+    };
+    if start == 0 || end < start {
+      return Err(format!("Invalid line range: {}-{}", start, end));
+    }
+    Ok(Self { start, end })
+  }
+}
+
+impl From<LineRange> for String {
+  fn from(range: LineRange) -> Self {
+    if range.start == range.end {
+      range.start.to_string()
+    } else {
+      format!("{}-{}", range.start, range.end)
+    }
+  }
+}
+
+impl LineRange {
+  pub fn contains(&self, line: u32) -> bool {
+    (self.start..=self.end).contains(&line)
+  }
+}
+
+/// Label pointing at a line of code, e.g.
+/// `{"line": 12, "text": "race condition here", "side": "right"}`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Annotation {
+  /// 1-based line the arrow points at
+  pub line: u32,
+  pub text: String,
+  #[serde(default)]
+  pub side: AnnotationSide,
+}
+
+/// Column beside the code that an annotation's label sits in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnotationSide {
+  /// Before the code, pointing at where the line's text starts
+  Left,
+  /// Past the longest line, pointing at where the line ends
+  #[default]
+  Right,
+}
+
+/// Region painted like a visual-mode selection, e.g.
+/// `{"start": {"line": 2, "column": 5}, "end": {"line": 4, "column": 12}}`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Selection {
+  pub start: Position,
+  pub end: Position,
+  #[serde(default)]
+  pub mode: SelectionMode,
+}
+
+/// 1-based line and display column, both ends of a selection included
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Position {
+  pub line: u32,
+  #[serde(default)]
+  pub column: u32,
+}
+
+/// Shape of a selection, after Neovim's `v`, `V`, and `CTRL-V`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SelectionMode {
+  /// From the start position through the end position, wrapping lines
+  #[default]
+  Char,
+  /// Whole lines, columns ignored
+  Line,
+  /// The same columns on every line
+  Block,
+}
+
+impl Selection {
+  /// Display columns selected on the 1-based `line`, a `width` columns long,
+  /// as a 0-based half-open range; past the text is the cell Neovim draws
+  /// for the line break
+  pub fn columns(&self, line: u32, width: usize) -> Option<(usize, usize)> {
+    // Selections made upward end before they start
+    let (start, end) =
+      match (self.start.line, self.start.column) <= (self.end.line, self.end.column) {
+        true => (self.start, self.end),
+        false => (self.end, self.start),
+      };
+    if line < start.line || line > end.line {
+      return None;
+    }
+    let first = start.column.saturating_sub(1) as usize;
+    let last = end.column as usize;
+    let (from, to) = match self.mode {
+      SelectionMode::Char => (
+        if line == start.line { first } else { 0 },
+        if line == end.line {
+          last.min(width + 1)
+        } else {
+          width + 1
+        },
+      ),
+      SelectionMode::Line => (0, width.max(1)),
+      SelectionMode::Block => {
+        let (left, right) = (
+          self.start.column.min(self.end.column).saturating_sub(1) as usize,
+          self.start.column.max(self.end.column) as usize,
+        );
+        (left, right.min(width))
+      }
+    };
+    (from < to).then_some((from, to))
+  }
+}
+
+/// Text cursor drawn at a 1-based line and display column, e.g.
+/// `{"line": 3, "column": 9, "style": "bar"}`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cursor {
+  pub line: u32,
+  #[serde(alias = "col")]
+  pub column: u32,
+  #[serde(default)]
+  pub style: CursorStyle,
+  /// Defaults to the theme's keyword color
+  #[serde(default)]
+  pub color: Option<String>,
+}
+
+/// Cursor shapes Neovim's 'guicursor' offers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CursorStyle {
+  /// The whole cell, with the character under it in the panel color
+  #[default]
+  Block,
+  /// A thin bar before the cell, as in insert mode
+  Bar,
+  /// A thin line under the cell, as in replace mode
+  Underline,
+}
+
+/// Problem reported on a span of a line, as from an LSP, e.g.
+/// `{"line": 4, "col_start": 5, "col_end": 11, "severity": "warning"}`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+  /// 1-based line
+  pub line: u32,
+  /// 1-based display columns, both included
+  pub col_start: u32,
+  pub col_end: u32,
+  #[serde(default)]
+  pub severity: Severity,
+  /// Shown after the line like Neovim's virtual text
+  #[serde(default)]
+  pub message: Option<String>,
+}
+
+/// Diagnostic severities, most severe first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+  #[default]
+  Error,
+  #[serde(alias = "warn")]
+  Warning,
+  Info,
+  Hint,
+}
+
+impl Severity {
+  /// Letter Neovim puts in the sign column
+  pub fn sign(self) -> &'static str {
+    match self {
+      Self::Error => "E",
+      Self::Warning => "W",
+      Self::Info => "I",
+      Self::Hint => "H",
+    }
+  }
+}
+
+/// Underline, sign, and message color per severity
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiagnosticColors {
+  pub error: String,
+  pub warning: String,
+  pub info: String,
+  pub hint: String,
+}
+
+impl Default for DiagnosticColors {
+  fn default() -> Self {
+    Self {
+      error: "#f14c4c".to_string(),
+      warning: "#cca700".to_string(),
+      info: "#3794ff".to_string(),
+      hint: "#8c8c8c".to_string(),
+    }
+  }
+}
+
+impl DiagnosticColors {
+  pub fn get(&self, severity: Severity) -> &str {
+    match severity {
+      Severity::Error => &self.error,
+      Severity::Warning => &self.warning,
+      Severity::Info => &self.info,
+      Severity::Hint => &self.hint,
+    }
+  }
+}
+
+/// Column of per-line notes such as short git blame, e.g.
+/// `{"lines": ["ada 2d", "", "linus 3y"], "side": "right"}`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Blame {
+  /// One entry per line from the first, empty for none
+  pub lines: Vec<String>,
+  pub side: BlameSide,
+}
+
+/// Panel edge the blame column sits against
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlameSide {
+  /// Before the line numbers
+  #[default]
+  Left,
+  /// After the code
+  Right,
+}
+
+/// Space on each side of a box, written as one number for all four or as
+/// `{"top": 32, "right": 96, "bottom": 32, "left": 64}`; sides missing from
+/// an object keep the value they had before it was merged in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "SidesSpec")]
+pub struct Sides {
+  pub top: u32,
+  pub right: u32,
+  pub bottom: u32,
+  pub left: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SidesSpec {
+  Uniform(u32),
+  Each(EachSide),
+}
+
+/// Object form of `Sides`; a misspelt side is an error rather than a zero
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EachSide {
+  #[serde(default)]
+  top: u32,
+  #[serde(default)]
+  right: u32,
+  #[serde(default)]
+  bottom: u32,
+  #[serde(default)]
+  left: u32,
+}
+
+impl From<SidesSpec> for Sides {
+  fn from(spec: SidesSpec) -> Self {
+    match spec {
+      SidesSpec::Uniform(size) => Self::uniform(size),
+      SidesSpec::Each(EachSide {
+        top,
+        right,
+        bottom,
+        left,
+      }) => Self {
+        top,
+        right,
+        bottom,
+        left,
+      },
+    }
+  }
+}
+
+impl Sides {
+  pub const fn uniform(size: u32) -> Self {
+    Self {
+      top: size,
+      right: size,
+      bottom: size,
+      left: size,
+    }
+  }
+
+  /// Each side multiplied by `factor`, truncated to whole pixels
+  pub fn scaled(self, factor: f32) -> Self {
+    let scale = |side: u32| (side as f32 * factor) as u32;
+    Self {
+      top: scale(self.top),
+      right: scale(self.right),
+      bottom: scale(self.bottom),
+      left: scale(self.left),
+    }
+  }
 
+  /// Left and right together
+  pub fn horizontal(&self) -> u32 {
+    self.left + self.right
+  }
+
+  /// Top and bottom together
+  pub fn vertical(&self) -> u32 {
+    self.top + self.bottom
+  }
+}
+
+impl Add for Sides {
+  type Output = Self;
+
+  fn add(self, other: Self) -> Self {
+    Self {
+      top: self.top + other.top,
+      right: self.right + other.right,
+      bottom: self.bottom + other.bottom,
+      left: self.left + other.left,
+    }
+  }
+}
+
+/// Panel width or height in pixels, or `"auto"` to fit the content within
+/// the matching min/max clamps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "SizeSpec", into = "SizeSpec")]
+pub enum PanelSize {
+  Fixed(u32),
+  Auto,
+}
+
+/// How the image is sized around the panel
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Fit {
+  /// Panel sized by `width` and `height` with `panel_padding` around it
+  #[default]
+  None,
+  /// Panel shrunk to its code with `fit_margin` around it
+  Tight,
+  /// Image exactly `width` by `height`, the panel sized to its code and
+  /// scaled down when it doesn't fit inside `panel_padding`
+  Exact,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum SizeSpec {
+  Pixels(u32),
+  Keyword(String),
+}
+
+impl TryFrom<SizeSpec> for PanelSize {
+  type Error = String;
+
+  fn try_from(spec: SizeSpec) -> Result<Self, Self::Error> {
+    match spec {
+      SizeSpec::Pixels(size) => Ok(Self::Fixed(size)),
+      SizeSpec::Keyword(keyword) if keyword.eq_ignore_ascii_case("auto") => Ok(Self::Auto),
+      SizeSpec::Keyword(keyword) => Err(format!("Invalid size: {}", keyword)),
+    }
+  }
+}
+
+impl From<PanelSize> for SizeSpec {
+  fn from(size: PanelSize) -> Self {
+    match size {
+      PanelSize::Fixed(size) => Self::Pixels(size),
+      PanelSize::Auto => Self::Keyword("auto".to_string()),
+    }
+  }
+}
+
+/// Render options; any field missing from the JSON falls back to `Default`
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RenderConfig {
-  pub width: u32,
-  pub height: Option<u32>,
-  pub padding: u32,
+  pub config_version: u32, // Schema the JSON was written for, older ones are migrated on load
+  pub width: PanelSize,
+  pub min_width: u32, // Bounds for `width: "auto"`
+  pub max_width: u32,
+  pub height: PanelSize,
+  pub min_height: u32,             // Bounds for `height: "auto"`
+  pub max_height: Option<u32>,     // Unbounded when unset
+  pub content_align: ContentAlign, // Placement of code in a panel larger than it
+  pub padding: Sides,              // Between the panel's edges and the code
   pub line_height: f32,
   pub font_size: f32,
   pub font_family: String,
-  pub background_color: String,
+  pub fallback_fonts: Vec<String>, // Families tried in order for characters font_family lacks
+  pub panel_color: Option<String>, // Panel color override, defaults to the theme background
+  pub themes_dir: Option<String>,  // Theme files loaded by name, scanned once until `reload_themes`
+  pub variant: ThemeVariant, // "auto-light" or "auto-dark" to redraw the theme for the other background
+  pub min_contrast: f32, // Nudge text colors below this WCAG ratio on the panel until they reach it, 0 disables
+  pub theme_file: Option<String>, // VS Code theme JSON or base16 YAML drawn instead of the named theme
   pub window_controls: bool,
+  pub window_style: WindowStyle, // Controls drawn on the title bar
+  pub control_fill: ControlFill, // Colored, hollow, or grayscale window controls
   pub window_title: Option<String>,
+  pub infer_title: bool, // Title unset windows after the first definition in the code
+  pub title_icon: TitleIcon, // Language icon beside the window title
+  pub language_badge: Option<Corner>, // Pill naming the language in a panel corner
+  pub language_badge_color: BadgeColor, // Brand color per language, or one from the theme
+  pub project_root: Option<String>, // Titles that are paths inside it are shown relative to it
+  pub project_name: Option<String>, // Project badge text, defaults to the root's folder name
+  pub project_icon: Option<String>, // Glyph or emoji drawn before the project name
   pub line_numbers: bool,
   pub drop_shadow: bool,
   pub border_radius: f32,
-  pub export_size: f32,        // Scale factor for high-res export
-  pub panel_padding: u32,      // Extra padding around the entire panel
+  pub border: Option<Border>, // Outline around the panel and below its title bar
+  pub export_size: f32,       // Scale factor for high-res export
+  pub panel_padding: Sides,   // Extra padding around the entire panel
+  pub canvas: Option<String>, // Exact output size to center the panel in, see `CANVAS_PRESETS`
+  pub fit: Fit, // Trim the backdrop to the code, or make width and height the image size
+  pub fit_margin: u32, // Backdrop left around the panel with fit "tight"
   pub gradient_backdrop: bool, // Enable randomized gradient backdrop
-  pub noise_effect: bool,      // Enable noise effect on gradient
+  pub noise_effect: bool, // Enable noise effect on gradient
+  pub gradient_dither: bool, // Ordered dithering against banding in the gradient
+  pub gradient_colors: Vec<String>, // Gradient stops, random theme-derived pair when empty
+  pub gradient_direction: Option<GradientDirection>, // Random when unset
+  pub gradient_seed: Option<u64>, // Fixes random colors, direction, and noise
+  pub gradient_preset: Option<String>, // Named backdrop, see `GRADIENT_PRESETS`; explicit colors win
+  pub backdrop_image: Option<String>,  // Wallpaper drawn instead of the gradient, cropped to cover
+  pub backdrop: Option<BackdropPattern>, // Procedural pattern drawn instead of the gradient
+  pub backdrop_blur: f32,              // Gaussian blur radius for the backdrop, 0 disables
+  pub frosted_glass: Option<FrostedGlass>, // Translucent panel over a blurred backdrop
+  pub backdrop_darken: f32,            // Fade the backdrop image toward black, 0.0 to 1.0
+  pub font_features: Vec<String>,      // OpenType features, e.g. ["ss01", "zero", "calt=0"]
+  pub ligatures: bool,                 // Shape text so programming ligatures such as => render
+  pub glyph_substitution: GlyphSubstitution, // Stand-ins for characters no font can draw
+  pub glyph_substitutions: HashMap<char, String>, // Replacements checked first by "custom"
+  pub bracket_pairs: bool,             // Color matched ()[]{} pairs by nesting depth
+  pub bracket_colors: Vec<String>, // Colors cycled per depth, defaults to the function, keyword, and string colors
+  pub highlight_lines: Vec<LineRange>, // 1-based lines or ranges to emphasize
+  pub highlight_color: Option<String>, // Strip color, defaults to a tint of the foreground
+  pub selection: Option<Selection>, // Region painted like a visual-mode selection
+  pub selection_color: Option<String>, // Selection color, defaults to a tint of the foreground
+  pub diagnostics: Vec<Diagnostic>, // Squiggly underlines, gutter signs, and messages
+  pub diagnostic_colors: DiagnosticColors, // Color per severity
+  pub cursor: Option<Cursor>,      // Block, bar, or underline cursor at a line and column
+  pub focus_lines: Vec<LineRange>, // Lines kept at full contrast, the rest are dimmed
+  pub unfocused_opacity: f32,      // Opacity of text outside focus_lines, 0.0 to 1.0
+  pub annotations: Vec<Annotation>, // Labels with arrows pointing at lines
+  pub annotation_color: Option<String>, // Label and arrow color, defaults to the keyword color
+  pub watermark: Option<Watermark>, // Credit in a backdrop corner or the panel footer
+  pub qr_link: Option<String>, // URL drawn as a QR code in a backdrop corner, e.g. the full gist
+  pub qr_corner: Corner,       // Backdrop corner of the QR code
+  pub header: Option<String>,  // Strip above the code, e.g. "{path} on {branch}"
+  pub footer: Option<String>,  // Strip below the code, with the same placeholders and {date}
+  pub source_path: Option<String>, // File the snippet came from, for {filename}, {path}, {branch}
+  pub max_lines: u32,          // Draw this many lines and a count of the rest, 0 draws every line
+  pub columns: u32,            // Side-by-side columns the lines flow into, 1 to 3
+  pub line_number_start: u32,  // Number shown for the first line
+  pub tab_width: u32,          // Columns per tab stop; tabs expand to spaces
+  pub text_width: u32,         // Mark the column where the editor hard-wraps, 0 disables
+  pub reflow_comments: bool,   // Rewrap comment blocks at text_width before rendering
+  pub highlighter: HighlighterBackend, // Tokenizer, falls back to syntect for unsupported languages
+  pub syntax_dirs: Vec<String>, // Extra folders of .sublime-syntax files
+  pub highlight_command: Vec<String>, // Program and arguments for "external", `{language}` filled in
+  pub highlight_timeout_ms: u64,      // Kill highlight_command after this long and fall back
+  pub blame: Option<Blame>,           // Dimmed per-line notes in a column of their own
+  pub minimap: bool,                  // Miniature of the code's shape along the panel's right edge
+  pub gutter_separator: bool,         // Draw a rule between line numbers and code
+  pub gutter_background: Option<String>, // Distinct gutter color, defaults to the panel color
+  pub scope_fonts: Vec<ScopeFont>,    // Per-scope font overrides, checked before the theme's
+  pub skeleton: bool, // Draw placeholder bars instead of glyphs for instant previews
+  pub threads: u32,   // Workers for backdrop and text drawing, 0 uses every core and 1 disables
+  pub layer: RenderLayer, // Draw everything, or only the panel or the backdrop
+  pub palette_colors: u32, // Quantize to an indexed PNG of up to 256 colors, 0 keeps full color
+  pub dither: bool,   // Diffuse quantization error to soften banding in indexed PNGs
+  pub color_space: ColorSpace, // Profile output PNGs are converted to and tagged with
+  pub embed_metadata: bool, // Write language, theme, and version into PNG text chunks
+  pub embed_source: bool, // Also store the snippet's code, compressed, with the metadata
+  pub preset: Option<String>, // Built-in starting point, see `RenderConfig::preset`
+  pub carousel: CarouselConfig, // Paging used by carousel exports
+  pub tabs: TabsConfig, // Layout used by tabbed multi-file renders
+  pub scroll: ScrollConfig, // Viewport and pacing for scrolling video exports
+  pub transition: TransitionConfig, // Pacing for theme transition exports
+}
+
+/// Axis along which the backdrop gradient runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GradientDirection {
+  Horizontal,
+  Vertical,
+  Radial,
+  Diagonal,
+}
+
+/// Curated backdrop gradient selectable by name with `gradient_preset`
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct GradientPreset {
+  pub name: &'static str,
+  pub colors: &'static [&'static str],
+  pub direction: GradientDirection,
+}
+
+pub const GRADIENT_PRESETS: &[GradientPreset] = &[
+  GradientPreset {
+    name: "sunset",
+    colors: &["#ff7e5f", "#feb47b"],
+    direction: GradientDirection::Diagonal,
+  },
+  GradientPreset {
+    name: "ocean",
+    colors: &["#2193b0", "#6dd5ed"],
+    direction: GradientDirection::Vertical,
+  },
+  GradientPreset {
+    name: "candy",
+    colors: &["#f78ca0", "#f9748f", "#fe9a8b"],
+    direction: GradientDirection::Horizontal,
+  },
+  GradientPreset {
+    name: "midnight",
+    colors: &["#0f2027", "#203a43", "#2c5364"],
+    direction: GradientDirection::Diagonal,
+  },
+  GradientPreset {
+    name: "aurora",
+    colors: &["#00c9ff", "#92fe9d"],
+    direction: GradientDirection::Diagonal,
+  },
+  GradientPreset {
+    name: "forest",
+    colors: &["#134e5e", "#71b280"],
+    direction: GradientDirection::Vertical,
+  },
+  GradientPreset {
+    name: "peach",
+    colors: &["#ffecd2", "#fcb69f"],
+    direction: GradientDirection::Radial,
+  },
+  GradientPreset {
+    name: "lavender",
+    colors: &["#a18cd1", "#fbc2eb"],
+    direction: GradientDirection::Diagonal,
+  },
+  GradientPreset {
+    name: "ember",
+    colors: &["#f12711", "#f5af19"],
+    direction: GradientDirection::Horizontal,
+  },
+  GradientPreset {
+    name: "slate",
+    colors: &["#485563", "#29323c"],
+    direction: GradientDirection::Radial,
+  },
+];
+
+/// Look up a backdrop gradient by case-insensitive name
+pub fn gradient_preset(name: &str) -> Option<&'static GradientPreset> {
+  GRADIENT_PRESETS
+    .iter()
+    .find(|preset| preset.name.eq_ignore_ascii_case(name))
+}
+
+/// Output size for a social media post selectable by name with `canvas`
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct CanvasPreset {
+  pub name: &'static str,
+  pub width: u32,
+  pub height: u32,
+}
+
+pub const CANVAS_PRESETS: &[CanvasPreset] = &[
+  // Open Graph link previews
+  CanvasPreset {
+    name: "og",
+    width: 1200,
+    height: 630,
+  },
+  CanvasPreset {
+    name: "twitter",
+    width: 1600,
+    height: 900,
+  },
+  CanvasPreset {
+    name: "instagram-square",
+    width: 1080,
+    height: 1080,
+  },
+  CanvasPreset {
+    name: "story",
+    width: 1080,
+    height: 1920,
+  },
+];
+
+/// Look up a canvas size by case-insensitive name
+pub fn canvas_preset(name: &str) -> Option<&'static CanvasPreset> {
+  CANVAS_PRESETS
+    .iter()
+    .find(|preset| preset.name.eq_ignore_ascii_case(name))
+}
+
+/// Where code sits in a panel that has room to spare
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContentAlign {
+  pub horizontal: HorizontalAlign,
+  pub vertical: VerticalAlign,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HorizontalAlign {
+  #[default]
+  Left,
+  Center,
+  Right,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VerticalAlign {
+  Top,
+  #[default]
+  Center,
+  Bottom,
+}
+
+/// Family asked for when the config names none; a quiet fallback is expected
+/// when it isn't installed
+pub const DEFAULT_FONT_FAMILY: &str = "Fira Code";
+
+/// Which parts of the image a render draws, so the panel and backdrop can be
+/// composited separately
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RenderLayer {
+  /// Backdrop with the panel on top
+  #[default]
+  Full,
+  /// Panel, chrome, and code over a transparent canvas of the same size
+  Panel,
+  /// Backdrop alone, sized as if the panel were drawn
+  Backdrop,
+}
+
+/// Panel outline, e.g. `{"width": 1, "color": "#000000", "opacity": 0.15}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Border {
+  /// Before export scaling
+  pub width: f32,
+  /// Defaults to the theme's foreground
+  pub color: Option<String>,
+  /// 0.0 to 1.0
+  pub opacity: f32,
+}
+
+impl Default for Border {
+  fn default() -> Self {
+    Self {
+      width: 1.0,
+      color: None,
+      opacity: 0.2,
+    }
+  }
+}
+
+/// Panel letting the backdrop through, blurred like frosted glass, e.g.
+/// `{"blur": 24, "opacity": 0.6}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FrostedGlass {
+  /// Gaussian blur radius before export scaling, 0 for clear glass
+  pub blur: f32,
+  /// Panel and title bar opacity, 0.0 to 1.0
+  pub opacity: f32,
+}
+
+impl Default for FrostedGlass {
+  fn default() -> Self {
+    Self {
+      blur: 24.0,
+      opacity: 0.6,
+    }
+  }
+}
+
+/// Procedural backdrop, e.g. `{"type": "dots", "colors": ["#1e1e2e", "#45475a"]}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackdropPattern {
+  #[serde(rename = "type")]
+  pub kind: PatternKind,
+  /// Ground first, then the colors the pattern cycles through; mesh blends
+  /// them all. Derived from the theme when empty.
+  pub colors: Vec<String>,
+  /// Scales how many dots, stripes, or waves fit the canvas, or how tightly
+  /// mesh colors hold to their points
+  pub density: f32,
+  /// Degrees clockwise
+  pub rotation: f32,
+}
+
+impl Default for BackdropPattern {
+  fn default() -> Self {
+    Self {
+      kind: PatternKind::Dots,
+      colors: Vec::new(),
+      density: 1.0,
+      rotation: 0.0,
+    }
+  }
+}
+
+/// Shape a procedural backdrop repeats
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PatternKind {
+  /// Grid of round dots
+  #[default]
+  Dots,
+  /// Diagonal stripes, at 45 degrees before rotation
+  Stripes,
+  /// Parallel sine waves
+  Waves,
+  /// Soft blend of colors pinned at points scattered over the canvas
+  Mesh,
+}
+
+/// Platform the title bar's window controls imitate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WindowStyle {
+  /// Red, yellow, and green lights on the left
+  #[default]
+  Macos,
+  /// Minimize, maximize, and close glyphs on the right
+  Windows,
+  /// Round buttons on the right, as GNOME draws them
+  Linux,
+  /// The bar and its title without any controls
+  NoneButTitlebar,
+}
+
+/// How window controls are filled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ControlFill {
+  #[default]
+  Color,
+  /// Rings of the control colors
+  Hollow,
+  /// Control colors with their hue taken out
+  Grayscale,
+}
+
+/// Corner of the panel, inside its padding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Corner {
+  TopLeft,
+  TopRight,
+  BottomLeft,
+  BottomRight,
+}
+
+/// Fill of the language badge
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BadgeColor {
+  /// The language's brand color, the theme's for languages without one
+  #[default]
+  Language,
+  /// A tint of the theme's keyword color
+  Theme,
+}
+
+/// Language icon drawn beside the window title
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TitleIcon {
+  #[default]
+  Off,
+  /// Short label such as `RS` on a tag of the language's brand color
+  Badge,
+  /// Glyph from an installed Nerd Font, or a badge when none is installed
+  NerdFont,
+}
+
+/// What replaces characters that neither the font nor its fallbacks cover
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GlyphSubstitution {
+  /// Draw them anyway, as empty boxes
+  Off,
+  /// Built-in ASCII stand-ins, e.g. `->` for an arrow and `"` for smart quotes
+  #[default]
+  Ascii,
+  /// `glyph_substitutions`, then the built-in ASCII stand-ins
+  Custom,
+}
+
+/// Color profile output images are converted to and tagged with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorSpace {
+  /// Standard web colors, tagged with an sRGB chunk
+  #[default]
+  Srgb,
+  /// Wide-gamut colors with an embedded Display P3 ICC profile
+  DisplayP3,
+}
+
+/// Tokenizer used to highlight code
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HighlighterBackend {
+  /// TextMate grammars bundled with syntect
+  #[default]
+  Syntect,
+  /// Grammars and highlight queries matching Neovim's, when built with the
+  /// `tree-sitter` feature
+  TreeSitter,
+  /// Whatever `highlight_command` prints, read from its ANSI colors
+  External,
+}
+
+/// Container and codec for video exports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoFormat {
+  Mp4,
+  Webm,
+}
+
+/// How a scrolling video pans down a long snippet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScrollConfig {
+  /// Lines visible in each frame
+  pub viewport_lines: u32,
+  pub lines_per_second: f32,
+  pub fps: u32,
+  /// Pause before scrolling starts and after it ends
+  pub hold_seconds: f32,
+  pub format: VideoFormat,
+  /// ffmpeg executable that frames are piped into
+  pub ffmpeg_path: String,
+}
+
+impl Default for ScrollConfig {
+  fn default() -> Self {
+    Self {
+      viewport_lines: 20,
+      lines_per_second: 3.0,
+      fps: 30,
+      hold_seconds: 1.0,
+      format: VideoFormat::Mp4,
+      ffmpeg_path: String::from("ffmpeg"),
+    }
+  }
+}
+
+/// How a theme transition GIF is paced
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransitionConfig {
+  /// Frames spent blending between the two themes
+  pub frames: u32,
+  /// Still frames shown before and after the blend
+  pub hold_frames: u32,
+  pub fps: u32,
+}
+
+impl Default for TransitionConfig {
+  fn default() -> Self {
+    Self {
+      frames: 30,
+      hold_frames: 15,
+      fps: 25,
+    }
+  }
+}
+
+/// How a long file is split into pages for a carousel export
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CarouselConfig {
+  pub lines_per_page: u32,
+  /// Trailing lines of each page repeated at the top of the next for context
+  pub overlap_lines: u32,
+}
+
+impl Default for CarouselConfig {
+  fn default() -> Self {
+    Self {
+      lines_per_page: 30,
+      overlap_lines: 2,
+    }
+  }
+}
+
+/// How several files render as browser-style tabs
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TabsConfig {
+  pub layout: TabLayout,
+  /// 0-based file shown by the "active" layout
+  pub active: usize,
+}
+
+/// Whether tabbed files show one at a time or all at once
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TabLayout {
+  /// Every file's tab in the title bar, only the active one's code below
+  #[default]
+  Active,
+  /// One panel per file, each under its own tab, stacked on one backdrop
+  Stacked,
+}
+
+/// One file of a tabbed render, e.g.
+/// `{"title": "main.rs", "code": "fn main() {}", "language": "rust"}`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TabFile {
+  pub title: String,
+  pub code: String,
+  /// Resolved like any snippet's, from the title when empty
+  #[serde(default)]
+  pub language: String,
+}
+
+/// Credit drawn over the snippet, e.g.
+/// `{"text": "@octocat", "position": "bottom-right", "opacity": 0.6}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Watermark {
+  pub text: String,
+  /// Logo drawn before the text, scaled to its height
+  pub image: Option<String>,
+  pub position: WatermarkPosition,
+  /// 0.0 to 1.0
+  pub opacity: f32,
+}
+
+impl Default for Watermark {
+  fn default() -> Self {
+    Self {
+      text: String::new(),
+      image: None,
+      position: WatermarkPosition::default(),
+      opacity: 0.6,
+    }
+  }
+}
+
+/// Where the watermark sits: a corner of the backdrop margin, or the bottom
+/// right of the panel inside its padding
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WatermarkPosition {
+  TopLeft,
+  TopRight,
+  BottomLeft,
+  #[default]
+  BottomRight,
+  Footer,
 }
 
 impl Default for RenderConfig {
   fn default() -> Self {
     Self {
-      width: 1200,
-      height: None, // Auto-calculate based on content
-      padding: 64,
+      config_version: CONFIG_VERSION,
+      width: PanelSize::Fixed(1200),
+      min_width: 400,
+      max_width: 1600,
+      height: PanelSize::Auto, // Fit the content
+      min_height: 0,
+      max_height: None,
+      content_align: ContentAlign::default(),
+      padding: Sides::uniform(64),
       line_height: 1.25, // Fine-tuned for optimized base line height calculation
       font_size: 18.0,
-      font_family: String::from("Fira Code"),
-      background_color: String::from("#1e1e1e"),
+      font_family: String::from(DEFAULT_FONT_FAMILY),
+      fallback_fonts: Vec::new(),
+      panel_color: None,
+      themes_dir: None,
+      variant: ThemeVariant::default(),
+      min_contrast: 0.0,
+      theme_file: None,
       window_controls: true,
+      window_style: WindowStyle::default(),
+      control_fill: ControlFill::default(),
       window_title: None,
+      infer_title: false,
+      title_icon: TitleIcon::Off,
+      language_badge: None,
+      language_badge_color: BadgeColor::default(),
+      project_root: None,
+      project_name: None,
+      project_icon: None,
       line_numbers: false,
       drop_shadow: true,
       border_radius: 8.0,
-      export_size: 2.0,        // 2x for retina displays
-      panel_padding: 80,       // Extra padding around the panel
+      border: None,
+      export_size: 2.0,                  // 2x for retina displays
+      panel_padding: Sides::uniform(80), // Extra padding around the panel
+      canvas: None,
+      fit: Fit::None,
+      fit_margin: 24,
       gradient_backdrop: true, // Enable gradient backdrop by default
       noise_effect: true,      // Enable noise effect by default
+      gradient_dither: false,
+      gradient_colors: Vec::new(),
+      gradient_direction: None,
+      gradient_seed: None,
+      gradient_preset: None,
+      backdrop_image: None,
+      backdrop: None,
+      backdrop_blur: 0.0,
+      frosted_glass: None,
+      backdrop_darken: 0.0,
+      font_features: Vec::new(),
+      ligatures: true,
+      glyph_substitution: GlyphSubstitution::Ascii,
+      glyph_substitutions: HashMap::new(),
+      bracket_pairs: false,
+      bracket_colors: Vec::new(),
+      highlight_lines: Vec::new(),
+      highlight_color: None,
+      selection: None,
+      selection_color: None,
+      diagnostics: Vec::new(),
+      diagnostic_colors: DiagnosticColors::default(),
+      cursor: None,
+      focus_lines: Vec::new(),
+      unfocused_opacity: 0.35,
+      annotations: Vec::new(),
+      annotation_color: None,
+      watermark: None,
+      qr_link: None,
+      // Clear of the watermark's default corner
+      qr_corner: Corner::BottomLeft,
+      header: None,
+      footer: None,
+      source_path: None,
+      max_lines: 0,
+      columns: 1,
+      line_number_start: 1,
+      tab_width: 4,
+      text_width: 0,
+      reflow_comments: false,
+      highlighter: HighlighterBackend::Syntect,
+      syntax_dirs: Vec::new(),
+      highlight_command: Vec::new(),
+      highlight_timeout_ms: 3000,
+      blame: None,
+      minimap: false,
+      gutter_separator: false,
+      gutter_background: None,
+      scope_fonts: Vec::new(),
+      skeleton: false,
+      threads: 0,
+      layer: RenderLayer::Full,
+      palette_colors: 0,
+      dither: true,
+      color_space: ColorSpace::Srgb,
+      embed_metadata: true,
+      embed_source: false,
+      preset: None,
+      carousel: CarouselConfig::default(),
+      tabs: TabsConfig::default(),
+      scroll: ScrollConfig::default(),
+      transition: TransitionConfig::default(),
     }
   }
 }
 
 impl RenderConfig {
-  pub fn get_actual_width(&self) -> u32 {
-    (self.width as f32 * self.export_size) as u32
+  /// Parse a JSON config, starting from the named `preset` when one is given
+  /// and letting every other field in the JSON override it
+  pub fn from_json(json: &str) -> Result<Self> {
+    Self::from_json_layers(&[json])
+  }
+
+  /// Deep-merge JSON layers in order (e.g. a stored profile, then per-call
+  /// overrides) on top of the `preset` they name, or the defaults
+  pub fn from_json_layers(layers: &[&str]) -> Result<Self> {
+    let mut overrides = Map::new();
+    for json in layers {
+      let layer: Value =
+        serde_json::from_str(json).map_err(|e| anyhow!("Invalid JSON config: {}", e))?;
+      let Value::Object(mut layer) = layer else {
+        return Err(anyhow!("Config JSON must be an object"));
+      };
+      migrate(&mut layer)?;
+      expand_sides(&mut layer);
+      merge_config(&mut overrides, layer);
+    }
+    let base = match overrides.get("preset") {
+      Some(Value::String(name)) => {
+        Self::preset(name).ok_or_else(|| anyhow!("Unknown preset: {}", name))?
+      }
+      None => Self::default(),
+      Some(other) => return Err(anyhow!("Preset must be a string, got {}", other)),
+    };
+    let mut merged = match serde_json::to_value(base)? {
+      Value::Object(map) => map,
+      _ => unreachable!("RenderConfig serializes to an object"),
+    };
+    merge_config(&mut merged, overrides);
+    serde_json::from_value(Value::Object(merged)).map_err(|e| anyhow!("Invalid JSON config: {}", e))
+  }
+
+  /// Built-in configurations tuned to resemble popular snippet tools
+  pub fn preset(name: &str) -> Option<Self> {
+    let base = Self {
+      line_height: 0.8,
+      preset: Some(name.to_lowercase()),
+      ..Self::default()
+    };
+    let config = match name.to_lowercase().as_str() {
+      // Smooth backdrop with window chrome
+      "carbon" => Self {
+        padding: Sides::uniform(48),
+        panel_padding: Sides::uniform(56),
+        noise_effect: false,
+        ..base
+      },
+      // Grainy gradient backdrop with softer corners
+      "rayso" => Self {
+        padding: Sides::uniform(32),
+        panel_padding: Sides::uniform(64),
+        border_radius: 12.0,
+        window_controls: true,
+        ..base
+      },
+      // Just the code panel, no chrome or backdrop
+      "minimal" => Self {
+        padding: Sides::uniform(32),
+        panel_padding: Sides::uniform(0),
+        border_radius: 0.0,
+        window_controls: false,
+        drop_shadow: false,
+        gradient_backdrop: false,
+        noise_effect: false,
+        ..base
+      },
+      // Narrower panel and larger text that survive feed downscaling
+      "social" => Self {
+        width: PanelSize::Fixed(1000),
+        padding: Sides::uniform(48),
+        panel_padding: Sides::uniform(100),
+        font_size: 22.0,
+        border_radius: 12.0,
+        ..base
+      },
+      _ => return None,
+    };
+    Some(config)
+  }
+
+  pub fn preset_names() -> Vec<String> {
+    ["carbon", "rayso", "minimal", "social"]
+      .iter()
+      .map(|name| name.to_string())
+      .collect()
+  }
+
+  /// Scaled panel width; `content_width` is what an auto-width panel needs
+  /// to fit its longest line, already scaled
+  pub fn get_actual_width(&self, content_width: u32) -> u32 {
+    let min = (self.min_width as f32 * self.export_size) as u32;
+    let max = (self.max_width as f32 * self.export_size) as u32;
+    match (self.fit, self.width) {
+      (Fit::None, PanelSize::Fixed(width)) => (width as f32 * self.export_size) as u32,
+      (Fit::None, PanelSize::Auto) => content_width.min(max).max(min),
+      (Fit::Tight, _) => content_width.min(max),
+      // Scaled down to the image afterwards rather than cut off
+      (Fit::Exact, _) => content_width,
+    }
   }
 
+  /// Scaled panel height; `total_height` is what an auto-height panel needs
+  /// to fit its lines, before scaling
   pub fn get_actual_height(&self, total_height: u32) -> u32 {
-    let height = self.height.unwrap_or(total_height);
+    let max = self.max_height.unwrap_or(u32::MAX);
+    let height = match (self.fit, self.height) {
+      (Fit::None, PanelSize::Fixed(height)) => height,
+      (Fit::None, PanelSize::Auto) => total_height.min(max).max(self.min_height),
+      (Fit::Tight, _) => total_height.min(max),
+      (Fit::Exact, _) => total_height,
+    };
     (height as f32 * self.export_size) as u32
   }
 
-  pub fn get_scaled_padding(&self) -> u32 {
-    (self.padding as f32 * self.export_size) as u32
+  pub fn get_scaled_padding(&self) -> Sides {
+    self.padding.scaled(self.export_size)
   }
 
   pub fn get_scaled_font_size(&self) -> f32 {
     self.font_size * self.export_size
   }
 
-  pub fn get_scaled_panel_padding(&self) -> u32 {
-    (self.panel_padding as f32 * self.export_size) as u32
+  pub fn get_scaled_panel_padding(&self) -> Sides {
+    match self.fit {
+      Fit::Tight => Sides::uniform(self.fit_margin).scaled(self.export_size),
+      _ => self.panel_padding.scaled(self.export_size),
+    }
+  }
+
+  /// Whether the 1-based line number falls within `highlight_lines`
+  pub fn is_line_highlighted(&self, line: u32) -> bool {
+    self
+      .highlight_lines
+      .iter()
+      .any(|range| range.contains(line))
+  }
+
+  /// Whether the 1-based line number is drawn dimmed, outside a non-empty
+  /// `focus_lines`
+  pub fn is_line_unfocused(&self, line: u32) -> bool {
+    !self.focus_lines.is_empty() && !self.focus_lines.iter().any(|range| range.contains(line))
+  }
+}
+
+/// Merge `overrides` into `base`. Objects merge key by key; arrays, scalars,
+/// and mismatched types replace. A null clears the key so whatever sits
+/// underneath (the preset or the default) applies again.
+pub fn merge_config(base: &mut Map<String, Value>, overrides: Map<String, Value>) {
+  for (key, value) in overrides {
+    match (base.get_mut(&key), value) {
+      (_, Value::Null) => {
+        base.remove(&key);
+      }
+      (Some(Value::Object(base_object)), Value::Object(object)) => {
+        merge_config(base_object, object)
+      }
+      (_, value) => {
+        base.insert(key, value);
+      }
+    }
+  }
+}
+
+/// Fields holding `Sides`
+const SIDES_FIELDS: &[&str] = &["padding", "panel_padding"];
+
+/// Spell out `Sides` given as one number as an object, so a later layer
+/// setting only some sides keeps the rest from this one
+fn expand_sides(config: &mut Map<String, Value>) {
+  for field in SIDES_FIELDS {
+    if let Some(Value::Number(size)) = config.get(*field) {
+      let size = size.clone();
+      let sides = ["top", "right", "bottom", "left"]
+        .into_iter()
+        .map(|side| (side.to_string(), Value::Number(size.clone())))
+        .collect();
+      config.insert(field.to_string(), Value::Object(sides));
+    }
+  }
+}
+
+/// Rewrite fields renamed since the config's `config_version` (1 when absent),
+/// queueing a deprecation warning for each one
+fn migrate(config: &mut Map<String, Value>) -> Result<()> {
+  let version = match config.get("config_version") {
+    None | Some(Value::Null) => 1,
+    Some(value) => value
+      .as_u64()
+      .and_then(|version| u32::try_from(version).ok())
+      .ok_or_else(|| anyhow!("Invalid config_version: {}", value))?,
+  };
+  if version > CONFIG_VERSION {
+    warnings::push(format!(
+      "config_version {} is newer than this build supports ({})",
+      version, CONFIG_VERSION
+    ));
+  }
+  for (since, old, new) in RENAMED_FIELDS {
+    if version >= *since {
+      continue;
+    }
+    let Some(value) = config.remove(*old) else {
+      continue;
+    };
+    warnings::push(format!("`{}` is deprecated, use `{}` instead", old, new));
+    config.entry(*new).or_insert(value);
+  }
+  config.insert("config_version".into(), CONFIG_VERSION.into());
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_partial_config_inherits_defaults() {
+    let config: RenderConfig = serde_json::from_str(r#"{"line_numbers": true}"#).unwrap();
+    let defaults = RenderConfig::default();
+    assert!(config.line_numbers);
+    assert_eq!(config.width, defaults.width);
+    assert_eq!(config.padding, defaults.padding);
+    assert_eq!(config.font_family, defaults.font_family);
+    assert_eq!(config.line_number_start, 1);
+
+    let config: RenderConfig = serde_json::from_str("{}").unwrap();
+    assert_eq!(config.export_size, defaults.export_size);
+
+    let config: RenderConfig = serde_json::from_str(r#"{"transition": {"fps": 12}}"#).unwrap();
+    assert_eq!(config.transition.fps, 12);
+    assert_eq!(config.transition.frames, 30);
+
+    let config: RenderConfig = serde_json::from_str(
+      r#"{"glyph_substitution": "custom", "glyph_substitutions": {"→": "~>"}}"#,
+    )
+    .unwrap();
+    assert_eq!(config.glyph_substitution, GlyphSubstitution::Custom);
+    assert_eq!(config.glyph_substitutions[&'→'], "~>");
+
+    let config: RenderConfig = serde_json::from_str(
+      r#"{"annotations": [{"line": 2, "text": "here"}, {"line": 4, "text": "and", "side": "left"}]}"#,
+    )
+    .unwrap();
+    assert_eq!(config.annotations[0].side, AnnotationSide::Right);
+    assert_eq!(config.annotations[1].side, AnnotationSide::Left);
+    assert!(!config.is_line_unfocused(1));
+
+    let config: RenderConfig = serde_json::from_str(r#"{"focus_lines": ["2-3"]}"#).unwrap();
+    assert!(config.is_line_unfocused(1));
+    assert!(!config.is_line_unfocused(3));
+  }
+
+  #[test]
+  fn test_selection_columns() {
+    // Selected upward, from line 4 column 3 back to line 2 column 5
+    let selection: Selection = serde_json::from_str(
+      r#"{"start": {"line": 4, "column": 3}, "end": {"line": 2, "column": 5}}"#,
+    )
+    .unwrap();
+    assert_eq!(selection.columns(1, 10), None);
+    assert_eq!(selection.columns(2, 10), Some((4, 11)));
+    assert_eq!(selection.columns(3, 0), Some((0, 1)));
+    assert_eq!(selection.columns(4, 10), Some((0, 3)));
+
+    let block = Selection {
+      mode: SelectionMode::Block,
+      ..selection
+    };
+    assert_eq!(block.columns(3, 10), Some((2, 5)));
+    assert_eq!(block.columns(3, 2), None);
+    let line = Selection {
+      mode: SelectionMode::Line,
+      ..selection
+    };
+    assert_eq!(line.columns(3, 10), Some((0, 10)));
+  }
+
+  #[test]
+  fn test_preset_with_overrides() {
+    let config = RenderConfig::from_json(r#"{"preset": "minimal", "padding": 10}"#).unwrap();
+    let minimal = RenderConfig::preset("minimal").unwrap();
+    assert_eq!(config.padding, Sides::uniform(10));
+    assert_eq!(config.panel_padding, minimal.panel_padding);
+    assert!(!config.window_controls);
+
+    let config = RenderConfig::from_json(r#"{"width": 800}"#).unwrap();
+    assert_eq!(config.panel_padding, RenderConfig::default().panel_padding);
+    assert!(RenderConfig::from_json(r#"{"preset": "nope"}"#).is_err());
+    for name in RenderConfig::preset_names() {
+      assert!(RenderConfig::preset(&name).is_some());
+    }
+  }
+
+  #[test]
+  fn test_layers_deep_merge() {
+    let profile = r#"{"preset": "rayso", "padding": 10, "carousel": {"lines_per_page": 12},
+      "highlight_lines": [1, 2], "window_title": "main.rs"}"#;
+    let overrides = r#"{"carousel": {"overlap_lines": 0}, "highlight_lines": [5],
+      "window_title": null, "padding": null}"#;
+    let config = RenderConfig::from_json_layers(&[profile, overrides]).unwrap();
+    let rayso = RenderConfig::preset("rayso").unwrap();
+
+    // Nested objects merge field by field
+    assert_eq!(config.carousel.lines_per_page, 12);
+    assert_eq!(config.carousel.overlap_lines, 0);
+    // Arrays replace rather than append
+    assert_eq!(config.highlight_lines.len(), 1);
+    // Null falls through to the preset
+    assert_eq!(config.window_title, None);
+    assert_eq!(config.padding, rayso.padding);
+    assert_eq!(config.panel_padding, rayso.panel_padding);
+
+    // A number sets every side, and later layers can replace some of them
+    let config =
+      RenderConfig::from_json_layers(&[r#"{"padding": 10}"#, r#"{"padding": {"right": 96}}"#])
+        .unwrap();
+    assert_eq!(
+      config.padding,
+      Sides {
+        right: 96,
+        ..Sides::uniform(10)
+      }
+    );
+    // A misspelt side is rejected instead of silently read as zero
+    assert!(RenderConfig::from_json(r#"{"padding": {"rigth": 96}}"#).is_err());
+  }
+
+  #[test]
+  fn test_migrates_renamed_fields() {
+    let config = RenderConfig::from_json(r##"{"background_color": "#101010"}"##).unwrap();
+    assert_eq!(config.panel_color.as_deref(), Some("#101010"));
+    assert_eq!(config.config_version, CONFIG_VERSION);
+    assert!(
+      warnings::drain()
+        .iter()
+        .any(|warning| warning.contains("background_color"))
+    );
+
+    // The new name wins when both are present
+    let config =
+      RenderConfig::from_json(r##"{"background_color": "#101010", "panel_color": "#202020"}"##)
+        .unwrap();
+    assert_eq!(config.panel_color.as_deref(), Some("#202020"));
+
+    // A version that doesn't fit is reported rather than wrapped around
+    let error = RenderConfig::from_json(r#"{"config_version": 4294967297}"#).unwrap_err();
+    assert!(error.to_string().contains("config_version"));
+    assert!(RenderConfig::from_json(r#"{"config_version": -1}"#).is_err());
+  }
+
+  #[test]
+  fn test_auto_size_clamps() {
+    let config = RenderConfig::from_json(r#"{"width": "auto", "export_size": 1}"#).unwrap();
+    assert_eq!(config.width, PanelSize::Auto);
+    assert_eq!(config.get_actual_width(100), config.min_width);
+    assert_eq!(config.get_actual_width(900), 900);
+    assert_eq!(config.get_actual_width(5000), config.max_width);
+
+    let config = RenderConfig::from_json(r#"{"width": 800}"#).unwrap();
+    assert_eq!(config.width, PanelSize::Fixed(800));
+    assert!(RenderConfig::from_json(r#"{"width": "wide"}"#).is_err());
+
+    let config =
+      RenderConfig::from_json(r#"{"min_height": 300, "max_height": 500, "export_size": 1}"#)
+        .unwrap();
+    assert_eq!(config.height, PanelSize::Auto);
+    assert_eq!(config.get_actual_height(100), 300);
+    assert_eq!(config.get_actual_height(900), 500);
+    let config = RenderConfig::from_json(
+      r#"{"height": 200, "export_size": 1, "content_align": {"horizontal": "right"}}"#,
+    )
+    .unwrap();
+    assert_eq!(config.get_actual_height(900), 200);
+    assert_eq!(config.content_align.horizontal, HorizontalAlign::Right);
+    assert_eq!(config.content_align.vertical, VerticalAlign::Center);
+
+    // Fitting ignores the fixed width and the minimum
+    let config =
+      RenderConfig::from_json(r#"{"fit": "tight", "fit_margin": 10, "export_size": 1}"#).unwrap();
+    assert_eq!(config.get_actual_width(100), 100);
+    assert_eq!(config.get_actual_width(5000), config.max_width);
+    assert_eq!(config.get_scaled_panel_padding(), Sides::uniform(10));
+    let config = RenderConfig::from_json(r#"{"fit": "exact", "height": 200}"#).unwrap();
+    assert_eq!(config.get_actual_width(5000), 5000);
+    assert_eq!(config.get_actual_height(900), 1800);
+  }
+
+  #[test]
+  fn test_gradient_presets_are_valid() {
+    for preset in GRADIENT_PRESETS {
+      assert!(preset.colors.len() >= 2, "{} needs two stops", preset.name);
+      for hex in preset.colors {
+        let digits = hex.trim_start_matches('#');
+        assert!(
+          digits.len() == 6 && u32::from_str_radix(digits, 16).is_ok(),
+          "{}",
+          hex
+        );
+      }
+    }
+    assert_eq!(gradient_preset("Ocean").unwrap().name, "ocean");
+    assert!(gradient_preset("nope").is_none());
   }
 }
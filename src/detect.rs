@@ -0,0 +1,441 @@
+/* ~~/src/detect.rs */
+
+// standard crates
+use std::borrow::Cow;
+use std::path::Path;
+
+/// Language to highlight `code` as. An empty or `"auto"` language is
+/// detected from the code, and a file name such as `main.rs` or `Dockerfile`
+/// is resolved by its name before falling back to detection. Only output
+/// with no language at all is taken as terminal output for its escapes.
+pub fn resolve_language<'a>(language: &'a str, code: &str) -> Cow<'a, str> {
+  let language = language.trim();
+  if language.is_empty() || language.eq_ignore_ascii_case("auto") {
+    // Output captured with its SGR escapes, e.g. from `:terminal`
+    if code.contains("\x1b[") {
+      return Cow::Borrowed("ansi");
+    }
+    return Cow::Borrowed(detect_language(code));
+  }
+  if language.contains(['.', '/', '\\']) || is_special_file_name(language) {
+    return match language_from_file_name(language) {
+      Some(found) => found,
+      None => Cow::Borrowed(detect_language(code)),
+    };
+  }
+  Cow::Borrowed(language)
+}
+
+/// Best guess from a shebang, an editor modeline, or the content itself,
+/// `"text"` when nothing stands out
+pub fn detect_language(code: &str) -> &'static str {
+  from_shebang(code)
+    .or_else(|| from_modeline(code))
+    .or_else(|| classify(code))
+    .unwrap_or("text")
+}
+
+fn is_special_file_name(name: &str) -> bool {
+  matches!(
+    name.to_lowercase().as_str(),
+    "dockerfile" | "makefile" | "justfile" | "gemfile" | "rakefile"
+  )
+}
+
+fn language_from_file_name(name: &str) -> Option<Cow<'_, str>> {
+  let path = Path::new(name);
+  let file_name = path.file_name()?.to_str()?.to_lowercase();
+  let special = match file_name.as_str() {
+    "dockerfile" | "containerfile" => Some("dockerfile"),
+    "makefile" | "gnumakefile" => Some("makefile"),
+    "justfile" | ".justfile" => Some("just"),
+    "gemfile" | "rakefile" => Some("ruby"),
+    "cargo.lock" => Some("toml"),
+    _ if file_name.starts_with("dockerfile.") => Some("dockerfile"),
+    _ => None,
+  };
+  if let Some(language) = special {
+    return Some(Cow::Borrowed(language));
+  }
+  // Extensions double as language tokens for syntect and the pattern path
+  let extension = path.extension()?.to_str()?.to_lowercase();
+  Some(Cow::Owned(match extension.as_str() {
+    "mjs" | "cjs" | "jsx" => "js".to_string(),
+    "tsx" | "mts" | "cts" => "ts".to_string(),
+    "h" => "c".to_string(),
+    "cc" | "cxx" | "hpp" | "hh" => "cpp".to_string(),
+    "bash" | "zsh" => "sh".to_string(),
+    "htm" => "html".to_string(),
+    "markdown" => "md".to_string(),
+    "kts" => "kt".to_string(),
+    _ => extension,
+  }))
+}
+
+/// Interpreter named on a `#!` first line
+fn from_shebang(code: &str) -> Option<&'static str> {
+  let line = code.lines().next()?.strip_prefix("#!")?;
+  let mut words = line.split_whitespace();
+  let mut program = words.next()?.rsplit('/').next()?;
+  if program == "env" {
+    program = words.find(|word| !word.starts_with('-'))?;
+  }
+  let program = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+  Some(match program {
+    "sh" | "bash" | "zsh" | "dash" | "ksh" => "bash",
+    "python" => "python",
+    "node" | "nodejs" => "javascript",
+    "deno" | "ts-node" | "bun" => "typescript",
+    "ruby" => "ruby",
+    "lua" | "luajit" => "lua",
+    "php" => "php",
+    _ => return None,
+  })
+}
+
+/// `vim: ft=rust` or `-*- mode: python -*-` in the first or last few lines
+fn from_modeline(code: &str) -> Option<&'static str> {
+  let lines: Vec<&str> = code.lines().collect();
+  let edges = lines
+    .iter()
+    .take(5)
+    .chain(lines.iter().skip(lines.len().saturating_sub(5)));
+  for line in edges {
+    let value = line
+      .split_once("vim:")
+      .or_else(|| line.split_once("vi:"))
+      .and_then(|(_, rest)| {
+        rest.split([' ', ':']).find_map(|setting| {
+          setting
+            .strip_prefix("ft=")
+            .or(setting.strip_prefix("filetype="))
+        })
+      })
+      .or_else(|| {
+        let (_, rest) = line.split_once("-*-")?;
+        let (_, mode) = rest.split_once("mode:")?;
+        mode.split([';', ' ']).find(|word| !word.is_empty())
+      });
+    if let Some(value) = value
+      && let Some(language) = canonical_name(value.trim())
+    {
+      return Some(language);
+    }
+  }
+  None
+}
+
+/// Editor filetype or mode names mapped onto the names the highlighter knows
+fn canonical_name(name: &str) -> Option<&'static str> {
+  Some(match name.to_lowercase().as_str() {
+    "rust" => "rust",
+    "python" => "python",
+    "javascript" | "js" => "javascript",
+    "typescript" | "ts" => "typescript",
+    "go" => "go",
+    "c" => "c",
+    "cpp" | "c++" => "cpp",
+    "java" => "java",
+    "ruby" => "ruby",
+    "lua" => "lua",
+    "sh" | "bash" | "shell-script" | "zsh" => "bash",
+    "sql" => "sql",
+    "html" => "html",
+    "css" => "css",
+    "json" => "json",
+    "yaml" => "yaml",
+    "toml" | "conf-toml" => "toml",
+    "markdown" => "markdown",
+    "php" => "php",
+    "vim" => "vim",
+    _ => return None,
+  })
+}
+
+/// Tell-tale fragments of each language and how strongly they point to it
+const SIGNALS: &[(&str, &[(&str, u32)])] = &[
+  (
+    "rust",
+    &[
+      ("fn ", 2),
+      ("let mut ", 4),
+      ("impl ", 3),
+      ("pub fn ", 4),
+      ("use std::", 5),
+      ("#[derive", 5),
+      ("&str", 3),
+      ("-> ", 1),
+      ("::new(", 2),
+    ],
+  ),
+  (
+    "python",
+    &[
+      ("def ", 3),
+      ("elif ", 4),
+      ("self.", 2),
+      ("import ", 1),
+      ("from ", 1),
+      ("__init__", 5),
+      ("@dataclass", 5),
+      ("print(", 2),
+      ("None", 1),
+    ],
+  ),
+  (
+    "javascript",
+    &[
+      ("function ", 2),
+      ("const ", 2),
+      ("=> ", 1),
+      ("console.log", 4),
+      ("require(", 4),
+      ("export ", 1),
+      ("setTimeout(", 3),
+      ("let ", 1),
+      ("===", 3),
+    ],
+  ),
+  (
+    "typescript",
+    &[
+      ("interface ", 3),
+      (": string", 4),
+      (": number", 4),
+      ("Promise<", 3),
+      ("export type ", 4),
+      ("const ", 1),
+      ("async ", 1),
+    ],
+  ),
+  (
+    "go",
+    &[
+      ("package ", 4),
+      ("func ", 3),
+      (":= ", 3),
+      ("fmt.", 4),
+      ("import \"", 3),
+    ],
+  ),
+  (
+    "c",
+    &[
+      ("#include <", 3),
+      ("printf(", 2),
+      ("int main(", 2),
+      ("static ", 1),
+      ("/*", 1),
+    ],
+  ),
+  (
+    "cpp",
+    &[
+      ("std::", 4),
+      ("#include <iostream>", 4),
+      ("template <", 5),
+      ("template<", 5),
+      ("auto& ", 3),
+      ("#include <", 1),
+    ],
+  ),
+  (
+    "java",
+    &[
+      ("public class ", 5),
+      ("System.out", 5),
+      ("private final ", 4),
+      ("public static void", 5),
+      ("this.", 1),
+    ],
+  ),
+  (
+    "ruby",
+    &[
+      ("attr_reader", 5),
+      ("def initialize", 5),
+      ("puts ", 3),
+      ("require '", 3),
+      ("end\n", 1),
+      ("do |", 4),
+      ("@", 1),
+    ],
+  ),
+  ("php", &[("<?php", 10), ("$this->", 5), ("namespace ", 1)]),
+  (
+    "html",
+    &[
+      ("<!DOCTYPE html", 10),
+      ("<html", 6),
+      ("<div", 3),
+      ("</body>", 5),
+    ],
+  ),
+  ("xml", &[("<?xml", 10), ("/>", 1)]),
+  (
+    "css",
+    &[
+      ("px;", 3),
+      ("rem;", 3),
+      ("color: ", 2),
+      ("margin", 1),
+      ("padding: ", 2),
+      ("display: ", 3),
+    ],
+  ),
+  (
+    "sql",
+    &[
+      ("SELECT ", 4),
+      ("FROM ", 2),
+      ("WHERE ", 3),
+      ("GROUP BY", 4),
+      ("INSERT INTO", 5),
+      ("CREATE TABLE", 5),
+    ],
+  ),
+  (
+    "bash",
+    &[
+      ("echo ", 2),
+      ("fi\n", 3),
+      ("; then", 4),
+      ("; do", 4),
+      ("done\n", 2),
+      ("$(", 2),
+      ("esac", 4),
+    ],
+  ),
+  (
+    "lua",
+    &[
+      ("local ", 3),
+      ("~=", 3),
+      (" then\n", 2),
+      ("end\n", 1),
+      ("pairs(", 4),
+      ("function M.", 4),
+    ],
+  ),
+  (
+    "markdown",
+    &[
+      ("\n# ", 3),
+      ("\n## ", 3),
+      ("```", 4),
+      ("**", 2),
+      ("\n- ", 1),
+      ("\n> ", 2),
+    ],
+  ),
+  (
+    "toml",
+    &[
+      ("[package]", 5),
+      ("[dependencies]", 5),
+      ("\n[", 1),
+      (" = \"", 2),
+    ],
+  ),
+  (
+    "dockerfile",
+    &[
+      ("FROM ", 2),
+      ("\nRUN ", 4),
+      ("\nCOPY ", 3),
+      ("WORKDIR ", 5),
+      ("\nENTRYPOINT", 5),
+    ],
+  ),
+];
+
+/// Weigh every language's signals against the code and pick the strongest
+/// clear winner. Structured formats are recognized by parsing instead.
+fn classify(code: &str) -> Option<&'static str> {
+  let trimmed = code.trim_start();
+  if (trimmed.starts_with('{') || trimmed.starts_with('['))
+    && serde_json::from_str::<serde_json::Value>(code).is_ok()
+  {
+    return Some("json");
+  }
+  // Lead with a newline so line-start signals match the first line too
+  let code = format!("\n{}", code);
+
+  let mut best = None;
+  let mut best_score = 0;
+  for &(language, signals) in SIGNALS {
+    let score: u32 = signals
+      .iter()
+      .filter(|(fragment, _)| code.contains(fragment))
+      .map(|(_, weight)| weight)
+      .sum();
+    if score > best_score {
+      (best, best_score) = (Some(language), score);
+    }
+  }
+  if best_score >= 4 {
+    return best;
+  }
+  looks_like_yaml(&code).then_some("yaml")
+}
+
+/// Mostly `key: value` and `- item` lines
+fn looks_like_yaml(code: &str) -> bool {
+  let lines: Vec<&str> = code
+    .lines()
+    .map(str::trim)
+    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+    .collect();
+  let mappings = lines
+    .iter()
+    .filter(|line| {
+      line.starts_with("- ")
+        || line
+          .split_once(':')
+          .is_some_and(|(key, _)| !key.is_empty() && !key.contains(' '))
+    })
+    .count();
+  !lines.is_empty() && mappings * 4 >= lines.len() * 3
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_detects_sample_languages() {
+    let samples = std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/samples")).unwrap();
+    for entry in samples {
+      let path = entry.unwrap().path();
+      let name = path.file_name().unwrap().to_string_lossy().into_owned();
+      let language = name.split('.').next().unwrap();
+      if !matches!(
+        language,
+        "clojure" | "haskell" | "just" | "kotlin" | "scala" | "swift" | "vim"
+      ) {
+        let code = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(detect_language(&code), language, "{}", name);
+      }
+    }
+  }
+
+  #[test]
+  fn test_resolves_hints() {
+    assert_eq!(resolve_language("rust", ""), "rust");
+    assert_eq!(resolve_language("src/main.rs", ""), "rs");
+    assert_eq!(resolve_language("Dockerfile", ""), "dockerfile");
+    assert_eq!(
+      resolve_language("auto", "#!/usr/bin/env python3\n"),
+      "python"
+    );
+    assert_eq!(resolve_language("", "x = 1 # vim: set ft=lua:"), "lua");
+    assert_eq!(resolve_language("auto", "hello there"), "text");
+    assert_eq!(
+      resolve_language("auto", "\x1b[31mFAILED\x1b[0m tests::it_works"),
+      "ansi"
+    );
+    // A filetype wins over escapes in the code, e.g. a script printing colors
+    assert_eq!(resolve_language("bash", "echo -e '\x1b[31mred'"), "bash");
+    assert_eq!(resolve_language("build.log", "\x1b[31mFAILED"), "log");
+  }
+}
@@ -1,15 +1,231 @@
 /* ~~/src/font.rs */
 
+// standard crates
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+
 // third-party crates
 use anyhow::{Result, anyhow};
 use fontdue::{Font, FontSettings};
 use image::{Rgba, RgbaImage};
+use owned_ttf_parser::gsub::{SingleSubstitution, SubstitutionSubtable};
+use owned_ttf_parser::{Face, GlyphId, Tag};
+
+// local modules
+use crate::syntax::TokenStyle;
+
+/// Horizontal shear applied when synthesizing italics (roughly 11 degrees)
+const SYNTHETIC_ITALIC_SLANT: f32 = 0.2;
 
 pub struct FontManager {
-  font: Font,
+  regular: FontFace,
+  /// Dedicated style faces; missing ones are synthesized from `regular`
+  bold: Option<FontFace>,
+  italic: Option<FontFace>,
+  bold_italic: Option<FontFace>,
+  /// Alternate faces addressed by name from the token stream
+  named: HashMap<String, FontFace>,
+  /// Installed families tried in order for characters the face lacks
+  fallbacks: Vec<FallbackFace>,
+  /// Shape runs of text so ligatures apply, rather than drawing each
+  /// character on its own
+  ligatures: bool,
   size: f32,
 }
 
+/// Fallback family, parsed the first time a character needs it since CJK
+/// fonts run to tens of megabytes
+struct FallbackFace {
+  family: String,
+  face: OnceLock<Option<FontFace>>,
+}
+
+impl FallbackFace {
+  fn face(&self) -> Option<&FontFace> {
+    self
+      .face
+      .get_or_init(|| {
+        let (font_data, index) = system_face(&self.family, TokenStyle::REGULAR)?;
+        FontFace::new(font_data, index, &[]).ok()
+      })
+      .as_ref()
+  }
+}
+
+/// Families tried after any configured `fallback_fonts` for symbols, box
+/// drawing, and CJK text, skipped when not installed
+pub const DEFAULT_FALLBACK_FAMILIES: &[&str] = &[
+  "DejaVu Sans Mono",
+  "Noto Sans Mono CJK JP",
+  "Noto Sans CJK JP",
+  "Source Han Sans",
+  "PingFang SC",
+  "Hiragino Sans",
+  "Microsoft YaHei",
+  "MS Gothic",
+  "Noto Sans Symbols",
+  "Noto Sans Symbols 2",
+  "Noto Sans Math",
+  "Apple Symbols",
+  "Segoe UI Symbol",
+  "DejaVu Sans",
+  "Symbola",
+];
+
+struct FontFace {
+  font: Font,
+  /// Font file contents and face index, kept for the shaper and shared
+  /// with other faces of the same file
+  #[cfg(feature = "shaping")]
+  data: Arc<[u8]>,
+  #[cfg(feature = "shaping")]
+  index: u32,
+  /// Glyph substitutions resolved from enabled OpenType features
+  substitutions: HashMap<u16, u16>,
+  /// The same features as passed to the shaper
+  #[cfg(feature = "shaping")]
+  shaping_features: Vec<rustybuzz::Feature>,
+  /// Whether the face itself is bold or italic, so styles are not synthesized twice
+  bold: bool,
+  italic: bool,
+}
+
+/// Glyph id, pen advance, and (x, y) offset in pixels
+type ShapedGlyph = (u16, f32, (f32, f32));
+
+/// Glyph picked for a stretch of text, positioned by the shaper or taken
+/// character by character
+struct PlacedGlyph<'a> {
+  face: &'a FontFace,
+  index: u16,
+  advance: f32,
+  offset: (f32, f32),
+  synthesize_bold: bool,
+  synthesize_italic: bool,
+}
+
+impl FontFace {
+  fn new(font_data: Arc<[u8]>, index: u32, features: &[FontFeature]) -> Result<Self> {
+    let settings = FontSettings {
+      collection_index: index,
+      ..FontSettings::default()
+    };
+    let font =
+      Font::from_bytes(&*font_data, settings).map_err(|e| anyhow!("Failed to load font: {}", e))?;
+    let substitutions = resolve_feature_substitutions(&font_data, index, features);
+    #[cfg(feature = "shaping")]
+    let shaping_features = features
+      .iter()
+      .map(|feature| {
+        let tag = rustybuzz::ttf_parser::Tag::from_bytes(&feature.tag);
+        rustybuzz::Feature::new(tag, feature.value, ..)
+      })
+      .collect();
+    let (bold, italic) = Face::from_slice(&font_data, index)
+      .map(|face| (face.is_bold(), face.is_italic() || face.is_oblique()))
+      .unwrap_or_default();
+    Ok(Self {
+      font,
+      #[cfg(feature = "shaping")]
+      data: font_data,
+      #[cfg(feature = "shaping")]
+      index,
+      substitutions,
+      #[cfg(feature = "shaping")]
+      shaping_features,
+      bold,
+      italic,
+    })
+  }
+
+  /// Glyph for a lone character, with feature substitutions applied
+  fn glyph_index(&self, character: char) -> u16 {
+    let index = self.font.lookup_glyph_index(character);
+    self.substitutions.get(&index).copied().unwrap_or(index)
+  }
+
+  /// Glyphs and pen advances for `text` after the shaper applies ligatures,
+  /// contextual alternates, and kerning
+  #[cfg(feature = "shaping")]
+  fn shape(&self, text: &str, size: f32) -> Option<Vec<ShapedGlyph>> {
+    let face = rustybuzz::Face::from_slice(&self.data, self.index)?;
+    let scale = size / face.units_per_em() as f32;
+    let mut buffer = rustybuzz::UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.set_direction(rustybuzz::Direction::LeftToRight);
+    let shaped = rustybuzz::shape(&face, &self.shaping_features, buffer);
+    Some(
+      shaped
+        .glyph_infos()
+        .iter()
+        .zip(shaped.glyph_positions())
+        .map(|(info, position)| {
+          (
+            info.glyph_id as u16,
+            position.x_advance as f32 * scale,
+            (
+              position.x_offset as f32 * scale,
+              position.y_offset as f32 * scale,
+            ),
+          )
+        })
+        .collect(),
+    )
+  }
+
+  /// Stand-in for builds without the `shaping` feature
+  #[cfg(not(feature = "shaping"))]
+  fn shape(&self, _text: &str, _size: f32) -> Option<Vec<ShapedGlyph>> {
+    None
+  }
+
+  fn rasterize(&self, index: u16, size: f32) -> GlyphInfo {
+    let (metrics, bitmap) = self.font.rasterize_indexed(index, size);
+    GlyphInfo {
+      data: bitmap,
+      width: metrics.width,
+      height: metrics.height,
+      advance_width: metrics.advance_width,
+      bearing_x: metrics.xmin,
+      bearing_y: metrics.ymin,
+    }
+  }
+}
+
+/// OpenType feature toggle such as `ss01`, `zero`, or `calt=0`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FontFeature {
+  pub tag: [u8; 4],
+  pub value: u32,
+}
+
+impl FontFeature {
+  /// Parse `tag`, `+tag`, `-tag`, or `tag=value` notation
+  pub fn parse(spec: &str) -> Result<Self> {
+    let spec = spec.trim();
+    let (name, value) = if let Some((name, value)) = spec.split_once('=') {
+      let value = value
+        .trim()
+        .parse::<u32>()
+        .map_err(|_| anyhow!("Invalid font feature value: {}", spec))?;
+      (name.trim(), value)
+    } else if let Some(name) = spec.strip_prefix('-') {
+      (name, 0)
+    } else {
+      (spec.strip_prefix('+').unwrap_or(spec), 1)
+    };
+    let tag: [u8; 4] = name
+      .as_bytes()
+      .try_into()
+      .map_err(|_| anyhow!("Font feature tag must be four characters: {}", spec))?;
+    if !tag.iter().all(|b| b.is_ascii_alphanumeric()) {
+      return Err(anyhow!("Invalid font feature tag: {}", spec));
+    }
+    Ok(Self { tag, value })
+  }
+}
+
 pub struct GlyphInfo {
   pub data: Vec<u8>,
   pub width: usize,
@@ -20,30 +236,273 @@ pub struct GlyphInfo {
 }
 
 impl FontManager {
-  pub fn new(font_data: &[u8], size: f32) -> Result<Self> {
-    let font = Font::from_bytes(font_data, FontSettings::default())
-      .map_err(|e| anyhow!("Failed to load font: {}", e))?;
-    Ok(Self { font, size })
+  /// Font manager for the face at `index` in `font_data`, which is 0 unless
+  /// the data is a collection
+  pub fn new(
+    font_data: impl Into<Arc<[u8]>>,
+    index: u32,
+    size: f32,
+    features: &[FontFeature],
+  ) -> Result<Self> {
+    Ok(Self {
+      regular: FontFace::new(font_data.into(), index, features)?,
+      bold: None,
+      italic: None,
+      bold_italic: None,
+      named: HashMap::new(),
+      fallbacks: Vec::new(),
+      ligatures: true,
+      size,
+    })
   }
 
-  pub fn render_glyph(&self, character: char) -> GlyphInfo {
-    let (metrics, bitmap) = self.font.rasterize(character, self.size);
-    GlyphInfo {
-      data: bitmap,
-      width: metrics.width,
-      height: metrics.height,
-      advance_width: metrics.advance_width,
-      bearing_x: metrics.xmin,
-      bearing_y: metrics.ymin,
+  /// Register an alternate face that tokens can request by `name`
+  pub fn add_named_face(
+    &mut self,
+    name: &str,
+    font_data: impl Into<Arc<[u8]>>,
+    features: &[FontFeature],
+  ) -> Result<()> {
+    let face = FontFace::new(font_data.into(), 0, features)?;
+    self.named.insert(name.to_string(), face);
+    Ok(())
+  }
+
+  pub fn has_named_face(&self, name: &str) -> bool {
+    self.named.contains_key(name)
+  }
+
+  pub fn set_ligatures(&mut self, ligatures: bool) {
+    self.ligatures = ligatures;
+  }
+
+  /// Add installed families to the per-character fallback chain, in order;
+  /// missing or repeated families are skipped
+  pub fn add_fallback_families<'a>(&mut self, families: impl IntoIterator<Item = &'a str>) {
+    for family in families.into_iter().filter_map(resolve_family) {
+      if !self
+        .fallbacks
+        .iter()
+        .any(|fallback| fallback.family == family)
+      {
+        self.fallbacks.push(FallbackFace {
+          family,
+          face: OnceLock::new(),
+        });
+      }
     }
   }
 
+  /// Register a dedicated face for a bold, italic, or bold-italic style
+  pub fn add_style_variant(
+    &mut self,
+    style: TokenStyle,
+    font_data: impl Into<Arc<[u8]>>,
+    index: u32,
+    features: &[FontFeature],
+  ) -> Result<()> {
+    let face = Some(FontFace::new(font_data.into(), index, features)?);
+    match (style.is_bold(), style.is_italic()) {
+      (true, true) => self.bold_italic = face,
+      (true, false) => self.bold = face,
+      (false, true) => self.italic = face,
+      (false, false) => return Err(anyhow!("Regular face cannot be registered as a variant")),
+    }
+    Ok(())
+  }
+
+  /// Pick the face for a style along with which effects must be synthesized;
+  /// a named face wins over the style variants and only lacks what it doesn't provide
+  fn select_face(&self, style: TokenStyle, face: Option<&str>) -> (&FontFace, bool, bool) {
+    if let Some(face) = face.and_then(|name| self.named.get(name)) {
+      return (
+        face,
+        style.is_bold() && !face.bold,
+        style.is_italic() && !face.italic,
+      );
+    }
+    match (style.is_bold(), style.is_italic()) {
+      (false, false) => (&self.regular, false, false),
+      (true, false) => match &self.bold {
+        Some(face) => (face, false, false),
+        None => (&self.regular, true, false),
+      },
+      (false, true) => match &self.italic {
+        Some(face) => (face, false, false),
+        None => (&self.regular, false, true),
+      },
+      (true, true) => match (&self.bold_italic, &self.bold, &self.italic) {
+        (Some(face), _, _) => (face, false, false),
+        (None, Some(face), _) => (face, false, true),
+        (None, None, Some(face)) => (face, true, false),
+        (None, None, None) => (&self.regular, true, true),
+      },
+    }
+  }
+
+  /// `select_face`, moving down the fallback chain when the chosen face has
+  /// no glyph for `character`
+  fn face_for(
+    &self,
+    character: char,
+    style: TokenStyle,
+    face: Option<&str>,
+  ) -> (&FontFace, bool, bool) {
+    let selected = self.select_face(style, face);
+    if character.is_whitespace() || selected.0.font.lookup_glyph_index(character) != 0 {
+      return selected;
+    }
+    self
+      .fallbacks
+      .iter()
+      .filter_map(FallbackFace::face)
+      .find(|fallback| fallback.font.lookup_glyph_index(character) != 0)
+      .map(|fallback| {
+        (
+          fallback,
+          style.is_bold() && !fallback.bold,
+          style.is_italic() && !fallback.italic,
+        )
+      })
+      .unwrap_or(selected)
+  }
+
+  /// Glyphs for `text` in `style`. Runs that share a face are shaped
+  /// together when ligatures are on; otherwise each character maps to its
+  /// own glyph.
+  fn place_glyphs(
+    &self,
+    text: &str,
+    style: TokenStyle,
+    face: Option<&str>,
+  ) -> Vec<PlacedGlyph<'_>> {
+    // Split the text into runs drawn from the same face
+    let mut runs: Vec<(String, (&FontFace, bool, bool))> = Vec::new();
+    for ch in text.chars().filter(|ch| !ch.is_control() || *ch == '\t') {
+      let selected = self.face_for(ch, style, face);
+      match runs.last_mut() {
+        Some((run, (current, bold, italic)))
+          if std::ptr::eq(*current, selected.0) && (*bold, *italic) == (selected.1, selected.2) =>
+        {
+          run.push(ch)
+        }
+        _ => runs.push((ch.to_string(), selected)),
+      }
+    }
+
+    let mut placed = Vec::new();
+    for (run, (face, synthesize_bold, synthesize_italic)) in runs {
+      let glyphs = match self.ligatures {
+        true => face.shape(&run, self.size),
+        false => None,
+      };
+      let glyphs = glyphs.unwrap_or_else(|| {
+        run
+          .chars()
+          .map(|ch| {
+            let index = face.glyph_index(ch);
+            let advance = face.font.metrics_indexed(index, self.size).advance_width;
+            (index, advance, (0.0, 0.0))
+          })
+          .collect()
+      });
+      placed.extend(
+        glyphs
+          .into_iter()
+          .map(|(index, advance, offset)| PlacedGlyph {
+            face,
+            index,
+            advance,
+            offset,
+            synthesize_bold,
+            synthesize_italic,
+          }),
+      );
+    }
+    placed
+  }
+
+  /// Rasterize `text` in the requested style, preferring dedicated faces and
+  /// synthesizing emboldening or obliquing where a face is missing
+  pub fn render_styled_text(
+    &self,
+    text: &str,
+    style: TokenStyle,
+    face: Option<&str>,
+  ) -> Vec<GlyphInfo> {
+    self
+      .place_glyphs(text, style, face)
+      .into_iter()
+      .map(|placed| {
+        let mut glyph = placed.face.rasterize(placed.index, self.size);
+        glyph.advance_width = placed.advance;
+        glyph.bearing_x += placed.offset.0.round() as i32;
+        glyph.bearing_y += placed.offset.1.round() as i32;
+        if placed.synthesize_bold {
+          glyph = embolden_glyph(glyph, self.synthetic_bold_strength());
+        }
+        if placed.synthesize_italic {
+          glyph = oblique_glyph(glyph, SYNTHETIC_ITALIC_SLANT);
+        }
+        glyph
+      })
+      .collect()
+  }
+
+  /// Width in pixels that `text` advances the pen when drawn in `style`,
+  /// truncating per glyph exactly like the renderer's pen
+  pub fn measure_text(&self, text: &str, style: TokenStyle, face: Option<&str>) -> u32 {
+    let bold_extra = self.synthetic_bold_strength() as f32;
+    self
+      .place_glyphs(text, style, face)
+      .iter()
+      .map(|placed| match placed.synthesize_bold {
+        true => (placed.advance + bold_extra) as u32,
+        false => placed.advance as u32,
+      })
+      .sum()
+  }
+
+  /// Smallest pen advance among printable ASCII in the regular face, a lower
+  /// bound for how little room one column of code can take
+  pub fn narrowest_advance(&self) -> u32 {
+    (' '..='~')
+      .map(|ch| self.measure_text(ch.encode_utf8(&mut [0; 4]), TokenStyle::REGULAR, None))
+      .filter(|&advance| advance > 0)
+      .min()
+      .unwrap_or(1)
+  }
+
+  /// Whether the regular face or a fallback has a real glyph for `ch` rather
+  /// than .notdef
+  pub fn has_glyph(&self, ch: char) -> bool {
+    let (face, _, _) = self.face_for(ch, TokenStyle::REGULAR, None);
+    face.font.lookup_glyph_index(ch) != 0
+  }
+
+  fn synthetic_bold_strength(&self) -> usize {
+    (self.size / 20.0).round().max(1.0) as usize
+  }
+
+  /// Ascent and descent (negative below the baseline) in pixels
+  pub fn get_vertical_metrics(&self) -> (f32, f32) {
+    match self.regular.font.horizontal_line_metrics(self.size) {
+      Some(metrics) => (metrics.ascent, metrics.descent),
+      None => (self.size * 0.8, -self.size * 0.2),
+    }
+  }
+
+  /// Underline offset below the baseline and thickness, in pixels
+  pub fn underline_metrics(&self) -> (i32, u32) {
+    underline_metrics(self.size)
+  }
+
   pub fn get_line_height(&self) -> u32 {
     // For optimal code rendering, use a simple but effective approach
     // Most code editors use font size * 0.9 to 1.0 as the base line height
     // This gives tight but readable spacing that works well with multipliers
 
-    if let Some(metrics) = self.font.horizontal_line_metrics(self.size) {
+    if let Some(metrics) = self.regular.font.horizontal_line_metrics(self.size) {
       // Use font metrics but cap it to prevent excessive spacing
       let metrics_height = metrics.ascent - metrics.descent;
       let font_size_height = self.size * 0.95; // Slightly tighter than font size
@@ -75,7 +534,12 @@ impl FontManager {
     // We want: glyph_top_y = baseline_y - (glyph_height + bearing_y)
     let glyph_y = y - (glyph.height as i32 + glyph.bearing_y);
 
-    for (i, &alpha) in glyph.data.iter().enumerate() {
+    for (i, &coverage) in glyph.data.iter().enumerate() {
+      // A translucent color scales the glyph's coverage
+      let alpha = match color[3] {
+        255 => coverage,
+        opacity => (coverage as u16 * opacity as u16 / 255) as u8,
+      };
       if alpha == 0 {
         continue;
       }
@@ -97,6 +561,63 @@ impl FontManager {
   }
 }
 
+/// Synthesize bold by dilating glyph coverage horizontally
+fn embolden_glyph(glyph: GlyphInfo, strength: usize) -> GlyphInfo {
+  if glyph.width == 0 || glyph.height == 0 {
+    return GlyphInfo {
+      advance_width: glyph.advance_width + strength as f32,
+      ..glyph
+    };
+  }
+  let width = glyph.width + strength;
+  let mut data = vec![0u8; width * glyph.height];
+  for row in 0..glyph.height {
+    for col in 0..glyph.width {
+      let alpha = glyph.data[row * glyph.width + col];
+      for offset in 0..=strength {
+        let target = &mut data[row * width + col + offset];
+        *target = (*target).max(alpha);
+      }
+    }
+  }
+  GlyphInfo {
+    data,
+    width,
+    advance_width: glyph.advance_width + strength as f32,
+    ..glyph
+  }
+}
+
+/// Synthesize italics by shearing rows proportionally to their height above the baseline
+fn oblique_glyph(glyph: GlyphInfo, slant: f32) -> GlyphInfo {
+  if glyph.width == 0 || glyph.height == 0 {
+    return glyph;
+  }
+  let row_shift = |row: usize| (glyph.height - row) as f32 + glyph.bearing_y as f32 - 0.5;
+  let min_shift = (row_shift(glyph.height - 1) * slant).floor();
+  let max_shift = (row_shift(0) * slant).ceil();
+  let width = glyph.width + (max_shift - min_shift) as usize + 1;
+  let mut data = vec![0u8; width * glyph.height];
+  for row in 0..glyph.height {
+    let shift = row_shift(row) * slant - min_shift;
+    let whole = shift.floor() as usize;
+    let fraction = shift - shift.floor();
+    for col in 0..glyph.width {
+      let alpha = glyph.data[row * glyph.width + col] as f32;
+      let left = &mut data[row * width + col + whole];
+      *left = (*left as f32 + alpha * (1.0 - fraction)).min(255.0) as u8;
+      let right = &mut data[row * width + col + whole + 1];
+      *right = (*right as f32 + alpha * fraction).min(255.0) as u8;
+    }
+  }
+  GlyphInfo {
+    data,
+    width,
+    bearing_x: glyph.bearing_x + min_shift as i32,
+    ..glyph
+  }
+}
+
 fn blend_alpha_pixel(background: Rgba<u8>, foreground: Rgba<u8>, alpha: u8) -> Rgba<u8> {
   if alpha == 255 {
     return foreground;
@@ -116,43 +637,276 @@ fn blend_alpha_pixel(background: Rgba<u8>, foreground: Rgba<u8>, alpha: u8) -> R
   ])
 }
 
-/// Try to load font from various sources
-pub fn load_font_with_fallback(preferred_size: f32) -> Result<FontManager> {
-  let font_paths = [
-    "./fonts/jet-brains-mono-regular.ttf",
-    "./fonts/fira-code-regular.ttf",
-    "/System/Library/Fonts/Monaco.ttf", // macOS fallback
-    "/System/Library/Fonts/Menlo.ttc",  // macOS fallback
-    "/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf", // Linux fallback
-    "/Windows/Fonts/consola.ttf",       // Windows fallback
-  ];
+/// Build a glyph substitution map for features that map one glyph to another
+/// (stylistic sets, slashed zero, character variants). Contextual features such
+/// as `calt` and `liga` need a shaper and are left untouched here.
+fn resolve_feature_substitutions(
+  font_data: &[u8],
+  index: u32,
+  features: &[FontFeature],
+) -> HashMap<u16, u16> {
+  let mut substitutions = HashMap::new();
+  let Ok(face) = Face::from_slice(font_data, index) else {
+    return substitutions;
+  };
+  let Some(gsub) = face.tables().gsub else {
+    return substitutions;
+  };
+  let glyph_count = face.number_of_glyphs();
 
-  // Try external fonts first
-  for path in &font_paths {
-    if std::path::Path::new(path).exists() {
-      if let Ok(font_data) = std::fs::read(path) {
-        if let Ok(font_manager) = FontManager::new(&font_data, preferred_size) {
-          return Ok(font_manager);
+  for feature in features.iter().filter(|f| f.value > 0) {
+    let tag = Tag::from_bytes(&feature.tag);
+    let mut feature_map: HashMap<u16, u16> = HashMap::new();
+    for feature_index in 0..gsub.features.len() {
+      let Some(record) = gsub.features.get(feature_index) else {
+        continue;
+      };
+      if record.tag != tag {
+        continue;
+      }
+      for lookup_index in record.lookup_indices {
+        let Some(lookup) = gsub.lookups.get(lookup_index) else {
+          continue;
+        };
+        for subtable_index in 0..lookup.subtables.len() {
+          let Some(subtable) = lookup.subtables.get::<SubstitutionSubtable>(subtable_index) else {
+            continue;
+          };
+          for glyph in 0..glyph_count {
+            if feature_map.contains_key(&glyph) {
+              continue;
+            }
+            if let Some(substitute) = substitute_glyph(&subtable, GlyphId(glyph), feature.value) {
+              feature_map.insert(glyph, substitute);
+            }
+          }
         }
       }
     }
+    // Compose with substitutions from previously applied features
+    for to in substitutions.values_mut() {
+      if let Some(next) = feature_map.get(to) {
+        *to = *next;
+      }
+    }
+    for (from, to) in feature_map {
+      substitutions.entry(from).or_insert(to);
+    }
+  }
+  substitutions
+}
+
+fn substitute_glyph(subtable: &SubstitutionSubtable, glyph: GlyphId, value: u32) -> Option<u16> {
+  match subtable {
+    SubstitutionSubtable::Single(SingleSubstitution::Format1 { coverage, delta }) => {
+      coverage.get(glyph)?;
+      Some((glyph.0 as i32 + *delta as i32) as u16)
+    }
+    SubstitutionSubtable::Single(SingleSubstitution::Format2 {
+      coverage,
+      substitutes,
+    }) => substitutes.get(coverage.get(glyph)?).map(|g| g.0),
+    SubstitutionSubtable::Alternate(alternate) => {
+      let set = alternate
+        .alternate_sets
+        .get(alternate.coverage.get(glyph)?)?;
+      set.alternates.get((value - 1) as u16).map(|g| g.0)
+    }
+    _ => None,
+  }
+}
+
+/// Underline offset below the baseline and thickness for a font size, shared
+/// with the shaped text path
+pub fn underline_metrics(size: f32) -> (i32, u32) {
+  (
+    (size * 0.12).round().max(1.0) as i32,
+    (size / 16.0).round().max(1.0) as u32,
+  )
+}
+
+/// Regular faces tried in order; the first readable one becomes the base font
+pub const FONT_PATHS: [&str; 6] = [
+  "./fonts/jet-brains-mono-regular.ttf",
+  "./fonts/fira-code-regular.ttf",
+  "/System/Library/Fonts/Monaco.ttf", // macOS fallback
+  "/System/Library/Fonts/Menlo.ttc",  // macOS fallback
+  "/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf", // Linux fallback
+  "/Windows/Fonts/consola.ttf",       // Windows fallback
+];
+
+/// Try to load font from various sources: `family` from the system fonts,
+/// falling back to the first readable face in `FONT_PATHS` when it is not
+/// installed
+pub fn load_font_with_fallback(
+  family: &str,
+  preferred_size: f32,
+  features: &[FontFeature],
+) -> Result<FontManager> {
+  if let Some(font_manager) = load_system_family(family, preferred_size, features) {
+    return Ok(font_manager);
+  }
+
+  // Try external fonts first
+  for path in &FONT_PATHS {
+    if std::path::Path::new(path).exists()
+      && let Ok(font_data) = std::fs::read(path)
+      && let Ok(mut font_manager) = FontManager::new(font_data, 0, preferred_size, features)
+    {
+      load_style_variants(&mut font_manager, path, features);
+      return Ok(font_manager);
+    }
+  }
+
+  // Fallback to the embedded font when one is compiled in
+  create_fallback_font(preferred_size, features)
+}
+
+/// Fonts installed on the system, scanned once on first use
+fn system_fonts() -> &'static fontdb::Database {
+  static DATABASE: OnceLock<fontdb::Database> = OnceLock::new();
+  DATABASE.get_or_init(|| {
+    let mut database = fontdb::Database::new();
+    database.load_system_fonts();
+    database
+  })
+}
+
+/// Installed family matching `name` regardless of case, as the font spells it
+pub fn resolve_family(name: &str) -> Option<String> {
+  let name = name.trim();
+  if name.is_empty() {
+    return None;
+  }
+  system_fonts()
+    .faces()
+    .flat_map(|face| &face.families)
+    .find(|(family, _)| family.eq_ignore_ascii_case(name))
+    .map(|(family, _)| family.clone())
+}
+
+/// Regular, bold, and italic faces of an installed family
+fn load_system_family(
+  family: &str,
+  preferred_size: f32,
+  features: &[FontFeature],
+) -> Option<FontManager> {
+  let family = resolve_family(family)?;
+  let (font_data, index) = system_face(&family, TokenStyle::REGULAR)?;
+  let mut font_manager = FontManager::new(font_data, index, preferred_size, features).ok()?;
+  for style in [
+    TokenStyle::BOLD,
+    TokenStyle::ITALIC,
+    TokenStyle::BOLD | TokenStyle::ITALIC,
+  ] {
+    if let Some((font_data, index)) = system_face(&family, style) {
+      let _ = font_manager.add_style_variant(style, font_data, index, features);
+    }
   }
+  Some(font_manager)
+}
+
+/// Installed font files in use, so faces taken from one collection share a copy
+static FONT_FILES: Mutex<Vec<(PathBuf, Weak<[u8]>)>> = Mutex::new(Vec::new());
 
-  // Fallback to minimal embedded font data
-  // For now, we'll create a very basic implementation
-  create_fallback_font(preferred_size)
+/// File contents and collection index of the face of `family` drawn in
+/// `style`, only when the family really has one rather than its nearest match
+fn system_face(family: &str, style: TokenStyle) -> Option<(Arc<[u8]>, u32)> {
+  let database = system_fonts();
+  let query = fontdb::Query {
+    families: &[fontdb::Family::Name(family)],
+    weight: match style.is_bold() {
+      true => fontdb::Weight::BOLD,
+      false => fontdb::Weight::NORMAL,
+    },
+    style: match style.is_italic() {
+      true => fontdb::Style::Italic,
+      false => fontdb::Style::Normal,
+    },
+    ..fontdb::Query::default()
+  };
+  let id = database.query(&query)?;
+  let face = database.face(id)?;
+  let bold = face.weight.0 >= fontdb::Weight::SEMIBOLD.0;
+  let italic = face.style != fontdb::Style::Normal;
+  if bold != style.is_bold() || italic != style.is_italic() {
+    return None;
+  }
+  let path = match &face.source {
+    fontdb::Source::File(path) | fontdb::Source::SharedFile(path, _) => Some(path.clone()),
+    fontdb::Source::Binary(_) => None,
+  };
+  let mut files = FONT_FILES.lock().unwrap_or_else(|e| e.into_inner());
+  files.retain(|(_, data)| data.strong_count() > 0);
+  let read = files
+    .iter()
+    .find(|(file, _)| Some(file) == path.as_ref())
+    .and_then(|(_, data)| data.upgrade());
+  if let Some(data) = read {
+    return Some((data, face.index));
+  }
+  let data: Arc<[u8]> = database.with_face_data(id, |data, _| Arc::from(data))?;
+  if let Some(path) = path {
+    files.push((path, Arc::downgrade(&data)));
+  }
+  Some((data, face.index))
 }
 
-fn create_fallback_font(_size: f32) -> Result<FontManager> {
-  // This is a placeholder for when no real font is available
-  // In a production implementation, you'd embed a real TTF font here
+/// Look for bold/italic siblings of a regular font file, e.g.
+/// `DejaVuSansMono.ttf` -> `DejaVuSansMono-Bold.ttf` or `fira-code-regular.ttf` -> `fira-code-bold.ttf`
+fn load_style_variants(font_manager: &mut FontManager, path: &str, features: &[FontFeature]) {
+  let path = std::path::Path::new(path);
+  let (Some(stem), Some(extension)) = (
+    path.file_stem().and_then(|s| s.to_str()),
+    path.extension().and_then(|s| s.to_str()),
+  ) else {
+    return;
+  };
+  let base = ["-Regular", "-regular", "Regular"]
+    .iter()
+    .find_map(|suffix| stem.strip_suffix(suffix))
+    .unwrap_or(stem);
+  let variants = [
+    (TokenStyle::BOLD, &["Bold"][..]),
+    (TokenStyle::ITALIC, &["Italic", "Oblique"][..]),
+    (
+      TokenStyle::BOLD | TokenStyle::ITALIC,
+      &["BoldItalic", "BoldOblique"][..],
+    ),
+  ];
+  for (style, suffixes) in variants {
+    let candidate = suffixes
+      .iter()
+      .flat_map(|suffix| [suffix.to_string(), suffix.to_lowercase()])
+      .map(|suffix| path.with_file_name(format!("{}-{}.{}", base, suffix, extension)))
+      .find(|candidate| candidate.exists());
+    if let Some(candidate) = candidate
+      && let Ok(font_data) = std::fs::read(candidate)
+    {
+      let _ = font_manager.add_style_variant(style, font_data, 0, features);
+    }
+  }
+}
+
+/// Fira Mono, compiled in so fresh machines and containers still render
+#[cfg(feature = "embedded-font")]
+const EMBEDDED_FONT: &[u8] = include_bytes!("../fonts/fira-mono-medium.ttf");
+
+#[cfg(feature = "embedded-font")]
+pub(crate) fn create_fallback_font(size: f32, features: &[FontFeature]) -> Result<FontManager> {
+  FontManager::new(EMBEDDED_FONT, 0, size, features)
+}
+
+#[cfg(not(feature = "embedded-font"))]
+pub(crate) fn create_fallback_font(_size: f32, _features: &[FontFeature]) -> Result<FontManager> {
   Err(anyhow!(
-    "No suitable font found. Please place a TTF font in assets/fonts/ directory.\n\
-         Recommended: JetBrains Mono, Fira Code, or any monospace programming font."
+    "No suitable font found. Install a monospace font such as JetBrains Mono or \
+     Fira Code, or build with the `embedded-font` feature."
   ))
 }
 
 /// Thai font paths to try for cosmic-text complex script rendering
+#[cfg(feature = "complex-scripts")]
 pub const THAI_FONT_PATHS: &[&str] = &[
   // macOS system Thai fonts
   "/System/Library/Fonts/Thonburi.ttc",
@@ -176,3 +930,83 @@ pub const THAI_FONT_PATHS: &[&str] = &[
   "./fonts/NotoSansThai-Regular.ttf",
   "./fonts/Sarabun-Regular.ttf",
 ];
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_font_feature_parsing() {
+    let zero = FontFeature::parse("zero").unwrap();
+    assert_eq!(&zero.tag, b"zero");
+    assert_eq!(zero.value, 1);
+    assert_eq!(FontFeature::parse("calt=0").unwrap().value, 0);
+    assert_eq!(FontFeature::parse("-liga").unwrap().value, 0);
+    assert_eq!(FontFeature::parse("cv01=2").unwrap().value, 2);
+    assert!(FontFeature::parse("stylistic").is_err());
+    assert!(FontFeature::parse("ss01=on").is_err());
+  }
+
+  #[test]
+  #[cfg(feature = "embedded-font")]
+  fn test_embedded_font_loads() {
+    let font_manager = create_fallback_font(16.0, &[]).unwrap();
+    assert!(font_manager.has_glyph('a') && font_manager.has_glyph('{'));
+  }
+
+  #[test]
+  #[cfg(feature = "embedded-font")]
+  fn test_fallback_chain_fills_missing_glyphs() {
+    let mut font_manager = create_fallback_font(16.0, &[]).unwrap();
+    font_manager.add_fallback_families(["No Such Family 1234"]);
+    assert!(font_manager.fallbacks.is_empty());
+    assert!(!font_manager.has_glyph('∰'));
+    // Only checkable where the family is installed
+    if resolve_family("DejaVu Sans").is_some() {
+      font_manager.add_fallback_families(["dejavu sans", "DejaVu Sans"]);
+      assert_eq!(font_manager.fallbacks.len(), 1);
+      assert!(font_manager.has_glyph('∰'));
+    }
+  }
+
+  #[test]
+  #[cfg(feature = "embedded-font")]
+  fn test_shaping_matches_per_character_layout_in_monospace() {
+    let mut font_manager = create_fallback_font(16.0, &[]).unwrap();
+    let text = "let x = a -> b; // ok";
+    let shaped = font_manager.measure_text(text, TokenStyle::REGULAR, None);
+    assert_eq!(
+      font_manager
+        .render_styled_text(text, TokenStyle::REGULAR, None)
+        .len(),
+      text.chars().count()
+    );
+    font_manager.set_ligatures(false);
+    assert_eq!(
+      font_manager.measure_text(text, TokenStyle::REGULAR, None),
+      shaped
+    );
+  }
+
+  #[test]
+  #[cfg(all(feature = "embedded-font", feature = "shaping"))]
+  fn test_shaping_forms_ligatures() {
+    // Fira Mono joins letters only under its discretionary ligatures
+    let dlig = [FontFeature::parse("dlig").unwrap()];
+    let mut font_manager = create_fallback_font(16.0, &dlig).unwrap();
+    let glyphs = |font_manager: &FontManager| {
+      font_manager
+        .render_styled_text("fl", TokenStyle::REGULAR, None)
+        .len()
+    };
+    assert_eq!(glyphs(&font_manager), 1);
+    font_manager.set_ligatures(false);
+    assert_eq!(glyphs(&font_manager), 2);
+  }
+
+  #[test]
+  fn test_resolve_family_rejects_unknown_names() {
+    assert_eq!(resolve_family(""), None);
+    assert_eq!(resolve_family("No Such Family 1234"), None);
+  }
+}
@@ -1,11 +1,19 @@
 /* ~~/src/layout.rs */
 
+#[cfg(feature = "complex-scripts")]
 use anyhow::Result;
+#[cfg(feature = "complex-scripts")]
 use cosmic_text::fontdb;
-use cosmic_text::{Attrs, Buffer, Color, FontSystem, Metrics, Shaping, SwashCache};
+#[cfg(feature = "complex-scripts")]
+use cosmic_text::{
+  Attrs, Buffer, Color, Family, FontSystem, Metrics, Shaping, Style, SwashCache, Weight,
+};
+#[cfg(feature = "complex-scripts")]
 use image::{Rgba, RgbaImage};
 
-use crate::font::THAI_FONT_PATHS;
+#[cfg(feature = "complex-scripts")]
+use crate::font::{THAI_FONT_PATHS, underline_metrics};
+#[cfg(feature = "complex-scripts")]
 use crate::syntax::HighlightedToken;
 
 /// Check if a line contains complex scripts requiring shaping
@@ -59,16 +67,20 @@ pub fn has_complex_script(text: &str) -> bool {
 
 /// Renderer for complex text using cosmic-text
 /// Handles Thai, Arabic, Indic scripts with proper shaping
+#[cfg(feature = "complex-scripts")]
 pub struct ComplexTextRenderer {
   font_system: FontSystem,
   swash_cache: SwashCache,
   metrics: Metrics,
+  /// Installed family to shape with, cosmic-text's default when unset
+  family: Option<String>,
 }
 
+#[cfg(feature = "complex-scripts")]
 impl ComplexTextRenderer {
-  /// Create a new complex text renderer with the given font size
+  /// Create a new complex text renderer with the given font size and family
   /// Explicitly loads Thai fonts for proper fallback support
-  pub fn new(font_size: f32) -> Result<Self> {
+  pub fn new(font_size: f32, family: Option<String>) -> Result<Self> {
     // Start with system fonts, then add explicit Thai font files when available.
     // This avoids disabling shaping on platforms where Thai fonts live in
     // different directories than our hardcoded fallback paths.
@@ -83,10 +95,12 @@ impl ComplexTextRenderer {
       font_system,
       swash_cache,
       metrics,
+      family,
     })
   }
 
-  /// Render a line of highlighted tokens with complex script support
+  /// Render a line of highlighted tokens with complex script support, at
+  /// `opacity` out of 255
   /// Returns the total width consumed
   pub fn render_line(
     &mut self,
@@ -94,6 +108,7 @@ impl ComplexTextRenderer {
     tokens: &[HighlightedToken],
     x: u32,
     y: u32,
+    opacity: u8,
   ) -> Result<u32> {
     let mut buffer = Buffer::new(&mut self.font_system, self.metrics);
 
@@ -107,7 +122,20 @@ impl ComplexTextRenderer {
       }
       has_text = true;
       let (r, g, b) = token.color.rgb;
-      let attrs = Attrs::new().color(Color::rgba(r, g, b, 255));
+      let mut attrs = Attrs::new().color(Color::rgba(r, g, b, 255));
+      if let Some(family) = &self.family {
+        attrs = attrs.family(Family::Name(family));
+      }
+      if token.style.is_bold() {
+        attrs = attrs.weight(Weight::BOLD);
+      }
+      if token.style.is_italic() {
+        attrs = attrs.style(Style::Italic);
+      }
+      // Glyph metadata marks underlined spans for the pass below
+      if token.style.is_underline() {
+        attrs = attrs.metadata(1);
+      }
       spans.push((token.text.as_str(), attrs));
     }
 
@@ -130,6 +158,7 @@ impl ComplexTextRenderer {
     let img_width = image.width() as i32;
     let img_height = image.height() as i32;
     let mut max_width = 0.0f32;
+    let (underline_offset, underline_thickness) = underline_metrics(self.metrics.font_size);
 
     for run in buffer.layout_runs() {
       let baseline_offset_y = y as f32 - run.line_y;
@@ -155,12 +184,31 @@ impl ComplexTextRenderer {
             if pixel_x < 0 || pixel_x >= img_width || pixel_y < 0 || pixel_y >= img_height {
               return;
             }
+            // swash hands over coverage as the alpha, ignoring the span's own
+            let alpha = (color.a() as u16 * opacity as u16 / 255) as u8;
+            let color = Color::rgba(color.r(), color.g(), color.b(), alpha);
 
             let existing = image.get_pixel(pixel_x as u32, pixel_y as u32);
             let blended = blend_color_pixel(*existing, color);
             image.put_pixel(pixel_x as u32, pixel_y as u32, blended);
           },
         );
+
+        if layout_glyph.metadata == 1 {
+          let underline = Color::rgba(glyph_color.r(), glyph_color.g(), glyph_color.b(), opacity);
+          let left = (x as f32 + layout_glyph.x).round() as i32;
+          let right = (x as f32 + layout_glyph.x + layout_glyph.w).round() as i32;
+          let top = y as i32 + underline_offset;
+          for pixel_y in top..top + underline_thickness as i32 {
+            for pixel_x in left..right {
+              if (0..img_width).contains(&pixel_x) && (0..img_height).contains(&pixel_y) {
+                let existing = image.get_pixel(pixel_x as u32, pixel_y as u32);
+                let blended = blend_color_pixel(*existing, underline);
+                image.put_pixel(pixel_x as u32, pixel_y as u32, blended);
+              }
+            }
+          }
+        }
       }
     }
 
@@ -169,6 +217,7 @@ impl ComplexTextRenderer {
 }
 
 /// Blend a pixel with rendered glyph color using alpha
+#[cfg(feature = "complex-scripts")]
 fn blend_color_pixel(background: Rgba<u8>, foreground: Color) -> Rgba<u8> {
   let alpha = foreground.a();
   if alpha == 255 {
@@ -191,14 +240,15 @@ fn blend_color_pixel(background: Rgba<u8>, foreground: Color) -> Rgba<u8> {
 
 /// Load Thai fonts for cosmic-text FontSystem
 /// Returns a vector of fontdb::Source for all available Thai fonts
+#[cfg(feature = "complex-scripts")]
 fn load_thai_fonts() -> Vec<fontdb::Source> {
   let mut sources = Vec::new();
 
   for path in THAI_FONT_PATHS {
-    if std::path::Path::new(path).exists() {
-      if let Ok(data) = std::fs::read(path) {
-        sources.push(fontdb::Source::Binary(std::sync::Arc::new(data)));
-      }
+    if std::path::Path::new(path).exists()
+      && let Ok(data) = std::fs::read(path)
+    {
+      sources.push(fontdb::Source::Binary(std::sync::Arc::new(data)));
     }
   }
 
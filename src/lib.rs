@@ -1,14 +1,27 @@
 /* ~~/src/lib.rs */
 
-use std::os::raw::{c_char, c_int};
+use std::os::raw::{c_char, c_int, c_uint};
 
+mod animation;
+mod build_info;
+#[cfg(feature = "cli")]
+pub mod cli;
 mod config;
+mod detect;
 mod font;
 mod layout;
+mod limits;
+mod profiles;
+mod reflow;
 mod renderer;
+#[cfg(feature = "cli")]
+mod rpc;
 mod safe_ffi;
 mod syntax;
+mod tempfiles;
+mod terminal;
 mod themes;
+mod warnings;
 
 /// FFI function to generate a code snippet image
 /// Returns a base64-encoded PNG image as a C string
@@ -22,6 +35,258 @@ pub extern "C" fn generate_snippet_image(
   safe_ffi::safe_generate_snippet_image(code, language, theme, config_json)
 }
 
+/// FFI function to generate a code snippet image along with its metadata
+/// Returns JSON {image, width, height, line_count, render_ms, theme, truncated}
+/// as a C string, where image is a base64-encoded PNG
+#[unsafe(no_mangle)]
+pub extern "C" fn generate_snippet_image_with_metadata(
+  code: *const c_char,
+  language: *const c_char,
+  theme: *const c_char,
+  config_json: *const c_char,
+) -> *mut c_char {
+  safe_ffi::safe_generate_snippet_image_with_metadata(code, language, theme, config_json)
+}
+
+/// FFI function to render a snippet as an inline image escape sequence for
+/// the terminal, in the `kitty`, `iterm2` or `sixel` protocol, or detected
+/// from the environment when the protocol is null or `auto`
+/// Returns the escape sequence as a C string
+#[unsafe(no_mangle)]
+pub extern "C" fn generate_terminal_preview(
+  code: *const c_char,
+  language: *const c_char,
+  theme: *const c_char,
+  config_json: *const c_char,
+  protocol: *const c_char,
+) -> *mut c_char {
+  safe_ffi::safe_generate_terminal_preview(code, language, theme, config_json, protocol)
+}
+
+/// FFI function to render tokens and colors captured from the editor's own
+/// highlighting, skipping the built-in highlighters
+/// Returns a base64-encoded PNG image as a C string
+#[unsafe(no_mangle)]
+pub extern "C" fn generate_image_from_tokens(
+  tokens_json: *const c_char,
+  theme: *const c_char,
+  config_json: *const c_char,
+) -> *mut c_char {
+  safe_ffi::safe_generate_image_from_tokens(tokens_json, theme, config_json)
+}
+
+/// FFI function to render only the backdrop, at `width`x`height` pixels
+/// Returns a base64-encoded PNG image as a C string
+#[unsafe(no_mangle)]
+pub extern "C" fn generate_backdrop(
+  theme: *const c_char,
+  config_json: *const c_char,
+  width: c_uint,
+  height: c_uint,
+) -> *mut c_char {
+  safe_ffi::safe_generate_backdrop(theme, config_json, width, height)
+}
+
+/// FFI function to store a named base config; a null config removes the profile
+/// Returns 1 on success, 0 if the config is invalid or the profile is missing
+#[unsafe(no_mangle)]
+pub extern "C" fn set_profile(name: *const c_char, config_json: *const c_char) -> c_int {
+  safe_ffi::safe_set_profile(name, config_json)
+}
+
+/// FFI function to render with a stored profile and deep-merged overrides
+/// Returns base64-encoded PNG data URL as a C string
+#[unsafe(no_mangle)]
+pub extern "C" fn render_with_profile(
+  code: *const c_char,
+  language: *const c_char,
+  theme: *const c_char,
+  profile: *const c_char,
+  overrides_json: *const c_char,
+) -> *mut c_char {
+  safe_ffi::safe_render_with_profile(code, language, theme, profile, overrides_json)
+}
+
+/// FFI function to list stored profile names as a JSON array
+#[unsafe(no_mangle)]
+pub extern "C" fn get_profiles() -> *mut c_char {
+  safe_ffi::safe_get_profiles()
+}
+
+/// FFI function to render a long snippet as several page images
+/// Returns a JSON array of base64-encoded PNG data URLs as a C string
+#[unsafe(no_mangle)]
+pub extern "C" fn generate_snippet_carousel(
+  code: *const c_char,
+  language: *const c_char,
+  theme: *const c_char,
+  config_json: *const c_char,
+) -> *mut c_char {
+  safe_ffi::safe_generate_snippet_carousel(code, language, theme, config_json)
+}
+
+/// FFI function to render several files as browser-style tabs
+/// Returns base64-encoded PNG data URL as a C string
+#[unsafe(no_mangle)]
+pub extern "C" fn generate_tabbed_image(
+  files_json: *const c_char,
+  theme: *const c_char,
+  config_json: *const c_char,
+) -> *mut c_char {
+  safe_ffi::safe_generate_tabbed_image(files_json, theme, config_json)
+}
+
+/// FFI function to export a video that scrolls through a long snippet
+/// Returns the output path as a C string, or null on failure
+#[unsafe(no_mangle)]
+pub extern "C" fn export_scrolling_video(
+  code: *const c_char,
+  language: *const c_char,
+  theme: *const c_char,
+  config_json: *const c_char,
+  output_path: *const c_char,
+) -> *mut c_char {
+  safe_ffi::safe_export_scrolling_video(code, language, theme, config_json, output_path)
+}
+
+/// FFI function to write a snippet as a one-page PDF
+/// Returns the output path as a C string, or null on failure
+#[unsafe(no_mangle)]
+pub extern "C" fn export_snippet_pdf(
+  code: *const c_char,
+  language: *const c_char,
+  theme: *const c_char,
+  config_json: *const c_char,
+  output_path: *const c_char,
+) -> *mut c_char {
+  safe_ffi::safe_export_snippet_pdf(code, language, theme, config_json, output_path)
+}
+
+/// FFI function to write an animation as numbered PNG frames into a directory
+/// Returns the frame pattern, count, and size as a JSON C string
+#[unsafe(no_mangle)]
+pub extern "C" fn export_animation_frames(
+  code: *const c_char,
+  language: *const c_char,
+  theme: *const c_char,
+  config_json: *const c_char,
+  spec_json: *const c_char,
+  output_dir: *const c_char,
+) -> *mut c_char {
+  safe_ffi::safe_export_animation_frames(code, language, theme, config_json, spec_json, output_dir)
+}
+
+/// FFI function to encode a GIF or APNG, by the output's extension, of a
+/// snippet being typed out
+/// Returns the output path as a C string, or null on failure
+#[unsafe(no_mangle)]
+pub extern "C" fn export_typing_animation(
+  code: *const c_char,
+  language: *const c_char,
+  theme: *const c_char,
+  config_json: *const c_char,
+  spec_json: *const c_char,
+  output_path: *const c_char,
+) -> *mut c_char {
+  safe_ffi::safe_export_typing_animation(code, language, theme, config_json, spec_json, output_path)
+}
+
+/// FFI function to encode a GIF morphing a snippet between two themes
+/// Returns the output path as a C string, or null on failure
+#[unsafe(no_mangle)]
+pub extern "C" fn export_theme_transition(
+  code: *const c_char,
+  language: *const c_char,
+  from_theme: *const c_char,
+  to_theme: *const c_char,
+  config_json: *const c_char,
+  output_path: *const c_char,
+) -> *mut c_char {
+  safe_ffi::safe_export_theme_transition(
+    code,
+    language,
+    from_theme,
+    to_theme,
+    config_json,
+    output_path,
+  )
+}
+
+/// FFI function to measure a snippet without rendering it
+/// Returns image, panel, and line metrics as a JSON C string
+#[unsafe(no_mangle)]
+pub extern "C" fn measure_snippet(
+  code: *const c_char,
+  language: *const c_char,
+  config_json: *const c_char,
+) -> *mut c_char {
+  safe_ffi::safe_measure_snippet(code, language, config_json)
+}
+
+/// FFI function to highlight code without rendering it
+/// Returns one array of tokens per line, each with text, fg, bold, italic,
+/// underline, group, and byte offsets, as a JSON C string
+#[unsafe(no_mangle)]
+pub extern "C" fn highlight_code(
+  code: *const c_char,
+  language: *const c_char,
+  theme: *const c_char,
+) -> *mut c_char {
+  safe_ffi::safe_highlight_code(code, language, theme)
+}
+
+/// FFI function to dump the highlighted token stream for debugging themes
+/// Returns language, highlighter, and tokens with scope, rule, and color as a JSON C string
+#[unsafe(no_mangle)]
+pub extern "C" fn dump_tokens(
+  code: *const c_char,
+  language: *const c_char,
+  theme: *const c_char,
+  config_json: *const c_char,
+) -> *mut c_char {
+  safe_ffi::safe_dump_tokens(code, language, theme, config_json)
+}
+
+/// FFI function to start highlighting `code` incrementally as it is edited
+/// Returns a handle for the other stream calls, or 0 on failure
+#[unsafe(no_mangle)]
+pub extern "C" fn open_highlight_stream(
+  code: *const c_char,
+  language: *const c_char,
+  theme: *const c_char,
+  config_json: *const c_char,
+) -> c_uint {
+  safe_ffi::safe_open_highlight_stream(code, language, theme, config_json)
+}
+
+/// FFI function to replace lines `start` to `end` (0-based, end exclusive) of a
+/// stream with the lines of `text`, or delete them when `text` is null; the
+/// lines are highlighted on the next poll
+/// Returns 1 on success, 0 for an unknown handle or out of range lines
+#[unsafe(no_mangle)]
+pub extern "C" fn edit_highlight_stream(
+  handle: c_uint,
+  start: c_uint,
+  end: c_uint,
+  text: *const c_char,
+) -> c_int {
+  safe_ffi::safe_edit_highlight_stream(handle, start, end, text)
+}
+
+/// FFI function to highlight up to `max_lines` edited lines (0 for all)
+/// Returns the lines with their tokens and whether more are pending as JSON
+#[unsafe(no_mangle)]
+pub extern "C" fn poll_highlight_stream(handle: c_uint, max_lines: c_uint) -> *mut c_char {
+  safe_ffi::safe_poll_highlight_stream(handle, max_lines)
+}
+
+/// FFI function to release a highlight stream
+/// Returns 1 if the stream was open, 0 otherwise
+#[unsafe(no_mangle)]
+pub extern "C" fn close_highlight_stream(handle: c_uint) -> c_int {
+  safe_ffi::safe_close_highlight_stream(handle)
+}
+
 /// FFI function to free memory allocated by generate_snippet_image
 #[unsafe(no_mangle)]
 pub extern "C" fn free_string(s: *mut c_char) {
@@ -34,8 +299,123 @@ pub extern "C" fn get_available_themes() -> *mut c_char {
   safe_ffi::safe_get_available_themes()
 }
 
+/// FFI function to get one theme's palette, font styles, and chrome colors
+/// Returns the theme as a JSON C string
+#[unsafe(no_mangle)]
+pub extern "C" fn get_theme_details(name: *const c_char) -> *mut c_char {
+  safe_ffi::safe_get_theme_details(name)
+}
+
+/// FFI function to check a theme's text colors against WCAG contrast levels
+/// on `background`, a hex color, or the theme's own background when null
+/// Returns the report as a JSON C string
+#[unsafe(no_mangle)]
+pub extern "C" fn validate_theme(name: *const c_char, background: *const c_char) -> *mut c_char {
+  safe_ffi::safe_validate_theme(name, background)
+}
+
+/// FFI function to build a theme from a JSON map of Neovim highlight groups,
+/// registering it for later renders when `name` isn't null
+/// Returns the theme as a JSON C string
+#[unsafe(no_mangle)]
+pub extern "C" fn theme_from_highlights(
+  groups_json: *const c_char,
+  name: *const c_char,
+) -> *mut c_char {
+  safe_ffi::safe_theme_from_highlights(groups_json, name)
+}
+
+/// FFI function to load a VS Code color theme JSON file, registering it for
+/// later renders when `name` isn't null
+/// Returns the theme as a JSON C string
+#[unsafe(no_mangle)]
+pub extern "C" fn import_vscode_theme(path: *const c_char, name: *const c_char) -> *mut c_char {
+  safe_ffi::safe_import_vscode_theme(path, name)
+}
+
+/// FFI function to load a base16 or base24 YAML scheme, registering it for
+/// later renders when `name` isn't null
+/// Returns the theme as a JSON C string
+#[unsafe(no_mangle)]
+pub extern "C" fn import_base16_scheme(path: *const c_char, name: *const c_char) -> *mut c_char {
+  safe_ffi::safe_import_base16_scheme(path, name)
+}
+
+/// FFI function to load the theme files in `dir`, or rescan the directory
+/// last loaded when it's null, replacing the themes found before
+/// Returns a JSON array of the theme names loaded
+#[unsafe(no_mangle)]
+pub extern "C" fn reload_themes(dir: *const c_char) -> *mut c_char {
+  safe_ffi::safe_reload_themes(dir)
+}
+
+/// FFI function to get built-in config presets
+#[unsafe(no_mangle)]
+pub extern "C" fn get_available_presets() -> *mut c_char {
+  safe_ffi::safe_get_available_presets()
+}
+
+/// FFI function to get named backdrop gradients
+/// Returns a JSON array of `{name, colors, direction}` objects
+#[unsafe(no_mangle)]
+pub extern "C" fn get_gradient_presets() -> *mut c_char {
+  safe_ffi::safe_get_gradient_presets()
+}
+
+/// FFI function to describe the build: version, commit, features, and formats
+/// Returns a JSON object as a C string
+#[unsafe(no_mangle)]
+pub extern "C" fn get_build_info() -> *mut c_char {
+  safe_ffi::safe_get_build_info()
+}
+
+/// FFI function to take queued warnings, such as deprecated config fields
+/// Returns a JSON array of messages and clears the queue
+#[unsafe(no_mangle)]
+pub extern "C" fn get_warnings() -> *mut c_char {
+  safe_ffi::safe_get_warnings()
+}
+
+/// FFI function to load `.sublime-syntax` files from a directory into later renders
+/// Returns 1 on success, 0 if the directory holds no loadable syntaxes
+#[unsafe(no_mangle)]
+pub extern "C" fn add_syntax_directory(path: *const c_char) -> c_int {
+  safe_ffi::safe_add_syntax_directory(path)
+}
+
 /// FFI function to validate language support
 #[unsafe(no_mangle)]
 pub extern "C" fn is_language_supported(language: *const c_char) -> c_int {
   safe_ffi::safe_is_language_supported(language)
 }
+
+/// FFI function to cap concurrent renders, canvas pixels, and cache size (0 disables a limit)
+#[unsafe(no_mangle)]
+pub extern "C" fn set_resource_limits(
+  max_concurrent: c_uint,
+  max_pixels: u64,
+  max_cache_mb: c_uint,
+) {
+  safe_ffi::safe_set_resource_limits(max_concurrent, max_pixels, max_cache_mb);
+}
+
+/// FFI function to stage exports under `dir` (the system temp directory when
+/// null or empty) and remove staged files older than `cleanup_older_than` seconds
+/// Returns 1 on success, 0 on invalid input
+#[unsafe(no_mangle)]
+pub extern "C" fn set_temp_policy(dir: *const c_char, cleanup_older_than: c_uint) -> c_int {
+  safe_ffi::safe_set_temp_policy(dir, cleanup_older_than)
+}
+
+/// FFI function to delete every staged temp file not in use
+/// Returns the number of files and bytes removed as JSON
+#[unsafe(no_mangle)]
+pub extern "C" fn purge_cache() -> *mut c_char {
+  safe_ffi::safe_purge_cache()
+}
+
+/// FFI function to get configured limits and render counters as JSON
+#[unsafe(no_mangle)]
+pub extern "C" fn get_resource_usage() -> *mut c_char {
+  safe_ffi::safe_get_resource_usage()
+}
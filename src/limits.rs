@@ -0,0 +1,211 @@
+/* ~~/src/limits.rs */
+
+// standard crates
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::Instant;
+
+// third-party crates
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+
+// Limits; zero means unlimited
+static MAX_CONCURRENT: AtomicUsize = AtomicUsize::new(0);
+static MAX_PIXELS: AtomicU64 = AtomicU64::new(0);
+static MAX_CACHE_MB: AtomicU64 = AtomicU64::new(0);
+
+static COUNTERS: Counters = Counters::new();
+
+/// Usage counters every render guard reports into
+struct Counters {
+  active_renders: AtomicUsize,
+  completed_renders: AtomicU64,
+  failed_renders: AtomicU64,
+  rejected_renders: AtomicU64,
+  pixels_rendered: AtomicU64,
+  peak_pixels: AtomicU64,
+  render_time_ms: AtomicU64,
+}
+
+impl Counters {
+  const fn new() -> Self {
+    Self {
+      active_renders: AtomicUsize::new(0),
+      completed_renders: AtomicU64::new(0),
+      failed_renders: AtomicU64::new(0),
+      rejected_renders: AtomicU64::new(0),
+      pixels_rendered: AtomicU64::new(0),
+      peak_pixels: AtomicU64::new(0),
+      render_time_ms: AtomicU64::new(0),
+    }
+  }
+}
+
+/// Snapshot of configured limits and usage counters, serialized for the FFI
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceUsage {
+  pub max_concurrent: usize,
+  pub max_pixels: u64,
+  pub max_cache_mb: u64,
+  pub active_renders: usize,
+  pub completed_renders: u64,
+  pub failed_renders: u64,
+  pub rejected_renders: u64,
+  pub pixels_rendered: u64,
+  pub peak_pixels: u64,
+  pub render_time_ms: u64,
+}
+
+/// Configure process-wide limits; pass zero for any limit to disable it
+pub fn set_limits(max_concurrent: usize, max_pixels: u64, max_cache_mb: u64) {
+  MAX_CONCURRENT.store(max_concurrent, Ordering::SeqCst);
+  MAX_PIXELS.store(max_pixels, Ordering::SeqCst);
+  MAX_CACHE_MB.store(max_cache_mb, Ordering::SeqCst);
+}
+
+/// Size the temp directory is trimmed to, 0 when unlimited
+pub fn max_cache_bytes() -> u64 {
+  MAX_CACHE_MB.load(Ordering::SeqCst) * 1024 * 1024
+}
+
+pub fn usage() -> ResourceUsage {
+  ResourceUsage {
+    max_concurrent: MAX_CONCURRENT.load(Ordering::SeqCst),
+    max_pixels: MAX_PIXELS.load(Ordering::SeqCst),
+    max_cache_mb: MAX_CACHE_MB.load(Ordering::SeqCst),
+    active_renders: COUNTERS.active_renders.load(Ordering::SeqCst),
+    completed_renders: COUNTERS.completed_renders.load(Ordering::SeqCst),
+    failed_renders: COUNTERS.failed_renders.load(Ordering::SeqCst),
+    rejected_renders: COUNTERS.rejected_renders.load(Ordering::SeqCst),
+    pixels_rendered: COUNTERS.pixels_rendered.load(Ordering::SeqCst),
+    peak_pixels: COUNTERS.peak_pixels.load(Ordering::SeqCst),
+    render_time_ms: COUNTERS.render_time_ms.load(Ordering::SeqCst),
+  }
+}
+
+/// RAII slot for an in-flight render; counts the render as failed unless
+/// `complete` is called or its canvas was rejected before the guard is dropped
+pub struct RenderGuard {
+  counters: &'static Counters,
+  max_pixels: u64,
+  started: Instant,
+  completed: bool,
+  /// Already counted as rejected, so not as failed too
+  rejected: AtomicBool,
+}
+
+impl RenderGuard {
+  pub fn acquire() -> Result<Self> {
+    Self::acquire_with(
+      &COUNTERS,
+      MAX_CONCURRENT.load(Ordering::SeqCst),
+      MAX_PIXELS.load(Ordering::SeqCst),
+    )
+  }
+
+  fn acquire_with(
+    counters: &'static Counters,
+    max_concurrent: usize,
+    max_pixels: u64,
+  ) -> Result<Self> {
+    let acquired =
+      counters
+        .active_renders
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |active| {
+          if max_concurrent == 0 || active < max_concurrent {
+            Some(active + 1)
+          } else {
+            None
+          }
+        });
+    if acquired.is_err() {
+      counters.rejected_renders.fetch_add(1, Ordering::SeqCst);
+      return Err(anyhow!(
+        "Too many concurrent renders (limit: {})",
+        max_concurrent
+      ));
+    }
+    Ok(Self {
+      counters,
+      max_pixels,
+      started: Instant::now(),
+      completed: false,
+      rejected: AtomicBool::new(false),
+    })
+  }
+
+  /// Reject canvases larger than the configured pixel budget before allocating them
+  pub fn check_pixels(&self, width: u32, height: u32) -> Result<()> {
+    let pixels = width as u64 * height as u64;
+    if self.max_pixels != 0 && pixels > self.max_pixels {
+      // A render asking for several canvases is still one rejected render
+      if !self.rejected.swap(true, Ordering::SeqCst) {
+        self
+          .counters
+          .rejected_renders
+          .fetch_add(1, Ordering::SeqCst);
+      }
+      return Err(anyhow!(
+        "Canvas of {}x{} exceeds pixel limit of {}",
+        width,
+        height,
+        self.max_pixels
+      ));
+    }
+    self
+      .counters
+      .pixels_rendered
+      .fetch_add(pixels, Ordering::SeqCst);
+    self
+      .counters
+      .peak_pixels
+      .fetch_max(pixels, Ordering::SeqCst);
+    Ok(())
+  }
+
+  pub fn complete(mut self) {
+    self.completed = true;
+  }
+}
+
+impl Drop for RenderGuard {
+  fn drop(&mut self) {
+    let counters = self.counters;
+    counters.active_renders.fetch_sub(1, Ordering::SeqCst);
+    let elapsed = self.started.elapsed().as_millis() as u64;
+    counters.render_time_ms.fetch_add(elapsed, Ordering::SeqCst);
+    if self.completed {
+      counters.completed_renders.fetch_add(1, Ordering::SeqCst);
+    } else if !self.rejected.load(Ordering::SeqCst) {
+      counters.failed_renders.fetch_add(1, Ordering::SeqCst);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_counts_each_render_once() {
+    // Counters of their own, as other tests render concurrently
+    static COUNTERS: Counters = Counters::new();
+    let count = |counter: &AtomicU64| counter.load(Ordering::SeqCst);
+
+    let guard = RenderGuard::acquire_with(&COUNTERS, 1, 100).unwrap();
+    assert!(RenderGuard::acquire_with(&COUNTERS, 1, 100).is_err());
+    assert!(guard.check_pixels(20, 20).is_err());
+    assert!(guard.check_pixels(20, 20).is_err());
+    drop(guard);
+    assert_eq!(count(&COUNTERS.rejected_renders), 2);
+    assert_eq!(count(&COUNTERS.failed_renders), 0);
+
+    let guard = RenderGuard::acquire_with(&COUNTERS, 1, 100).unwrap();
+    guard.check_pixels(10, 10).unwrap();
+    guard.complete();
+    drop(RenderGuard::acquire_with(&COUNTERS, 1, 100).unwrap());
+    assert_eq!(count(&COUNTERS.completed_renders), 1);
+    assert_eq!(count(&COUNTERS.failed_renders), 1);
+    assert_eq!(count(&COUNTERS.pixels_rendered), 100);
+    assert_eq!(COUNTERS.active_renders.load(Ordering::SeqCst), 0);
+  }
+}
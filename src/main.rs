@@ -0,0 +1,5 @@
+/* ~~/src/main.rs */
+
+fn main() -> std::process::ExitCode {
+  shoyu::cli::run(std::env::args_os().skip(1))
+}
@@ -0,0 +1,47 @@
+/* ~~/src/profiles.rs */
+
+// standard crates
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+// third-party crates
+use anyhow::{Result, anyhow};
+
+// local modules
+use crate::config::RenderConfig;
+
+/// Named base configs, kept as JSON so they merge with overrides field by field
+static PROFILES: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+/// Store `config_json` under `name`, replacing any profile already there
+pub fn set(name: &str, config_json: &str) -> Result<()> {
+  // Reject broken profiles now rather than on every render that uses them
+  RenderConfig::from_json(config_json)?;
+  let mut profiles = PROFILES.lock().unwrap_or_else(|e| e.into_inner());
+  profiles.insert(name.to_string(), config_json.to_string());
+  Ok(())
+}
+
+pub fn remove(name: &str) -> bool {
+  let mut profiles = PROFILES.lock().unwrap_or_else(|e| e.into_inner());
+  profiles.remove(name).is_some()
+}
+
+pub fn names() -> Vec<String> {
+  let profiles = PROFILES.lock().unwrap_or_else(|e| e.into_inner());
+  profiles.keys().cloned().collect()
+}
+
+/// Resolve a profile with per-call overrides deep-merged on top
+pub fn resolve(name: &str, overrides_json: Option<&str>) -> Result<RenderConfig> {
+  let profile = PROFILES
+    .lock()
+    .unwrap_or_else(|e| e.into_inner())
+    .get(name)
+    .cloned()
+    .ok_or_else(|| anyhow!("Unknown profile: {}", name))?;
+  match overrides_json {
+    Some(overrides) => RenderConfig::from_json_layers(&[&profile, overrides]),
+    None => RenderConfig::from_json(&profile),
+  }
+}
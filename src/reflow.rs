@@ -0,0 +1,131 @@
+/* ~~/src/reflow.rs */
+
+// standard crates
+use std::borrow::Cow;
+
+/// Line comment leaders by language, longest first so `///` wins over `//`
+fn comment_leaders(language: &str) -> &'static [&'static str] {
+  match language.to_lowercase().as_str() {
+    "rust" | "rs" => &["//!", "///", "//"],
+    "c" | "cpp" | "c++" | "cs" | "csharp" | "go" | "java" | "javascript" | "js" | "typescript"
+    | "ts" | "kotlin" | "kt" | "swift" | "scala" | "dart" | "zig" | "php" => &["///", "//"],
+    "python" | "py" | "ruby" | "rb" | "bash" | "sh" | "zsh" | "toml" | "yaml" | "yml" | "r"
+    | "perl" | "dockerfile" | "makefile" | "just" | "nix" | "elixir" | "ex" => &["#"],
+    "lua" | "sql" | "haskell" | "hs" | "elm" => &["---", "--"],
+    "clojure" | "clj" | "lisp" | "scheme" | "racket" => &[";;;", ";;", ";"],
+    "vim" => &["\""],
+    _ => &[],
+  }
+}
+
+/// Rewrap runs of line comments so no line runs past `text_width` columns,
+/// the way `gq` would. Code lines and blank comment lines, which separate
+/// paragraphs, are left as they are.
+pub fn reflow_comments<'a>(
+  code: &'a str,
+  language: &str,
+  text_width: u32,
+  tab_width: u32,
+) -> Cow<'a, str> {
+  let leaders = comment_leaders(language);
+  if leaders.is_empty() || text_width == 0 {
+    return Cow::Borrowed(code);
+  }
+
+  let mut output: Vec<String> = Vec::new();
+  // Indentation and leader of the paragraph being collected, with its words
+  let mut paragraph: Option<(String, &str, Vec<&str>)> = None;
+  let flush = |paragraph: &mut Option<(String, &str, Vec<&str>)>, output: &mut Vec<String>| {
+    if let Some((prefix, leader, words)) = paragraph.take() {
+      output.extend(wrap(&prefix, leader, &words, text_width, tab_width));
+    }
+  };
+
+  for line in code.lines() {
+    let body = line.trim_start();
+    let indent = &line[..line.len() - body.len()];
+    let comment = leaders.iter().find_map(|leader| {
+      let text = body.strip_prefix(leader)?;
+      // `//!` is not a `//` comment starting with "!"
+      (text.is_empty() || text.starts_with(' ')).then_some((*leader, text.trim()))
+    });
+    match comment {
+      Some((leader, text)) if !text.is_empty() => {
+        let continues = matches!(&paragraph, Some((prefix, current, _)) if prefix == indent && *current == leader);
+        if !continues {
+          flush(&mut paragraph, &mut output);
+          paragraph = Some((indent.to_string(), leader, Vec::new()));
+        }
+        if let Some((_, _, words)) = &mut paragraph {
+          words.extend(text.split_whitespace());
+        }
+      }
+      _ => {
+        flush(&mut paragraph, &mut output);
+        output.push(line.to_string());
+      }
+    }
+  }
+  flush(&mut paragraph, &mut output);
+
+  let mut reflowed = output.join("\n");
+  if code.ends_with('\n') {
+    reflowed.push('\n');
+  }
+  Cow::Owned(reflowed)
+}
+
+/// Greedily fill lines of `prefix` + `leader` + words; a word longer than
+/// the width gets a line of its own
+fn wrap(
+  prefix: &str,
+  leader: &str,
+  words: &[&str],
+  text_width: u32,
+  tab_width: u32,
+) -> Vec<String> {
+  let start = format!("{}{}", prefix, leader);
+  let start_width: usize = start
+    .chars()
+    .map(|ch| match ch {
+      '\t' => tab_width.max(1) as usize,
+      _ => 1,
+    })
+    .sum();
+  let mut lines = Vec::new();
+  let mut line = start.clone();
+  let mut width = start_width;
+  for word in words {
+    let word_width = word.chars().count();
+    if width > start_width && width + 1 + word_width > text_width as usize {
+      lines.push(std::mem::replace(&mut line, start.clone()));
+      width = start_width;
+    }
+    line.push(' ');
+    line.push_str(word);
+    width += 1 + word_width;
+  }
+  lines.push(line);
+  lines
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_reflows_comment_paragraphs() {
+    let code = "fn main() {\n    // one two three four five six\n    // seven\n    //\n    // eight\n    let x = 1; // trailing comments stay put\n}\n";
+    let reflowed = reflow_comments(code, "rust", 20, 4);
+    assert_eq!(
+      reflowed,
+      "fn main() {\n    // one two three\n    // four five six\n    // seven\n    //\n    // eight\n    let x = 1; // trailing comments stay put\n}\n"
+    );
+    // Doc comments and plain comments are separate paragraphs
+    assert_eq!(
+      reflow_comments("/// a b\n// c d\n", "rust", 80, 4),
+      "/// a b\n// c d\n"
+    );
+    assert_eq!(reflow_comments("# a\n", "text", 10, 4), "# a\n");
+  }
+}
@@ -0,0 +1,178 @@
+/* ~~/src/renderer/annotations.rs */
+
+// third-party crates
+use anyhow::Result;
+use image::{Rgba, RgbaImage};
+
+// local modules
+use crate::config::AnnotationSide;
+use crate::renderer::color::rgba_from_hex;
+use crate::renderer::{CodeLayout, SnippetRenderer};
+use crate::syntax::{HighlightedLine, TokenStyle};
+
+/// Length of the connector to the longest line, before export scaling
+const CONNECTOR_LENGTH: f32 = 32.0;
+
+impl SnippetRenderer {
+  /// Label per annotated line and side within the first `line_count` lines,
+  /// with several annotations on one line and side sharing a label
+  fn annotation_labels(&self, line_count: u32) -> Vec<(u32, AnnotationSide, String)> {
+    // The row counting lines past `max_lines` has nothing to point at
+    let line_count = line_count - (self.hidden_lines > 0) as u32;
+    // Between columns there is no free side for a label
+    if self.column_grid(line_count).0 > 1 {
+      return Vec::new();
+    }
+    let mut labels: Vec<(u32, AnnotationSide, String)> = Vec::new();
+    for annotation in &self.config.annotations {
+      if annotation.line == 0 || annotation.line > line_count {
+        continue;
+      }
+      let key = (annotation.line, annotation.side);
+      match labels
+        .iter_mut()
+        .find(|(line, side, _)| (*line, *side) == key)
+      {
+        Some((_, _, text)) => {
+          text.push_str(" · ");
+          text.push_str(&annotation.text);
+        }
+        None => labels.push((annotation.line, annotation.side, annotation.text.clone())),
+      }
+    }
+    labels
+  }
+
+  fn label_width(&self, text: &str) -> u32 {
+    self
+      .font_manager
+      .measure_text(text, TokenStyle::REGULAR, None)
+      + self.badge_padding() * 2
+  }
+
+  /// Space left between a connector's ends and what they point at
+  fn connector_gap(&self) -> u32 {
+    self
+      .font_manager
+      .measure_text(" ", TokenStyle::REGULAR, None)
+  }
+
+  /// Space between the code and a label column, connector included
+  fn connector_space(&self) -> u32 {
+    (CONNECTOR_LENGTH * self.config.export_size) as u32 + self.connector_gap() * 2
+  }
+
+  /// Widths of the label columns left and right of the code, 0 for a side
+  /// without annotations
+  pub(super) fn annotation_columns(&self, line_count: u32) -> (u32, u32) {
+    let mut columns = (0, 0);
+    for (_, side, text) in self.annotation_labels(line_count) {
+      let width = self.connector_space() + self.label_width(&text);
+      let column = match side {
+        AnnotationSide::Left => &mut columns.0,
+        AnnotationSide::Right => &mut columns.1,
+      };
+      *column = (*column).max(width);
+    }
+    columns
+  }
+
+  fn annotation_color(&self) -> Result<Rgba<u8>> {
+    rgba_from_hex(
+      self
+        .config
+        .annotation_color
+        .as_deref()
+        .unwrap_or(&self.theme.keyword.hex),
+    )
+  }
+
+  /// Draw each label in its column with a connector pointing at its line:
+  /// at the end of the line for the right column, and at the start of its
+  /// text for the left
+  pub(super) fn draw_annotations(
+    &self,
+    image: &mut RgbaImage,
+    layout: &CodeLayout,
+    highlighted_lines: &[HighlightedLine],
+  ) -> Result<()> {
+    let labels = self.annotation_labels(highlighted_lines.len() as u32);
+    if labels.is_empty() {
+      return Ok(());
+    }
+    let color = self.annotation_color()?;
+    let text_color = self.panel_background()?;
+    let code_x = layout.x
+      + self.config.get_scaled_padding().left
+      + self.gutter_width(layout.line_count)
+      + layout.indent;
+    let content_width = self
+      .widest_line(highlighted_lines)
+      .max(self.reserved_line_width);
+    let (gap, space, padding) = (
+      self.connector_gap(),
+      self.connector_space(),
+      self.badge_padding(),
+    );
+    let (ascent, descent) = self.font_manager.get_vertical_metrics();
+    let label_height = (ascent - descent).ceil() as u32 + padding;
+    let thickness = (1.5 * self.config.export_size).max(1.0);
+
+    for (line, side, text) in labels {
+      let index = line as usize - 1;
+      let highlighted_line = &highlighted_lines[index];
+      // Centered on the line's highlight strip
+      let middle = layout.baseline(index) as f32 - (ascent + descent) / 2.0;
+      let label_width = self.label_width(&text);
+      let (label_x, from, to) = match side {
+        AnnotationSide::Right => {
+          let label_x = code_x + content_width + space;
+          let line_end = code_x + self.line_width(highlighted_line);
+          (label_x, label_x - gap, line_end + gap)
+        }
+        AnnotationSide::Left => {
+          let label_x = code_x.saturating_sub(space + label_width);
+          let line_text: String = highlighted_line
+            .tokens
+            .iter()
+            .map(|token| token.text.as_str())
+            .collect();
+          let indent = line_text.len() - line_text.trim_start().len();
+          let text_start = code_x
+            + self
+              .font_manager
+              .measure_text(&line_text[..indent], TokenStyle::REGULAR, None);
+          (label_x, label_x + label_width + gap, text_start - gap)
+        }
+      };
+      self.draw_arrow(
+        image,
+        (from as f32, middle),
+        (to as f32, middle),
+        thickness,
+        color,
+      );
+      let label_y = (middle - label_height as f32 / 2.0).round() as i32;
+      self.draw_rounded_rect(
+        image,
+        label_x as i32,
+        label_y,
+        label_width,
+        label_height,
+        4.0,
+        color,
+      )?;
+      self.draw_text(
+        image,
+        &text,
+        label_x + padding,
+        (label_y + ascent.ceil() as i32 + padding as i32 / 2).max(0) as u32,
+        self.config.get_scaled_font_size(),
+        text_color,
+        TokenStyle::REGULAR,
+        None,
+      )?;
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,109 @@
+/* ~~/src/renderer/backdrop.rs */
+
+// third-party crates
+use anyhow::{Result, anyhow};
+use image::imageops::{self, FilterType};
+use image::{Rgba, RgbaImage};
+
+// local modules
+use crate::config::RenderConfig;
+use crate::renderer::SnippetRenderer;
+use crate::renderer::color::{blend_colors, rgba_from_hex};
+
+impl SnippetRenderer {
+  /// Fill the canvas with the backdrop image, the procedural pattern, the
+  /// gradient if enabled, or the theme background
+  pub(super) fn draw_backdrop(&self, image: &mut RgbaImage) -> Result<()> {
+    if let Some(backdrop) = &self.backdrop_image {
+      return self.draw_image_backdrop(image, backdrop);
+    }
+    let (width, height) = image.dimensions();
+    let blur = self.config.backdrop_blur.max(0.0) * self.config.export_size;
+    if let Some(pattern) = &self.config.backdrop {
+      self.draw_pattern_backdrop(image, pattern)?;
+      if blur > 0.0 {
+        *image = blurred(image, width, height, blur);
+      }
+      return Ok(());
+    }
+    #[cfg(feature = "gradient")]
+    if self.config.gradient_backdrop {
+      self.draw_gradient_backdrop(image, width, height)?;
+      if blur > 0.0 {
+        *image = blurred(image, width, height, blur);
+      }
+      return Ok(());
+    }
+    let background = rgba_from_hex(&self.theme.background.hex)?;
+    for pixel in image.pixels_mut() {
+      *pixel = background;
+    }
+    Ok(())
+  }
+
+  /// Decode `backdrop_image` once so every render reuses the pixels
+  pub(super) fn load_backdrop_image(config: &RenderConfig) -> Result<Option<RgbaImage>> {
+    let Some(path) = &config.backdrop_image else {
+      return Ok(None);
+    };
+    let image =
+      image::open(path).map_err(|e| anyhow!("Failed to load backdrop image '{}': {}", path, e))?;
+    Ok(Some(image.to_rgba8()))
+  }
+
+  /// Fill the canvas with the backdrop image scaled to cover it, then blur and
+  /// darken it so the panel stays readable on busy wallpapers
+  pub(super) fn draw_image_backdrop(
+    &self,
+    image: &mut RgbaImage,
+    source: &RgbaImage,
+  ) -> Result<()> {
+    let (width, height) = image.dimensions();
+    let blur = self.config.backdrop_blur.max(0.0) * self.config.export_size;
+    let backdrop = match blur > 0.0 {
+      true => blurred(source, width, height, blur),
+      false => cover(source, width, height),
+    };
+
+    // Transparent wallpapers sit on the theme background
+    let background = rgba_from_hex(&self.theme.background.hex)?;
+    let darken = self.config.backdrop_darken.clamp(0.0, 1.0);
+    for (target, pixel) in image.pixels_mut().zip(backdrop.pixels()) {
+      let opaque = Rgba([pixel[0], pixel[1], pixel[2], 255]);
+      let color = blend_colors(background, opaque, pixel[3] as f32 / 255.0);
+      *target = blend_colors(color, Rgba([0, 0, 0, 255]), darken);
+    }
+    Ok(())
+  }
+}
+
+/// `source` scaled to cover `width`x`height` and gaussian blurred by `radius`.
+/// The blur runs at reduced resolution: a wide kernel on the full canvas is
+/// slow, and the detail it would preserve is smeared away anyway.
+pub(super) fn blurred(source: &RgbaImage, width: u32, height: u32, radius: f32) -> RgbaImage {
+  let reduction = (radius / 4.0).max(1.0);
+  let small = cover(
+    source,
+    ((width as f32 / reduction) as u32).max(1),
+    ((height as f32 / reduction) as u32).max(1),
+  );
+  let small = imageops::blur(&small, radius / reduction);
+  imageops::resize(&small, width, height, FilterType::Triangle)
+}
+
+/// Scale `source` to fill `width`x`height`, cropping the overflow evenly from
+/// both sides of the longer axis
+fn cover(source: &RgbaImage, width: u32, height: u32) -> RgbaImage {
+  let scale = (width as f32 / source.width() as f32).max(height as f32 / source.height() as f32);
+  let crop_width = ((width as f32 / scale).round() as u32).clamp(1, source.width());
+  let crop_height = ((height as f32 / scale).round() as u32).clamp(1, source.height());
+  let cropped = imageops::crop_imm(
+    source,
+    (source.width() - crop_width) / 2,
+    (source.height() - crop_height) / 2,
+    crop_width,
+    crop_height,
+  )
+  .to_image();
+  imageops::resize(&cropped, width, height, FilterType::Triangle)
+}
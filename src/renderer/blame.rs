@@ -0,0 +1,129 @@
+/* ~~/src/renderer/blame.rs */
+
+// third-party crates
+use anyhow::Result;
+use image::RgbaImage;
+
+// local modules
+use crate::config::{Blame, BlameSide};
+use crate::renderer::{PanelRect, SnippetRenderer};
+use crate::syntax::TokenStyle;
+
+impl SnippetRenderer {
+  /// Blame entries for the first `line_count` lines, `None` when none of
+  /// them has one
+  fn shown_blame(&self, line_count: u32) -> Option<(&Blame, &[String])> {
+    let blame = self.config.blame.as_ref()?;
+    let lines = &blame.lines[..blame.lines.len().min(line_count as usize)];
+    lines
+      .iter()
+      .any(|line| !line.is_empty())
+      .then_some((blame, lines))
+  }
+
+  /// Width of the blame column on `side`, its spacing and rule included, 0
+  /// when it sits on the other side or has nothing to show
+  pub(super) fn blame_width(&self, line_count: u32, side: BlameSide) -> u32 {
+    let Some((blame, lines)) = self.shown_blame(line_count) else {
+      return 0;
+    };
+    if blame.side != side {
+      return 0;
+    }
+    let widest = lines
+      .iter()
+      .map(|line| {
+        self
+          .font_manager
+          .measure_text(line, TokenStyle::REGULAR, None)
+      })
+      .max()
+      .unwrap_or(0);
+    widest + self.gutter_spacing() * 2 + self.gutter_rule_width()
+  }
+
+  /// Rule between the blame column and the code in every column, spanning
+  /// the code area
+  pub(super) fn draw_blame_rule(
+    &self,
+    image: &mut RgbaImage,
+    panel: PanelRect,
+    line_count: u32,
+  ) -> Result<()> {
+    let Some((blame, _)) = self.shown_blame(line_count) else {
+      return Ok(());
+    };
+    let padding = self.config.get_scaled_padding();
+    let width = self.blame_width(line_count, blame.side);
+    let top = panel.y + self.top_chrome_height();
+    let height = (panel.y + panel.height).saturating_sub(top + self.bottom_chrome_height());
+    let color = self.chrome()?.gutter_rule;
+    for column in self.column_rects(panel, line_count) {
+      let rule_x = match blame.side {
+        BlameSide::Left => {
+          column.x + padding.left + width - self.gutter_spacing() - self.gutter_rule_width()
+        }
+        BlameSide::Right => {
+          (column.x + column.width).saturating_sub(padding.right + width - self.gutter_spacing())
+        }
+      };
+      self.fill_rect_in_panel(
+        image,
+        panel,
+        rule_x,
+        top,
+        self.gutter_rule_width(),
+        height,
+        color,
+      );
+    }
+    Ok(())
+  }
+
+  /// Draw a line's blame entry with its baseline at `y`, `panel_x` and
+  /// `panel_width` locating the panel; returns the width it takes before the
+  /// code
+  pub(super) fn draw_blame(
+    &self,
+    image: &mut RgbaImage,
+    line_index: usize,
+    line_count: u32,
+    panel_x: u32,
+    panel_width: u32,
+    y: u32,
+  ) -> Result<u32> {
+    let Some((blame, lines)) = self.shown_blame(line_count) else {
+      return Ok(0);
+    };
+    let width = self.blame_width(line_count, blame.side);
+    let padding = self.config.get_scaled_padding();
+    let x = match blame.side {
+      BlameSide::Left => panel_x + padding.left,
+      BlameSide::Right => {
+        (panel_x + panel_width).saturating_sub(padding.right + width)
+          + self.gutter_spacing() * 2
+          + self.gutter_rule_width()
+      }
+    };
+    if let Some(text) = lines.get(line_index)
+      && !text.is_empty()
+      && !self.is_marker_row(line_index, line_count)
+    {
+      let color = self.focus_color(line_index, self.chrome()?.line_numbers);
+      self.draw_text(
+        image,
+        text,
+        x,
+        y,
+        self.config.get_scaled_font_size(),
+        color,
+        TokenStyle::REGULAR,
+        None,
+      )?;
+    }
+    Ok(match blame.side {
+      BlameSide::Left => width,
+      BlameSide::Right => 0,
+    })
+  }
+}
@@ -0,0 +1,54 @@
+/* ~~/src/renderer/border.rs */
+
+// third-party crates
+use anyhow::Result;
+use image::{Rgba, RgbaImage};
+
+// local modules
+use crate::renderer::color::rgba_from_hex;
+use crate::renderer::{PanelRect, SnippetRenderer};
+
+impl SnippetRenderer {
+  /// Outline the panel along its rounded contour, with dividers where the
+  /// title bar or header meets the code and the code meets the footer
+  pub(super) fn draw_border(&self, image: &mut RgbaImage, panel: PanelRect) -> Result<()> {
+    let Some(border) = &self.config.border else {
+      return Ok(());
+    };
+    let width = (border.width * self.config.export_size).max(0.0);
+    if width == 0.0 {
+      return Ok(());
+    }
+    let color = rgba_from_hex(
+      border
+        .color
+        .as_deref()
+        .unwrap_or(&self.theme.foreground.hex),
+    )?;
+    let alpha = (255.0 * border.opacity.clamp(0.0, 1.0)).round() as u8;
+    let color = Rgba([color[0], color[1], color[2], alpha]);
+    self.stroke_panel(image, panel, width, color);
+
+    // Dividers stop short of the outline so the two don't double up
+    let thickness = width.round().max(1.0) as u32;
+    let (top, bottom) = (self.top_chrome_height(), self.bottom_chrome_height());
+    let mut dividers = Vec::new();
+    if top > 0 {
+      dividers.push(panel.y + top - thickness);
+    }
+    if bottom > 0 {
+      dividers.push(panel.y + panel.height - bottom);
+    }
+    for y in dividers {
+      self.blend_rect(
+        image,
+        (panel.x + thickness) as i32,
+        y as i32,
+        panel.width.saturating_sub(thickness * 2),
+        thickness,
+        color,
+      );
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,158 @@
+/* ~~/src/renderer/brackets.rs */
+
+// local modules
+use crate::renderer::SnippetRenderer;
+use crate::syntax::{HighlightedLine, HighlightedToken};
+use crate::themes::ThemeColor;
+
+/// Closing bracket paired with an opening one
+fn closer(open: char) -> Option<char> {
+  match open {
+    '(' => Some(')'),
+    '[' => Some(']'),
+    '{' => Some('}'),
+    _ => None,
+  }
+}
+
+/// Brackets in strings and comments are text, not structure; scopes are
+/// TextMate stacks or editor groups such as `@string`
+fn is_structural(token: &HighlightedToken) -> bool {
+  !token
+    .scope
+    .split([' ', '.', '@'])
+    .any(|part| part == "string" || part == "comment")
+}
+
+impl SnippetRenderer {
+  fn bracket_palette(&self) -> Vec<ThemeColor> {
+    match self.config.bracket_colors.is_empty() {
+      true => vec![
+        self.theme.function.clone(),
+        self.theme.keyword.clone(),
+        self.theme.string.clone(),
+      ],
+      false => self
+        .config
+        .bracket_colors
+        .iter()
+        .map(|hex| ThemeColor::new(hex))
+        .collect(),
+    }
+  }
+
+  /// Recolor matched `()[]{}` pairs by nesting depth when `bracket_pairs` is
+  /// on, splitting each into a token of its own; unmatched brackets keep
+  /// their color
+  pub(crate) fn colorize_brackets(&self, lines: &mut [HighlightedLine]) {
+    if !self.config.bracket_pairs {
+      return;
+    }
+    // Depth of every matched bracket, keyed by line, token, and byte offset
+    let mut open: Vec<(char, (usize, usize, usize))> = Vec::new();
+    let mut depths: Vec<((usize, usize, usize), usize)> = Vec::new();
+    for (line_index, line) in lines.iter().enumerate() {
+      for (token_index, token) in line.tokens.iter().enumerate() {
+        if !is_structural(token) {
+          continue;
+        }
+        for (offset, ch) in token.text.char_indices() {
+          let at = (line_index, token_index, offset);
+          if let Some(close) = closer(ch) {
+            open.push((close, at));
+          } else if matches!(ch, ')' | ']' | '}')
+            && let Some(&(close, opened)) = open.last()
+            && close == ch
+          {
+            open.pop();
+            depths.push((opened, open.len()));
+            depths.push((at, open.len()));
+          }
+        }
+      }
+    }
+    if depths.is_empty() {
+      return;
+    }
+    depths.sort_unstable();
+
+    let palette = self.bracket_palette();
+    let mut depths = depths.into_iter().peekable();
+    for (line_index, line) in lines.iter_mut().enumerate() {
+      let mut tokens = Vec::with_capacity(line.tokens.len());
+      for (token_index, token) in line.tokens.drain(..).enumerate() {
+        let mut split = Vec::new();
+        while let Some(&((l, t, offset), depth)) = depths.peek()
+          && (l, t) == (line_index, token_index)
+        {
+          split.push((offset, depth));
+          depths.next();
+        }
+        if split.is_empty() {
+          tokens.push(token);
+          continue;
+        }
+        let piece = |from: usize, to: usize, color: &ThemeColor| HighlightedToken {
+          text: token.text[from..to].to_string(),
+          color: color.clone(),
+          background: token.background.clone(),
+          style: token.style,
+          scope: token.scope.clone(),
+          start_col: token.start_col.advance(&token.text[..from]),
+          end_col: token.start_col.advance(&token.text[..to]),
+        };
+        let mut from = 0;
+        for (offset, depth) in split {
+          if offset > from {
+            tokens.push(piece(from, offset, &token.color));
+          }
+          tokens.push(piece(offset, offset + 1, &palette[depth % palette.len()]));
+          from = offset + 1;
+        }
+        if from < token.text.len() {
+          tokens.push(piece(from, token.text.len(), &token.color));
+        }
+      }
+      line.tokens = tokens;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::config::RenderConfig;
+  use crate::renderer::SnippetRenderer;
+
+  #[test]
+  fn test_colors_brackets_by_depth() {
+    let config = RenderConfig {
+      bracket_pairs: true,
+      bracket_colors: vec!["#ff0000".to_string(), "#00ff00".to_string()],
+      ..RenderConfig::default()
+    };
+    let renderer = SnippetRenderer::new("dracula", config).unwrap();
+    let lines = renderer.highlight("f(a[0], \"(\")\n]\n", "python");
+    let color_of = |text: &str| {
+      lines
+        .iter()
+        .flat_map(|line| &line.tokens)
+        .filter(|token| token.text == text)
+        .map(|token| token.color.hex.as_str())
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(color_of(")"), ["#ff0000"]);
+    assert_eq!(color_of("["), ["#00ff00"]);
+    // The bracket in the string and the stray closer keep their colors
+    let (opening, closing) = (color_of("("), color_of("]"));
+    assert_eq!(opening[0], "#ff0000");
+    assert_ne!(opening[1], "#ff0000");
+    assert_eq!(closing.len(), 2);
+    assert_ne!(closing[1], "#00ff00");
+    let text: String = lines[0]
+      .tokens
+      .iter()
+      .map(|token| token.text.as_str())
+      .collect();
+    assert_eq!(text, "f(a[0], \"(\")");
+  }
+}
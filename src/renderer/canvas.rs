@@ -0,0 +1,104 @@
+/* ~~/src/renderer/canvas.rs */
+
+// third-party crates
+use image::RgbaImage;
+use image::imageops::{self, FilterType};
+
+// local modules
+use crate::config::{Fit, PanelSize, Sides, canvas_preset};
+use crate::renderer::SnippetRenderer;
+
+impl SnippetRenderer {
+  /// Width and height of the `canvas` preset, or of the scaled `width` and
+  /// `height` with fit "exact"; `None` when the image fits the panel
+  fn canvas_size(&self) -> Option<(u32, u32)> {
+    if let Some(preset) = self.config.canvas.as_deref().and_then(canvas_preset) {
+      return Some((preset.width, preset.height));
+    }
+    let scale = |size: u32| (size as f32 * self.config.export_size) as u32;
+    match (self.config.fit, self.config.width, self.config.height) {
+      (Fit::Exact, PanelSize::Fixed(width), PanelSize::Fixed(height)) => {
+        Some((scale(width), scale(height)))
+      }
+      _ => None,
+    }
+  }
+
+  /// Backdrop to add around a `width` by `height` image, split evenly so the
+  /// panel stays centered, to reach the canvas's aspect ratio at no less than
+  /// its size; an image larger than the canvas is drawn at its own scale and
+  /// shrunk by [`Self::fit_canvas`]
+  pub(super) fn canvas_margin(&self, width: u32, height: u32) -> Sides {
+    let Some((canvas_width, canvas_height)) = self.canvas_size() else {
+      return Sides::uniform(0);
+    };
+    let scale = (width as f32 / canvas_width as f32)
+      .max(height as f32 / canvas_height as f32)
+      .max(1.0);
+    let extra_width = ((canvas_width as f32 * scale).ceil() as u32).saturating_sub(width);
+    let extra_height = ((canvas_height as f32 * scale).ceil() as u32).saturating_sub(height);
+    Sides {
+      top: extra_height / 2,
+      right: extra_width - extra_width / 2,
+      bottom: extra_height - extra_height / 2,
+      left: extra_width / 2,
+    }
+  }
+
+  /// `image` scaled to exactly the canvas size, unchanged without a canvas
+  pub(super) fn fit_canvas(&self, image: RgbaImage) -> RgbaImage {
+    match self.canvas_size() {
+      Some(size) if image.dimensions() != size => {
+        imageops::resize(&image, size.0, size.1, FilterType::Lanczos3)
+      }
+      _ => image,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::RenderConfig;
+
+  #[test]
+  fn test_letterboxes_to_canvas() {
+    let config = RenderConfig {
+      canvas: Some("og".to_string()),
+      export_size: 1.0,
+      ..RenderConfig::default()
+    };
+    let mut renderer = SnippetRenderer::new("nord", config).unwrap();
+    // Small images grow to the canvas, centered
+    let margin = renderer.canvas_margin(600, 400);
+    assert_eq!((margin.left, margin.right), (300, 300));
+    assert_eq!((margin.top, margin.bottom), (115, 115));
+    // Larger ones only grow to its shape
+    let margin = renderer.canvas_margin(2400, 630);
+    assert_eq!(margin.vertical(), 630);
+    assert_eq!(margin.horizontal(), 0);
+
+    let code = "fn main() {}\n".repeat(60);
+    let png_data = renderer.render_snippet_png(&code, "rust").unwrap();
+    let image = image::load_from_memory(&png_data).unwrap();
+    assert_eq!((image.width(), image.height()), (1200, 630));
+
+    // Exact fitting uses the configured size as the canvas
+    let config = RenderConfig {
+      fit: Fit::Exact,
+      width: PanelSize::Fixed(300),
+      height: PanelSize::Fixed(200),
+      export_size: 1.0,
+      ..RenderConfig::default()
+    };
+    let mut renderer = SnippetRenderer::new("nord", config).unwrap();
+    let png_data = renderer.render_snippet_png(&code, "rust").unwrap();
+    let image = image::load_from_memory(&png_data).unwrap();
+    assert_eq!((image.width(), image.height()), (300, 200));
+    let config = RenderConfig {
+      fit: Fit::Exact,
+      ..RenderConfig::default()
+    };
+    assert!(SnippetRenderer::new("nord", config).is_err());
+  }
+}
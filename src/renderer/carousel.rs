@@ -0,0 +1,122 @@
+/* ~~/src/renderer/carousel.rs */
+
+// standard crates
+use std::ops::Range;
+
+// third-party crates
+use anyhow::Result;
+
+// local modules
+use crate::config::LineRange;
+use crate::limits::RenderGuard;
+use crate::renderer::SnippetRenderer;
+use crate::syntax::HighlightedLine;
+
+impl SnippetRenderer {
+  /// Split a long file into overlapping pages that share size, gutter width,
+  /// and a "title (i/N)" header, returning one data URL per page
+  pub fn render_carousel(&mut self, code: &str, language: &str) -> Result<Vec<String>> {
+    // Highlight once so multi-line constructs keep their state across page breaks
+    let highlighted_lines = self.highlight_snippet(code, language);
+    let carousel = self.config.carousel;
+    let pages = page_ranges(
+      highlighted_lines.len(),
+      carousel.lines_per_page as usize,
+      carousel.overlap_lines as usize,
+    );
+
+    let saved = (
+      self.config.window_title.clone(),
+      self.config.line_number_start,
+      self.config.highlight_lines.clone(),
+      self.config.width,
+    );
+    self.gutter_reserved_line = saved
+      .1
+      .saturating_add((highlighted_lines.len() as u32).max(1) - 1);
+    self.pin_width(&highlighted_lines);
+    let tallest_page = pages.iter().map(|page| page.len()).max().unwrap_or(0);
+    self.reserve_content(tallest_page as u32, self.widest_line(&highlighted_lines));
+
+    let result = self.render_pages(&highlighted_lines, &pages);
+
+    (
+      self.config.window_title,
+      self.config.line_number_start,
+      self.config.highlight_lines,
+      self.config.width,
+    ) = saved;
+    self.gutter_reserved_line = 0;
+    self.reserve_content(0, 0);
+    result
+  }
+
+  fn render_pages(
+    &mut self,
+    highlighted_lines: &[HighlightedLine],
+    pages: &[Range<usize>],
+  ) -> Result<Vec<String>> {
+    let title = self.window_title().map(str::to_owned);
+    let first_line = self.config.line_number_start;
+    let highlights = self.config.highlight_lines.clone();
+    let mut images = Vec::with_capacity(pages.len());
+
+    for (index, page) in pages.iter().enumerate() {
+      let guard = RenderGuard::acquire()?;
+      self.config.window_title = match (&title, pages.len()) {
+        (title, 1) => title.clone(),
+        (Some(title), count) => Some(format!("{} ({}/{})", title, index + 1, count)),
+        (None, count) => Some(format!("{}/{}", index + 1, count)),
+      };
+      self.config.line_number_start = first_line.saturating_add(page.start as u32);
+      self.config.highlight_lines = highlights
+        .iter()
+        .filter_map(|range| shift_range(*range, page))
+        .collect();
+      images.push(self.render_highlighted(guard, &highlighted_lines[page.clone()])?);
+    }
+    Ok(images)
+  }
+}
+
+/// Windows of `lines_per_page` lines where each page starts `overlap` lines
+/// before the previous one ended
+fn page_ranges(line_count: usize, lines_per_page: usize, overlap: usize) -> Vec<Range<usize>> {
+  let lines_per_page = lines_per_page.max(1);
+  let step = lines_per_page - overlap.min(lines_per_page - 1);
+  let mut pages = Vec::new();
+  let mut start = 0;
+  loop {
+    let end = (start + lines_per_page).min(line_count);
+    pages.push(start..end);
+    if end >= line_count {
+      return pages;
+    }
+    start += step;
+  }
+}
+
+/// Re-base a 1-based file line range onto a page, dropping it if it misses the page
+fn shift_range(range: LineRange, page: &Range<usize>) -> Option<LineRange> {
+  let (page_start, page_end) = (page.start as u32, page.end as u32);
+  let start = range.start.max(page_start + 1);
+  let end = range.end.min(page_end);
+  (start <= end).then(|| LineRange {
+    start: start - page_start,
+    end: end - page_start,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_page_ranges_overlap() {
+    assert_eq!(page_ranges(10, 4, 1), vec![0..4, 3..7, 6..10]);
+    assert_eq!(page_ranges(3, 30, 2), vec![0..3]);
+    assert_eq!(page_ranges(0, 30, 2), vec![0..0]);
+    // Overlap never stalls the pager
+    assert_eq!(page_ranges(3, 2, 5), vec![0..2, 1..3]);
+  }
+}
@@ -0,0 +1,85 @@
+/* ~~/src/renderer/chrome.rs */
+
+// third-party crates
+use anyhow::Result;
+use image::Rgba;
+
+// local modules
+use crate::renderer::SnippetRenderer;
+use crate::renderer::color::{blend_colors, darken_color, relative_luminance, rgba_from_hex};
+use crate::themes::ThemeColor;
+
+/// Luminance past which a panel counts as light
+const LIGHT_PANEL: f32 = 0.5;
+
+/// Colors of the title bar, window controls, and gutter for a render
+pub(super) struct Chrome {
+  pub title_bar: Rgba<u8>,
+  pub title_text: Rgba<u8>,
+  pub controls: [Rgba<u8>; 3],
+  /// Outline around each control, so the pale yellow and green still read
+  /// against a light title bar
+  pub control_rim: Option<[Rgba<u8>; 3]>,
+  pub line_numbers: Rgba<u8>,
+  pub gutter_rule: Rgba<u8>,
+}
+
+impl SnippetRenderer {
+  /// Theme chrome overrides, with the rest derived from the panel color
+  pub(super) fn chrome(&self) -> Result<Chrome> {
+    let chrome = &self.theme.chrome;
+    let panel = self.panel_background()?;
+    let comment = rgba_from_hex(&self.theme.comment.hex)?;
+    let light = relative_luminance(panel) > LIGHT_PANEL;
+    let pick = |color: &Option<ThemeColor>, derived: Rgba<u8>| match color {
+      Some(color) => rgba_from_hex(&color.hex),
+      None => Ok(derived),
+    };
+
+    // Darkening by a fraction barely moves a dark panel, so those stay
+    // close; light panels shift toward the foreground for a visible bar
+    let title_bar = match light {
+      true => blend_colors(panel, rgba_from_hex(&self.theme.foreground.hex)?, 0.08),
+      false => darken_color(self.panel_background_hex(), 0.1)?,
+    };
+    let controls = [
+      pick(&chrome.close, rgba_from_hex("#ff5f56")?)?,
+      pick(&chrome.minimize, rgba_from_hex("#ffbd2e")?)?,
+      pick(&chrome.maximize, rgba_from_hex("#27ca3f")?)?,
+    ];
+    Ok(Chrome {
+      title_bar: pick(&chrome.title_bar, title_bar)?,
+      title_text: pick(&chrome.title_text, comment)?,
+      control_rim: light
+        .then(|| controls.map(|color| blend_colors(color, Rgba([0, 0, 0, 255]), 0.15))),
+      controls,
+      line_numbers: pick(&chrome.line_numbers, comment)?,
+      gutter_rule: pick(&chrome.gutter_rule, blend_colors(panel, comment, 0.5))?,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::config::RenderConfig;
+  use crate::renderer::SnippetRenderer;
+  use crate::renderer::color::relative_luminance;
+
+  #[test]
+  fn test_chrome_follows_panel_lightness() {
+    let dark = SnippetRenderer::new("dracula", RenderConfig::default()).unwrap();
+    let light = SnippetRenderer::new("github", RenderConfig::default()).unwrap();
+    let (dark, light) = (dark.chrome().unwrap(), light.chrome().unwrap());
+    assert!(dark.control_rim.is_none());
+    assert!(light.control_rim.is_some());
+    // The bar sits visibly darker than a white panel
+    assert!(relative_luminance(light.title_bar) < 0.9);
+
+    // Theme overrides win over derived colors
+    let solarized = SnippetRenderer::new("solarized-light", RenderConfig::default()).unwrap();
+    assert_eq!(
+      solarized.chrome().unwrap().title_bar.0,
+      [0xee, 0xe8, 0xd5, 255]
+    );
+  }
+}
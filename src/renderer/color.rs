@@ -22,3 +22,36 @@ pub(super) fn darken_color(hex: &str, factor: f32) -> Result<Rgba<u8>> {
   let b = ((base_color[2] as f32) * (1.0 - factor)) as u8;
   Ok(Rgba([r, g, b, base_color[3]]))
 }
+
+/// Mix `overlay` onto `base` with the given opacity
+pub(super) fn blend_colors(base: Rgba<u8>, overlay: Rgba<u8>, opacity: f32) -> Rgba<u8> {
+  let opacity = opacity.clamp(0.0, 1.0);
+  let mix = |a: u8, b: u8| (a as f32 * (1.0 - opacity) + b as f32 * opacity).round() as u8;
+  Rgba([
+    mix(base[0], overlay[0]),
+    mix(base[1], overlay[1]),
+    mix(base[2], overlay[2]),
+    base[3],
+  ])
+}
+
+/// Move `color` toward the gray of equal brightness by `amount`, 0.0 keeping
+/// it and 1.0 graying it fully
+pub(super) fn desaturate(color: Rgba<u8>, amount: f32) -> Rgba<u8> {
+  let luma = (0.299 * color[0] as f32 + 0.587 * color[1] as f32 + 0.114 * color[2] as f32)
+    .round()
+    .clamp(0.0, 255.0) as u8;
+  blend_colors(color, Rgba([luma, luma, luma, color[3]]), amount)
+}
+
+/// WCAG relative luminance, 0.0 for black to 1.0 for white
+pub(super) fn relative_luminance(color: Rgba<u8>) -> f32 {
+  let linear = |channel: u8| {
+    let value = channel as f32 / 255.0;
+    match value <= 0.04045 {
+      true => value / 12.92,
+      false => ((value + 0.055) / 1.055).powf(2.4),
+    }
+  };
+  0.2126 * linear(color[0]) + 0.7152 * linear(color[1]) + 0.0722 * linear(color[2])
+}
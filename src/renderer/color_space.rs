@@ -0,0 +1,213 @@
+/* ~~/src/renderer/color_space.rs */
+
+// standard crates
+use std::borrow::Cow;
+use std::io::Write;
+
+// third-party crates
+use anyhow::{Result, anyhow};
+use image::RgbaImage;
+
+// local modules
+use crate::config::ColorSpace;
+use crate::renderer::png_text::{PngText, add_text_chunks};
+
+/// Linear sRGB to linear Display P3; both share the D65 white point
+const SRGB_TO_DISPLAY_P3: [[f32; 3]; 3] = [
+  [0.822_462, 0.177_538, 0.0],
+  [0.033_194, 0.966_806, 0.0],
+  [0.017_083, 0.072_397, 0.910_520],
+];
+
+/// Display P3 primaries adapted to the D50 profile connection space
+const DISPLAY_P3_COLORANTS: [[f64; 3]; 3] = [
+  [0.515_102, 0.241_182, -0.001_050],
+  [0.291_965, 0.692_236, 0.041_885],
+  [0.157_153, 0.066_582, 0.784_378],
+];
+
+/// Bradford adaptation from D65 to D50, required by ICC v4 display profiles
+const D65_TO_D50: [f64; 9] = [
+  1.047_882, 0.022_919, -0.050_217, 0.029_587, 0.990_479, -0.017_059, -0.009_232, 0.015_076,
+  0.751_678,
+];
+
+/// D50, the illuminant of the profile connection space
+const D50: [f64; 3] = [0.964_203, 1.0, 0.824_905];
+
+/// PNG encoder whose output is tagged with `color_space`, so viewers don't
+/// have to guess how to interpret the pixels, and carries `texts`
+pub(crate) fn png_encoder<W: Write>(
+  writer: W,
+  width: u32,
+  height: u32,
+  color_space: ColorSpace,
+  texts: &[PngText],
+) -> Result<png::Encoder<'static, W>> {
+  let mut info = png::Info::with_size(width, height);
+  add_text_chunks(&mut info, texts);
+  match color_space {
+    ColorSpace::Srgb => info.srgb = Some(png::SrgbRenderingIntent::Perceptual),
+    ColorSpace::DisplayP3 => info.icc_profile = Some(Cow::Owned(display_p3_profile())),
+  }
+  png::Encoder::with_info(writer, info).map_err(|e| anyhow!("Failed to start PNG: {}", e))
+}
+
+/// Re-express sRGB pixels in Display P3 so they look the same once tagged
+pub(super) fn convert_to_display_p3(image: &mut RgbaImage) {
+  let linear: Vec<f32> = (0..=255u8)
+    .map(|value| srgb_to_linear(value as f32 / 255.0))
+    .collect();
+  for pixel in image.pixels_mut() {
+    let rgb = [
+      linear[pixel[0] as usize],
+      linear[pixel[1] as usize],
+      linear[pixel[2] as usize],
+    ];
+    for (channel, row) in SRGB_TO_DISPLAY_P3.iter().enumerate() {
+      let value = row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2];
+      pixel[channel] = (linear_to_srgb(value) * 255.0).round().clamp(0.0, 255.0) as u8;
+    }
+  }
+}
+
+/// sRGB transfer curve, which Display P3 shares
+fn srgb_to_linear(value: f32) -> f32 {
+  if value <= 0.040_45 {
+    value / 12.92
+  } else {
+    ((value + 0.055) / 1.055).powf(2.4)
+  }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+  if value <= 0.003_130_8 {
+    value * 12.92
+  } else {
+    1.055 * value.powf(1.0 / 2.4) - 0.055
+  }
+}
+
+/// Minimal ICC v4 display profile for Display P3: primaries, white point,
+/// adaptation, and the sRGB tone curve
+pub(crate) fn display_p3_profile() -> Vec<u8> {
+  let curve = {
+    // parametricCurveType, function 3: the piecewise sRGB curve
+    let mut data = b"para\0\0\0\0\0\x03\0\0".to_vec();
+    for value in [2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.040_45] {
+      data.extend(s15_fixed16(value));
+    }
+    data
+  };
+  let tags: Vec<(&[u8; 4], Vec<u8>)> = vec![
+    (b"desc", text("Display P3")),
+    (b"cprt", text("No copyright, use freely")),
+    (b"wtpt", xyz(&D50)),
+    (b"chad", sf32(&D65_TO_D50)),
+    (b"rXYZ", xyz(&DISPLAY_P3_COLORANTS[0])),
+    (b"gXYZ", xyz(&DISPLAY_P3_COLORANTS[1])),
+    (b"bXYZ", xyz(&DISPLAY_P3_COLORANTS[2])),
+    (b"rTRC", curve.clone()),
+    (b"gTRC", curve.clone()),
+    (b"bTRC", curve),
+  ];
+
+  // Tag data follows the header and tag table, each element 4-byte aligned
+  let table_len = 4 + 12 * tags.len();
+  let mut table = (tags.len() as u32).to_be_bytes().to_vec();
+  let mut data = Vec::new();
+  for (signature, element) in &tags {
+    table.extend(*signature);
+    table.extend(((128 + table_len + data.len()) as u32).to_be_bytes());
+    table.extend((element.len() as u32).to_be_bytes());
+    data.extend(element);
+    while data.len() % 4 != 0 {
+      data.push(0);
+    }
+  }
+  let size = 128 + table.len() + data.len();
+
+  let mut profile = Vec::with_capacity(size);
+  profile.extend((size as u32).to_be_bytes());
+  profile.extend([0; 4]); // Preferred CMM
+  profile.extend([4, 0x30, 0, 0]); // Version 4.3
+  profile.extend(b"mntrRGB XYZ ");
+  profile.extend([0; 12]); // Creation date
+  profile.extend(b"acsp");
+  profile.extend([0; 24]); // Platform, flags, manufacturer, model, attributes
+  profile.extend([0; 4]); // Perceptual rendering intent
+  profile.extend(xyz(&D50)[8..].iter());
+  profile.extend([0; 4 + 16 + 28]); // Creator, profile ID, reserved
+  profile.extend(table);
+  profile.extend(data);
+  profile
+}
+
+fn s15_fixed16(value: f64) -> [u8; 4] {
+  ((value * 65536.0).round() as i32).to_be_bytes()
+}
+
+fn xyz(values: &[f64; 3]) -> Vec<u8> {
+  sf32_typed(b"XYZ ", values)
+}
+
+fn sf32(values: &[f64]) -> Vec<u8> {
+  sf32_typed(b"sf32", values)
+}
+
+fn sf32_typed(signature: &[u8; 4], values: &[f64]) -> Vec<u8> {
+  let mut data = signature.to_vec();
+  data.extend([0; 4]);
+  for &value in values {
+    data.extend(s15_fixed16(value));
+  }
+  data
+}
+
+/// multiLocalizedUnicodeType with a single en-US record
+fn text(value: &str) -> Vec<u8> {
+  let utf16: Vec<u8> = value.encode_utf16().flat_map(u16::to_be_bytes).collect();
+  let mut data = b"mluc\0\0\0\0".to_vec();
+  data.extend(1u32.to_be_bytes());
+  data.extend(12u32.to_be_bytes());
+  data.extend(b"enUS");
+  data.extend((utf16.len() as u32).to_be_bytes());
+  data.extend(28u32.to_be_bytes());
+  data.extend(utf16);
+  data
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use image::Rgba;
+
+  #[test]
+  fn test_display_p3_profile_is_well_formed() {
+    let profile = display_p3_profile();
+    let word = |at: usize| u32::from_be_bytes(profile[at..at + 4].try_into().unwrap()) as usize;
+    assert_eq!(word(0), profile.len());
+    assert_eq!(&profile[36..40], b"acsp");
+    let count = word(128);
+    assert_eq!(count, 10);
+    for tag in 0..count {
+      let entry = 132 + 12 * tag;
+      let (offset, size) = (word(entry + 4), word(entry + 8));
+      assert_eq!(offset % 4, 0);
+      assert!(offset + size <= profile.len());
+    }
+  }
+
+  #[test]
+  fn test_convert_keeps_neutrals_and_narrows_primaries() {
+    let mut image = RgbaImage::from_fn(3, 1, |x, _| match x {
+      0 => Rgba([255, 255, 255, 255]),
+      1 => Rgba([128, 128, 128, 200]),
+      _ => Rgba([255, 0, 0, 255]),
+    });
+    convert_to_display_p3(&mut image);
+    assert_eq!(image.get_pixel(0, 0), &Rgba([255, 255, 255, 255]));
+    assert_eq!(image.get_pixel(1, 0), &Rgba([128, 128, 128, 200]));
+    assert_eq!(image.get_pixel(2, 0), &Rgba([234, 51, 35, 255]));
+  }
+}
@@ -0,0 +1,83 @@
+/* ~~/src/renderer/columns.rs */
+
+// third-party crates
+use anyhow::Result;
+use image::RgbaImage;
+
+// local modules
+use crate::renderer::{PanelRect, SnippetRenderer};
+
+impl SnippetRenderer {
+  /// Columns `line_count` lines flow into and the rows each one holds,
+  /// leaving no column empty
+  pub(crate) fn column_grid(&self, line_count: u32) -> (u32, u32) {
+    let lines = line_count.max(self.reserved_lines).max(1);
+    let rows = lines.div_ceil(self.config.columns);
+    (lines.div_ceil(rows), rows)
+  }
+
+  /// Slices of `panel` left of the minimap side by side, one per column,
+  /// each laid out like a panel of its own
+  pub(super) fn column_rects(&self, panel: PanelRect, line_count: u32) -> Vec<PanelRect> {
+    let (columns, _) = self.column_grid(line_count);
+    let width = panel.width.saturating_sub(self.minimap_width()) / columns;
+    (0..columns)
+      .map(|column| PanelRect {
+        x: panel.x + column * width,
+        width,
+        ..panel
+      })
+      .collect()
+  }
+
+  /// Rule between neighboring columns, spanning the code area
+  pub(super) fn draw_column_rules(
+    &self,
+    image: &mut RgbaImage,
+    panel: PanelRect,
+    line_count: u32,
+  ) -> Result<()> {
+    let top = panel.y + self.top_chrome_height();
+    let height = (panel.y + panel.height).saturating_sub(top + self.bottom_chrome_height());
+    let rule_width = self.gutter_rule_width();
+    let color = self.chrome()?.gutter_rule;
+    for column in self.column_rects(panel, line_count).iter().skip(1) {
+      self.fill_rect_in_panel(
+        image,
+        panel,
+        column.x.saturating_sub(rule_width / 2),
+        top,
+        rule_width,
+        height,
+        color,
+      );
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::RenderConfig;
+
+  #[test]
+  fn test_column_grid_leaves_no_column_empty() {
+    let config = RenderConfig {
+      columns: 3,
+      ..RenderConfig::default()
+    };
+    let renderer = SnippetRenderer::new("dracula", config).unwrap();
+    assert_eq!(renderer.column_grid(120), (3, 40));
+    assert_eq!(renderer.column_grid(5), (3, 2));
+    // Two rows already hold four lines, so a third column would be empty
+    assert_eq!(renderer.column_grid(4), (2, 2));
+    assert_eq!(renderer.column_grid(0), (1, 1));
+
+    let config = RenderConfig {
+      columns: 4,
+      ..RenderConfig::default()
+    };
+    assert!(SnippetRenderer::new("dracula", config).is_err());
+  }
+}
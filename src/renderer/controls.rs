@@ -0,0 +1,144 @@
+/* ~~/src/renderer/controls.rs */
+
+// third-party crates
+use image::{Rgba, RgbaImage};
+
+// local modules
+use crate::config::{ControlFill, WindowStyle};
+use crate::renderer::chrome::Chrome;
+use crate::renderer::color::{blend_colors, desaturate};
+use crate::renderer::{PanelRect, SnippetRenderer};
+
+/// Glyph a right-aligned control button shows
+#[derive(Clone, Copy)]
+enum Button {
+  Minimize,
+  Maximize,
+  Close,
+}
+
+const BUTTONS: [Button; 3] = [Button::Minimize, Button::Maximize, Button::Close];
+
+impl SnippetRenderer {
+  /// Draw the window controls of `window_style` on the title bar `bar`,
+  /// returning the width they take at its right end
+  pub(super) fn draw_window_controls(
+    &self,
+    image: &mut RgbaImage,
+    bar: PanelRect,
+    chrome: &Chrome,
+  ) -> u32 {
+    let scale = self.config.export_size;
+    let padding = self.config.get_scaled_padding();
+    let center_y = bar.y as i32 + (bar.height / 2) as i32;
+    match self.config.window_style {
+      WindowStyle::NoneButTitlebar => 0,
+      WindowStyle::Macos => {
+        let control_radius = (6.0 * scale) as i32;
+        let control_spacing = (20.0 * scale) as i32;
+        let start_x = bar.x as i32 + (padding.left / 2) as i32;
+        let rim_width = (scale.round() as i32).max(1);
+        for (index, color) in chrome.controls.into_iter().enumerate() {
+          let x = start_x + control_spacing * index as i32;
+          let rim = chrome.control_rim.map(|rims| rims[index]);
+          let (color, rim) = match self.config.control_fill {
+            ControlFill::Grayscale => (desaturate(color, 1.0), rim.map(|rim| desaturate(rim, 1.0))),
+            _ => (color, rim),
+          };
+          if let Some(rim) = rim {
+            self.draw_circle(image, x, center_y, control_radius, rim);
+          }
+          let radius = control_radius - rim.map_or(0, |_| rim_width);
+          self.draw_circle(image, x, center_y, radius, color);
+          if self.config.control_fill == ControlFill::Hollow {
+            let ring = ((1.5 * scale).round() as i32).max(1);
+            self.draw_circle(image, x, center_y, radius - ring, chrome.title_bar);
+          }
+        }
+        0
+      }
+      // Flat buttons the height of the bar, glyphs in the title color
+      WindowStyle::Windows => {
+        let cell = (44.0 * scale) as i32;
+        let right = (bar.x + bar.width) as i32;
+        // A panel narrower than the three buttons keeps the rightmost ones
+        let fitting = BUTTONS.len().min((bar.width as i32 / cell.max(1)) as usize);
+        let first = BUTTONS.len() - fitting;
+        for (index, button) in BUTTONS.into_iter().enumerate().skip(first) {
+          let center_x = right - cell * (BUTTONS.len() - index) as i32 + cell / 2;
+          self.draw_button_glyph(
+            image,
+            button,
+            (center_x as f32, center_y as f32),
+            5.0 * scale,
+            scale.max(1.0),
+            chrome.title_text,
+          );
+        }
+        (cell * fitting as i32) as u32
+      }
+      WindowStyle::Linux => {
+        let radius = (11.0 * scale) as i32;
+        let spacing = (30.0 * scale) as i32;
+        let end_x = (bar.x + bar.width) as i32 - (padding.right / 2) as i32 - radius;
+        let fill = blend_colors(chrome.title_bar, chrome.title_text, 0.15);
+        for (index, button) in BUTTONS.into_iter().enumerate() {
+          let x = end_x - spacing * (BUTTONS.len() - 1 - index) as i32;
+          self.draw_circle(image, x, center_y, radius, fill);
+          if self.config.control_fill == ControlFill::Hollow {
+            let ring = ((1.5 * scale).round() as i32).max(1);
+            self.draw_circle(image, x, center_y, radius - ring, chrome.title_bar);
+          }
+          self.draw_button_glyph(
+            image,
+            button,
+            (x as f32 + 0.5, center_y as f32 + 0.5),
+            4.0 * scale,
+            (1.5 * scale).max(1.0),
+            chrome.title_text,
+          );
+        }
+        (spacing * (BUTTONS.len() as i32 - 1) + radius * 2) as u32 + padding.right / 2
+      }
+    }
+  }
+
+  /// Stroke a button's glyph within `half` of `center` each way
+  fn draw_button_glyph(
+    &self,
+    image: &mut RgbaImage,
+    button: Button,
+    center: (f32, f32),
+    half: f32,
+    width: f32,
+    color: Rgba<u8>,
+  ) {
+    let (x, y) = center;
+    match button {
+      Button::Minimize => {
+        self.stroke_polyline(image, &[(x - half, y), (x + half, y)], false, width, color);
+      }
+      Button::Maximize => self.stroke_polyline(
+        image,
+        &[
+          (x - half, y - half),
+          (x + half, y - half),
+          (x + half, y + half),
+          (x - half, y + half),
+        ],
+        true,
+        width,
+        color,
+      ),
+      Button::Close => {
+        let diagonals = [
+          [(x - half, y - half), (x + half, y + half)],
+          [(x - half, y + half), (x + half, y - half)],
+        ];
+        for diagonal in diagonals {
+          self.stroke_polyline(image, &diagonal, false, width, color);
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,155 @@
+/* ~~/src/renderer/corpus.rs */
+
+// standard crates
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// local modules
+use crate::config::{LineRange, PanelSize, RenderConfig, RenderLayer, Sides};
+use crate::limits::RenderGuard;
+use crate::renderer::SnippetRenderer;
+use crate::syntax::is_language_supported;
+use crate::themes::{get_theme, get_theme_names};
+
+/// Every file under `samples/`, named after the language it is written in
+fn samples() -> Vec<PathBuf> {
+  let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples");
+  let mut samples: Vec<PathBuf> = fs::read_dir(dir)
+    .unwrap()
+    .map(|entry| entry.unwrap().path())
+    .collect();
+  samples.sort();
+  samples
+}
+
+#[test]
+fn test_sample_corpus_renders_with_every_theme() {
+  // Small canvas and a pinned backdrop keep the run quick and repeatable
+  let config = RenderConfig {
+    width: PanelSize::Fixed(600),
+    export_size: 1.0,
+    panel_padding: Sides::uniform(16),
+    noise_effect: false,
+    gradient_seed: Some(7),
+    ..RenderConfig::default()
+  };
+  let mut renderer = SnippetRenderer::new("dracula", config).unwrap();
+
+  for path in samples() {
+    let name = path.file_name().unwrap().to_string_lossy().into_owned();
+    let language = name.split('.').next().unwrap().to_string();
+    assert!(
+      is_language_supported(&language),
+      "{}: unknown language",
+      name
+    );
+    let code = fs::read_to_string(&path).unwrap();
+
+    let missing: Vec<char> = code
+      .chars()
+      .filter(|ch| !ch.is_control() && !renderer.font_manager.has_glyph(*ch))
+      .collect();
+    assert!(missing.is_empty(), "{}: no glyph for {:?}", name, missing);
+
+    for theme in get_theme_names() {
+      renderer.set_theme(get_theme(&theme).unwrap());
+      let lines = renderer.highlight(&code, &language);
+      assert_eq!(lines.len(), code.lines().count(), "{} ({})", name, theme);
+      // The pattern highlighter alone leaves some languages monochrome
+      if language != "text" && cfg!(feature = "bundled-syntaxes") {
+        let colors: HashSet<&str> = lines
+          .iter()
+          .flat_map(|line| &line.tokens)
+          .filter(|token| !token.text.trim().is_empty())
+          .map(|token| token.color.hex.as_str())
+          .collect();
+        assert!(colors.len() > 1, "{} ({}): not highlighted", name, theme);
+      }
+
+      let guard = RenderGuard::acquire().unwrap();
+      let image = renderer.rasterize(&guard, &lines).unwrap();
+      let metrics = renderer.compute_metrics(&lines);
+      assert_eq!(image.dimensions(), (metrics.width, metrics.height));
+      guard.complete();
+    }
+  }
+}
+
+#[test]
+fn test_seeded_render_is_deterministic() {
+  let config = RenderConfig {
+    width: PanelSize::Fixed(600),
+    export_size: 1.0,
+    gradient_seed: Some(7),
+    ..RenderConfig::default()
+  };
+  let mut renderer = SnippetRenderer::new("nord", config).unwrap();
+  let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/rust.rs");
+  let code = fs::read_to_string(path).unwrap();
+  let lines = renderer.highlight(&code, "rust");
+  let guard = RenderGuard::acquire().unwrap();
+  let first = renderer.rasterize(&guard, &lines).unwrap();
+  let second = renderer.rasterize(&guard, &lines).unwrap();
+  guard.complete();
+  assert!(first == second);
+}
+
+#[test]
+fn test_thread_count_does_not_change_render() {
+  let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/rust.rs");
+  let code = fs::read_to_string(path).unwrap();
+  let renders: Vec<_> = [1, 3, 8]
+    .into_iter()
+    .map(|threads| {
+      let config = RenderConfig {
+        width: PanelSize::Fixed(600),
+        export_size: 1.0,
+        gradient_seed: Some(7),
+        line_numbers: true,
+        highlight_lines: vec![LineRange { start: 2, end: 5 }],
+        threads,
+        ..RenderConfig::default()
+      };
+      let mut renderer = SnippetRenderer::new("gruvbox", config).unwrap();
+      let lines = renderer.highlight(&code, "rust");
+      let guard = RenderGuard::acquire().unwrap();
+      let image = renderer.rasterize(&guard, &lines).unwrap();
+      guard.complete();
+      image
+    })
+    .collect();
+  assert!(renders[0] == renders[1]);
+  assert!(renders[0] == renders[2]);
+}
+
+#[test]
+fn test_layers_composite_back_to_full() {
+  let config = RenderConfig {
+    width: PanelSize::Fixed(400),
+    export_size: 1.0,
+    gradient_seed: Some(7),
+    ..RenderConfig::default()
+  };
+  let mut renderer = SnippetRenderer::new("nord", config).unwrap();
+  let lines = renderer.highlight("fn main() {}\n", "rust");
+  let guard = RenderGuard::acquire().unwrap();
+  let mut layers = Vec::new();
+  for layer in [RenderLayer::Full, RenderLayer::Panel, RenderLayer::Backdrop] {
+    renderer.config_mut().layer = layer;
+    layers.push(renderer.rasterize(&guard, &lines).unwrap());
+  }
+  guard.complete();
+
+  let (full, panel, backdrop) = (&layers[0], &layers[1], &layers[2]);
+  assert_eq!(panel.dimensions(), full.dimensions());
+  assert_eq!(backdrop.dimensions(), full.dimensions());
+  assert_eq!(panel.get_pixel(0, 0)[3], 0);
+  for ((full, panel), backdrop) in full.pixels().zip(panel.pixels()).zip(backdrop.pixels()) {
+    match panel[3] {
+      0 => assert_eq!(full, backdrop),
+      255 => assert_eq!(full, panel),
+      _ => {}
+    }
+  }
+}
@@ -0,0 +1,95 @@
+/* ~~/src/renderer/cursor.rs */
+
+// third-party crates
+use anyhow::Result;
+use image::RgbaImage;
+
+// local modules
+use crate::config::CursorStyle;
+use crate::renderer::color::rgba_from_hex;
+use crate::renderer::{CodeLayout, SnippetRenderer};
+use crate::syntax::{HighlightedLine, HighlightedToken, TokenStyle};
+
+/// Character of `line` covering the 0-based display `column`, with the token
+/// it belongs to and the column it starts at
+fn character_at(line: &HighlightedLine, column: usize) -> Option<(&HighlightedToken, char, usize)> {
+  for token in &line.tokens {
+    let mut start = token.start_col;
+    for character in token.text.chars() {
+      let end = start.advance(character.encode_utf8(&mut [0; 4]));
+      if (start.display..end.display).contains(&column) {
+        return Some((token, character, start.display));
+      }
+      start = end;
+    }
+  }
+  None
+}
+
+impl SnippetRenderer {
+  /// Draw the configured cursor over a line whose code starts at `code_x`,
+  /// its baseline at `y`
+  pub(super) fn draw_cursor(
+    &self,
+    image: &mut RgbaImage,
+    layout: &CodeLayout,
+    line_index: usize,
+    line: &HighlightedLine,
+    code_x: u32,
+    y: u32,
+  ) -> Result<()> {
+    let Some(cursor) = &self.config.cursor else {
+      return Ok(());
+    };
+    let column = cursor.column.saturating_sub(1) as usize;
+    if cursor.line != line_index as u32 + 1
+      || self.is_marker_row(line_index, layout.line_count)
+      || column >= layout.visible_columns
+    {
+      return Ok(());
+    }
+    let color = rgba_from_hex(cursor.color.as_deref().unwrap_or(&self.theme.keyword.hex))?;
+    let measure = |text: &str, style, face| self.font_manager.measure_text(text, style, face);
+    // A wide character takes the cursor from wherever in it the column falls
+    let under = character_at(line, column);
+    let start = under.map_or(column, |(_, _, start)| start);
+    let x = code_x + measure(&" ".repeat(start), TokenStyle::REGULAR, None);
+    let cell_width = match under {
+      Some((token, character, _)) if !character.is_whitespace() => measure(
+        character.encode_utf8(&mut [0; 4]),
+        token.style,
+        self.scope_face(&token.scope),
+      ),
+      _ => measure(" ", TokenStyle::REGULAR, None),
+    };
+    let top = y as i32 - layout.strip_offset;
+    let thickness = (2.0 * self.config.export_size).max(1.0) as u32;
+    match cursor.style {
+      CursorStyle::Block => {
+        self.fill_rect(image, x as i32, top, cell_width, layout.line_height, color);
+        if let Some((token, character, _)) = under
+          && !character.is_whitespace()
+        {
+          self.draw_text(
+            image,
+            character.encode_utf8(&mut [0; 4]),
+            x,
+            y,
+            self.config.get_scaled_font_size(),
+            self.panel_background()?,
+            token.style,
+            self.scope_face(&token.scope),
+          )?;
+        }
+      }
+      CursorStyle::Bar => {
+        self.fill_rect(image, x as i32, top, thickness, layout.line_height, color);
+      }
+      CursorStyle::Underline => {
+        let bottom = top + layout.line_height as i32 - thickness as i32;
+        self.fill_rect(image, x as i32, bottom, cell_width, thickness, color);
+      }
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,129 @@
+/* ~~/src/renderer/diagnostics.rs */
+
+// third-party crates
+use anyhow::Result;
+use image::RgbaImage;
+
+// local modules
+use crate::config::Diagnostic;
+use crate::renderer::color::rgba_from_hex;
+use crate::renderer::{CodeLayout, SnippetRenderer};
+use crate::syntax::{HighlightedLine, TokenStyle};
+
+/// Space between the end of a line and its diagnostic message
+const MESSAGE_GAP: &str = "    ";
+
+impl SnippetRenderer {
+  /// The most severe diagnostic on the 1-based `line`, the first listed
+  /// among equals
+  pub(super) fn most_severe_diagnostic(&self, line: u32) -> Option<&Diagnostic> {
+    self
+      .config
+      .diagnostics
+      .iter()
+      .filter(|diagnostic| diagnostic.line == line)
+      .min_by_key(|diagnostic| diagnostic.severity)
+  }
+
+  /// Pen advance of the message shown after the 1-based `line`, gap
+  /// included, 0 without one
+  pub(super) fn diagnostic_message_width(&self, line: u32) -> u32 {
+    match self
+      .most_severe_diagnostic(line)
+      .and_then(|diagnostic| diagnostic.message.as_deref())
+    {
+      Some(message) => self.font_manager.measure_text(
+        &format!("{}{}", MESSAGE_GAP, message),
+        TokenStyle::ITALIC,
+        None,
+      ),
+      None => 0,
+    }
+  }
+
+  /// Squiggly underlines under a line's diagnostics and the most severe
+  /// one's message after it, for a line whose code starts at `code_x` with
+  /// its baseline at `y`
+  pub(super) fn draw_diagnostics(
+    &self,
+    image: &mut RgbaImage,
+    layout: &CodeLayout,
+    line_index: usize,
+    line: &HighlightedLine,
+    code_x: u32,
+    y: u32,
+  ) -> Result<()> {
+    let line_number = line_index as u32 + 1;
+    if self.is_marker_row(line_index, layout.line_count) {
+      return Ok(());
+    }
+    let scale = self.config.export_size;
+    let offset = |columns: usize| {
+      self
+        .font_manager
+        .measure_text(&" ".repeat(columns), TokenStyle::REGULAR, None) as f32
+    };
+    // Zigzag just below the baseline, in the descender space
+    let (amplitude, step) = (1.5 * scale, 2.0 * scale);
+    let middle = y as f32 + 2.5 * scale;
+    for diagnostic in &self.config.diagnostics {
+      if diagnostic.line != line_number {
+        continue;
+      }
+      let from = diagnostic.col_start.saturating_sub(1) as usize;
+      let to = (diagnostic.col_end.max(diagnostic.col_start) as usize).min(layout.visible_columns);
+      if from >= to {
+        continue;
+      }
+      let (left, right) = (code_x as f32 + offset(from), code_x as f32 + offset(to));
+      let points: Vec<(f32, f32)> = (0..)
+        .map(|index| (left + index as f32 * step, index))
+        .take_while(|(x, _)| *x <= right)
+        .map(|(x, index)| {
+          let rise = if index % 2 == 0 {
+            amplitude
+          } else {
+            -amplitude
+          };
+          (x, middle + rise / 2.0)
+        })
+        .collect();
+      let color = rgba_from_hex(self.config.diagnostic_colors.get(diagnostic.severity))?;
+      self.stroke_polyline(
+        image,
+        &points,
+        false,
+        scale.max(1.0),
+        self.focus_color(line_index, color),
+      );
+    }
+
+    let Some(diagnostic) = self.most_severe_diagnostic(line_number) else {
+      return Ok(());
+    };
+    let Some(message) = &diagnostic.message else {
+      return Ok(());
+    };
+    // Cut to what fits before the panel's right edge
+    let line_columns = line.tokens.last().map_or(0, |token| token.end_col.display);
+    let room = layout
+      .visible_columns
+      .saturating_sub(line_columns + MESSAGE_GAP.len());
+    let message: String = message.chars().take(room).collect();
+    if message.is_empty() {
+      return Ok(());
+    }
+    let color = rgba_from_hex(self.config.diagnostic_colors.get(diagnostic.severity))?;
+    self.draw_text(
+      image,
+      &message,
+      code_x + self.line_width(line) + offset(MESSAGE_GAP.len()) as u32,
+      y,
+      self.config.get_scaled_font_size(),
+      self.focus_color(line_index, color),
+      TokenStyle::ITALIC,
+      None,
+    )?;
+    Ok(())
+  }
+}
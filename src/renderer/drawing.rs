@@ -3,37 +3,168 @@
 // third-party crates
 use anyhow::Result;
 use image::{Rgba, RgbaImage};
+use tiny_skia::{
+  BlendMode, Color, ColorU8, FillRule, LineCap, Mask, Paint, Path, PathBuilder, Pixmap,
+  PixmapPaint, Rect, Stroke, Transform,
+};
 
 // local modules
-use crate::renderer::SnippetRenderer;
-use crate::renderer::color::rgba_from_hex;
+use crate::renderer::{PanelRect, SnippetRenderer};
+
+/// Control point distance, as a share of the radius, for a cubic that
+/// approximates a quarter circle
+const KAPPA: f32 = 0.552_284_8;
 
 impl SnippetRenderer {
+  pub(super) fn fill_rect(
+    &self,
+    image: &mut RgbaImage,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    color: Rgba<u8>,
+  ) {
+    let Some(rect) = Rect::from_xywh(x as f32, y as f32, width as f32, height as f32) else {
+      return;
+    };
+    let paint = Paint {
+      blend_mode: BlendMode::Source,
+      ..solid(color)
+    };
+    paint_area(image, x, y, width, height, |pixmap, transform| {
+      pixmap.fill_rect(rect, &paint, transform, None);
+    });
+  }
+
+  /// Like `fill_rect`, but a translucent color is composited over what is
+  /// already there rather than replacing it
+  pub(super) fn blend_rect(
+    &self,
+    image: &mut RgbaImage,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    color: Rgba<u8>,
+  ) {
+    let Some(rect) = Rect::from_xywh(x as f32, y as f32, width as f32, height as f32) else {
+      return;
+    };
+    paint_area(image, x, y, width, height, |pixmap, transform| {
+      pixmap.fill_rect(rect, &solid(color), transform, None);
+    });
+  }
+
+  /// Composite `source` with its top-left corner at `(x, y)`, faded to
+  /// `opacity`
+  pub(super) fn draw_image(
+    &self,
+    image: &mut RgbaImage,
+    source: &RgbaImage,
+    x: i32,
+    y: i32,
+    opacity: f32,
+  ) {
+    let Some(mut overlay) = Pixmap::new(source.width(), source.height()) else {
+      return;
+    };
+    for (target, pixel) in overlay.pixels_mut().iter_mut().zip(source.pixels()) {
+      *target = ColorU8::from_rgba(pixel[0], pixel[1], pixel[2], pixel[3]).premultiply();
+    }
+    let paint = PixmapPaint {
+      opacity: opacity.clamp(0.0, 1.0),
+      ..PixmapPaint::default()
+    };
+    paint_area(
+      image,
+      x,
+      y,
+      source.width(),
+      source.height(),
+      |pixmap, transform| {
+        pixmap.draw_pixmap(x, y, overlay.as_ref(), &paint, transform, None);
+      },
+    );
+  }
+
+  /// Fill a circle centered on pixel (`x`, `y`) with an antialiased rim
   pub(super) fn draw_circle(
     &self,
     image: &mut RgbaImage,
     x: i32,
     y: i32,
     radius: i32,
-    color_hex: &str,
-  ) -> Result<()> {
-    let color = rgba_from_hex(color_hex)?;
-
-    // Simple circle drawing algorithm
-    for dy in -radius..=radius {
-      for dx in -radius..=radius {
-        if dx * dx + dy * dy <= radius * radius {
-          let px = x + dx;
-          let py = y + dy;
-          if px >= 0 && py >= 0 && (px as u32) < image.width() && (py as u32) < image.height() {
-            image.put_pixel(px as u32, py as u32, color);
-          }
-        }
-      }
-    }
-    Ok(())
+    color: Rgba<u8>,
+  ) {
+    let reach = radius + 1;
+    let Some(path) = PathBuilder::from_circle(x as f32 + 0.5, y as f32 + 0.5, radius as f32 + 0.5)
+    else {
+      return;
+    };
+    let size = (reach * 2 + 1).max(0) as u32;
+    paint_area(
+      image,
+      x - reach,
+      y - reach,
+      size,
+      size,
+      |pixmap, transform| {
+        pixmap.fill_path(&path, &solid(color), FillRule::Winding, transform, None);
+      },
+    );
+  }
+
+  /// Fill a rectangle, leaving out what falls outside the panel's rounded
+  /// corners
+  #[allow(clippy::too_many_arguments)]
+  pub(super) fn fill_rect_in_panel(
+    &self,
+    image: &mut RgbaImage,
+    panel: PanelRect,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    color: Rgba<u8>,
+  ) {
+    let radius = (panel.width.min(panel.height) as f32 / 2.0)
+      .min(self.config.border_radius * self.config.export_size);
+    let left = x.max(panel.x);
+    let top = y.max(panel.y);
+    let right = (x + width).min(panel.x + panel.width);
+    let bottom = (y + height).min(panel.y + panel.height);
+    let (Some(rect), Some(outline)) = (
+      Rect::from_ltrb(left as f32, top as f32, right as f32, bottom as f32),
+      rounded_rect_path(
+        panel.x as f32,
+        panel.y as f32,
+        panel.width as f32,
+        panel.height as f32,
+        radius,
+        radius,
+      ),
+    ) else {
+      return;
+    };
+    let (area_width, area_height) = (right - left, bottom - top);
+    paint_area(
+      image,
+      left as i32,
+      top as i32,
+      area_width,
+      area_height,
+      |pixmap, transform| {
+        let Some(mut mask) = Mask::new(pixmap.width(), pixmap.height()) else {
+          return;
+        };
+        mask.fill_path(&outline, FillRule::Winding, true, transform);
+        pixmap.fill_rect(rect, &solid(color), transform, Some(&mask));
+      },
+    );
   }
 
+  #[allow(clippy::too_many_arguments)]
   pub(super) fn draw_rounded_rect(
     &self,
     image: &mut RgbaImage,
@@ -44,85 +175,49 @@ impl SnippetRenderer {
     radius: f32,
     color: Rgba<u8>,
   ) -> Result<()> {
-    let scaled_radius = radius * self.config.export_size;
-
-    // Clamp radius to not exceed half the smaller dimension
-    let max_radius = (width.min(height) as f32 / 2.0).min(scaled_radius);
-
-    for py in 0..height {
-      for px in 0..width {
-        let pixel_x = x + px as i32;
-        let pixel_y = y + py as i32;
-
-        // Check bounds
-        if pixel_x < 0
-          || pixel_y < 0
-          || pixel_x >= image.width() as i32
-          || pixel_y >= image.height() as i32
-        {
-          continue;
-        }
-
-        if self.is_inside_rounded_rect(
-          px as f32,
-          py as f32,
-          width as f32,
-          height as f32,
-          max_radius,
-        ) {
-          image.put_pixel(pixel_x as u32, pixel_y as u32, color);
-        }
-      }
-    }
-
+    let radius = self.clamp_radius(width, height, radius);
+    self.fill_rounded_rect(image, x, y, width, height, (radius, radius), color);
     Ok(())
   }
 
-  pub(super) fn is_inside_rounded_rect(
+  /// Stroke the panel's rounded outline `width` thick, inside its edge
+  pub(super) fn stroke_panel(
     &self,
-    x: f32,
-    y: f32,
+    image: &mut RgbaImage,
+    panel: PanelRect,
     width: f32,
-    height: f32,
-    radius: f32,
-  ) -> bool {
-    // Check if point is in the main rectangular area (excluding corners)
-    if x >= radius && x <= width - radius {
-      return true; // Inside horizontal strip
-    }
-    if y >= radius && y <= height - radius {
-      return true; // Inside vertical strip
-    }
-
-    // Check corner regions
-    let corners = [
-      (radius, radius),                  // Top-left
-      (width - radius, radius),          // Top-right
-      (radius, height - radius),         // Bottom-left
-      (width - radius, height - radius), // Bottom-right
-    ];
-
-    for (cx, cy) in &corners {
-      let dx = x - cx;
-      let dy = y - cy;
-      let distance_sq = dx * dx + dy * dy;
-
-      // If we're in this corner's quadrant and within the circle
-      if (x <= radius && y <= radius && cx == &radius && cy == &radius)
-        || (x >= width - radius && y <= radius && cx == &(width - radius) && cy == &radius)
-        || (x <= radius && y >= height - radius && cx == &radius && cy == &(height - radius))
-        || (x >= width - radius
-          && y >= height - radius
-          && cx == &(width - radius)
-          && cy == &(height - radius))
-      {
-        return distance_sq <= radius * radius;
-      }
-    }
-
-    false
+    color: Rgba<u8>,
+  ) {
+    let radius = self.clamp_radius(panel.width, panel.height, self.config.border_radius);
+    let inset = width / 2.0;
+    let Some(path) = rounded_rect_path(
+      panel.x as f32 + inset,
+      panel.y as f32 + inset,
+      panel.width as f32 - width,
+      panel.height as f32 - width,
+      (radius - inset).max(0.0),
+      (radius - inset).max(0.0),
+    ) else {
+      return;
+    };
+    let stroke = Stroke {
+      width,
+      ..Stroke::default()
+    };
+    paint_area(
+      image,
+      panel.x as i32,
+      panel.y as i32,
+      panel.width,
+      panel.height,
+      |pixmap, transform| {
+        pixmap.stroke_path(&path, &solid(color), &stroke, transform, None);
+      },
+    );
   }
 
+  /// Like `draw_rounded_rect`, but only the top corners are rounded
+  #[allow(clippy::too_many_arguments)]
   pub(super) fn draw_rounded_rect_top_only(
     &self,
     image: &mut RgbaImage,
@@ -133,77 +228,269 @@ impl SnippetRenderer {
     radius: f32,
     color: Rgba<u8>,
   ) -> Result<()> {
-    let scaled_radius = radius * self.config.export_size;
-
-    // Clamp radius to not exceed half the smaller dimension
-    let max_radius = (width.min(height) as f32 / 2.0).min(scaled_radius);
-
-    for py in 0..height {
-      for px in 0..width {
-        let pixel_x = x + px as i32;
-        let pixel_y = y + py as i32;
-
-        // Check bounds
-        if pixel_x < 0
-          || pixel_y < 0
-          || pixel_x >= image.width() as i32
-          || pixel_y >= image.height() as i32
-        {
-          continue;
-        }
-
-        if self.is_inside_rounded_rect_top_only(
-          px as f32,
-          py as f32,
-          width as f32,
-          height as f32,
-          max_radius,
-        ) {
-          image.put_pixel(pixel_x as u32, pixel_y as u32, color);
-        }
-      }
-    }
-
+    let radius = self.clamp_radius(width, height, radius);
+    self.fill_rounded_rect(image, x, y, width, height, (radius, 0.0), color);
     Ok(())
   }
 
-  pub(super) fn is_inside_rounded_rect_top_only(
+  /// Stroke straight segments through `points`, back to the first when
+  /// `closed`
+  pub(super) fn stroke_polyline(
     &self,
-    x: f32,
-    y: f32,
+    image: &mut RgbaImage,
+    points: &[(f32, f32)],
+    closed: bool,
     width: f32,
-    _height: f32,
-    radius: f32,
-  ) -> bool {
-    // For title bar, we want rounded corners only at the top
-
-    // Check if point is in the main rectangular area (excluding top corners)
-    if x >= radius && x <= width - radius {
-      return true; // Inside horizontal strip
+    color: Rgba<u8>,
+  ) {
+    let Some((&first, rest)) = points.split_first() else {
+      return;
+    };
+    let mut builder = PathBuilder::new();
+    builder.move_to(first.0, first.1);
+    for point in rest {
+      builder.line_to(point.0, point.1);
     }
-    if y >= radius {
-      return true; // Inside lower area (no rounding at bottom)
+    if closed {
+      builder.close();
     }
+    let Some(path) = builder.finish() else {
+      return;
+    };
+    let stroke = Stroke {
+      width,
+      ..Stroke::default()
+    };
+    let bounds = path.bounds();
+    paint_area(
+      image,
+      (bounds.left() - width).floor() as i32,
+      (bounds.top() - width).floor() as i32,
+      (bounds.width() + width * 2.0).ceil() as u32 + 1,
+      (bounds.height() + width * 2.0).ceil() as u32 + 1,
+      |pixmap, transform| {
+        pixmap.stroke_path(&path, &solid(color), &stroke, transform, None);
+      },
+    );
+  }
 
-    // Check only top corner regions
-    let top_corners = [
-      (radius, radius),         // Top-left
-      (width - radius, radius), // Top-right
-    ];
-
-    for (cx, cy) in &top_corners {
-      let dx = x - cx;
-      let dy = y - cy;
-      let distance_sq = dx * dx + dy * dy;
-
-      // If we're in this corner's quadrant and within the circle
-      if (x <= radius && y <= radius && cx == &radius && cy == &radius)
-        || (x >= width - radius && y <= radius && cx == &(width - radius) && cy == &radius)
-      {
-        return distance_sq <= radius * radius;
-      }
+  /// Stroke a line `width` thick from `from` to `to`, ending in an
+  /// arrowhead whose tip touches `to`
+  pub(super) fn draw_arrow(
+    &self,
+    image: &mut RgbaImage,
+    from: (f32, f32),
+    to: (f32, f32),
+    width: f32,
+    color: Rgba<u8>,
+  ) {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length = (dx * dx + dy * dy).sqrt();
+    if length < 1.0 {
+      return;
     }
+    let (ux, uy) = (dx / length, dy / length);
+    let head = (width * 4.0).min(length);
+    let base = (to.0 - ux * head, to.1 - uy * head);
+
+    let mut shaft = PathBuilder::new();
+    shaft.move_to(from.0, from.1);
+    shaft.line_to(base.0, base.1);
+    let mut tip = PathBuilder::new();
+    tip.move_to(to.0, to.1);
+    tip.line_to(base.0 - uy * head / 2.0, base.1 + ux * head / 2.0);
+    tip.line_to(base.0 + uy * head / 2.0, base.1 - ux * head / 2.0);
+    tip.close();
+    let (Some(shaft), Some(tip)) = (shaft.finish(), tip.finish()) else {
+      return;
+    };
+    let stroke = Stroke {
+      width,
+      line_cap: LineCap::Round,
+      ..Stroke::default()
+    };
+
+    let reach = head + width;
+    let left = from.0.min(to.0) - reach;
+    let top = from.1.min(to.1) - reach;
+    paint_area(
+      image,
+      left.floor() as i32,
+      top.floor() as i32,
+      (dx.abs() + reach * 2.0).ceil() as u32 + 1,
+      (dy.abs() + reach * 2.0).ceil() as u32 + 1,
+      |pixmap, transform| {
+        let paint = solid(color);
+        pixmap.stroke_path(&shaft, &paint, &stroke, transform, None);
+        pixmap.fill_path(&tip, &paint, FillRule::Winding, transform, None);
+      },
+    );
+  }
+
+  /// Scale `radius` by the export size, keeping it within half the smaller
+  /// dimension
+  fn clamp_radius(&self, width: u32, height: u32, radius: f32) -> f32 {
+    (width.min(height) as f32 / 2.0).min(radius * self.config.export_size)
+  }
+
+  /// Fill a rectangle with `top` and `bottom` corner radii
+  #[allow(clippy::too_many_arguments)]
+  fn fill_rounded_rect(
+    &self,
+    image: &mut RgbaImage,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    (top, bottom): (f32, f32),
+    color: Rgba<u8>,
+  ) {
+    let Some(path) =
+      rounded_rect_path(x as f32, y as f32, width as f32, height as f32, top, bottom)
+    else {
+      return;
+    };
+    paint_area(image, x, y, width, height, |pixmap, transform| {
+      pixmap.fill_path(&path, &solid(color), FillRule::Winding, transform, None);
+    });
+  }
+}
+
+/// Antialiased paint of a single color
+fn solid(color: Rgba<u8>) -> Paint<'static> {
+  let mut paint = Paint::default();
+  paint.set_color(Color::from_rgba8(color[0], color[1], color[2], color[3]));
+  paint.anti_alias = true;
+  paint
+}
+
+/// Outline of a rectangle whose top and bottom corners are rounded by
+/// separate radii, `None` when it is empty
+fn rounded_rect_path(
+  x: f32,
+  y: f32,
+  width: f32,
+  height: f32,
+  top: f32,
+  bottom: f32,
+) -> Option<Path> {
+  let (right, bottom_edge) = (x + width, y + height);
+  let mut builder = PathBuilder::new();
+  builder.move_to(x + top, y);
+  builder.line_to(right - top, y);
+  if top > 0.0 {
+    arc(&mut builder, (right, y), (right - top, y), (right, y + top));
+  }
+  builder.line_to(right, bottom_edge - bottom);
+  if bottom > 0.0 {
+    arc(
+      &mut builder,
+      (right, bottom_edge),
+      (right, bottom_edge - bottom),
+      (right - bottom, bottom_edge),
+    );
+  }
+  builder.line_to(x + bottom, bottom_edge);
+  if bottom > 0.0 {
+    arc(
+      &mut builder,
+      (x, bottom_edge),
+      (x + bottom, bottom_edge),
+      (x, bottom_edge - bottom),
+    );
+  }
+  builder.line_to(x, y + top);
+  if top > 0.0 {
+    arc(&mut builder, (x, y), (x, y + top), (x + top, y));
+  }
+  builder.close();
+  builder.finish()
+}
+
+/// Quarter turn from `start` to `end` around the corner at `corner`
+fn arc(builder: &mut PathBuilder, corner: (f32, f32), start: (f32, f32), end: (f32, f32)) {
+  let pull = |from: f32, to: f32| from + (to - from) * KAPPA;
+  builder.cubic_to(
+    pull(start.0, corner.0),
+    pull(start.1, corner.1),
+    pull(end.0, corner.0),
+    pull(end.1, corner.1),
+    end.0,
+    end.1,
+  );
+}
+
+/// Hand `draw` a premultiplied copy of the part of `image` inside the given
+/// area, with the transform that maps image coordinates onto it, then write
+/// back only the pixels it changed so untouched translucent pixels do not
+/// pick up rounding from the round trip
+fn paint_area(
+  image: &mut RgbaImage,
+  x: i32,
+  y: i32,
+  width: u32,
+  height: u32,
+  draw: impl FnOnce(&mut Pixmap, Transform),
+) {
+  let left = x.max(0) as u32;
+  let top = y.max(0) as u32;
+  let right = (x.saturating_add(width as i32).max(0) as u32).min(image.width());
+  let bottom = (y.saturating_add(height as i32).max(0) as u32).min(image.height());
+  if left >= right || top >= bottom {
+    return;
+  }
+  let Some(mut pixmap) = Pixmap::new(right - left, bottom - top) else {
+    return;
+  };
+  let premultiply =
+    |pixel: &Rgba<u8>| ColorU8::from_rgba(pixel[0], pixel[1], pixel[2], pixel[3]).premultiply();
+  let area_width = pixmap.width();
+  for (index, target) in pixmap.pixels_mut().iter_mut().enumerate() {
+    let (px, py) = (index as u32 % area_width, index as u32 / area_width);
+    *target = premultiply(image.get_pixel(left + px, top + py));
+  }
+  draw(
+    &mut pixmap,
+    Transform::from_translate(-(left as f32), -(top as f32)),
+  );
+  for (index, painted) in pixmap.pixels().iter().enumerate() {
+    let (px, py) = (
+      left + index as u32 % area_width,
+      top + index as u32 / area_width,
+    );
+    let pixel = image.get_pixel_mut(px, py);
+    if premultiply(pixel) != *painted {
+      let color = painted.demultiply();
+      *pixel = Rgba([color.red(), color.green(), color.blue(), color.alpha()]);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
 
-    false
+  #[test]
+  fn test_rounded_rect_edges_are_antialiased() {
+    let mut image = RgbaImage::new(120, 60);
+    let path = rounded_rect_path(10.0, 10.0, 100.0, 40.0, 10.0, 10.0).unwrap();
+    paint_area(&mut image, 10, 10, 100, 40, |pixmap, transform| {
+      pixmap.fill_path(
+        &path,
+        &solid(Rgba([200, 40, 40, 255])),
+        FillRule::Winding,
+        transform,
+        None,
+      );
+    });
+    // Straight edges and the interior are solid, and untouched pixels stay clear
+    assert_eq!(*image.get_pixel(10, 30), Rgba([200, 40, 40, 255]));
+    assert_eq!(*image.get_pixel(60, 10), Rgba([200, 40, 40, 255]));
+    assert_eq!(image.get_pixel(9, 30)[3], 0);
+    // The corner pixel is outside the arc, and the arc itself is blended
+    assert_eq!(image.get_pixel(10, 10)[3], 0);
+    let arc = image.get_pixel(13, 12)[3];
+    assert!(arc > 0 && arc < 255, "{}", arc);
+    assert_eq!(image.get_pixel(109, 49)[3], 0);
   }
 }
@@ -0,0 +1,57 @@
+/* ~~/src/renderer/focus.rs */
+
+// standard crates
+use std::borrow::Cow;
+
+// third-party crates
+use image::Rgba;
+
+// local modules
+use crate::renderer::SnippetRenderer;
+use crate::renderer::color::desaturate;
+use crate::syntax::HighlightedToken;
+use crate::themes::ThemeColor;
+
+/// How far text outside `focus_lines` is pulled toward gray
+const UNFOCUSED_DESATURATION: f32 = 0.7;
+
+impl SnippetRenderer {
+  /// Alpha of text on lines outside `focus_lines`
+  fn unfocused_alpha(&self) -> u8 {
+    (255.0 * self.config.unfocused_opacity.clamp(0.0, 1.0)).round() as u8
+  }
+
+  /// `color` as text on the 0-based line is drawn, grayed and translucent
+  /// when the line is out of focus
+  pub(super) fn focus_color(&self, line_index: usize, color: Rgba<u8>) -> Rgba<u8> {
+    if !self.config.is_line_unfocused(line_index as u32 + 1) {
+      return color;
+    }
+    let color = desaturate(color, UNFOCUSED_DESATURATION);
+    Rgba([color[0], color[1], color[2], self.unfocused_alpha()])
+  }
+
+  /// Tokens of the 0-based line with their colors grayed when it is out of
+  /// focus, along with the opacity to draw them at
+  pub(super) fn focus_tokens<'a>(
+    &self,
+    tokens: &'a [HighlightedToken],
+    line_index: usize,
+  ) -> (Cow<'a, [HighlightedToken]>, u8) {
+    if !self.config.is_line_unfocused(line_index as u32 + 1) {
+      return (Cow::Borrowed(tokens), 255);
+    }
+    let grayed = tokens
+      .iter()
+      .map(|token| {
+        let (r, g, b) = token.color.rgb;
+        let [r, g, b, _] = desaturate(Rgba([r, g, b, 255]), UNFOCUSED_DESATURATION).0;
+        HighlightedToken {
+          color: ThemeColor::new(&format!("#{:02x}{:02x}{:02x}", r, g, b)),
+          ..token.clone()
+        }
+      })
+      .collect();
+    (Cow::Owned(grayed), self.unfocused_alpha())
+  }
+}
@@ -0,0 +1,86 @@
+/* ~~/src/renderer/frost.rs */
+
+// third-party crates
+use anyhow::Result;
+use image::imageops;
+use image::{Rgba, RgbaImage};
+
+// local modules
+use crate::config::RenderLayer;
+use crate::renderer::backdrop::blurred;
+use crate::renderer::{PanelRect, SnippetRenderer};
+
+impl SnippetRenderer {
+  /// `color` faded to the frosted glass opacity, unchanged for an opaque
+  /// panel
+  pub(super) fn glass(&self, color: Rgba<u8>) -> Rgba<u8> {
+    match &self.config.frosted_glass {
+      Some(frost) => {
+        let alpha = color[3] as f32 * frost.opacity.clamp(0.0, 1.0);
+        Rgba([color[0], color[1], color[2], alpha.round() as u8])
+      }
+      None => color,
+    }
+  }
+
+  /// Fill the panel with `color`, over a blurred copy of the backdrop behind
+  /// it when `frosted_glass` is set
+  pub(super) fn draw_panel(
+    &self,
+    image: &mut RgbaImage,
+    panel: PanelRect,
+    color: Rgba<u8>,
+  ) -> Result<()> {
+    let radius = self.config.border_radius;
+    // A panel layer has no backdrop to blur, so the glass stays clear for
+    // whatever it is composited over
+    let blur = match (&self.config.frosted_glass, self.config.layer) {
+      (Some(frost), RenderLayer::Full) => frost.blur.max(0.0) * self.config.export_size,
+      _ => 0.0,
+    };
+    if blur > 0.0 {
+      // Blur a margin around the panel too, so its edges take in the backdrop
+      // beyond them rather than fading out
+      let margin = (blur * 3.0).ceil() as u32;
+      let left = panel.x.saturating_sub(margin);
+      let top = panel.y.saturating_sub(margin);
+      let right = (panel.x + panel.width + margin).min(image.width());
+      let bottom = (panel.y + panel.height + margin).min(image.height());
+      let behind = imageops::crop_imm(image, left, top, right - left, bottom - top).to_image();
+      let behind = blurred(&behind, behind.width(), behind.height(), blur);
+      let mut frosted = imageops::crop_imm(
+        &behind,
+        panel.x - left,
+        panel.y - top,
+        panel.width,
+        panel.height,
+      )
+      .to_image();
+
+      // Keep the blur inside the panel's rounded corners
+      let mut corners = RgbaImage::new(panel.width, panel.height);
+      self.draw_rounded_rect(
+        &mut corners,
+        0,
+        0,
+        panel.width,
+        panel.height,
+        radius,
+        Rgba([255, 255, 255, 255]),
+      )?;
+      for (pixel, coverage) in frosted.pixels_mut().zip(corners.pixels()) {
+        pixel[3] = coverage[3];
+      }
+      self.draw_image(image, &frosted, panel.x as i32, panel.y as i32, 1.0);
+    }
+    self.draw_rounded_rect(
+      image,
+      panel.x as i32,
+      panel.y as i32,
+      panel.width,
+      panel.height,
+      radius,
+      self.glass(color),
+    )
+  }
+}
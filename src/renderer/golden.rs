@@ -0,0 +1,686 @@
+/* ~~/src/renderer/golden.rs */
+
+// standard crates
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// third-party crates
+use image::{Rgba, RgbaImage};
+
+// local modules
+use crate::config::{
+  Annotation, AnnotationSide, BackdropPattern, Blame, BlameSide, Border, ControlFill, Corner,
+  Cursor, CursorStyle, Diagnostic, Fit, FrostedGlass, GradientDirection, LineRange, PanelSize,
+  PatternKind, Position, RenderConfig, RenderLayer, Selection, SelectionMode, Severity, Sides,
+  TitleIcon, Watermark, WindowStyle,
+};
+use crate::font::create_fallback_font;
+use crate::limits::RenderGuard;
+use crate::renderer::SnippetRenderer;
+use crate::themes::ThemeVariant;
+
+/// Set to regenerate the references under `tests/golden/` from the current
+/// renders instead of comparing against them
+const UPDATE_VARIABLE: &str = "SHOYU_UPDATE_GOLDEN";
+/// Per-pixel YIQ distance, as a fraction of the largest possible, below which
+/// pixels count as equal; absorbs antialiasing jitter between toolchains
+const PIXEL_THRESHOLD: f64 = 0.03;
+/// Share of pixels allowed to differ before a render counts as changed
+const MAX_MISMATCH_RATIO: f64 = 0.001;
+/// Mean structural similarity a render must keep against its reference
+const MIN_SSIM: f64 = 0.995;
+/// Side of the square windows SSIM is averaged over
+const SSIM_WINDOW: u32 = 8;
+
+const SNIPPET: &str = "/// Greets the world\nfn main() {\n\tlet name = \"shoyu\";\n\tprintln!(\"hello {}\", name); // => stdout\n}\n";
+
+/// How far a render strays from its reference
+struct Comparison {
+  mismatched: u64,
+  ssim: f64,
+  /// Reference faded to gray with mismatched pixels in red
+  diff: RgbaImage,
+}
+
+impl Comparison {
+  fn mismatch_ratio(&self) -> f64 {
+    self.mismatched as f64 / (self.diff.width() as u64 * self.diff.height() as u64).max(1) as f64
+  }
+
+  fn passes(&self) -> bool {
+    self.mismatch_ratio() <= MAX_MISMATCH_RATIO && self.ssim >= MIN_SSIM
+  }
+}
+
+/// Pixel over white, so transparent areas compare by what a viewer sees
+fn flatten(pixel: Rgba<u8>) -> [f64; 3] {
+  let alpha = pixel[3] as f64 / 255.0;
+  [0, 1, 2].map(|channel| 255.0 + (pixel[channel] as f64 - 255.0) * alpha)
+}
+
+fn luma([r, g, b]: [f64; 3]) -> f64 {
+  0.298_895_31 * r + 0.586_622_47 * g + 0.114_482_23 * b
+}
+
+/// Squared YIQ distance between two pixels, weighted toward brightness the
+/// way pixelmatch does
+fn color_delta(a: Rgba<u8>, b: Rgba<u8>) -> f64 {
+  let (a, b) = (flatten(a), flatten(b));
+  let i = |[r, g, b]: [f64; 3]| 0.595_977_99 * r - 0.274_176_40 * g - 0.321_801_59 * b;
+  let q = |[r, g, b]: [f64; 3]| 0.211_470_17 * r - 0.522_617_20 * g + 0.311_147_03 * b;
+  let (y, i, q) = (luma(a) - luma(b), i(a) - i(b), q(a) - q(b));
+  0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q
+}
+
+/// Mean SSIM of luma over non-overlapping windows
+fn ssim(expected: &RgbaImage, actual: &RgbaImage) -> f64 {
+  const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+  const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+  let (width, height) = expected.dimensions();
+  let mut total = 0.0;
+  let mut windows = 0;
+  for top in (0..height).step_by(SSIM_WINDOW as usize) {
+    for left in (0..width).step_by(SSIM_WINDOW as usize) {
+      let samples: Vec<(f64, f64)> = (top..(top + SSIM_WINDOW).min(height))
+        .flat_map(|y| (left..(left + SSIM_WINDOW).min(width)).map(move |x| (x, y)))
+        .map(|(x, y)| {
+          (
+            luma(flatten(*expected.get_pixel(x, y))),
+            luma(flatten(*actual.get_pixel(x, y))),
+          )
+        })
+        .collect();
+      let count = samples.len() as f64;
+      let mean_a = samples.iter().map(|(a, _)| a).sum::<f64>() / count;
+      let mean_b = samples.iter().map(|(_, b)| b).sum::<f64>() / count;
+      let (mut var_a, mut var_b, mut covariance) = (0.0, 0.0, 0.0);
+      for (a, b) in &samples {
+        var_a += (a - mean_a) * (a - mean_a);
+        var_b += (b - mean_b) * (b - mean_b);
+        covariance += (a - mean_a) * (b - mean_b);
+      }
+      let (var_a, var_b, covariance) = (var_a / count, var_b / count, covariance / count);
+      total += ((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2))
+        / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2));
+      windows += 1;
+    }
+  }
+  match windows {
+    0 => 1.0,
+    _ => total / windows as f64,
+  }
+}
+
+/// Perceptual comparison of two renders, `None` when their sizes differ
+fn compare(expected: &RgbaImage, actual: &RgbaImage) -> Option<Comparison> {
+  if expected.dimensions() != actual.dimensions() {
+    return None;
+  }
+  // Largest possible delta, between black and white
+  let max_delta = 35215.0 * PIXEL_THRESHOLD * PIXEL_THRESHOLD;
+  let mut mismatched = 0;
+  let diff = RgbaImage::from_fn(expected.width(), expected.height(), |x, y| {
+    let (a, b) = (*expected.get_pixel(x, y), *actual.get_pixel(x, y));
+    if color_delta(a, b) > max_delta {
+      mismatched += 1;
+      return Rgba([255, 0, 0, 255]);
+    }
+    let gray = (255.0 - (255.0 - luma(flatten(a))) * 0.1) as u8;
+    Rgba([gray, gray, gray, 255])
+  });
+  Some(Comparison {
+    mismatched,
+    ssim: ssim(expected, actual),
+    diff,
+  })
+}
+
+/// Render `code` with the embedded font, so references do not depend on
+/// the fonts installed where the tests run
+fn render(theme: &str, config: RenderConfig, code: &str, language: &str) -> RgbaImage {
+  render_tabbed(theme, config, code, language, &[])
+}
+
+/// [`render`] with `tabs` in the title bar, the first one active
+fn render_tabbed(
+  theme: &str,
+  config: RenderConfig,
+  code: &str,
+  language: &str,
+  tabs: &[&str],
+) -> RgbaImage {
+  let mut renderer = SnippetRenderer::new(theme, config).unwrap();
+  let mut font_manager = create_fallback_font(renderer.config.get_scaled_font_size(), &[]).unwrap();
+  font_manager.set_ligatures(renderer.config.ligatures);
+  renderer.font_manager = font_manager;
+  renderer.tab_titles = tabs.iter().map(|tab| tab.to_string()).collect();
+  let lines = renderer.highlight_snippet(code, language);
+  let lines = renderer.limit_lines(lines);
+  let guard = RenderGuard::acquire().unwrap();
+  let image = renderer.rasterize(&guard, &lines).unwrap();
+  guard.complete();
+  image
+}
+
+/// Small canvas and a pinned backdrop keep references small and repeatable
+fn base_config() -> RenderConfig {
+  RenderConfig {
+    width: PanelSize::Fixed(520),
+    export_size: 1.0,
+    panel_padding: Sides::uniform(16),
+    noise_effect: false,
+    gradient_seed: Some(7),
+    ..RenderConfig::default()
+  }
+}
+
+fn root() -> &'static Path {
+  Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+/// Compare `actual` against `tests/golden/<name>.png`, writing the render and
+/// a diff under `target/golden-diff/` for review when they differ
+fn check(name: &str, actual: &RgbaImage) -> Result<(), String> {
+  let reference = root().join("tests/golden").join(format!("{}.png", name));
+  if std::env::var_os(UPDATE_VARIABLE).is_some() {
+    fs::create_dir_all(reference.parent().unwrap()).unwrap();
+    actual.save(&reference).unwrap();
+    return Ok(());
+  }
+  let Ok(expected) = image::open(&reference) else {
+    return Err(format!(
+      "{}: no reference, run with {}=1 to create it",
+      name, UPDATE_VARIABLE
+    ));
+  };
+  let expected = expected.to_rgba8();
+  let output: PathBuf = root().join("target/golden-diff");
+  let keep = |diff: Option<&RgbaImage>| {
+    fs::create_dir_all(&output).unwrap();
+    actual
+      .save(output.join(format!("{}.actual.png", name)))
+      .unwrap();
+    if let Some(diff) = diff {
+      diff
+        .save(output.join(format!("{}.diff.png", name)))
+        .unwrap();
+    }
+  };
+  match compare(&expected, actual) {
+    None => {
+      keep(None);
+      Err(format!(
+        "{}: size changed from {:?} to {:?}",
+        name,
+        expected.dimensions(),
+        actual.dimensions()
+      ))
+    }
+    Some(comparison) if !comparison.passes() => {
+      keep(Some(&comparison.diff));
+      Err(format!(
+        "{}: {:.3}% of pixels differ, SSIM {:.4}; see target/golden-diff/{}.diff.png",
+        name,
+        comparison.mismatch_ratio() * 100.0,
+        comparison.ssim,
+        name
+      ))
+    }
+    Some(_) => Ok(()),
+  }
+}
+
+#[test]
+fn test_perceptual_diff_ignores_faint_changes() {
+  let base = RgbaImage::from_fn(64, 64, |x, y| Rgba([(x * 4) as u8, (y * 4) as u8, 90, 255]));
+  let same = compare(&base, &base).unwrap();
+  assert_eq!(same.mismatched, 0);
+  assert!((same.ssim - 1.0).abs() < 1e-9);
+
+  // Off-by-one rounding stays under the threshold
+  let mut jitter = base.clone();
+  jitter
+    .pixels_mut()
+    .for_each(|pixel| pixel[0] = pixel[0].saturating_add(1));
+  assert!(compare(&base, &jitter).unwrap().passes());
+
+  let mut block = base.clone();
+  for y in 20..30 {
+    for x in 20..30 {
+      block.put_pixel(x, y, Rgba([255, 255, 255, 255]));
+    }
+  }
+  let changed = compare(&base, &block).unwrap();
+  assert_eq!(changed.mismatched, 100);
+  assert!(!changed.passes());
+  assert_eq!(*changed.diff.get_pixel(25, 25), Rgba([255, 0, 0, 255]));
+  assert!(compare(&base, &RgbaImage::new(32, 32)).is_none());
+}
+
+#[test]
+fn test_renders_match_golden_references() {
+  let cases: Vec<(&str, &str, RenderConfig)> = vec![
+    ("theme-dracula", "dracula", base_config()),
+    ("theme-github", "github", base_config()),
+    ("theme-solarized-light", "solarized-light", base_config()),
+    ("theme-rose-pine-dawn", "rose-pine-dawn", base_config()),
+    ("theme-catppuccin-mocha", "catppuccin-mocha", base_config()),
+    (
+      "variant-auto-light",
+      "dracula",
+      RenderConfig {
+        variant: ThemeVariant::AutoLight,
+        ..base_config()
+      },
+    ),
+    (
+      "gutter",
+      "nord",
+      RenderConfig {
+        line_numbers: true,
+        gutter_separator: true,
+        line_number_start: 9,
+        ..base_config()
+      },
+    ),
+    (
+      "title",
+      "one-dark",
+      RenderConfig {
+        window_title: Some("main.rs".to_string()),
+        title_icon: TitleIcon::Badge,
+        ..base_config()
+      },
+    ),
+    (
+      "project",
+      "solarized-dark",
+      RenderConfig {
+        window_title: Some("/work/shoyu/src/main.rs".to_string()),
+        project_root: Some("/work/shoyu".to_string()),
+        project_icon: Some("#".to_string()),
+        ..base_config()
+      },
+    ),
+    (
+      "highlight-lines",
+      "gruvbox",
+      RenderConfig {
+        highlight_lines: vec![LineRange { start: 3, end: 4 }],
+        text_width: 30,
+        ..base_config()
+      },
+    ),
+    (
+      "annotations",
+      "dracula",
+      RenderConfig {
+        width: PanelSize::Auto,
+        annotations: vec![
+          Annotation {
+            line: 3,
+            text: "owned".to_string(),
+            side: AnnotationSide::Left,
+          },
+          Annotation {
+            line: 4,
+            text: "prints once".to_string(),
+            side: AnnotationSide::Right,
+          },
+        ],
+        ..base_config()
+      },
+    ),
+    (
+      "focus",
+      "one-dark",
+      RenderConfig {
+        focus_lines: vec![LineRange { start: 3, end: 4 }],
+        ..base_config()
+      },
+    ),
+    (
+      "watermark",
+      "nord",
+      RenderConfig {
+        panel_padding: Sides::uniform(40),
+        watermark: Some(Watermark {
+          text: "@shoyu.nvim".to_string(),
+          ..Watermark::default()
+        }),
+        ..base_config()
+      },
+    ),
+    (
+      "strips",
+      "github",
+      RenderConfig {
+        header: Some("{path}".to_string()),
+        footer: Some("{filename} · shared for review".to_string()),
+        source_path: Some("src/greet.rs".to_string()),
+        line_numbers: true,
+        gutter_separator: true,
+        ..base_config()
+      },
+    ),
+    (
+      "language-badge",
+      "dracula",
+      RenderConfig {
+        language_badge: Some(Corner::BottomRight),
+        ..base_config()
+      },
+    ),
+    (
+      "window-windows",
+      "nord",
+      RenderConfig {
+        window_style: WindowStyle::Windows,
+        window_title: Some("main.rs".to_string()),
+        project_name: Some("shoyu".to_string()),
+        ..base_config()
+      },
+    ),
+    (
+      "window-linux",
+      "github",
+      RenderConfig {
+        window_style: WindowStyle::Linux,
+        control_fill: ControlFill::Hollow,
+        ..base_config()
+      },
+    ),
+    (
+      "border",
+      "github",
+      RenderConfig {
+        border: Some(Border::default()),
+        gradient_colors: vec!["#f6f8fa".to_string(), "#ffffff".to_string()],
+        ..base_config()
+      },
+    ),
+    (
+      "gradient-diagonal",
+      "one-dark",
+      RenderConfig {
+        gradient_colors: vec!["#ff7e5f".to_string(), "#6a82fb".to_string()],
+        gradient_direction: Some(GradientDirection::Diagonal),
+        ..base_config()
+      },
+    ),
+    (
+      "gradient-dither",
+      "nord",
+      RenderConfig {
+        gradient_colors: vec!["#2e3440".to_string(), "#434c5e".to_string()],
+        gradient_dither: true,
+        ..base_config()
+      },
+    ),
+    (
+      "frosted-glass",
+      "one-dark",
+      RenderConfig {
+        frosted_glass: Some(FrostedGlass::default()),
+        noise_effect: true,
+        ..base_config()
+      },
+    ),
+    (
+      "backdrop-dots",
+      "nord",
+      RenderConfig {
+        backdrop: Some(BackdropPattern {
+          kind: PatternKind::Dots,
+          ..BackdropPattern::default()
+        }),
+        ..base_config()
+      },
+    ),
+    (
+      "backdrop-stripes",
+      "gruvbox",
+      RenderConfig {
+        backdrop: Some(BackdropPattern {
+          kind: PatternKind::Stripes,
+          colors: vec!["#282828".to_string(), "#3c3836".to_string()],
+          ..BackdropPattern::default()
+        }),
+        ..base_config()
+      },
+    ),
+    (
+      "backdrop-waves",
+      "one-dark",
+      RenderConfig {
+        backdrop: Some(BackdropPattern {
+          kind: PatternKind::Waves,
+          density: 1.5,
+          rotation: -15.0,
+          ..BackdropPattern::default()
+        }),
+        ..base_config()
+      },
+    ),
+    (
+      "backdrop-mesh",
+      "catppuccin-mocha",
+      RenderConfig {
+        backdrop: Some(BackdropPattern {
+          kind: PatternKind::Mesh,
+          ..BackdropPattern::default()
+        }),
+        ..base_config()
+      },
+    ),
+    (
+      "padding-sides",
+      "one-dark",
+      RenderConfig {
+        width: PanelSize::Auto,
+        padding: Sides {
+          top: 16,
+          right: 96,
+          bottom: 40,
+          left: 24,
+        },
+        panel_padding: Sides {
+          left: 48,
+          ..Sides::uniform(16)
+        },
+        ..base_config()
+      },
+    ),
+    (
+      "max-lines",
+      "dracula",
+      RenderConfig {
+        max_lines: 3,
+        line_numbers: true,
+        ..base_config()
+      },
+    ),
+    (
+      "selection",
+      "one-dark",
+      RenderConfig {
+        selection: Some(Selection {
+          start: Position { line: 2, column: 4 },
+          end: Position {
+            line: 4,
+            column: 12,
+          },
+          mode: SelectionMode::Char,
+        }),
+        ..base_config()
+      },
+    ),
+    (
+      "cursor",
+      "dracula",
+      RenderConfig {
+        cursor: Some(Cursor {
+          line: 2,
+          column: 4,
+          style: CursorStyle::Block,
+          color: None,
+        }),
+        ..base_config()
+      },
+    ),
+    (
+      "bracket-pairs",
+      "gruvbox",
+      RenderConfig {
+        bracket_pairs: true,
+        ..base_config()
+      },
+    ),
+    (
+      "diagnostics",
+      "one-dark",
+      RenderConfig {
+        line_numbers: true,
+        diagnostics: vec![
+          Diagnostic {
+            line: 3,
+            col_start: 9,
+            col_end: 12,
+            severity: Severity::Warning,
+            message: Some("unused variable".to_string()),
+          },
+          Diagnostic {
+            line: 4,
+            col_start: 5,
+            col_end: 12,
+            severity: Severity::Error,
+            message: None,
+          },
+        ],
+        ..base_config()
+      },
+    ),
+    (
+      "blame",
+      "nord",
+      RenderConfig {
+        line_numbers: true,
+        blame: Some(Blame {
+          lines: vec![
+            "ada 2y".to_string(),
+            "ada 2y".to_string(),
+            String::new(),
+            "linus 3d".to_string(),
+          ],
+          side: BlameSide::Left,
+        }),
+        ..base_config()
+      },
+    ),
+    (
+      "blame-right",
+      "nord",
+      RenderConfig {
+        width: PanelSize::Auto,
+        blame: Some(Blame {
+          lines: vec!["ada 2y".to_string(), String::new(), "linus 3d".to_string()],
+          side: BlameSide::Right,
+        }),
+        ..base_config()
+      },
+    ),
+    (
+      "columns",
+      "gruvbox",
+      RenderConfig {
+        width: PanelSize::Auto,
+        columns: 2,
+        line_numbers: true,
+        gutter_separator: true,
+        highlight_lines: vec![LineRange { start: 4, end: 4 }],
+        ..base_config()
+      },
+    ),
+    (
+      "fit-tight",
+      "dracula",
+      RenderConfig {
+        fit: Fit::Tight,
+        ..base_config()
+      },
+    ),
+    (
+      "minimap",
+      "one-dark",
+      RenderConfig {
+        width: PanelSize::Auto,
+        minimap: true,
+        focus_lines: vec![LineRange { start: 2, end: 4 }],
+        ..base_config()
+      },
+    ),
+    (
+      "qr-link",
+      "monokai",
+      RenderConfig {
+        panel_padding: Sides {
+          bottom: 100,
+          ..Sides::uniform(16)
+        },
+        qr_link: Some("https://github.com/aekasitt/shoyu.nvim".to_string()),
+        ..base_config()
+      },
+    ),
+    (
+      "panel-layer",
+      "monokai",
+      RenderConfig {
+        layer: RenderLayer::Panel,
+        window_controls: false,
+        border_radius: 12.0,
+        ..base_config()
+      },
+    ),
+  ];
+  let mut failures: Vec<String> = cases
+    .into_iter()
+    .filter_map(|(name, theme, config)| check(name, &render(theme, config, SNIPPET, "rust")).err())
+    .collect();
+  let tabs = render_tabbed(
+    "one-dark",
+    base_config(),
+    SNIPPET,
+    "rust",
+    &["main.rs", "lib.rs", "config.rs"],
+  );
+  failures.extend(check("tabs", &tabs).err());
+  let terminal = render(
+    "dracula",
+    base_config(),
+    "\x1b[1;32mok\x1b[0m parses config\n\x1b[1;31mno\x1b[0m renders \x1b[38;5;214mgolden\x1b[0m\n\x1b[7m FAIL \x1b[27m \x1b[41;97m 1 failed \x1b[0m\n",
+    "ansi",
+  );
+  failures.extend(check("ansi", &terminal).err());
+  // Too narrow for all three buttons, which keeps the rightmost that fit
+  let narrow = render(
+    "nord",
+    RenderConfig {
+      width: PanelSize::Auto,
+      fit: Fit::Tight,
+      window_style: WindowStyle::Windows,
+      padding: Sides::uniform(4),
+      panel_padding: Sides::uniform(0),
+      gradient_backdrop: false,
+      ..base_config()
+    },
+    "x\n",
+    "python",
+  );
+  failures.extend(check("window-windows-narrow", &narrow).err());
+  assert!(
+    failures.is_empty(),
+    "Renders changed; if intended, rerun with {}=1 and commit tests/golden/\n{}",
+    UPDATE_VARIABLE,
+    failures.join("\n")
+  );
+}
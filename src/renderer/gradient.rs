@@ -3,12 +3,30 @@
 // third-party crates
 use anyhow::Result;
 use image::{Rgba, RgbaImage};
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
 // local modules
+use crate::config::{GradientDirection, gradient_preset};
 use crate::renderer::SnippetRenderer;
 use crate::renderer::color::rgba_from_hex;
 
+/// Rows each parallel job fills
+const BAND_ROWS: u32 = 32;
+
+/// 8x8 Bayer matrix; each cell's rank spreads rounding thresholds evenly
+/// over every 8x8 tile
+const BAYER: [[u8; 8]; 8] = [
+  [0, 32, 8, 40, 2, 34, 10, 42],
+  [48, 16, 56, 24, 50, 18, 58, 26],
+  [12, 44, 4, 36, 14, 46, 6, 38],
+  [60, 28, 52, 20, 62, 30, 54, 22],
+  [3, 35, 11, 43, 1, 33, 9, 41],
+  [51, 19, 59, 27, 49, 17, 57, 25],
+  [15, 47, 7, 39, 13, 45, 5, 37],
+  [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
 impl SnippetRenderer {
   pub(super) fn draw_gradient_backdrop(
     &self,
@@ -16,32 +34,90 @@ impl SnippetRenderer {
     width: u32,
     height: u32,
   ) -> Result<()> {
-    let mut rng = rand::thread_rng();
-
-    // Generate random gradient colors
-    let color1 = self.generate_random_gradient_color(&mut rng);
-    let color2 = self.generate_random_gradient_color(&mut rng);
-
-    // Choose random gradient direction
-    let gradient_type = rng.gen_range(0..4);
-
-    for y in 0..height {
-      for x in 0..width {
-        let pixel_color = match gradient_type {
-          0 => self.linear_gradient_horizontal(x, width, color1, color2),
-          1 => self.linear_gradient_vertical(y, height, color1, color2),
-          2 => self.radial_gradient(x, y, width, height, color1, color2),
-          _ => self.diagonal_gradient(x, y, width, height, color1, color2),
-        };
-        // Apply noise effect if enabled
-        let final_color = if self.config.noise_effect {
-          self.apply_noise_effect(pixel_color, &mut rng)
-        } else {
-          pixel_color
-        };
-        image.put_pixel(x, y, final_color);
+    let mut rng = match self.config.gradient_seed {
+      Some(seed) => StdRng::seed_from_u64(seed),
+      None => StdRng::from_entropy(),
+    };
+
+    // Explicit stops win over a named preset; otherwise generate a random pair
+    // that complements the theme
+    let preset = self
+      .config
+      .gradient_preset
+      .as_deref()
+      .and_then(gradient_preset);
+    let stops = if !self.config.gradient_colors.is_empty() {
+      self
+        .config
+        .gradient_colors
+        .iter()
+        .map(|hex| rgba_from_hex(hex))
+        .collect::<Result<Vec<_>>>()?
+    } else if let Some(preset) = preset {
+      preset
+        .colors
+        .iter()
+        .map(|hex| rgba_from_hex(hex))
+        .collect::<Result<Vec<_>>>()?
+    } else {
+      vec![
+        self.generate_random_gradient_color(&mut rng),
+        self.generate_random_gradient_color(&mut rng),
+      ]
+    };
+
+    // Choose random gradient direction unless one is configured or preset
+    let direction = match self
+      .config
+      .gradient_direction
+      .or(preset.map(|preset| preset.direction))
+    {
+      Some(direction) => direction,
+      None => match rng.gen_range(0..4) {
+        0 => GradientDirection::Horizontal,
+        1 => GradientDirection::Vertical,
+        2 => GradientDirection::Radial,
+        _ => GradientDirection::Diagonal,
+      },
+    };
+
+    // Bands of rows draw in parallel, each row seeding its own noise so the
+    // result does not depend on how many threads share the work
+    let gradient = Gradient::new(direction, &stops, width, height);
+    let dither = self.config.gradient_dither;
+    let noise_seed: u64 = rng.r#gen();
+    let row_bytes = width as usize * 4;
+    self.for_each_chunk(image, row_bytes * BAND_ROWS as usize, |index, band| {
+      let top = index as u32 * BAND_ROWS;
+      for (offset, row) in band.chunks_exact_mut(row_bytes).enumerate() {
+        let y = top + offset as u32;
+        let mut rng = StdRng::seed_from_u64(noise_seed.wrapping_add(y as u64));
+        for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+          let x = x as u32;
+          // Rounding thresholds vary per pixel in a fine pattern, so a
+          // band between two 8-bit levels becomes a mix of both
+          let threshold = match dither {
+            true => (BAYER[y as usize % 8][x as usize % 8] as f32 + 0.5) / 64.0 - 0.5,
+            false => 0.0,
+          };
+          let [r, g, b, a] = gradient.color_at(x, y);
+          let quantize =
+            |channel: f32| (channel * 255.0 + threshold).round().clamp(0.0, 255.0) as u8;
+          let color = Rgba([
+            quantize(r),
+            quantize(g),
+            quantize(b),
+            (a * 255.0).round() as u8,
+          ]);
+          let color = match self.config.noise_effect {
+            true => self.apply_noise_effect(color, &mut rng),
+            false => color,
+          };
+          pixel.copy_from_slice(&color.0);
+        }
       }
-    }
+      Ok(())
+    })?;
     Ok(())
   }
 
@@ -67,77 +143,119 @@ impl SnippetRenderer {
     Rgba([r, g, b, 255])
   }
 
-  pub(super) fn linear_gradient_horizontal(
-    &self,
-    x: u32,
-    width: u32,
-    color1: Rgba<u8>,
-    color2: Rgba<u8>,
-  ) -> Rgba<u8> {
-    let ratio = x as f32 / width as f32;
-    self.interpolate_color(color1, color2, ratio)
+  pub(super) fn apply_noise_effect(&self, color: Rgba<u8>, rng: &mut impl Rng) -> Rgba<u8> {
+    let noise_strength = 15.0; // Adjust noise intensity
+    let noise = rng.gen_range(-noise_strength..noise_strength);
+    let r = ((color[0] as f32 + noise).clamp(0.0, 255.0)) as u8;
+    let g = ((color[1] as f32 + noise).clamp(0.0, 255.0)) as u8;
+    let b = ((color[2] as f32 + noise).clamp(0.0, 255.0)) as u8;
+    Rgba([r, g, b, color[3]])
   }
+}
 
-  pub(super) fn linear_gradient_vertical(
-    &self,
-    y: u32,
-    height: u32,
-    color1: Rgba<u8>,
-    color2: Rgba<u8>,
-  ) -> Rgba<u8> {
-    let ratio = y as f32 / height as f32;
-    self.interpolate_color(color1, color2, ratio)
+/// `stops` spread evenly across a `width`x`height` canvas, evaluated in
+/// floating point so quantizing to 8 bits happens once, per pixel
+struct Gradient {
+  direction: GradientDirection,
+  stops: Vec<[f32; 4]>,
+  width: f32,
+  height: f32,
+}
+
+impl Gradient {
+  fn new(direction: GradientDirection, stops: &[Rgba<u8>], width: u32, height: u32) -> Self {
+    let mut stops: Vec<[f32; 4]> = stops
+      .iter()
+      .map(|stop| stop.0.map(|channel| channel as f32 / 255.0))
+      .collect();
+    if stops.is_empty() {
+      stops.push([30.0 / 255.0, 30.0 / 255.0, 30.0 / 255.0, 1.0]);
+    }
+    Self {
+      direction,
+      stops,
+      width: width.max(1) as f32,
+      height: height.max(1) as f32,
+    }
   }
 
-  pub(super) fn radial_gradient(
-    &self,
-    x: u32,
-    y: u32,
-    width: u32,
-    height: u32,
-    color1: Rgba<u8>,
-    color2: Rgba<u8>,
-  ) -> Rgba<u8> {
-    let center_x = width as f32 / 2.0;
-    let center_y = height as f32 / 2.0;
-    let max_distance = ((width * width + height * height) as f32).sqrt() / 2.0;
-    let distance = ((x as f32 - center_x).powi(2) + (y as f32 - center_y).powi(2)).sqrt();
-    let ratio = (distance / max_distance).clamp(0.0, 1.0);
-    self.interpolate_color(color1, color2, ratio)
+  /// Position along the stops of the pixel at `x`, `y`, sampled at its
+  /// center and held at the end stops past them
+  fn position(&self, x: u32, y: u32) -> f32 {
+    let (x, y) = (x as f32 + 0.5, y as f32 + 0.5);
+    let (width, height) = (self.width, self.height);
+    let t = match self.direction {
+      GradientDirection::Horizontal => x / width,
+      GradientDirection::Vertical => y / height,
+      // Out from the center, reaching the last stop at the corners
+      GradientDirection::Radial => {
+        (x - width / 2.0).hypot(y - height / 2.0) / (width.hypot(height) / 2.0)
+      }
+      // From the top-left corner to the bottom-right, lines of equal color
+      // running parallel to the one joining the other two corners
+      GradientDirection::Diagonal => (x / width + y / height) / 2.0,
+    };
+    t.clamp(0.0, 1.0)
   }
 
-  pub(super) fn diagonal_gradient(
-    &self,
-    x: u32,
-    y: u32,
-    width: u32,
-    height: u32,
-    color1: Rgba<u8>,
-    color2: Rgba<u8>,
-  ) -> Rgba<u8> {
-    let ratio = ((x as f32 / width as f32) + (y as f32 / height as f32)) / 2.0;
-    self.interpolate_color(color1, color2, ratio)
+  /// Unpremultiplied RGBA from 0.0 to 1.0 at the pixel at `x`, `y`
+  fn color_at(&self, x: u32, y: u32) -> [f32; 4] {
+    let last = self.stops.len() - 1;
+    if last == 0 {
+      return self.stops[0];
+    }
+    let scaled = self.position(x, y) * last as f32;
+    let index = (scaled.floor() as usize).min(last - 1);
+    let t = scaled - index as f32;
+    let (from, to) = (self.stops[index], self.stops[index + 1]);
+    [0, 1, 2, 3].map(|channel| from[channel] + (to[channel] - from[channel]) * t)
   }
+}
 
-  pub(super) fn interpolate_color(
-    &self,
-    color1: Rgba<u8>,
-    color2: Rgba<u8>,
-    ratio: f32,
-  ) -> Rgba<u8> {
-    let ratio = ratio.clamp(0.0, 1.0);
-    let r = (color1[0] as f32 * (1.0 - ratio) + color2[0] as f32 * ratio) as u8;
-    let g = (color1[1] as f32 * (1.0 - ratio) + color2[1] as f32 * ratio) as u8;
-    let b = (color1[2] as f32 * (1.0 - ratio) + color2[2] as f32 * ratio) as u8;
-    Rgba([r, g, b, 255])
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::RenderConfig;
+
+  #[test]
+  fn test_diagonal_spans_the_whole_canvas() {
+    let stops = [Rgba([0, 0, 0, 255]), Rgba([255, 255, 255, 255])];
+    let gradient = Gradient::new(GradientDirection::Diagonal, &stops, 300, 100);
+    // Corner to corner, halfway along the anti-diagonal through the center
+    assert!(gradient.position(0, 0) < 0.01);
+    assert!((gradient.position(149, 49) - 0.5).abs() < 0.01);
+    assert!((gradient.position(299, 0) - 0.5).abs() < 0.01);
+    assert!(gradient.position(299, 99) > 0.99);
   }
 
-  pub(super) fn apply_noise_effect(&self, color: Rgba<u8>, rng: &mut impl Rng) -> Rgba<u8> {
-    let noise_strength = 15.0; // Adjust noise intensity
-    let noise = rng.gen_range(-noise_strength..noise_strength);
-    let r = ((color[0] as f32 + noise).clamp(0.0, 255.0)) as u8;
-    let g = ((color[1] as f32 + noise).clamp(0.0, 255.0)) as u8;
-    let b = ((color[2] as f32 + noise).clamp(0.0, 255.0)) as u8;
-    Rgba([r, g, b, color[3]])
+  #[test]
+  fn test_dithers_gradient_bands() {
+    // Four 8-bit levels across 400 pixels: undithered, each 8x8 tile is off
+    // the exact gradient by up to half a level, dithered its average tracks it
+    let worst_error = |dither: bool| {
+      let config = RenderConfig {
+        gradient_colors: vec!["#101010".to_string(), "#141414".to_string()],
+        gradient_direction: Some(GradientDirection::Horizontal),
+        gradient_dither: dither,
+        noise_effect: false,
+        ..RenderConfig::default()
+      };
+      let renderer = SnippetRenderer::new("dracula", config).unwrap();
+      let mut image = RgbaImage::new(400, 8);
+      renderer.draw_gradient_backdrop(&mut image, 400, 8).unwrap();
+      (0..50)
+        .map(|tile| {
+          let pixels = (tile * 8..tile * 8 + 8).flat_map(|x| (0..8).map(move |y| (x, y)));
+          let average = pixels
+            .map(|(x, y)| image.get_pixel(x, y)[0] as f32)
+            .sum::<f32>()
+            / 64.0;
+          let exact = 16.0 + 4.0 * (tile as f32 * 8.0 + 4.0) / 400.0;
+          (average - exact).abs()
+        })
+        .fold(0.0, f32::max)
+    };
+    assert!(worst_error(false) > 0.4);
+    assert!(worst_error(true) < 0.1);
   }
 }
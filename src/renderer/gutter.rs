@@ -0,0 +1,235 @@
+/* ~~/src/renderer/gutter.rs */
+
+// third-party crates
+use anyhow::Result;
+use image::RgbaImage;
+
+// local modules
+use crate::config::BlameSide;
+use crate::renderer::color::{blend_colors, rgba_from_hex};
+use crate::renderer::{PanelRect, SnippetRenderer};
+use crate::syntax::TokenStyle;
+
+impl SnippetRenderer {
+  /// Number of digits needed for the largest line number shown
+  fn gutter_digits(&self, line_count: u32) -> usize {
+    // Widened so a start near u32::MAX cannot overflow
+    let last_line = self.config.line_number_start as u64 + line_count.max(1) as u64 - 1;
+    last_line
+      .max(self.gutter_reserved_line as u64)
+      .to_string()
+      .len()
+  }
+
+  pub(super) fn gutter_spacing(&self) -> u32 {
+    (10.0 * self.config.export_size) as u32
+  }
+
+  pub(super) fn gutter_rule_width(&self) -> u32 {
+    self.config.export_size.round().max(1.0) as u32
+  }
+
+  fn gutter_number_width(&self, line_count: u32) -> u32 {
+    let widest = "0".repeat(self.gutter_digits(line_count));
+    self
+      .font_manager
+      .measure_text(&widest, TokenStyle::REGULAR, None)
+  }
+
+  /// Space for diagnostic signs before the line numbers, 0 without
+  /// diagnostics
+  pub(super) fn sign_column_width(&self) -> u32 {
+    if self.config.diagnostics.is_empty() {
+      return 0;
+    }
+    self.font_manager.measure_text("W", TokenStyle::BOLD, None) + self.gutter_spacing()
+  }
+
+  /// Horizontal space taken by a left blame column, the sign column, and
+  /// the line-number gutter before the code starts
+  pub(super) fn gutter_width(&self, line_count: u32) -> u32 {
+    self.blame_width(line_count, BlameSide::Left)
+      + self.sign_column_width()
+      + self.number_column_width(line_count)
+  }
+
+  /// Horizontal space taken by line numbers and their spacing or rule
+  pub(super) fn number_column_width(&self, line_count: u32) -> u32 {
+    if !self.config.line_numbers {
+      return 0;
+    }
+    let number_width = self.gutter_number_width(line_count);
+    if self.config.gutter_separator {
+      number_width + self.gutter_spacing() * 2 + self.gutter_rule_width()
+    } else {
+      number_width + self.gutter_spacing()
+    }
+  }
+
+  /// Paint the gutter background and separator rule between the title bar
+  /// or header and the footer
+  pub(super) fn draw_gutter_background(
+    &self,
+    image: &mut RgbaImage,
+    panel: PanelRect,
+    line_count: u32,
+  ) -> Result<()> {
+    if !self.config.line_numbers {
+      return Ok(());
+    }
+    let top = panel.y + self.top_chrome_height();
+    let height = (panel.y + panel.height).saturating_sub(top + self.bottom_chrome_height());
+    for column in self.column_rects(panel, line_count) {
+      let rule_x = column.x
+        + self.config.get_scaled_padding().left
+        + self.blame_width(line_count, BlameSide::Left)
+        + self.sign_column_width()
+        + self.gutter_number_width(line_count)
+        + self.gutter_spacing();
+
+      if let Some(hex) = &self.config.gutter_background {
+        let color = rgba_from_hex(hex)?;
+        self.fill_rect_in_panel(
+          image,
+          panel,
+          column.x,
+          top,
+          rule_x - column.x,
+          height,
+          color,
+        );
+      }
+      if self.config.gutter_separator {
+        let color = self.chrome()?.gutter_rule;
+        self.fill_rect_in_panel(
+          image,
+          panel,
+          rule_x,
+          top,
+          self.gutter_rule_width(),
+          height,
+          color,
+        );
+      }
+    }
+    Ok(())
+  }
+
+  /// Faint rule at `text_width` columns into the code, where the editor
+  /// would hard-wrap
+  pub(super) fn draw_text_width_marker(
+    &self,
+    image: &mut RgbaImage,
+    panel: PanelRect,
+    line_count: u32,
+    indent: u32,
+  ) -> Result<()> {
+    if self.config.text_width == 0 {
+      return Ok(());
+    }
+    let columns = " ".repeat(self.config.text_width as usize);
+    let offset = self.config.get_scaled_padding().left
+      + self.gutter_width(line_count)
+      + indent
+      + self
+        .font_manager
+        .measure_text(&columns, TokenStyle::REGULAR, None);
+    let top = panel.y + self.top_chrome_height();
+    let height = (panel.y + panel.height).saturating_sub(top + self.bottom_chrome_height());
+    let color = blend_colors(
+      self.panel_background()?,
+      rgba_from_hex(&self.theme.comment.hex)?,
+      0.25,
+    );
+    for column in self.column_rects(panel, line_count) {
+      self.fill_rect_in_panel(
+        image,
+        panel,
+        column.x + offset,
+        top,
+        self.gutter_rule_width(),
+        height,
+        color,
+      );
+    }
+    Ok(())
+  }
+
+  /// Letter for the most severe diagnostic on a line, at the start of the
+  /// sign column
+  pub(super) fn draw_sign(
+    &self,
+    image: &mut RgbaImage,
+    line_index: usize,
+    x: u32,
+    y: u32,
+  ) -> Result<()> {
+    let Some(diagnostic) = self.most_severe_diagnostic(line_index as u32 + 1) else {
+      return Ok(());
+    };
+    let color = rgba_from_hex(self.config.diagnostic_colors.get(diagnostic.severity))?;
+    self.draw_text(
+      image,
+      diagnostic.severity.sign(),
+      x,
+      y,
+      self.config.get_scaled_font_size(),
+      self.focus_color(line_index, color),
+      TokenStyle::BOLD,
+      None,
+    )?;
+    Ok(())
+  }
+
+  /// Draw the right-aligned number for a line and return the width of the
+  /// number column
+  pub(super) fn draw_line_number(
+    &self,
+    image: &mut RgbaImage,
+    line_index: usize,
+    line_count: u32,
+    x: u32,
+    y: u32,
+  ) -> Result<u32> {
+    let number = (self.config.line_number_start as usize + line_index).to_string();
+    let number_width = self
+      .font_manager
+      .measure_text(&number, TokenStyle::REGULAR, None);
+    let aligned_x = x
+      + self
+        .gutter_number_width(line_count)
+        .saturating_sub(number_width);
+    let color = self.focus_color(line_index, self.chrome()?.line_numbers);
+    self.draw_text(
+      image,
+      &number,
+      aligned_x,
+      y,
+      self.config.get_scaled_font_size(),
+      color,
+      TokenStyle::REGULAR,
+      None,
+    )?;
+    Ok(self.number_column_width(line_count))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::config::RenderConfig;
+  use crate::renderer::SnippetRenderer;
+
+  #[test]
+  fn test_gutter_digits_past_u32_max() {
+    let config = RenderConfig {
+      line_number_start: u32::MAX,
+      ..RenderConfig::default()
+    };
+    let mut renderer = SnippetRenderer::new("dracula", config).unwrap();
+    assert_eq!(
+      renderer.gutter_digits(3),
+      (u32::MAX as u64 + 2).to_string().len()
+    );
+    assert!(renderer.render_snippet("a\nb\nc\n", "rust").is_ok());
+  }
+}
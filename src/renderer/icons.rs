@@ -0,0 +1,279 @@
+/* ~~/src/renderer/icons.rs */
+
+// third-party crates
+use anyhow::Result;
+use image::{Rgba, RgbaImage};
+
+// local modules
+use crate::config::{BadgeColor, Corner, TitleIcon};
+use crate::renderer::color::{blend_colors, rgba_from_hex};
+use crate::renderer::{PanelRect, SnippetRenderer};
+use crate::syntax::TokenStyle;
+use crate::warnings;
+
+/// Short badge label, brand color, and Nerd Font glyph for a language
+fn language_icon(language: &str) -> Option<(&'static str, &'static str, char)> {
+  Some(match language.to_lowercase().as_str() {
+    "rs" | "rust" => ("RS", "#dea584", '\u{e7a8}'),
+    "py" | "python" => ("PY", "#3572a5", '\u{e73c}'),
+    "js" | "javascript" => ("JS", "#f1e05a", '\u{e74e}'),
+    "ts" | "typescript" => ("TS", "#3178c6", '\u{e628}'),
+    "go" => ("GO", "#00add8", '\u{e627}'),
+    "lua" => ("LUA", "#51a0cf", '\u{e620}'),
+    "c" => ("C", "#599eff", '\u{e61e}'),
+    "cpp" | "c++" => ("C++", "#f34b7d", '\u{e61d}'),
+    "java" => ("JAVA", "#cc3e44", '\u{e738}'),
+    "ruby" | "rb" => ("RB", "#701516", '\u{e739}'),
+    "php" => ("PHP", "#a074c4", '\u{e73d}'),
+    "swift" => ("SW", "#e37933", '\u{e755}'),
+    "kotlin" | "kt" => ("KT", "#7f52ff", '\u{e634}'),
+    "scala" => ("SC", "#cc3e44", '\u{e737}'),
+    "haskell" | "hs" => ("HS", "#a074c4", '\u{e777}'),
+    "html" => ("HTML", "#e44d26", '\u{e736}'),
+    "css" => ("CSS", "#42a5f5", '\u{e749}'),
+    "json" => ("JSON", "#cbcb41", '\u{e60b}'),
+    "md" | "markdown" => ("MD", "#dddddd", '\u{e609}'),
+    "bash" | "shell" | "sh" | "zsh" => ("SH", "#89e051", '\u{e795}'),
+    "sql" => ("SQL", "#dad8d8", '\u{e706}'),
+    "vim" => ("VIM", "#019833", '\u{e62b}'),
+    _ => return None,
+  })
+}
+
+/// Mark drawn beside the window title
+enum Mark {
+  Glyph(char, Rgba<u8>),
+  /// Label on a rounded tag of the brand color
+  Badge(&'static str, Rgba<u8>),
+}
+
+impl SnippetRenderer {
+  /// Mark for the snippet's language in the configured style, falling back
+  /// to a badge when no installed font has the Nerd Font glyph
+  fn title_mark(&self) -> Option<Mark> {
+    if self.config.title_icon == TitleIcon::Off {
+      return None;
+    }
+    let (label, color, glyph) = language_icon(self.language.as_deref()?)?;
+    let color = rgba_from_hex(color).ok()?;
+    if self.config.title_icon == TitleIcon::NerdFont {
+      if self.font_manager.has_glyph(glyph) {
+        return Some(Mark::Glyph(glyph, color));
+      }
+      warnings::push(
+        "No installed font has Nerd Font icons, drawing a badge instead; add one to fallback_fonts",
+      );
+    }
+    Some(Mark::Badge(label, color))
+  }
+
+  pub(super) fn badge_padding(&self) -> u32 {
+    (5.0 * self.config.export_size) as u32
+  }
+
+  /// Width of the title icon, 0 when there is none
+  pub(super) fn title_icon_width(&self) -> u32 {
+    match self.title_mark() {
+      None => 0,
+      Some(Mark::Glyph(glyph, _)) => {
+        let text = glyph.to_string();
+        self
+          .font_manager
+          .measure_text(&text, TokenStyle::REGULAR, None)
+      }
+      Some(Mark::Badge(label, _)) => {
+        self
+          .font_manager
+          .measure_text(label, TokenStyle::BOLD, None)
+          + self.badge_padding() * 2
+      }
+    }
+  }
+
+  /// Draw the language icon with its left edge at `x` on the title's
+  /// baseline
+  pub(super) fn draw_title_icon(&self, image: &mut RgbaImage, x: u32, baseline: u32) -> Result<()> {
+    let font_size = self.config.get_scaled_font_size();
+    match self.title_mark() {
+      None => {}
+      Some(Mark::Glyph(glyph, color)) => {
+        let text = glyph.to_string();
+        self.draw_text(
+          image,
+          &text,
+          x,
+          baseline,
+          font_size,
+          color,
+          TokenStyle::REGULAR,
+          None,
+        )?;
+      }
+      Some(Mark::Badge(label, color)) => {
+        let padding = self.badge_padding();
+        let label_width = self
+          .font_manager
+          .measure_text(label, TokenStyle::BOLD, None);
+        let (ascent, descent) = self.font_manager.get_vertical_metrics();
+        let text_color = badge_text_color(color);
+        self.draw_rounded_rect(
+          image,
+          x as i32,
+          baseline as i32 - ascent.ceil() as i32 - padding as i32 / 2,
+          label_width + padding * 2,
+          (ascent - descent).ceil() as u32 + padding,
+          4.0,
+          color,
+        )?;
+        self.draw_text(
+          image,
+          label,
+          x + padding,
+          baseline,
+          font_size,
+          text_color,
+          TokenStyle::BOLD,
+          None,
+        )?;
+      }
+    }
+    Ok(())
+  }
+
+  /// Fill and text colors of the language badge
+  fn language_badge_colors(&self, language: &str) -> Result<(Rgba<u8>, Rgba<u8>)> {
+    let brand = match self.config.language_badge_color {
+      BadgeColor::Language => language_icon(language).map(|(_, color, _)| color),
+      BadgeColor::Theme => None,
+    };
+    Ok(match brand {
+      Some(color) => {
+        let color = rgba_from_hex(color)?;
+        (color, badge_text_color(color))
+      }
+      None => {
+        let keyword = rgba_from_hex(&self.theme.keyword.hex)?;
+        (
+          blend_colors(self.panel_background()?, keyword, 0.2),
+          keyword,
+        )
+      }
+    })
+  }
+
+  /// Grow the padding on the language badge's side to hold the badge with
+  /// a margin, so it never covers the first or last line of code
+  pub(super) fn reserve_badge_padding(&mut self) {
+    let Some(corner) = self.config.language_badge else {
+      return;
+    };
+    let (ascent, descent) = self.font_manager.get_vertical_metrics();
+    let height = (ascent - descent).ceil() as u32 + self.badge_padding() * 2;
+    let needed = (height as f32 / self.config.export_size).ceil() as u32;
+    let side = match corner {
+      Corner::TopLeft | Corner::TopRight => &mut self.config.padding.top,
+      Corner::BottomLeft | Corner::BottomRight => &mut self.config.padding.bottom,
+    };
+    *side = (*side).max(needed);
+  }
+
+  /// Pill naming the snippet's language in the configured corner, centered
+  /// in the padding between the code and the panel's edge or strips
+  pub(super) fn draw_language_badge(&self, image: &mut RgbaImage, panel: PanelRect) -> Result<()> {
+    let (Some(corner), Some(language)) = (self.config.language_badge, &self.language) else {
+      return Ok(());
+    };
+    if language == "text" {
+      return Ok(());
+    }
+    let label = language.to_lowercase();
+    let (fill, text_color) = self.language_badge_colors(&label)?;
+    let padding = self.badge_padding();
+    let (ascent, descent) = self.font_manager.get_vertical_metrics();
+    let width = self
+      .font_manager
+      .measure_text(&label, TokenStyle::BOLD, None)
+      + padding * 3;
+    let height = (ascent - descent).ceil() as u32 + padding;
+    let panel_padding = self.config.get_scaled_padding();
+
+    let x = match corner {
+      Corner::TopLeft | Corner::BottomLeft => panel.x + panel_padding.left / 2,
+      Corner::TopRight | Corner::BottomRight => {
+        (panel.x + panel.width).saturating_sub(panel_padding.right / 2 + width)
+      }
+    };
+    let y = match corner {
+      Corner::TopLeft | Corner::TopRight => {
+        let inset = panel_padding.top.saturating_sub(height) / 2;
+        panel.y + self.top_chrome_height() + inset
+      }
+      Corner::BottomLeft | Corner::BottomRight => {
+        let inset = panel_padding.bottom.saturating_sub(height) / 2;
+        (panel.y + panel.height).saturating_sub(self.bottom_chrome_height() + inset + height)
+      }
+    };
+    self.draw_rounded_rect(
+      image,
+      x as i32,
+      y as i32,
+      width,
+      height,
+      height as f32 / 2.0,
+      fill,
+    )?;
+    self.draw_text(
+      image,
+      &label,
+      x + padding * 3 / 2,
+      y + ascent.ceil() as u32 + padding / 2,
+      self.config.get_scaled_font_size(),
+      text_color,
+      TokenStyle::BOLD,
+      None,
+    )?;
+    Ok(())
+  }
+}
+
+/// Dark text on light brand colors, light text on dark ones
+fn badge_text_color(color: Rgba<u8>) -> Rgba<u8> {
+  let luma = 0.299 * color[0] as f32 + 0.587 * color[1] as f32 + 0.114 * color[2] as f32;
+  match luma > 140.0 {
+    true => Rgba([24, 24, 24, 255]),
+    false => Rgba([250, 250, 250, 255]),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::{RenderConfig, Sides};
+
+  #[test]
+  fn test_language_icons_cover_aliases() {
+    assert_eq!(language_icon("rs"), language_icon("Rust"));
+    assert!(language_icon("python").is_some());
+    assert!(language_icon("brainfuck").is_none());
+    // Badge colors must parse
+    for language in ["rust", "lua", "go", "html", "vim"] {
+      let (_, color, _) = language_icon(language).unwrap();
+      assert!(rgba_from_hex(color).is_ok());
+    }
+  }
+
+  #[test]
+  fn test_badge_padding_fits_badge() {
+    let config = RenderConfig {
+      padding: Sides::uniform(0),
+      language_badge: Some(Corner::BottomRight),
+      ..RenderConfig::default()
+    };
+    let renderer = SnippetRenderer::new("dracula", config).unwrap();
+    let (ascent, descent) = renderer.font_manager.get_vertical_metrics();
+    let height = (ascent - descent).ceil() as u32 + renderer.badge_padding();
+    let padding = renderer.config.get_scaled_padding();
+    assert!(padding.bottom > height);
+    assert_eq!(padding.top, 0);
+  }
+}
@@ -0,0 +1,206 @@
+/* ~~/src/renderer/metrics.rs */
+
+// third-party crates
+use serde::Serialize;
+
+// local modules
+use crate::config::{BlameSide, Fit, HorizontalAlign, PanelSize, Sides, VerticalAlign};
+use crate::renderer::{SnippetRenderer, title};
+use crate::syntax::HighlightedLine;
+
+/// Output geometry of a snippet in final (export-scaled) pixels
+#[derive(Debug, Clone, Serialize)]
+pub struct SnippetMetrics {
+  /// Full image size including the backdrop margin
+  pub width: u32,
+  pub height: u32,
+  pub panel_width: u32,
+  pub panel_height: u32,
+  /// Backdrop margin on each side of the panel
+  pub panel_padding: Sides,
+  pub line_count: u32,
+  pub line_height: u32,
+  pub gutter_width: u32,
+  /// Pen advance of the longest line, excluding the gutter
+  pub max_line_width: u32,
+  /// Whether the longest line runs past the panel's right padding
+  pub overflows: bool,
+  /// Whether any code is cut off, past the panel's edge or below `max_height`
+  pub truncated: bool,
+  /// Space added left of and above the code by `content_align` when the
+  /// panel is larger than the code, and by left annotations
+  pub content_offset_x: u32,
+  pub content_offset_y: u32,
+  /// Title the window would show, including one inferred from the code
+  pub window_title: Option<String>,
+  /// Whether `window_title` was inferred rather than configured
+  pub title_inferred: bool,
+}
+
+/// A rendered image with what the plugin needs to place it and report progress
+#[derive(Debug, Clone, Serialize)]
+pub struct RenderOutput {
+  /// Base64 PNG data URL
+  pub image: String,
+  pub width: u32,
+  pub height: u32,
+  pub line_count: u32,
+  /// Time spent highlighting, drawing, and encoding
+  pub render_ms: u64,
+  pub theme: String,
+  pub truncated: bool,
+}
+
+impl SnippetRenderer {
+  /// Highlight and measure `code` without allocating or rasterizing the image
+  pub fn measure_snippet(&self, code: &str, language: &str) -> SnippetMetrics {
+    let (highlighted_lines, hidden) = self.truncate_lines(self.highlight(code, language));
+    let mut metrics = self.compute_metrics(&highlighted_lines);
+    metrics.truncated |= hidden > 0;
+    if metrics.window_title.is_none() && self.config.infer_title {
+      metrics.window_title = title::infer_title(&highlighted_lines);
+      metrics.title_inferred = metrics.window_title.is_some();
+    }
+    metrics
+  }
+
+  /// Line advance before export scaling
+  fn unscaled_line_height(&self) -> u32 {
+    // Get base line height from font metrics (unscaled)
+    let base_line_height = self.font_manager.get_line_height();
+
+    // Apply line height multiplier but NOT export scaling yet (that's done in get_actual_height)
+    (base_line_height as f32 * self.config.line_height) as u32
+  }
+
+  /// Panel height before export scaling for a given number of code lines
+  pub(crate) fn unscaled_panel_height(&self, line_count: u32) -> u32 {
+    let line_height = self.unscaled_line_height();
+    let padding = self.config.padding; // Use unscaled padding
+
+    // Calculate window controls height (unscaled)
+    let window_controls_height = if self.has_title_bar() {
+      40 // Base window controls height
+    } else {
+      0
+    };
+
+    // Calculate content area height (unscaled), columns sharing their rows
+    let content_height = if line_count > 0 {
+      line_count.div_ceil(self.config.columns) * line_height
+    } else {
+      line_height // Minimum height for empty content
+    };
+
+    // Calculate panel dimensions (unscaled)
+    content_height + padding.vertical() + window_controls_height + self.unscaled_strips_height()
+  }
+
+  /// Resolve `width: "auto"` against all of `highlighted_lines`, so frames or
+  /// pages drawn from parts of them keep one width
+  pub(crate) fn pin_width(&mut self, highlighted_lines: &[HighlightedLine]) {
+    // A fitted panel follows its code whatever the width, so the code's
+    // width is what gets pinned
+    if self.config.fit != Fit::None {
+      let widest = self.widest_line(highlighted_lines);
+      self.reserved_line_width = self.reserved_line_width.max(widest);
+      return;
+    }
+    if self.config.width == PanelSize::Auto {
+      let panel_width = self.compute_metrics(highlighted_lines).panel_width;
+      let unscaled = (panel_width as f32 / self.config.export_size).ceil() as u32;
+      self.config.width = PanelSize::Fixed(unscaled);
+    }
+  }
+
+  /// Pen advance of the longest line, diagnostic messages after it included
+  pub(crate) fn widest_line(&self, highlighted_lines: &[HighlightedLine]) -> u32 {
+    highlighted_lines
+      .iter()
+      .enumerate()
+      .map(|(index, line)| self.line_width(line) + self.diagnostic_message_width(index as u32 + 1))
+      .max()
+      .unwrap_or(0)
+  }
+
+  /// Pen advance of one line
+  pub(super) fn line_width(&self, line: &HighlightedLine) -> u32 {
+    line
+      .tokens
+      .iter()
+      .map(|token| {
+        let face = self.scope_face(&token.scope);
+        self
+          .font_manager
+          .measure_text(&token.text, token.style, face)
+      })
+      .sum()
+  }
+
+  pub(crate) fn compute_metrics(&self, highlighted_lines: &[HighlightedLine]) -> SnippetMetrics {
+    let line_count = highlighted_lines.len() as u32;
+    let line_height = self.unscaled_line_height();
+    let content_height = self.unscaled_panel_height(line_count.max(self.reserved_lines));
+
+    let gutter_width = self.gutter_width(line_count);
+    let max_line_width = self.widest_line(highlighted_lines);
+    let content_width = max_line_width.max(self.reserved_line_width);
+    let (left_column, right_column) = self.annotation_columns(line_count);
+    let padding = self.config.get_scaled_padding();
+    // Every column has the padding and gutter of a panel of its own
+    let horizontal_chrome = padding.horizontal()
+      + gutter_width
+      + left_column
+      + right_column
+      + self.blame_width(line_count, BlameSide::Right);
+    let (columns, _) = self.column_grid(line_count);
+
+    // Calculate final image dimensions with panel padding
+    let minimap_width = self.minimap_width();
+    let panel_width = self
+      .config
+      .get_actual_width((content_width + horizontal_chrome) * columns + minimap_width);
+    let panel_height = self.config.get_actual_height(content_height);
+    let panel_padding = self.config.get_scaled_panel_padding();
+    // A canvas preset extends the backdrop to its shape
+    let margin = self.canvas_margin(
+      panel_width + panel_padding.horizontal(),
+      panel_height + panel_padding.vertical(),
+    );
+    let panel_padding = panel_padding + margin;
+    let scaled_content_height = (content_height as f32 * self.config.export_size) as u32;
+    let available_width =
+      (panel_width.saturating_sub(minimap_width) / columns).saturating_sub(horizontal_chrome);
+    let spare_width = available_width.saturating_sub(content_width);
+    let spare_height = panel_height.saturating_sub(scaled_content_height);
+
+    SnippetMetrics {
+      width: panel_width + panel_padding.horizontal(),
+      height: panel_height + panel_padding.vertical(),
+      panel_width,
+      panel_height,
+      panel_padding,
+      line_count,
+      line_height: (line_height as f32 * self.config.export_size) as u32,
+      gutter_width,
+      max_line_width,
+      overflows: max_line_width > available_width,
+      // Code may run into the padding before anything is cut off
+      truncated: max_line_width > available_width + padding.right
+        || scaled_content_height > panel_height + padding.bottom,
+      content_offset_x: left_column
+        + match self.config.content_align.horizontal {
+          HorizontalAlign::Left => 0,
+          HorizontalAlign::Center => spare_width / 2,
+          HorizontalAlign::Right => spare_width,
+        },
+      content_offset_y: match self.config.content_align.vertical {
+        VerticalAlign::Top => 0,
+        VerticalAlign::Center => spare_height / 2,
+        VerticalAlign::Bottom => spare_height,
+      },
+      window_title: self.config.window_title.clone(),
+      title_inferred: false,
+    }
+  }
+}
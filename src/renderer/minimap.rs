@@ -0,0 +1,80 @@
+/* ~~/src/renderer/minimap.rs */
+
+// third-party crates
+use anyhow::Result;
+use image::RgbaImage;
+
+// local modules
+use crate::renderer::color::{blend_colors, rgba_from_hex};
+use crate::renderer::{PanelRect, SnippetRenderer};
+use crate::syntax::{HighlightedLine, display_width};
+
+/// Size of one character and one line in the minimap, before export scaling
+const CHARACTER_WIDTH: f32 = 1.5;
+const LINE_HEIGHT: f32 = 3.0;
+/// Share of a minimap line its blocks fill, leaving a gap between lines
+const BLOCK_FILL: f32 = 0.67;
+/// Columns the minimap is wide, cutting longer lines off like an editor's
+const MAX_COLUMNS: usize = 80;
+
+impl SnippetRenderer {
+  /// Width the minimap takes at the panel's right edge, its margin included;
+  /// 0 when it's off
+  pub(super) fn minimap_width(&self) -> u32 {
+    if !self.config.minimap {
+      return 0;
+    }
+    (MAX_COLUMNS as f32 * CHARACTER_WIDTH * self.config.export_size).ceil() as u32
+      + self.config.get_scaled_padding().right / 2
+  }
+
+  /// Each line shrunk to blocks of its token colors, one per run of
+  /// characters between spaces, along the panel's right edge level with
+  /// the first line of code at `top`
+  pub(super) fn draw_minimap(
+    &self,
+    image: &mut RgbaImage,
+    panel: PanelRect,
+    highlighted_lines: &[HighlightedLine],
+    top: u32,
+  ) -> Result<()> {
+    let width = self.minimap_width();
+    if width == 0 {
+      return Ok(());
+    }
+    let scale = self.config.export_size;
+    let (character_width, line_height) = (CHARACTER_WIDTH * scale, LINE_HEIGHT * scale);
+    let block_height = (line_height * BLOCK_FILL).round().max(1.0) as u32;
+    let left = (panel.x + panel.width).saturating_sub(width) as f32;
+    let background = self.panel_background()?;
+    for (line_index, line) in highlighted_lines.iter().enumerate() {
+      if self.is_marker_row(line_index, highlighted_lines.len() as u32) {
+        continue;
+      }
+      let y = (top as f32 + line_index as f32 * line_height).round() as i32;
+      for token in &line.tokens {
+        let color = rgba_from_hex(&token.color.hex)?;
+        let color = self.focus_color(line_index, blend_colors(background, color, 0.6));
+        let mut column = token.start_col.display;
+        let mut run_start = None;
+        for ch in token.text.chars().chain([' ']) {
+          match (ch.is_whitespace(), run_start) {
+            (false, None) => run_start = Some(column),
+            (true, Some(start)) => {
+              let end = column.min(MAX_COLUMNS);
+              if start < end {
+                let x = (left + start as f32 * character_width).round();
+                let right = (left + end as f32 * character_width).round();
+                self.fill_rect(image, x as i32, y, (right - x) as u32, block_height, color);
+              }
+              run_start = None;
+            }
+            _ => {}
+          }
+          column += display_width(ch);
+        }
+      }
+    }
+    Ok(())
+  }
+}
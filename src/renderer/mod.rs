@@ -1,152 +1,655 @@
 /* ~~/src/renderer/mod.rs */
 
 // standard crates
-use std::time::Duration;
+use std::borrow::Cow;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 
 // third-party crates
 use anyhow::{Result, anyhow};
 use base64::Engine;
 use base64::engine::general_purpose;
-use image::{ImageBuffer, ImageEncoder, Rgba, RgbaImage};
+use image::{ImageBuffer, Rgba, RgbaImage};
+use syntect::highlighting::ScopeSelectors;
+use syntect::parsing::ScopeStack;
 
 // local modules
-use crate::config::RenderConfig;
-use crate::font::{FontManager, load_font_with_fallback};
-use crate::layout::{ComplexTextRenderer, has_complex_script};
-use crate::syntax::{HighlightedLine, SyntaxHighlighter};
-use crate::themes::{Theme, get_theme};
+use crate::config::{
+  BlameSide, ColorSpace, DEFAULT_FONT_FAMILY, Fit, HighlighterBackend, PanelSize, RenderConfig,
+  RenderLayer, canvas_preset, gradient_preset,
+};
+use crate::detect::resolve_language;
+use crate::font::{
+  DEFAULT_FALLBACK_FAMILIES, FontFeature, FontManager, load_font_with_fallback, resolve_family,
+};
+#[cfg(feature = "complex-scripts")]
+use crate::layout::ComplexTextRenderer;
+use crate::layout::has_complex_script;
+use crate::limits::RenderGuard;
+use crate::reflow::reflow_comments;
+use crate::syntax::ansi::parse_ansi;
+use crate::syntax::{
+  ExternalHighlighter, HighlightedLine, Highlighter, SyntaxHighlighter, TokenStyle, expand_tabs,
+};
+use crate::themes::{Theme, ThemeColor, get_theme, load_theme_file, load_themes_dir};
+use crate::warnings;
+mod annotations;
+mod backdrop;
+mod blame;
+mod border;
+mod brackets;
+mod canvas;
+mod carousel;
+mod chrome;
 mod color;
+mod color_space;
+mod columns;
+mod controls;
+#[cfg(test)]
+mod corpus;
+mod cursor;
+mod diagnostics;
 mod drawing;
+mod focus;
+mod frost;
+// References are drawn with the embedded font over a seeded gradient
+#[cfg(all(
+  test,
+  feature = "bundled-syntaxes",
+  feature = "complex-scripts",
+  feature = "embedded-font",
+  feature = "gradient",
+  feature = "qr",
+  feature = "shaping"
+))]
+mod golden;
+#[cfg(feature = "gradient")]
 mod gradient;
+mod gutter;
+mod icons;
+mod metrics;
+mod minimap;
+mod parallel;
+mod pattern;
+mod pdf;
+mod png_text;
+mod project;
+#[cfg(feature = "qr")]
+mod qr;
+mod quantize;
+mod selection;
+mod skeleton;
+mod strips;
+mod substitution;
+mod tabs;
+mod title;
+mod tokens;
+mod truncation;
+mod viewport;
+mod watermark;
 
-use self::color::{darken_color, rgba_from_hex};
+use self::color::{blend_colors, rgba_from_hex};
+use self::metrics::RenderOutput;
+use self::png_text::{PngText, SOURCE_KEYWORD};
+use self::viewport::clip_tokens;
+
+/// Position and size of the code panel within the final image
+#[derive(Debug, Clone, Copy)]
+pub(super) struct PanelRect {
+  pub x: u32,
+  pub y: u32,
+  pub width: u32,
+  pub height: u32,
+}
+
+/// Placement shared by every line of code in a render
+struct CodeLayout {
+  /// Left edge of the panel
+  x: u32,
+  /// Baseline of the first line
+  start_y: u32,
+  line_height: u32,
+  /// Width of each column of code, the whole panel's with one column
+  column_width: u32,
+  /// Lines in each column before the next one starts
+  rows: usize,
+  /// Gap between the gutter and the code
+  indent: u32,
+  /// Distance from a baseline up to the top of its highlight strip
+  strip_offset: i32,
+  highlight_color: Rgba<u8>,
+  selection_color: Rgba<u8>,
+  visible_columns: usize,
+  line_count: u32,
+}
+
+impl CodeLayout {
+  fn baseline(&self, line_index: usize) -> u32 {
+    self.start_y + (line_index % self.rows) as u32 * self.line_height
+  }
+
+  /// Left edge of the column a line flows into
+  fn column_x(&self, line_index: usize) -> u32 {
+    self.x + (line_index / self.rows) as u32 * self.column_width
+  }
+}
 
 pub struct SnippetRenderer {
   theme: Theme,
   config: RenderConfig,
   highlighter: SyntaxHighlighter,
   font_manager: FontManager,
+  /// Scope selectors paired with the named face their tokens are drawn in
+  scope_fonts: Vec<(ScopeSelectors, String)>,
+  /// Line number the gutter is sized for even when fewer lines are drawn,
+  /// so every page of a carousel lines its code up at the same column
+  gutter_reserved_line: u32,
+  /// Line count and widest line the code block is sized and placed for even
+  /// when less is drawn, so partial frames and short pages don't drift
+  reserved_lines: u32,
+  reserved_line_width: u32,
+  /// Lines cut by `max_lines` from the snippet being drawn, whose count the
+  /// last row shows instead
+  hidden_lines: u32,
+  /// Titles of the files a tabbed render shows in the title bar, and which
+  /// of them is in front
+  tab_titles: Vec<String>,
+  active_tab: usize,
+  /// Language of the snippet being drawn, for the title icon
+  language: Option<String>,
+  /// Code of the snippet being drawn, kept only when `embed_source` is on
+  source: Option<String>,
+  /// Decoded `backdrop_image`, cropped to the canvas at draw time
+  backdrop_image: Option<RgbaImage>,
+  /// Decoded logo of the `watermark`
+  watermark_logo: Option<RgbaImage>,
+  /// Title inferred from the code drawn last, shown while `window_title` is unset
+  inferred_title: Option<String>,
   /// Complex text renderer for Thai, Arabic, and other scripts requiring shaping
+  #[cfg(feature = "complex-scripts")]
   complex_renderer: Option<ComplexTextRenderer>,
 }
 
 impl SnippetRenderer {
-  pub fn new(theme_name: &str, config: RenderConfig) -> Result<Self> {
-    let theme = get_theme(theme_name).ok_or_else(|| anyhow!("Unknown theme: {}", theme_name))?;
+  pub fn new(theme_name: &str, mut config: RenderConfig) -> Result<Self> {
+    // Scanned on first use; `reload_themes` picks up edits after that
+    if let Some(dir) = &config.themes_dir {
+      load_themes_dir(Path::new(dir))?;
+    }
+    let mut theme = match &config.theme_file {
+      Some(path) => load_theme_file(Path::new(path))?,
+      None => get_theme(theme_name).ok_or_else(|| anyhow!("Unknown theme: {}", theme_name))?,
+    }
+    .with_variant(config.variant);
+    // Checked against the panel color the code is actually drawn on
+    if config.min_contrast > 0.0 {
+      let panel = config.panel_color.as_deref().map(ThemeColor::new);
+      theme = theme.with_min_contrast(config.min_contrast.min(21.0), panel.as_ref());
+    }
 
-    let highlighter = SyntaxHighlighter::new();
+    if let Some(hex) = &config.panel_color {
+      rgba_from_hex(hex).map_err(|e| anyhow!("Invalid panel_color '{}': {}", hex, e))?;
+    }
+    if let Some(hex) = config
+      .border
+      .as_ref()
+      .and_then(|border| border.color.as_ref())
+    {
+      rgba_from_hex(hex).map_err(|e| anyhow!("Invalid border color '{}': {}", hex, e))?;
+    }
+    for hex in config.backdrop.iter().flat_map(|pattern| &pattern.colors) {
+      rgba_from_hex(hex).map_err(|e| anyhow!("Invalid backdrop color '{}': {}", hex, e))?;
+    }
+    if let Some(hex) = &config.selection_color {
+      rgba_from_hex(hex).map_err(|e| anyhow!("Invalid selection_color '{}': {}", hex, e))?;
+    }
+    if let Some(hex) = config
+      .cursor
+      .as_ref()
+      .and_then(|cursor| cursor.color.as_ref())
+    {
+      rgba_from_hex(hex).map_err(|e| anyhow!("Invalid cursor color '{}': {}", hex, e))?;
+    }
+    let colors = &config.diagnostic_colors;
+    for hex in [&colors.error, &colors.warning, &colors.info, &colors.hint] {
+      rgba_from_hex(hex).map_err(|e| anyhow!("Invalid diagnostic color '{}': {}", hex, e))?;
+    }
+    for hex in &config.bracket_colors {
+      rgba_from_hex(hex).map_err(|e| anyhow!("Invalid bracket color '{}': {}", hex, e))?;
+    }
+    if let Some(hex) = &config.annotation_color {
+      rgba_from_hex(hex).map_err(|e| anyhow!("Invalid annotation_color '{}': {}", hex, e))?;
+    }
+    if !(1..=3).contains(&config.columns) {
+      return Err(anyhow!("Columns must be 1, 2 or 3, got {}", config.columns));
+    }
+    if config.fit == Fit::Exact
+      && (config.width == PanelSize::Auto || config.height == PanelSize::Auto)
+    {
+      return Err(anyhow!("Fit \"exact\" needs a fixed width and height"));
+    }
+    #[cfg(feature = "qr")]
+    if let Some(link) = &config.qr_link {
+      qr::encode_link(link)?;
+    }
+    if cfg!(not(feature = "qr")) && config.qr_link.is_some() {
+      warnings::push("Built without the qr feature; skipping qr_link");
+    }
+    if let Some(name) = &config.canvas
+      && canvas_preset(name).is_none()
+    {
+      return Err(anyhow!("Unknown canvas preset: {}", name));
+    }
+    if let Some(name) = &config.gradient_preset
+      && gradient_preset(name).is_none()
+    {
+      return Err(anyhow!("Unknown gradient preset: {}", name));
+    }
+    if cfg!(not(feature = "tree-sitter")) && config.highlighter == HighlighterBackend::TreeSitter {
+      warnings::push("Built without the tree-sitter feature; highlighting with syntect");
+    }
+    // A missing command fails up front rather than warning on every render
+    if config.highlighter == HighlighterBackend::External {
+      ExternalHighlighter::new(
+        &config.highlight_command,
+        Duration::from_millis(config.highlight_timeout_ms),
+      )?;
+    }
+
+    if let (Some(root), Some(title)) = (&config.project_root, &config.window_title) {
+      config.window_title = Some(project::relative_title(title, root));
+    }
+
+    let highlighter = SyntaxHighlighter::new(&config.syntax_dirs)?;
 
     // Load font with fallback chain
     let font_size = config.get_scaled_font_size();
-    let font_manager = load_font_with_fallback(font_size)?;
+    let font_features = config
+      .font_features
+      .iter()
+      .map(|feature| FontFeature::parse(feature))
+      .collect::<Result<Vec<_>>>()?;
+    let family = resolve_family(&config.font_family);
+    if family.is_none() && config.font_family != DEFAULT_FONT_FAMILY {
+      warnings::push(format!(
+        "Font family '{}' is not installed, using a fallback font",
+        config.font_family
+      ));
+    }
+    let mut font_manager = load_font_with_fallback(&config.font_family, font_size, &font_features)?;
+    font_manager.set_ligatures(config.ligatures);
+    font_manager.add_fallback_families(
+      config
+        .fallback_fonts
+        .iter()
+        .map(String::as_str)
+        .chain(DEFAULT_FALLBACK_FAMILIES.iter().copied()),
+    );
+
+    // Config rules come first so they override the theme's
+    let mut scope_fonts = Vec::new();
+    for rule in config.scope_fonts.iter().chain(&theme.scope_fonts) {
+      let selectors = ScopeSelectors::from_str(&rule.scope)
+        .map_err(|e| anyhow!("Invalid scope selector '{}': {}", rule.scope, e))?;
+      if !font_manager.has_named_face(&rule.font) {
+        let font_data = std::fs::read(&rule.font)
+          .map_err(|e| anyhow!("Failed to read font '{}': {}", rule.font, e))?;
+        font_manager.add_named_face(&rule.font, font_data, &font_features)?;
+      }
+      scope_fonts.push((selectors, rule.font.clone()));
+    }
+
+    let backdrop_image = Self::load_backdrop_image(&config)?;
+    let watermark_logo = Self::load_watermark_logo(&config)?;
 
     // Always initialize complex text renderer so system font fallback can shape
     // Thai/Arabic/Indic text even when custom Thai font paths are unavailable.
-    let complex_renderer = Some(ComplexTextRenderer::new(font_size)?);
+    #[cfg(feature = "complex-scripts")]
+    let complex_renderer = Some(ComplexTextRenderer::new(font_size, family)?);
 
-    Ok(Self {
+    let mut renderer = Self {
       theme,
       config,
       highlighter,
       font_manager,
+      scope_fonts,
+      gutter_reserved_line: 0,
+      reserved_lines: 0,
+      reserved_line_width: 0,
+      hidden_lines: 0,
+      tab_titles: Vec::new(),
+      active_tab: 0,
+      language: None,
+      source: None,
+      backdrop_image,
+      watermark_logo,
+      inferred_title: None,
+      #[cfg(feature = "complex-scripts")]
       complex_renderer,
-    })
+    };
+    renderer.reserve_badge_padding();
+    Ok(renderer)
   }
 
-  pub fn render_snippet(&mut self, code: &str, language: &str) -> Result<String> {
-    let highlighted_lines = self.highlighter.highlight_code(code, language, &self.theme);
-    let line_count = highlighted_lines.len() as u32;
+  pub(crate) fn config(&self) -> &RenderConfig {
+    &self.config
+  }
 
-    // Get base line height from font metrics (unscaled)
-    let base_line_height = self.font_manager.get_line_height();
+  pub(crate) fn config_mut(&mut self) -> &mut RenderConfig {
+    &mut self.config
+  }
 
-    // Apply line height multiplier but NOT export scaling yet (that's done in get_actual_height)
-    let line_height = (base_line_height as f32 * self.config.line_height) as u32;
-    let padding = self.config.padding; // Use unscaled padding
+  pub(crate) fn theme(&self) -> &Theme {
+    &self.theme
+  }
 
-    // Calculate window controls height (unscaled)
-    let window_controls_height = if self.config.window_controls {
-      40 // Base window controls height
-    } else {
-      0
+  pub(crate) fn set_theme(&mut self, theme: Theme) {
+    self.theme = theme;
+  }
+
+  pub(crate) fn highlight(&self, code: &str, language: &str) -> Vec<HighlightedLine> {
+    let language = resolve_language(language, code);
+    // Terminal output carries its own colors, so it is drawn as captured
+    if language.eq_ignore_ascii_case("ansi") {
+      let mut highlighted_lines = parse_ansi(code, &self.theme);
+      expand_tabs(&mut highlighted_lines, self.config.tab_width);
+      return highlighted_lines;
+    }
+    let code = match self.config.reflow_comments {
+      true => reflow_comments(
+        code,
+        &language,
+        self.config.text_width,
+        self.config.tab_width,
+      ),
+      false => Cow::Borrowed(code),
     };
+    let mut highlighted_lines = match self.config.highlighter {
+      // Falls back to syntect rather than patterns when the command fails
+      HighlighterBackend::External => match ExternalHighlighter::new(
+        &self.config.highlight_command,
+        Duration::from_millis(self.config.highlight_timeout_ms),
+      ) {
+        Ok(external) => external.highlight_or(&code, &language, &self.theme, &self.highlighter),
+        Err(e) => {
+          warnings::push(format!("{}; highlighting with syntect", e));
+          self.highlighter.highlight(&code, &language, &self.theme)
+        }
+      },
+      backend => self
+        .highlighter
+        .highlight_code_with(&code, &language, &self.theme, backend),
+    };
+    self.substitute_glyphs(&mut highlighted_lines);
+    self.colorize_brackets(&mut highlighted_lines);
+    expand_tabs(&mut highlighted_lines, self.config.tab_width);
+    highlighted_lines
+  }
 
-    // Calculate content area height (unscaled)
-    let content_height = if line_count > 0 {
-      line_count * line_height
-    } else {
-      line_height // Minimum height for empty content
+  /// Highlight `code` to be drawn, remembering its language for the title icon
+  pub(crate) fn highlight_snippet(&mut self, code: &str, language: &str) -> Vec<HighlightedLine> {
+    self.language = Some(resolve_language(language, code).into_owned());
+    self.hidden_lines = 0;
+    if self.config.embed_source {
+      self.source = Some(code.to_string());
+    }
+    let highlighted_lines = self.highlight(code, language);
+    self.apply_inferred_title(&highlighted_lines);
+    highlighted_lines
+  }
+
+  /// Title the window after the first definition when `infer_title` is on
+  /// and no title is configured, for this render only
+  fn apply_inferred_title(&mut self, highlighted_lines: &[HighlightedLine]) {
+    self.inferred_title = match self.config.window_title.is_none() && self.config.infer_title {
+      true => title::infer_title(highlighted_lines),
+      false => None,
+    };
+  }
+
+  /// The configured title, or else the one inferred for this render
+  pub(super) fn window_title(&self) -> Option<&str> {
+    self
+      .config
+      .window_title
+      .as_deref()
+      .or(self.inferred_title.as_deref())
+  }
+
+  /// Size the gutter for `last_line` regardless of how many lines are drawn
+  pub(crate) fn reserve_gutter(&mut self, last_line: u32) {
+    self.gutter_reserved_line = last_line;
+  }
+
+  /// Size and place the code block for `line_count` lines as wide as
+  /// `line_width` regardless of what is drawn
+  pub(crate) fn reserve_content(&mut self, line_count: u32, line_width: u32) {
+    self.reserved_lines = line_count;
+    self.reserved_line_width = line_width;
+  }
+
+  pub fn render_snippet(&mut self, code: &str, language: &str) -> Result<String> {
+    let guard = RenderGuard::acquire()?;
+    let highlighted_lines = self.highlight_snippet(code, language);
+    let highlighted_lines = self.limit_lines(highlighted_lines);
+    self.render_highlighted(guard, &highlighted_lines)
+  }
+
+  /// Like `render_snippet`, with the image's size and how long it took
+  pub fn render_snippet_with_metadata(
+    &mut self,
+    code: &str,
+    language: &str,
+  ) -> Result<RenderOutput> {
+    let started = Instant::now();
+    let guard = RenderGuard::acquire()?;
+    let highlighted_lines = self.highlight_snippet(code, language);
+    let highlighted_lines = self.limit_lines(highlighted_lines);
+    let metrics = self.compute_metrics(&highlighted_lines);
+    let image = self.render_highlighted(guard, &highlighted_lines)?;
+    Ok(RenderOutput {
+      image,
+      width: metrics.width,
+      height: metrics.height,
+      line_count: metrics.line_count,
+      render_ms: started.elapsed().as_millis() as u64,
+      theme: self.theme.name.clone(),
+      truncated: metrics.truncated || self.hidden_lines > 0,
+    })
+  }
+
+  /// Render lines highlighted elsewhere, such as by the editor, as they are
+  pub fn render_tokens(&mut self, mut highlighted_lines: Vec<HighlightedLine>) -> Result<String> {
+    let guard = RenderGuard::acquire()?;
+    self.substitute_glyphs(&mut highlighted_lines);
+    self.colorize_brackets(&mut highlighted_lines);
+    expand_tabs(&mut highlighted_lines, self.config.tab_width);
+    self.apply_inferred_title(&highlighted_lines);
+    self.render_highlighted(guard, &highlighted_lines)
+  }
+
+  /// Render only the backdrop as a `width`x`height` pixel image, for
+  /// compositing behind a panel rendered with `layer: "panel"`
+  pub fn render_backdrop(&mut self, width: u32, height: u32) -> Result<String> {
+    let guard = RenderGuard::acquire()?;
+    if width == 0 || height == 0 {
+      return Err(anyhow!(
+        "Backdrop size must be non-zero, got {}x{}",
+        width,
+        height
+      ));
+    }
+    guard.check_pixels(width, height)?;
+    let mut image = ImageBuffer::new(width, height);
+    self.draw_backdrop(&mut image)?;
+    self.encode(guard, &image)
+  }
+
+  /// Rasterize already-highlighted lines into a base64 PNG data URL
+  fn render_highlighted(
+    &mut self,
+    guard: RenderGuard,
+    highlighted_lines: &[HighlightedLine],
+  ) -> Result<String> {
+    let image = self.rasterize(&guard, highlighted_lines)?;
+    self.encode(guard, &image)
+  }
+
+  /// Render the snippet as PNG bytes rather than a data URL, for writing
+  /// straight to a file
+  pub fn render_snippet_png(&mut self, code: &str, language: &str) -> Result<Vec<u8>> {
+    let guard = RenderGuard::acquire()?;
+    let highlighted_lines = self.highlight_snippet(code, language);
+    let highlighted_lines = self.limit_lines(highlighted_lines);
+    let image = self.rasterize(&guard, &highlighted_lines)?;
+    let png_data = self.encode_png(&image)?;
+    guard.complete();
+    Ok(png_data)
+  }
+
+  /// Encode a finished image as a base64 PNG data URL
+  fn encode(&self, guard: RenderGuard, image: &RgbaImage) -> Result<String> {
+    let base64_data = general_purpose::STANDARD.encode(self.encode_png(image)?);
+    guard.complete();
+    Ok(format!("data:image/png;base64,{}", base64_data))
+  }
+
+  /// Convert to the output color space and PNG, then minify
+  fn encode_png(&self, image: &RgbaImage) -> Result<Vec<u8>> {
+    let color_space = self.config.color_space;
+    let image = match color_space {
+      ColorSpace::Srgb => Cow::Borrowed(image),
+      ColorSpace::DisplayP3 => {
+        let mut converted = image.clone();
+        color_space::convert_to_display_p3(&mut converted);
+        Cow::Owned(converted)
+      }
+    };
+    let texts = self.png_texts();
+    let png_data = match self.config.palette_colors {
+      0 => Self::png_bytes(&image, color_space, &texts)?,
+      colors => {
+        quantize::indexed_png_bytes(&image, colors, self.config.dither, color_space, &texts)?
+      }
     };
+    // Skeletons are throwaway previews, so skip the slow optimization pass
+    Ok(match self.config.skeleton {
+      true => png_data,
+      false => Self::optimize_png_bytes(png_data),
+    })
+  }
 
-    // Calculate panel dimensions (unscaled)
-    let panel_height = content_height + (padding * 2) + window_controls_height;
+  /// What the snippet was rendered from, written into the PNG when
+  /// `embed_metadata` is on
+  fn png_texts(&self) -> Vec<PngText> {
+    if !self.config.embed_metadata {
+      return Vec::new();
+    }
+    let mut texts = vec![
+      PngText::new("Software", format!("shoyu {}", env!("CARGO_PKG_VERSION"))),
+      PngText::new("shoyu:theme", self.theme.name.as_str()),
+    ];
+    if let Some(language) = &self.language {
+      texts.push(PngText::new("shoyu:language", language.as_str()));
+    }
+    if let Some(source) = &self.source {
+      texts.push(PngText::compressed(SOURCE_KEYWORD, source.as_str()));
+    }
+    texts
+  }
 
-    // Calculate final image dimensions with panel padding
-    let final_width = self.config.get_actual_width() + (self.config.get_scaled_panel_padding() * 2);
-    let final_height =
-      self.config.get_actual_height(panel_height) + (self.config.get_scaled_panel_padding() * 2);
+  /// Draw backdrop, panel, chrome, and code for already-highlighted lines
+  pub(crate) fn rasterize(
+    &mut self,
+    guard: &RenderGuard,
+    highlighted_lines: &[HighlightedLine],
+  ) -> Result<RgbaImage> {
+    let metrics = self.compute_metrics(highlighted_lines);
+    let (final_width, final_height) = (metrics.width, metrics.height);
 
     // Create image with panel padding
+    guard.check_pixels(final_width, final_height)?;
     let mut image = ImageBuffer::new(final_width, final_height);
 
-    // Draw gradient backdrop if enabled
-    if self.config.gradient_backdrop {
-      self.draw_gradient_backdrop(&mut image, final_width, final_height)?;
-    } else {
-      // Fill with solid background
-      let bg_color = rgba_from_hex(&self.theme.background.hex)?;
-      for pixel in image.pixels_mut() {
-        *pixel = bg_color;
+    // A panel layer leaves the backdrop transparent for compositing elsewhere
+    match self.config.layer {
+      RenderLayer::Full => self.draw_backdrop(&mut image)?,
+      RenderLayer::Panel => {}
+      RenderLayer::Backdrop => {
+        self.draw_backdrop(&mut image)?;
+        return Ok(self.fit_canvas(image));
       }
     }
 
     // Create panel area (offset by panel padding)
-    let panel_x = self.config.get_scaled_panel_padding();
-    let panel_y = self.config.get_scaled_panel_padding();
-    let panel_actual_width = self.config.get_actual_width();
-    let panel_actual_height = self.config.get_actual_height(panel_height);
+    let panel_x = metrics.panel_padding.left;
+    let panel_y = metrics.panel_padding.top;
+    let panel_actual_width = metrics.panel_width;
+    let panel_actual_height = metrics.panel_height;
 
     // Draw panel background with rounded corners
-    let panel_bg_color = rgba_from_hex(&self.theme.background.hex)?;
-    self.draw_rounded_rect(
+    let panel = PanelRect {
+      x: panel_x,
+      y: panel_y,
+      width: panel_actual_width,
+      height: panel_actual_height,
+    };
+    self.draw_panel(&mut image, panel, self.panel_background()?)?;
+
+    // Draw gutter background and separator (within the panel area)
+    self.draw_gutter_background(&mut image, panel, metrics.line_count)?;
+    self.draw_blame_rule(&mut image, panel, metrics.line_count)?;
+    self.draw_column_rules(&mut image, panel, metrics.line_count)?;
+    self.draw_text_width_marker(
       &mut image,
-      panel_x as i32,
-      panel_y as i32,
-      panel_actual_width,
-      panel_actual_height,
-      self.config.border_radius,
-      panel_bg_color,
+      panel,
+      metrics.line_count,
+      metrics.content_offset_x,
     )?;
 
     // Draw window frame if enabled (within the panel area)
-    if self.config.window_controls {
+    if self.has_title_bar() {
       self.draw_window_frame(
         &mut image,
         panel_actual_width,
         panel_actual_height,
-        padding,
+        self.config.padding.right,
         panel_x,
         panel_y,
       )?;
     }
 
+    self.draw_strips(&mut image, panel)?;
+    self.draw_language_badge(&mut image, panel)?;
+    self.draw_border(&mut image, panel)?;
+
     // Draw code content (within the panel area)
     // Note: draw_code_content now uses &mut self for complex renderer
     self.draw_code_content(
       &mut image,
-      &highlighted_lines,
-      padding,
-      line_height,
+      highlighted_lines,
+      metrics.panel_width,
+      metrics.line_height,
+      metrics.content_offset_x,
       panel_x,
-      panel_y,
+      panel_y + metrics.content_offset_y,
     )?;
-
-    // Convert to PNG, minify, and encode as base64
-    let png_data = self.image_to_png_bytes(&image)?;
-    let optimized_png_data = Self::optimize_png_bytes(png_data);
-    let base64_data = general_purpose::STANDARD.encode(&optimized_png_data);
-
-    Ok(format!("data:image/png;base64,{}", base64_data))
+    let (ascent, _) = self.font_manager.get_vertical_metrics();
+    let code_top = panel_y
+      + metrics.content_offset_y
+      + self.config.get_scaled_padding().top
+      + self.top_chrome_height();
+    self.draw_minimap(
+      &mut image,
+      panel,
+      highlighted_lines,
+      code_top.saturating_sub(ascent.ceil() as u32),
+    )?;
+    self.draw_watermark(&mut image, panel)?;
+    #[cfg(feature = "qr")]
+    self.draw_qr_code(&mut image, panel)?;
+    Ok(self.fit_canvas(image))
   }
 
   fn draw_window_frame(
@@ -161,7 +664,7 @@ impl SnippetRenderer {
     let frame_height = (40.0 * self.config.export_size) as u32;
 
     // Draw window title bar with rounded corners (only top corners)
-    let title_bar_color = darken_color(&self.theme.background.hex, 0.1)?;
+    let chrome = self.chrome()?;
     self.draw_rounded_rect_top_only(
       image,
       offset_x as i32,
@@ -169,87 +672,276 @@ impl SnippetRenderer {
       width,
       frame_height,
       self.config.border_radius,
-      title_bar_color,
+      self.glass(chrome.title_bar),
     )?;
 
-    // Draw window controls (circles)
-    let control_radius = (6.0 * self.config.export_size) as i32;
+    let bar = PanelRect {
+      x: offset_x,
+      y: offset_y,
+      width,
+      height: frame_height,
+    };
+    let controls_width = match self.config.window_controls {
+      true => self.draw_window_controls(image, bar, &chrome),
+      false => 0,
+    };
     let control_y = offset_y as i32 + (frame_height / 2) as i32;
-    let control_spacing = (20.0 * self.config.export_size) as i32;
-    let start_x = offset_x as i32 + (padding / 2) as i32;
-    self.draw_circle(image, start_x, control_y, control_radius, "#ff5f56")?;
-    self.draw_circle(
-      image,
-      start_x + control_spacing,
-      control_y,
-      control_radius,
-      "#ffbd2e",
-    )?;
-    self.draw_circle(
-      image,
-      start_x + control_spacing * 2,
-      control_y,
-      control_radius,
-      "#27ca3f",
-    )?;
+    if !self.tab_titles.is_empty() {
+      self.draw_tabs(image, bar, controls_width, &chrome)?;
+    }
 
-    // Draw window title if provided
-    if let Some(_title) = &self.config.window_title {
-      // Title drawing would go here - simplified for now
+    // Draw the language icon and window title centered together in the title bar
+    let icon_width = self.title_icon_width();
+    let title_width = self.window_title().map_or(0, |title| {
+      self
+        .font_manager
+        .measure_text(title, TokenStyle::REGULAR, None)
+    });
+    let gap = match icon_width > 0 && title_width > 0 {
+      true => self
+        .font_manager
+        .measure_text(" ", TokenStyle::REGULAR, None),
+      false => 0,
+    };
+    let (ascent, descent) = self.font_manager.get_vertical_metrics();
+    let title_y = (control_y + ((ascent + descent) / 2.0) as i32).max(0) as u32;
+    if icon_width + title_width > 0 && self.tab_titles.is_empty() {
+      let icon_x = offset_x + width.saturating_sub(icon_width + gap + title_width) / 2;
+      self.draw_title_icon(image, icon_x, title_y)?;
+      if let Some(title) = self.window_title() {
+        self.draw_text(
+          image,
+          title,
+          icon_x + icon_width + gap,
+          title_y,
+          self.config.get_scaled_font_size(),
+          chrome.title_text,
+          TokenStyle::REGULAR,
+          None,
+        )?;
+      }
     }
-    Ok(())
+
+    // Project badge mirrors macOS controls at the right end of the bar, or
+    // sits just left of controls already there
+    self.draw_project_badge(
+      image,
+      match controls_width {
+        0 => (offset_x + width).saturating_sub(padding / 2),
+        _ => (offset_x + width).saturating_sub(controls_width + padding / 4),
+      },
+      title_y,
+      chrome.title_bar,
+      chrome.title_text,
+    )
   }
 
+  #[allow(clippy::too_many_arguments)]
   fn draw_code_content(
     &mut self,
     image: &mut RgbaImage,
     highlighted_lines: &[HighlightedLine],
-    _padding: u32,
-    line_height: u32,
+    panel_width: u32,
+    scaled_line_height: u32,
+    indent: u32,
     offset_x: u32,
     offset_y: u32,
   ) -> Result<()> {
-    let font_size = self.config.get_scaled_font_size();
-    let scaled_padding = self.config.get_scaled_padding();
-    let start_y = offset_y
-      + if self.config.window_controls {
-        scaled_padding + (40.0 * self.config.export_size) as u32
-      } else {
-        scaled_padding
+    // Line highlight strips span the panel and are centered on the glyph box
+    let (ascent, descent) = self.font_manager.get_vertical_metrics();
+    let (columns, rows) = self.column_grid(highlighted_lines.len() as u32);
+    let column_width = panel_width.saturating_sub(self.minimap_width()) / columns;
+    let layout = CodeLayout {
+      x: offset_x,
+      start_y: offset_y + self.config.get_scaled_padding().top + self.top_chrome_height(),
+      line_height: scaled_line_height,
+      column_width,
+      rows: rows as usize,
+      indent,
+      strip_offset: ((ascent + descent) / 2.0 + scaled_line_height as f32 / 2.0) as i32,
+      highlight_color: self.line_highlight_color()?,
+      selection_color: self.selection_color()?,
+      visible_columns: self.visible_columns(
+        column_width
+          .saturating_sub(self.blame_width(highlighted_lines.len() as u32, BlameSide::Right)),
+      ),
+      line_count: highlighted_lines.len() as u32,
+    };
+
+    // cosmic-text needs the renderer mutably, so snippets with complex
+    // scripts are drawn on this thread alone
+    #[cfg(feature = "complex-scripts")]
+    let shape_complex = self.complex_renderer.is_some() && !self.config.skeleton;
+    #[cfg(not(feature = "complex-scripts"))]
+    let shape_complex = false;
+    let line_text = |line: &HighlightedLine| -> String {
+      clip_tokens(&line.tokens, layout.visible_columns)
+        .iter()
+        .map(|token| token.text.as_str())
+        .collect()
+    };
+    if shape_complex
+      && highlighted_lines
+        .iter()
+        .any(|line| has_complex_script(&line_text(line)))
+    {
+      for (line_index, line) in highlighted_lines.iter().enumerate() {
+        let y = layout.baseline(line_index);
+        if !has_complex_script(&line_text(line)) {
+          self.draw_code_line(image, &layout, line_index, line, y)?;
+          continue;
+        }
+        let x = self.draw_line_decorations(image, &layout, line_index, line, y)?;
+        let tokens = clip_tokens(&line.tokens, layout.visible_columns);
+        self.render_complex_line(image, &tokens, line_index, x, y)?;
+        self.draw_diagnostics(image, &layout, line_index, line, x, y)?;
+        self.draw_cursor(image, &layout, line_index, line, x, y)?;
+      }
+      return self.draw_annotations(image, &layout, highlighted_lines);
+    }
+
+    // Glyphs and strips stay within two lines of their baseline
+    let margin = scaled_line_height * 2;
+    self.draw_in_bands(image, margin, |band, top| {
+      for (line_index, line) in highlighted_lines.iter().enumerate() {
+        let y = layout.baseline(line_index);
+        if y >= top && y < top + band.height() + margin {
+          self.draw_code_line(band, &layout, line_index, line, y - top)?;
+        }
+      }
+      Ok(())
+    })?;
+    self.draw_annotations(image, &layout, highlighted_lines)
+  }
+
+  /// Highlight strip, selection, token backgrounds, and line number for a line with its
+  /// baseline at `y`, returning where its code starts
+  fn draw_line_decorations(
+    &self,
+    image: &mut RgbaImage,
+    layout: &CodeLayout,
+    line_index: usize,
+    line: &HighlightedLine,
+    y: u32,
+  ) -> Result<u32> {
+    let column_x = layout.column_x(line_index);
+    let mut x = column_x + self.config.get_scaled_padding().left;
+    let marker = self.is_marker_row(line_index, layout.line_count);
+    if self.config.is_line_highlighted(line_index as u32 + 1) && !marker {
+      self.fill_rect(
+        image,
+        column_x as i32,
+        y as i32 - layout.strip_offset,
+        layout.column_width,
+        layout.line_height,
+        layout.highlight_color,
+      );
+    }
+    x += self.draw_blame(
+      image,
+      line_index,
+      layout.line_count,
+      column_x,
+      layout.column_width,
+      y,
+    )?;
+    if !marker {
+      self.draw_sign(image, line_index, x, y)?;
+    }
+    x += self.sign_column_width();
+    if self.config.line_numbers {
+      x += match marker {
+        true => self.number_column_width(layout.line_count),
+        false => self.draw_line_number(image, line_index, layout.line_count, x, y)?,
       };
+    }
+    if !marker {
+      self.draw_selection(image, layout, line_index, line, x + layout.indent, y);
+      self.draw_token_backgrounds(image, layout, line_index, line, x + layout.indent, y)?;
+    }
+    Ok(x + layout.indent)
+  }
 
-    // Use scaled line height for actual rendering
-    let scaled_line_height = (line_height as f32 * self.config.export_size) as u32;
+  /// Draw a line without complex scripts, its baseline at `y`
+  fn draw_code_line(
+    &self,
+    image: &mut RgbaImage,
+    layout: &CodeLayout,
+    line_index: usize,
+    line: &HighlightedLine,
+    y: u32,
+  ) -> Result<()> {
+    let code_x = self.draw_line_decorations(image, layout, line_index, line, y)?;
+    let mut x = code_x;
+    let font_size = self.config.get_scaled_font_size();
+    // Nothing past the panel's right edge is worth shaping or rasterizing
+    for token in clip_tokens(&line.tokens, layout.visible_columns).iter() {
+      let token_color = self.focus_color(line_index, rgba_from_hex(&token.color.hex)?);
+      let face = self.scope_face(&token.scope);
+      x += self.draw_text(
+        image,
+        &token.text,
+        x,
+        y,
+        font_size,
+        token_color,
+        token.style,
+        face,
+      )?;
+    }
+    self.draw_diagnostics(image, layout, line_index, line, code_x, y)?;
+    self.draw_cursor(image, layout, line_index, line, code_x, y)
+  }
 
-    for (line_index, line) in highlighted_lines.iter().enumerate() {
-      let y = start_y + (line_index as u32 * scaled_line_height);
-      let mut x = offset_x + scaled_padding;
+  /// Whether a title bar is drawn, for window controls or tabs
+  pub(crate) fn has_title_bar(&self) -> bool {
+    self.config.window_controls || !self.tab_titles.is_empty()
+  }
 
-      // Check if line contains complex scripts requiring shaping
-      let line_text: String = line.tokens.iter().map(|t| t.text.as_str()).collect();
-      let needs_shaping = has_complex_script(&line_text);
+  /// Height of the title bar in scaled pixels, zero without one
+  pub(crate) fn title_bar_height(&self) -> u32 {
+    if self.has_title_bar() {
+      (40.0 * self.config.export_size) as u32
+    } else {
+      0
+    }
+  }
 
-      // Draw line numbers (always use simple rendering)
-      if self.config.line_numbers {
-        let line_num = format!("{:3} ", line_index + 1);
-        let line_num_color = rgba_from_hex(&self.theme.comment.hex)?;
-        x += self.draw_text(image, &line_num, x, y, font_size, line_num_color)?;
-        x += (10.0 * self.config.export_size) as u32; // Add some spacing
-      }
+  /// Hex color of the code panel, honoring the `panel_color` override
+  fn panel_background_hex(&self) -> &str {
+    self
+      .config
+      .panel_color
+      .as_deref()
+      .unwrap_or(&self.theme.background.hex)
+  }
 
-      // Route to appropriate renderer based on content
-      if needs_shaping && self.complex_renderer.is_some() {
-        // Use cosmic-text for complex scripts
-        self.render_complex_line(image, &line.tokens, x, y)?;
-      } else {
-        // Use fontdue for simple ASCII text (fast path)
-        for token in &line.tokens {
-          let token_color = rgba_from_hex(&token.color.hex)?;
-          x += self.draw_text(image, &token.text, x, y, font_size, token_color)?;
-        }
-      }
+  fn panel_background(&self) -> Result<Rgba<u8>> {
+    rgba_from_hex(self.panel_background_hex())
+  }
+
+  fn line_highlight_color(&self) -> Result<Rgba<u8>> {
+    match &self.config.highlight_color {
+      Some(hex) => rgba_from_hex(hex),
+      None => Ok(blend_colors(
+        self.panel_background()?,
+        rgba_from_hex(&self.theme.foreground.hex)?,
+        0.1,
+      )),
     }
-    Ok(())
+  }
+
+  /// Named face for the first scope font rule matching a token's scope stack
+  fn scope_face(&self, scope: &str) -> Option<&str> {
+    if self.scope_fonts.is_empty() {
+      return None;
+    }
+    let stack = ScopeStack::from_str(scope).ok()?;
+    self
+      .scope_fonts
+      .iter()
+      .find(|(selectors, _)| selectors.does_match(stack.as_slice()).is_some())
+      .map(|(_, font)| font.as_str())
   }
 
   /// Render a line with complex script support using cosmic-text
@@ -257,23 +949,37 @@ impl SnippetRenderer {
     &mut self,
     image: &mut RgbaImage,
     tokens: &[crate::syntax::HighlightedToken],
+    line_index: usize,
     x: u32,
     y: u32,
   ) -> Result<u32> {
+    let (tokens, opacity) = self.focus_tokens(tokens, line_index);
+    #[cfg(feature = "complex-scripts")]
     if let Some(ref mut renderer) = self.complex_renderer {
-      renderer.render_line(image, tokens, x, y)
-    } else {
-      // Fallback to simple rendering if complex renderer unavailable
-      let mut current_x = x;
-      let font_size = self.config.get_scaled_font_size();
-      for token in tokens {
-        let color = rgba_from_hex(&token.color.hex)?;
-        current_x += self.draw_text(image, &token.text, current_x, y, font_size, color)?;
-      }
-      Ok(current_x - x)
+      return renderer.render_line(image, &tokens, x, y, opacity);
+    }
+    // Fallback to simple rendering if complex renderer unavailable
+    let mut current_x = x;
+    let font_size = self.config.get_scaled_font_size();
+    for token in tokens.iter() {
+      let color = rgba_from_hex(&token.color.hex)?;
+      let color = Rgba([color[0], color[1], color[2], opacity]);
+      let face = self.scope_face(&token.scope);
+      current_x += self.draw_text(
+        image,
+        &token.text,
+        current_x,
+        y,
+        font_size,
+        color,
+        token.style,
+        face,
+      )?;
     }
+    Ok(current_x - x)
   }
 
+  #[allow(clippy::too_many_arguments)]
   fn draw_text(
     &self,
     image: &mut RgbaImage,
@@ -282,19 +988,20 @@ impl SnippetRenderer {
     y: u32,
     _font_size: f32, // Now using font_manager's size
     color: Rgba<u8>,
+    style: TokenStyle,
+    face: Option<&str>,
   ) -> Result<u32> {
+    if self.config.skeleton {
+      return self.draw_skeleton_text(image, text, x, y, style, face);
+    }
+
     let mut current_x = x as i32;
     // The y coordinate already represents the baseline position
     // No additional calculation needed - use it directly
     let baseline_y = y as i32;
 
-    for ch in text.chars() {
-      // Skip control characters that might cause tofu glyphs
-      if ch.is_control() && ch != '\t' {
-        continue;
-      }
-      let glyph = self.font_manager.render_glyph(ch);
-
+    // Control characters are skipped so they don't draw as tofu
+    for glyph in self.font_manager.render_styled_text(text, style, face) {
       // Blend the glyph onto the image using the calculated baseline
       self
         .font_manager
@@ -304,7 +1011,19 @@ impl SnippetRenderer {
       current_x += glyph.advance_width as i32;
     }
 
-    Ok((current_x - x as i32) as u32)
+    let width = (current_x - x as i32) as u32;
+    if style.is_underline() {
+      let (offset, thickness) = self.font_manager.underline_metrics();
+      self.blend_rect(
+        image,
+        x as i32,
+        baseline_y + offset,
+        width,
+        thickness,
+        color,
+      );
+    }
+    Ok(width)
   }
 
   fn optimize_png_bytes(png_data: Vec<u8>) -> Vec<u8> {
@@ -316,15 +1035,30 @@ impl SnippetRenderer {
     }
   }
 
-  fn image_to_png_bytes(&self, image: &RgbaImage) -> Result<Vec<u8>> {
+  pub(crate) fn image_to_png_bytes(image: &RgbaImage) -> Result<Vec<u8>> {
+    Self::png_bytes(image, ColorSpace::Srgb, &[])
+  }
+
+  fn png_bytes(image: &RgbaImage, color_space: ColorSpace, texts: &[PngText]) -> Result<Vec<u8>> {
     let mut png_data = Vec::new();
-    let encoder = image::codecs::png::PngEncoder::new(&mut png_data);
-    encoder.write_image(
-      image.as_raw(),
+    let mut encoder = color_space::png_encoder(
+      &mut png_data,
       image.width(),
       image.height(),
-      image::ColorType::Rgba8,
+      color_space,
+      texts,
     )?;
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder
+      .write_header()
+      .map_err(|e| anyhow!("Failed to write PNG: {}", e))?;
+    writer
+      .write_image_data(image.as_raw())
+      .map_err(|e| anyhow!("Failed to write PNG: {}", e))?;
+    writer
+      .finish()
+      .map_err(|e| anyhow!("Failed to write PNG: {}", e))?;
     Ok(png_data)
   }
 }
@@ -0,0 +1,120 @@
+/* ~~/src/renderer/parallel.rs */
+
+// standard crates
+#[cfg(feature = "parallel")]
+use std::sync::{Arc, Mutex};
+
+// third-party crates
+use anyhow::{Result, anyhow};
+use image::RgbaImage;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+#[cfg(feature = "parallel")]
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+// local modules
+use crate::renderer::SnippetRenderer;
+
+/// Pools for explicit `threads` counts, built once and shared by renders
+#[cfg(feature = "parallel")]
+static POOLS: Mutex<Vec<(u32, Arc<ThreadPool>)>> = Mutex::new(Vec::new());
+
+#[cfg(feature = "parallel")]
+fn thread_pool(threads: u32) -> Result<Arc<ThreadPool>> {
+  let mut pools = POOLS.lock().unwrap_or_else(|e| e.into_inner());
+  if let Some((_, pool)) = pools.iter().find(|(count, _)| *count == threads) {
+    return Ok(pool.clone());
+  }
+  let pool = ThreadPoolBuilder::new()
+    .num_threads(threads as usize)
+    .thread_name(|index| format!("shoyu-render-{}", index))
+    .build()
+    .map_err(|e| anyhow!("Failed to start {} render threads: {}", threads, e))?;
+  let pool = Arc::new(pool);
+  pools.push((threads, pool.clone()));
+  Ok(pool)
+}
+
+impl SnippetRenderer {
+  /// Run `work` with `threads` workers, or rayon's global pool when it is 0
+  #[cfg(feature = "parallel")]
+  pub(super) fn in_thread_pool<R: Send>(&self, work: impl FnOnce() -> R + Send) -> Result<R> {
+    match self.config.threads {
+      0 => Ok(work()),
+      threads => Ok(thread_pool(threads)?.install(work)),
+    }
+  }
+
+  #[cfg(feature = "parallel")]
+  fn worker_count(&self) -> usize {
+    match self.config.threads {
+      0 => rayon::current_num_threads(),
+      threads => threads as usize,
+    }
+  }
+
+  /// Stand-in for builds without the `parallel` feature
+  #[cfg(not(feature = "parallel"))]
+  fn worker_count(&self) -> usize {
+    1
+  }
+
+  /// Run `work` on each `size`-byte chunk of `buffer` along with its index,
+  /// spread over the render's workers
+  #[cfg(feature = "parallel")]
+  pub(super) fn for_each_chunk<F>(&self, buffer: &mut [u8], size: usize, work: F) -> Result<()>
+  where
+    F: Fn(usize, &mut [u8]) -> Result<()> + Sync,
+  {
+    self.in_thread_pool(|| {
+      buffer
+        .par_chunks_mut(size)
+        .enumerate()
+        .try_for_each(|(index, chunk)| work(index, chunk))
+    })?
+  }
+
+  /// Stand-in for builds without the `parallel` feature
+  #[cfg(not(feature = "parallel"))]
+  pub(super) fn for_each_chunk<F>(&self, buffer: &mut [u8], size: usize, work: F) -> Result<()>
+  where
+    F: Fn(usize, &mut [u8]) -> Result<()> + Sync,
+  {
+    buffer
+      .chunks_mut(size)
+      .enumerate()
+      .try_for_each(|(index, chunk)| work(index, chunk))
+  }
+
+  /// Split `image` into row bands and run `draw` on each in parallel. `draw`
+  /// gets a copy of its band grown by `margin` rows each way and the row the
+  /// copy starts at; anything it draws reaching no more than `margin` rows
+  /// outside a band comes out as if drawn onto the whole image at once.
+  pub(super) fn draw_in_bands<F>(&self, image: &mut RgbaImage, margin: u32, draw: F) -> Result<()>
+  where
+    F: Fn(&mut RgbaImage, u32) -> Result<()> + Sync,
+  {
+    let (width, height) = image.dimensions();
+    let workers = self.worker_count() as u32;
+    if workers <= 1 || width == 0 || height == 0 {
+      return draw(image, 0);
+    }
+    // A few bands per worker even out lines of uneven length, but bands
+    // shorter than their margins would mostly be copying
+    let band_height = height.div_ceil(workers * 4).max(margin).max(1);
+    let row_bytes = width as usize * 4;
+    let source = image.clone();
+    self.for_each_chunk(image, band_height as usize * row_bytes, |index, band| {
+      let start = index as u32 * band_height;
+      let top = start.saturating_sub(margin);
+      let bottom = (start + band_height + margin).min(height);
+      let rows = source.as_raw()[top as usize * row_bytes..bottom as usize * row_bytes].to_vec();
+      let mut scratch = RgbaImage::from_raw(width, bottom - top, rows)
+        .ok_or_else(|| anyhow!("Render band {} is out of bounds", index))?;
+      draw(&mut scratch, top)?;
+      let offset = (start - top) as usize * row_bytes;
+      band.copy_from_slice(&scratch.as_raw()[offset..offset + band.len()]);
+      Ok(())
+    })
+  }
+}
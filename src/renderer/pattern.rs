@@ -0,0 +1,205 @@
+/* ~~/src/renderer/pattern.rs */
+
+// standard crates
+use std::f32::consts::TAU;
+
+// third-party crates
+use anyhow::Result;
+use image::{Rgba, RgbaImage};
+
+// local modules
+use crate::config::{BackdropPattern, PatternKind};
+use crate::renderer::SnippetRenderer;
+use crate::renderer::color::{blend_colors, rgba_from_hex};
+
+/// Distance between dots, stripes, or waves at density 1, before export
+/// scaling
+const SPACING: f32 = 28.0;
+
+/// Angle between successive mesh points, which spreads any number of them
+/// evenly over a disc
+const GOLDEN_ANGLE: f32 = 2.399_963;
+
+impl SnippetRenderer {
+  /// Fill the canvas with `pattern`, drawn about its center so rotation keeps
+  /// it symmetric
+  pub(super) fn draw_pattern_backdrop(
+    &self,
+    image: &mut RgbaImage,
+    pattern: &BackdropPattern,
+  ) -> Result<()> {
+    let colors = self.pattern_colors(pattern)?;
+    let (width, height) = image.dimensions();
+    let shape = Shape {
+      kind: pattern.kind,
+      spacing: (SPACING * self.config.export_size / pattern.density.max(0.1)).max(2.0),
+      density: pattern.density.max(0.1),
+      rotation: pattern.rotation.to_radians(),
+      center: (width as f32 / 2.0, height as f32 / 2.0),
+      size: (width.max(1) as f32, height.max(1) as f32),
+    };
+    let row_bytes = width as usize * 4;
+    self.for_each_chunk(image, row_bytes.max(4), |y, row| {
+      for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+        pixel.copy_from_slice(&shape.color_at(x as f32 + 0.5, y as f32 + 0.5, &colors).0);
+      }
+      Ok(())
+    })
+  }
+
+  /// The pattern's colors, or a ground of the theme background under faint
+  /// tints of its keyword, function, and string colors
+  fn pattern_colors(&self, pattern: &BackdropPattern) -> Result<Vec<Rgba<u8>>> {
+    let mut colors = pattern
+      .colors
+      .iter()
+      .map(|hex| rgba_from_hex(hex))
+      .collect::<Result<Vec<_>>>()?;
+    if colors.is_empty() {
+      let ground = rgba_from_hex(&self.theme.background.hex)?;
+      let tint = match pattern.kind {
+        PatternKind::Mesh => 0.6,
+        _ => 0.35,
+      };
+      colors.push(ground);
+      for color in [
+        &self.theme.keyword,
+        &self.theme.function,
+        &self.theme.string,
+      ] {
+        colors.push(blend_colors(ground, rgba_from_hex(&color.hex)?, tint));
+      }
+    }
+    Ok(colors)
+  }
+}
+
+/// Geometry of a pattern on one canvas
+struct Shape {
+  kind: PatternKind,
+  spacing: f32,
+  density: f32,
+  /// Radians clockwise
+  rotation: f32,
+  center: (f32, f32),
+  size: (f32, f32),
+}
+
+impl Shape {
+  fn color_at(&self, x: f32, y: f32, colors: &[Rgba<u8>]) -> Rgba<u8> {
+    let ground = colors[0];
+    let inks = match colors.len() {
+      1 => colors,
+      _ => &colors[1..],
+    };
+    let ink = |index: f32| inks[(index as i64).rem_euclid(inks.len() as i64) as usize];
+    let (u, v) = self.unrotated(x, y, self.rotation);
+    let spacing = self.spacing;
+    // Each shape yields how far inside its edge the pixel lies, which becomes
+    // an antialiased coverage a pixel wide
+    let (inside, color) = match self.kind {
+      PatternKind::Dots => {
+        let (column, row) = ((u / spacing).floor(), (v / spacing).floor());
+        let distance = (u - (column + 0.5) * spacing).hypot(v - (row + 0.5) * spacing);
+        (spacing * 0.18 - distance, ink(column + row))
+      }
+      PatternKind::Stripes => {
+        let (u, _) = self.unrotated(x, y, self.rotation + TAU / 8.0);
+        let index = (u / spacing).floor();
+        let offset = u - index * spacing;
+        let half = spacing / 2.0;
+        let inside = match offset < half {
+          true => offset.min(half - offset),
+          false => -(offset - half).min(spacing - offset),
+        };
+        (inside, ink(index))
+      }
+      PatternKind::Waves => {
+        let (amplitude, wavelength) = (spacing * 0.3, spacing * 3.0);
+        let phase = u * TAU / wavelength;
+        let w = v - amplitude * phase.sin();
+        let index = (w / spacing).round();
+        // Distance across the curve rather than straight down
+        let slope = amplitude * TAU / wavelength * phase.cos();
+        let distance = (w - index * spacing).abs() / slope.hypot(1.0);
+        ((spacing * 0.07).max(0.5) - distance, ink(index))
+      }
+      PatternKind::Mesh => return self.mesh_at(x, y, colors),
+    };
+    blend_colors(ground, color, (inside + 0.5).clamp(0.0, 1.0))
+  }
+
+  /// Inverse-distance blend of every color, each pinned to a point on a
+  /// spiral over the canvas
+  fn mesh_at(&self, x: f32, y: f32, colors: &[Rgba<u8>]) -> Rgba<u8> {
+    let (width, height) = self.size;
+    let diagonal_squared = width * width + height * height;
+    let count = colors.len() as f32;
+    let mut total = 0.0;
+    let mut mixed = [0.0f32; 4];
+    for (index, color) in colors.iter().enumerate() {
+      let angle = index as f32 * GOLDEN_ANGLE + self.rotation;
+      let reach = 0.75 * ((index as f32 + 0.5) / count).sqrt();
+      let point = (
+        self.center.0 + angle.cos() * reach * width / 2.0,
+        self.center.1 + angle.sin() * reach * height / 2.0,
+      );
+      let distance_squared = ((x - point.0).powi(2) + (y - point.1).powi(2)) / diagonal_squared;
+      let weight = (distance_squared + 0.01).powf(-1.5 * self.density);
+      total += weight;
+      for (channel, value) in mixed.iter_mut().zip(color.0) {
+        *channel += weight * value as f32;
+      }
+    }
+    Rgba(mixed.map(|channel| (channel / total).round().clamp(0.0, 255.0) as u8))
+  }
+
+  /// Canvas point `x`, `y` in the pattern's own axes, turned back by
+  /// `rotation` about the center
+  fn unrotated(&self, x: f32, y: f32, rotation: f32) -> (f32, f32) {
+    let (dx, dy) = (x - self.center.0, y - self.center.1);
+    let (sin, cos) = rotation.sin_cos();
+    (dx * cos + dy * sin, dy * cos - dx * sin)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::RenderConfig;
+
+  #[test]
+  fn test_draws_pattern_backdrops() {
+    let draw = |kind: PatternKind| {
+      let pattern = BackdropPattern {
+        kind,
+        colors: vec!["#000000".to_string(), "#ffffff".to_string()],
+        ..BackdropPattern::default()
+      };
+      let config = RenderConfig {
+        export_size: 1.0,
+        ..RenderConfig::default()
+      };
+      let renderer = SnippetRenderer::new("dracula", config).unwrap();
+      let mut image = RgbaImage::new(112, 112);
+      renderer
+        .draw_pattern_backdrop(&mut image, &pattern)
+        .unwrap();
+      image
+    };
+
+    // Dots sit at cell centers, 28 pixels apart, on a black ground
+    let dots = draw(PatternKind::Dots);
+    assert_eq!(dots.get_pixel(42, 42), &Rgba([255, 255, 255, 255]));
+    assert_eq!(dots.get_pixel(56, 56), &Rgba([0, 0, 0, 255]));
+
+    // Stripes cover about half the canvas
+    let stripes = draw(PatternKind::Stripes);
+    let lit = stripes.pixels().filter(|pixel| pixel[0] > 127).count() as f32;
+    assert!((lit / (112.0 * 112.0) - 0.5).abs() < 0.05);
+
+    // Mesh blends smoothly, with no pixel at either extreme
+    let mesh = draw(PatternKind::Mesh);
+    assert!(mesh.pixels().all(|pixel| (1..255).contains(&pixel[0])));
+  }
+}
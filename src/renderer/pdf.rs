@@ -0,0 +1,213 @@
+/* ~~/src/renderer/pdf.rs */
+
+// standard crates
+use std::fmt::Write as _;
+
+// third-party crates
+use anyhow::{Result, anyhow};
+use image::RgbaImage;
+
+// local modules
+use crate::limits::RenderGuard;
+use crate::renderer::SnippetRenderer;
+
+/// PDF points per CSS pixel, at 72 points and 96 pixels to the inch
+const POINTS_PER_PIXEL: f32 = 0.75;
+
+impl SnippetRenderer {
+  /// Render the snippet as a one-page PDF embedding the image at full
+  /// export resolution, on a page the size of the image at 1x
+  pub fn render_snippet_pdf(&mut self, code: &str, language: &str) -> Result<Vec<u8>> {
+    let guard = RenderGuard::acquire()?;
+    let highlighted_lines = self.highlight_snippet(code, language);
+    let highlighted_lines = self.limit_lines(highlighted_lines);
+    let image = self.rasterize(&guard, &highlighted_lines)?;
+    let pdf_data = pdf_bytes(
+      &image,
+      POINTS_PER_PIXEL / self.config.export_size,
+      self.window_title(),
+    )?;
+    guard.complete();
+    Ok(pdf_data)
+  }
+}
+
+/// One page showing `image` at `scale` points per pixel. The pixels stay in
+/// PNG's deflate stream, which PDF reads directly given the PNG predictor.
+fn pdf_bytes(image: &RgbaImage, scale: f32, title: Option<&str>) -> Result<Vec<u8>> {
+  let (width, height) = image.dimensions();
+  let rgb: Vec<u8> = image
+    .pixels()
+    .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+    .collect();
+  let alpha: Option<Vec<u8>> = image
+    .pixels()
+    .any(|pixel| pixel[3] < 255)
+    .then(|| image.pixels().map(|pixel| pixel[3]).collect());
+  let (page_width, page_height) = (width as f32 * scale, height as f32 * scale);
+
+  let image_stream = |data: &[u8], color: png::ColorType| -> Result<(String, Vec<u8>)> {
+    let (space, colors) = match color {
+      png::ColorType::Grayscale => ("DeviceGray", 1),
+      _ => ("DeviceRGB", 3),
+    };
+    let header = format!(
+      "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /{} \
+       /BitsPerComponent 8 /Filter /FlateDecode \
+       /DecodeParms << /Predictor 15 /Colors {} /BitsPerComponent 8 /Columns {} >>",
+      width, height, space, colors, width
+    );
+    Ok((header, deflate_rows(data, width, height, color)?))
+  };
+  let (mut image_header, image_data) = image_stream(&rgb, png::ColorType::Rgb)?;
+  let mask = alpha
+    .map(|alpha| image_stream(&alpha, png::ColorType::Grayscale))
+    .transpose()?;
+  if mask.is_some() {
+    image_header.push_str(" /SMask 6 0 R");
+  }
+  let content = format!(
+    "q {:.2} 0 0 {:.2} 0 0 cm /Im1 Do Q",
+    page_width, page_height
+  );
+  let mut info = "/Producer (shoyu)".to_string();
+  if let Some(title) = title {
+    let _ = write!(info, " /Title {}", pdf_string(title));
+  }
+
+  let mut objects: Vec<(String, Option<Vec<u8>>)> = vec![
+    ("/Type /Catalog /Pages 2 0 R".to_string(), None),
+    ("/Type /Pages /Kids [3 0 R] /Count 1".to_string(), None),
+    (
+      format!(
+        "/Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] \
+         /Resources << /XObject << /Im1 5 0 R >> >> /Contents 4 0 R",
+        page_width, page_height
+      ),
+      None,
+    ),
+    (String::new(), Some(content.into_bytes())),
+    (image_header, Some(image_data)),
+  ];
+  if let Some((mask_header, mask_data)) = mask {
+    objects.push((mask_header, Some(mask_data)));
+  }
+  objects.push((info, None));
+  let info_id = objects.len();
+
+  // The binary comment tells transfer tools the file isn't text
+  let mut pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+  let mut offsets = Vec::with_capacity(objects.len());
+  for (index, (dictionary, stream)) in objects.into_iter().enumerate() {
+    offsets.push(pdf.len());
+    match stream {
+      Some(data) => {
+        pdf.extend(
+          format!(
+            "{} 0 obj\n<< {} >>\nstream\n",
+            index + 1,
+            format!("{} /Length {}", dictionary, data.len()).trim_start(),
+          )
+          .into_bytes(),
+        );
+        pdf.extend(data);
+        pdf.extend(b"\nendstream\nendobj\n");
+      }
+      None => {
+        pdf.extend(format!("{} 0 obj\n<< {} >>\nendobj\n", index + 1, dictionary).into_bytes())
+      }
+    }
+  }
+  let xref = pdf.len();
+  let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1);
+  for offset in &offsets {
+    let _ = writeln!(table, "{:010} 00000 n ", offset);
+  }
+  let _ = write!(
+    table,
+    "trailer\n<< /Size {} /Root 1 0 R /Info {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+    offsets.len() + 1,
+    info_id,
+    xref
+  );
+  pdf.extend(table.into_bytes());
+  Ok(pdf)
+}
+
+/// Rows of 8-bit `color` pixels filtered and deflated the way PNG stores
+/// them, taken from the IDAT chunks of an encoded PNG
+fn deflate_rows(data: &[u8], width: u32, height: u32, color: png::ColorType) -> Result<Vec<u8>> {
+  let png_error = |e: png::EncodingError| anyhow!("Failed to compress the PDF image: {}", e);
+  let mut png_data = Vec::new();
+  let mut encoder = png::Encoder::new(&mut png_data, width, height);
+  encoder.set_color(color);
+  encoder.set_depth(png::BitDepth::Eight);
+  let mut writer = encoder.write_header().map_err(png_error)?;
+  writer.write_image_data(data).map_err(png_error)?;
+  writer.finish().map_err(png_error)?;
+
+  // Chunks after the signature are a length, a type, the data, and a CRC
+  let mut deflated = Vec::new();
+  let mut rest = &png_data[8..];
+  while rest.len() >= 12 {
+    let length = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+    if &rest[4..8] == b"IDAT" {
+      deflated.extend_from_slice(&rest[8..8 + length]);
+    }
+    rest = &rest[12 + length..];
+  }
+  Ok(deflated)
+}
+
+/// `text` as a PDF string literal, UTF-16 when it isn't plain ASCII
+fn pdf_string(text: &str) -> String {
+  if text.is_ascii() {
+    let escaped = text
+      .replace('\\', "\\\\")
+      .replace('(', "\\(")
+      .replace(')', "\\)");
+    return format!("({})", escaped);
+  }
+  let hex: String = text
+    .encode_utf16()
+    .map(|unit| format!("{:04X}", unit))
+    .collect();
+  format!("<FEFF{}>", hex)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use image::Rgba;
+
+  #[test]
+  fn test_writes_pdf_with_cross_references() {
+    let mut image = RgbaImage::from_pixel(4, 2, Rgba([40, 42, 54, 255]));
+    image.put_pixel(0, 0, Rgba([0, 0, 0, 0]));
+    let pdf = pdf_bytes(&image, 0.375, Some("main.rs (draft)")).unwrap();
+    let text = String::from_utf8_lossy(&pdf);
+    assert!(text.starts_with("%PDF-1.4"));
+    assert!(text.ends_with("%%EOF\n"));
+    assert!(text.contains("/MediaBox [0 0 1.50 0.75]"));
+    assert!(text.contains("/SMask 6 0 R"));
+    assert!(text.contains("/Title (main.rs \\(draft\\))"));
+
+    // Every cross-reference entry points at its object
+    let tail = String::from_utf8_lossy(&pdf[pdf.len() - 32..]).into_owned();
+    let startxref: usize = tail
+      .rsplit("startxref\n")
+      .next()
+      .and_then(|tail| tail.lines().next())
+      .unwrap()
+      .parse()
+      .unwrap();
+    let table = std::str::from_utf8(&pdf[startxref..]).unwrap();
+    assert!(table.starts_with("xref\n0 8\n"));
+    for (index, entry) in table.lines().skip(3).take(7).enumerate() {
+      let offset: usize = entry[..10].parse().unwrap();
+      assert!(pdf[offset..].starts_with(format!("{} 0 obj", index + 1).as_bytes()));
+    }
+
+    assert_eq!(pdf_string("café"), "<FEFF00630061006600E9>");
+  }
+}
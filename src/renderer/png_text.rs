@@ -0,0 +1,92 @@
+/* ~~/src/renderer/png_text.rs */
+
+// third-party crates
+use png::text_metadata::{ITXtChunk, TEXtChunk, ZTXtChunk};
+
+/// Keyword the original source is stored under
+pub(crate) const SOURCE_KEYWORD: &str = "shoyu:source";
+
+/// Text chunk written into an encoded PNG so the image describes itself
+#[derive(Debug, Clone)]
+pub(crate) struct PngText {
+  pub keyword: &'static str,
+  pub text: String,
+  /// Deflate the text, worth it for the source but not for short fields
+  pub compressed: bool,
+}
+
+impl PngText {
+  pub fn new(keyword: &'static str, text: impl Into<String>) -> Self {
+    Self {
+      keyword,
+      text: text.into(),
+      compressed: false,
+    }
+  }
+
+  pub fn compressed(keyword: &'static str, text: impl Into<String>) -> Self {
+    Self {
+      compressed: true,
+      ..Self::new(keyword, text)
+    }
+  }
+}
+
+/// Queue `texts` on `info`: Latin-1 text goes in `tEXt` or `zTXt`, which
+/// every reader understands, and anything else in UTF-8 `iTXt`
+pub(super) fn add_text_chunks(info: &mut png::Info, texts: &[PngText]) {
+  for PngText {
+    keyword,
+    text,
+    compressed,
+  } in texts
+  {
+    let latin1 = text.chars().all(|ch| (ch as u32) < 0x100);
+    match (latin1, compressed) {
+      (true, false) => info
+        .uncompressed_latin1_text
+        .push(TEXtChunk::new(*keyword, text.as_str())),
+      (true, true) => info
+        .compressed_latin1_text
+        .push(ZTXtChunk::new(*keyword, text.as_str())),
+      (false, _) => {
+        let mut chunk = ITXtChunk::new(*keyword, text.as_str());
+        chunk.compressed = *compressed;
+        info.utf8_text.push(chunk);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::ColorSpace;
+  use crate::renderer::SnippetRenderer;
+  use image::{Rgba, RgbaImage};
+
+  #[test]
+  fn test_text_survives_optimization() {
+    let image = RgbaImage::from_pixel(8, 8, Rgba([40, 42, 54, 255]));
+    let source = "fn main() {\n\tprintln!(\"→ 醤油\");\n}\n";
+    let texts = [
+      PngText::new("shoyu:theme", "dracula"),
+      PngText::compressed(SOURCE_KEYWORD, "fn main() {}"),
+      PngText::compressed("shoyu:unicode", source),
+    ];
+    let png_data = SnippetRenderer::png_bytes(&image, ColorSpace::Srgb, &texts).unwrap();
+    let png_data = SnippetRenderer::optimize_png_bytes(png_data);
+
+    let mut reader = png::Decoder::new(png_data.as_slice()).read_info().unwrap();
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut buffer).unwrap();
+    let info = reader.info();
+    assert_eq!(info.uncompressed_latin1_text[0].keyword, "shoyu:theme");
+    assert_eq!(info.uncompressed_latin1_text[0].text, "dracula");
+    assert_eq!(
+      info.compressed_latin1_text[0].get_text().unwrap(),
+      "fn main() {}"
+    );
+    assert_eq!(info.utf8_text[0].get_text().unwrap(), source);
+  }
+}
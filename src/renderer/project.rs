@@ -0,0 +1,120 @@
+/* ~~/src/renderer/project.rs */
+
+// standard crates
+use std::path::Path;
+
+// third-party crates
+use anyhow::Result;
+use image::{Rgba, RgbaImage};
+
+// local modules
+use crate::renderer::SnippetRenderer;
+use crate::renderer::color::blend_colors;
+use crate::syntax::TokenStyle;
+
+/// `title` relative to `root` with `/` separators when it is a path inside
+/// the root, otherwise unchanged
+pub(super) fn relative_title(title: &str, root: &str) -> String {
+  match Path::new(title).strip_prefix(root) {
+    Ok(relative) if !relative.as_os_str().is_empty() => relative
+      .iter()
+      .map(|part| part.to_string_lossy())
+      .collect::<Vec<_>>()
+      .join("/"),
+    _ => title.to_string(),
+  }
+}
+
+impl SnippetRenderer {
+  /// Text of the project badge: the icon and the configured name, or the
+  /// root's folder name, `None` without either
+  fn project_label(&self) -> Option<String> {
+    let name = self.config.project_name.clone().or_else(|| {
+      let root = self.config.project_root.as_deref()?;
+      Some(Path::new(root).file_name()?.to_string_lossy().into_owned())
+    })?;
+    Some(match &self.config.project_icon {
+      Some(icon) => format!("{} {}", icon, name),
+      None => name,
+    })
+  }
+
+  /// Draw the project badge with its right edge at `right` on the title's
+  /// baseline, tinted from the title text over the title bar
+  pub(super) fn draw_project_badge(
+    &self,
+    image: &mut RgbaImage,
+    right: u32,
+    baseline: u32,
+    title_bar: Rgba<u8>,
+    title_text: Rgba<u8>,
+  ) -> Result<()> {
+    let Some(label) = self.project_label() else {
+      return Ok(());
+    };
+    let padding = self.badge_padding();
+    let label_width = self
+      .font_manager
+      .measure_text(&label, TokenStyle::BOLD, None);
+    let badge_width = label_width + padding * 2;
+    let x = right.saturating_sub(badge_width);
+    let (ascent, descent) = self.font_manager.get_vertical_metrics();
+    self.draw_rounded_rect(
+      image,
+      x as i32,
+      baseline as i32 - ascent.ceil() as i32 - padding as i32 / 2,
+      badge_width,
+      (ascent - descent).ceil() as u32 + padding,
+      4.0,
+      blend_colors(title_bar, title_text, 0.2),
+    )?;
+    self.draw_text(
+      image,
+      &label,
+      x + padding,
+      baseline,
+      self.config.get_scaled_font_size(),
+      title_text,
+      TokenStyle::BOLD,
+      None,
+    )?;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::RenderConfig;
+
+  #[test]
+  fn test_titles_relative_to_project_root() {
+    assert_eq!(
+      relative_title("/home/me/shoyu/src/lib.rs", "/home/me/shoyu"),
+      "src/lib.rs"
+    );
+    assert_eq!(
+      relative_title("/home/me/shoyu/src/lib.rs", "/home/me/shoyu/"),
+      "src/lib.rs"
+    );
+    // Outside the root, partial folder names, and plain titles stay as given
+    assert_eq!(
+      relative_title("/home/me/shoyu-extra/a.rs", "/home/me/shoyu"),
+      "/home/me/shoyu-extra/a.rs"
+    );
+    assert_eq!(relative_title("main()", "/home/me/shoyu"), "main()");
+
+    let renderer = SnippetRenderer::new(
+      "dracula",
+      RenderConfig {
+        window_title: Some("/work/api/src/main.rs".to_string()),
+        project_root: Some("/work/api".to_string()),
+        project_icon: Some("◆".to_string()),
+        ..RenderConfig::default()
+      },
+    )
+    .unwrap();
+    assert_eq!(renderer.config.window_title.as_deref(), Some("src/main.rs"));
+    assert_eq!(renderer.project_label().as_deref(), Some("◆ api"));
+  }
+}
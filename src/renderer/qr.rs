@@ -0,0 +1,103 @@
+/* ~~/src/renderer/qr.rs */
+
+// third-party crates
+use anyhow::{Result, anyhow};
+use image::{Rgba, RgbaImage};
+use qrcodegen::{QrCode, QrCodeEcc};
+
+// local modules
+use crate::config::Corner;
+use crate::renderer::{PanelRect, SnippetRenderer};
+use crate::warnings;
+
+/// Largest side of the code, before export scaling
+const QR_SIZE: f32 = 96.0;
+/// Light modules around the code, the four the spec asks for
+const QUIET_ZONE: i32 = 4;
+/// Smallest module that still scans once printed
+const MIN_MODULE: u32 = 2;
+
+/// `link` as a QR code, with medium error correction so a downscaled or
+/// slightly blurred print still scans
+pub(crate) fn encode_link(link: &str) -> Result<QrCode> {
+  QrCode::encode_text(link, QrCodeEcc::Medium)
+    .map_err(|e| anyhow!("Cannot fit qr_link in a QR code: {}", e))
+}
+
+impl SnippetRenderer {
+  /// Draw `qr_link` as a QR code on a white tile in its corner of the
+  /// backdrop, sized to the backdrop margin there and skipped with a warning
+  /// when the margin is too thin to scan
+  pub(super) fn draw_qr_code(&self, image: &mut RgbaImage, panel: PanelRect) -> Result<()> {
+    let Some(link) = &self.config.qr_link else {
+      return Ok(());
+    };
+    let code = encode_link(link)?;
+    let modules = (code.size() + QUIET_ZONE * 2) as u32;
+    let top = matches!(self.config.qr_corner, Corner::TopLeft | Corner::TopRight);
+    let left = matches!(self.config.qr_corner, Corner::TopLeft | Corner::BottomLeft);
+    let margin = match top {
+      true => panel.y,
+      false => image.height().saturating_sub(panel.y + panel.height),
+    };
+    let largest = (QR_SIZE * self.config.export_size) as u32;
+    let module = largest.min(margin * 3 / 4) / modules;
+    if module < MIN_MODULE {
+      warnings::push(format!(
+        "QR code doesn't fit in panel_padding; it needs {} px of backdrop",
+        modules * MIN_MODULE * 4 / 3 + 1
+      ));
+      return Ok(());
+    }
+    let side = module * modules;
+    let inset = (margin - side) / 2;
+    let x = match left {
+      true => inset,
+      false => image.width().saturating_sub(inset + side),
+    };
+    let y = match top {
+      true => inset,
+      false => image.height().saturating_sub(inset + side),
+    };
+    self.draw_rounded_rect(
+      image,
+      x as i32,
+      y as i32,
+      side,
+      side,
+      module as f32 / self.config.export_size,
+      Rgba([255, 255, 255, 255]),
+    )?;
+    for row in 0..code.size() {
+      for column in 0..code.size() {
+        if code.get_module(column, row) {
+          self.fill_rect(
+            image,
+            (x + (column + QUIET_ZONE) as u32 * module) as i32,
+            (y + (row + QUIET_ZONE) as u32 * module) as i32,
+            module,
+            module,
+            Rgba([0, 0, 0, 255]),
+          );
+        }
+      }
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::RenderConfig;
+
+  #[test]
+  fn test_rejects_links_too_long_to_encode() {
+    assert_eq!(encode_link("https://example.com").unwrap().size(), 25);
+    let config = RenderConfig {
+      qr_link: Some(format!("https://example.com/{}", "x".repeat(4000))),
+      ..RenderConfig::default()
+    };
+    assert!(SnippetRenderer::new("nord", config).is_err());
+  }
+}
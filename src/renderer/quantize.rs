@@ -0,0 +1,96 @@
+/* ~~/src/renderer/quantize.rs */
+
+// third-party crates
+use anyhow::{Result, anyhow};
+use color_quant::NeuQuant;
+use image::RgbaImage;
+use image::imageops::{self, ColorMap};
+
+// local modules
+use crate::config::ColorSpace;
+use crate::renderer::color_space::png_encoder;
+use crate::renderer::png_text::PngText;
+
+/// Pixels NeuQuant skips between samples while learning the palette; 1 looks
+/// at every pixel, 10 is its recommended balance of speed and quality
+const SAMPLE_FACTOR: i32 = 10;
+
+/// Encode `image` as an indexed PNG of at most `colors` colors, diffusing the
+/// rounding error into neighboring pixels when `dither` is set
+pub(super) fn indexed_png_bytes(
+  image: &RgbaImage,
+  colors: u32,
+  dither: bool,
+  color_space: ColorSpace,
+  texts: &[PngText],
+) -> Result<Vec<u8>> {
+  let colors = colors.clamp(2, 256) as usize;
+  let quantizer = NeuQuant::new(SAMPLE_FACTOR, colors, image.as_raw());
+
+  let mut mapped = image.clone();
+  if dither {
+    imageops::dither(&mut mapped, &quantizer);
+  }
+  let indices: Vec<u8> = mapped
+    .pixels()
+    .map(|pixel| ColorMap::index_of(&quantizer, pixel) as u8)
+    .collect();
+
+  // PLTE holds the colors and tRNS their alpha, entry for entry
+  let palette = quantizer.color_map_rgba();
+  let rgb: Vec<u8> = palette
+    .chunks_exact(4)
+    .flat_map(|color| [color[0], color[1], color[2]])
+    .collect();
+  let alpha: Vec<u8> = palette.chunks_exact(4).map(|color| color[3]).collect();
+
+  let mut png_data = Vec::new();
+  let mut encoder = png_encoder(
+    &mut png_data,
+    image.width(),
+    image.height(),
+    color_space,
+    texts,
+  )?;
+  encoder.set_color(png::ColorType::Indexed);
+  encoder.set_depth(png::BitDepth::Eight);
+  encoder.set_palette(rgb);
+  if alpha.iter().any(|&alpha| alpha < 255) {
+    encoder.set_trns(alpha);
+  }
+  let mut writer = encoder
+    .write_header()
+    .map_err(|e| anyhow!("Failed to write indexed PNG: {}", e))?;
+  writer
+    .write_image_data(&indices)
+    .map_err(|e| anyhow!("Failed to write indexed PNG: {}", e))?;
+  writer
+    .finish()
+    .map_err(|e| anyhow!("Failed to write indexed PNG: {}", e))?;
+  Ok(png_data)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use image::Rgba;
+
+  #[test]
+  fn test_indexed_png_round_trips_flat_colors() {
+    let mut image = RgbaImage::from_pixel(16, 16, Rgba([40, 42, 54, 255]));
+    for x in 0..8 {
+      image.put_pixel(x, 0, Rgba([255, 121, 198, 255]));
+    }
+    image.put_pixel(15, 15, Rgba([0, 0, 0, 0]));
+
+    let png_data = indexed_png_bytes(&image, 16, true, ColorSpace::Srgb, &[]).unwrap();
+    let decoder = png::Decoder::new(png_data.as_slice());
+    let reader = decoder.read_info().unwrap();
+    assert_eq!(reader.info().color_type, png::ColorType::Indexed);
+
+    let decoded = image::load_from_memory(&png_data).unwrap().to_rgba8();
+    assert_eq!(decoded.dimensions(), (16, 16));
+    assert_eq!(decoded.get_pixel(15, 15)[3], 0);
+    assert_eq!(decoded.get_pixel(12, 8), &Rgba([40, 42, 54, 255]));
+  }
+}
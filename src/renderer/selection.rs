@@ -0,0 +1,98 @@
+/* ~~/src/renderer/selection.rs */
+
+// third-party crates
+use anyhow::Result;
+use image::{Rgba, RgbaImage};
+
+// local modules
+use crate::renderer::color::{blend_colors, rgba_from_hex};
+use crate::renderer::{CodeLayout, SnippetRenderer};
+use crate::syntax::{HighlightedLine, TokenStyle};
+
+impl SnippetRenderer {
+  pub(super) fn selection_color(&self) -> Result<Rgba<u8>> {
+    match &self.config.selection_color {
+      Some(hex) => rgba_from_hex(hex),
+      None => Ok(blend_colors(
+        self.panel_background()?,
+        rgba_from_hex(&self.theme.foreground.hex)?,
+        0.25,
+      )),
+    }
+  }
+
+  /// Paint the selected columns of a line behind its text, which starts at
+  /// `code_x` with its baseline at `y`
+  pub(super) fn draw_selection(
+    &self,
+    image: &mut RgbaImage,
+    layout: &CodeLayout,
+    line_index: usize,
+    line: &HighlightedLine,
+    code_x: u32,
+    y: u32,
+  ) {
+    let Some(selection) = &self.config.selection else {
+      return;
+    };
+    let width = line.tokens.last().map_or(0, |token| token.end_col.display);
+    let Some((from, to)) = selection.columns(line_index as u32 + 1, width) else {
+      return;
+    };
+    let to = to.min(layout.visible_columns);
+    if from >= to {
+      return;
+    }
+    let offset = |columns: usize| {
+      self
+        .font_manager
+        .measure_text(&" ".repeat(columns), TokenStyle::REGULAR, None)
+    };
+    let (left, right) = (offset(from), offset(to));
+    self.fill_rect(
+      image,
+      (code_x + left) as i32,
+      y as i32 - layout.strip_offset,
+      right - left,
+      layout.line_height,
+      layout.selection_color,
+    );
+  }
+
+  /// Paint the backgrounds terminal output set behind its text, over any
+  /// selection so reversed text stays legible
+  pub(super) fn draw_token_backgrounds(
+    &self,
+    image: &mut RgbaImage,
+    layout: &CodeLayout,
+    line_index: usize,
+    line: &HighlightedLine,
+    code_x: u32,
+    y: u32,
+  ) -> Result<()> {
+    let offset = |columns: usize| {
+      self
+        .font_manager
+        .measure_text(&" ".repeat(columns), TokenStyle::REGULAR, None)
+    };
+    for token in &line.tokens {
+      let Some(background) = &token.background else {
+        continue;
+      };
+      let to = token.end_col.display.min(layout.visible_columns);
+      if token.start_col.display >= to {
+        continue;
+      }
+      let (left, right) = (offset(token.start_col.display), offset(to));
+      self.fill_rect(
+        image,
+        (code_x + left) as i32,
+        y as i32 - layout.strip_offset,
+        right - left,
+        layout.line_height,
+        self.focus_color(line_index, rgba_from_hex(&background.hex)?),
+      );
+    }
+    Ok(())
+  }
+}
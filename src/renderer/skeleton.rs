@@ -0,0 +1,51 @@
+/* ~~/src/renderer/skeleton.rs */
+
+// third-party crates
+use anyhow::Result;
+use image::RgbaImage;
+
+// local modules
+use crate::renderer::SnippetRenderer;
+use crate::renderer::color::{blend_colors, rgba_from_hex};
+use crate::syntax::TokenStyle;
+
+impl SnippetRenderer {
+  /// Stand in for `draw_text` in skeleton mode: cover each word with a grey
+  /// bar and advance the pen by the same width the glyphs would take
+  pub(super) fn draw_skeleton_text(
+    &self,
+    image: &mut RgbaImage,
+    text: &str,
+    x: u32,
+    y: u32,
+    style: TokenStyle,
+    face: Option<&str>,
+  ) -> Result<u32> {
+    // Bars sit on the baseline and stop short of the ascent, roughly x-height tall
+    let (ascent, _) = self.font_manager.get_vertical_metrics();
+    let bar_top = y as i32 - (ascent * 0.75) as i32;
+    let bar_height = (ascent * 0.65).max(1.0) as u32;
+    let color = blend_colors(
+      self.panel_background()?,
+      rgba_from_hex(&self.theme.foreground.hex)?,
+      0.18,
+    );
+
+    let mut word_start = None;
+    for (index, ch) in text.char_indices().chain([(text.len(), ' ')]) {
+      match (word_start, ch.is_whitespace()) {
+        (None, false) => word_start = Some(index),
+        (Some(start), true) => {
+          let bar_x = x + self.font_manager.measure_text(&text[..start], style, face);
+          let bar_width = self
+            .font_manager
+            .measure_text(&text[start..index], style, face);
+          self.fill_rect(image, bar_x as i32, bar_top, bar_width, bar_height, color);
+          word_start = None;
+        }
+        _ => {}
+      }
+    }
+    Ok(self.font_manager.measure_text(text, style, face))
+  }
+}
@@ -0,0 +1,199 @@
+/* ~~/src/renderer/strips.rs */
+
+// standard crates
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// third-party crates
+use anyhow::Result;
+use image::RgbaImage;
+
+// local modules
+use crate::renderer::project::relative_title;
+use crate::renderer::{PanelRect, SnippetRenderer};
+use crate::syntax::TokenStyle;
+
+/// Height of a header or footer strip, before export scaling
+const STRIP_HEIGHT: u32 = 32;
+
+impl SnippetRenderer {
+  /// Unscaled height the header and footer strips add to the panel
+  pub(crate) fn unscaled_strips_height(&self) -> u32 {
+    let count = self.config.header.is_some() as u32 + self.config.footer.is_some() as u32;
+    STRIP_HEIGHT * count
+  }
+
+  fn strip_height(&self, template: &Option<String>) -> u32 {
+    match template {
+      Some(_) => (STRIP_HEIGHT as f32 * self.config.export_size) as u32,
+      None => 0,
+    }
+  }
+
+  /// Height above the code taken by the title bar and header strip
+  pub(crate) fn top_chrome_height(&self) -> u32 {
+    self.title_bar_height() + self.strip_height(&self.config.header)
+  }
+
+  /// Height below the code taken by the footer strip
+  pub(crate) fn bottom_chrome_height(&self) -> u32 {
+    self.strip_height(&self.config.footer)
+  }
+
+  /// `template` with `{filename}`, `{path}`, `{date}`, and `{branch}` filled
+  /// in, each empty when unknown
+  fn expand_strip(&self, template: &str) -> String {
+    let path = self.config.source_path.as_deref().unwrap_or_default();
+    let shown_path = match &self.config.project_root {
+      Some(root) => relative_title(path, root),
+      None => path.to_string(),
+    };
+    let filename = Path::new(path)
+      .file_name()
+      .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+    let mut text = template
+      .replace("{filename}", &filename)
+      .replace("{path}", &shown_path);
+    if text.contains("{date}") {
+      text = text.replace("{date}", &today());
+    }
+    if text.contains("{branch}") {
+      let start = match path {
+        "" => self.config.project_root.as_deref().map(PathBuf::from),
+        path => Path::new(path).parent().map(Path::to_path_buf),
+      };
+      let branch = start.and_then(|start| git_branch(&start));
+      text = text.replace("{branch}", branch.as_deref().unwrap_or_default());
+    }
+    text
+  }
+
+  /// Header strip below the title bar and footer strip along the panel's
+  /// bottom edge, colored like the title bar
+  pub(super) fn draw_strips(&self, image: &mut RgbaImage, panel: PanelRect) -> Result<()> {
+    let chrome = self.chrome()?;
+    let strips = [
+      (&self.config.header, panel.y + self.title_bar_height()),
+      (
+        &self.config.footer,
+        (panel.y + panel.height).saturating_sub(self.bottom_chrome_height()),
+      ),
+    ];
+    let (ascent, descent) = self.font_manager.get_vertical_metrics();
+    for (strip, top) in strips {
+      let Some(template) = strip else {
+        continue;
+      };
+      let height = self.strip_height(strip);
+      self.fill_rect_in_panel(
+        image,
+        panel,
+        panel.x,
+        top,
+        panel.width,
+        height,
+        self.glass(chrome.title_bar),
+      );
+      let baseline = top as f32 + height as f32 / 2.0 + (ascent + descent) / 2.0;
+      self.draw_text(
+        image,
+        &self.expand_strip(template),
+        panel.x + self.config.get_scaled_padding().left / 2,
+        baseline.max(0.0) as u32,
+        self.config.get_scaled_font_size(),
+        chrome.title_text,
+        TokenStyle::REGULAR,
+        None,
+      )?;
+    }
+    Ok(())
+  }
+}
+
+/// Today's date in UTC as `YYYY-MM-DD`
+fn today() -> String {
+  let seconds = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map_or(0, |elapsed| elapsed.as_secs());
+  let (year, month, day) = civil_date((seconds / 86_400) as i64);
+  format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Calendar date of a day counted from 1970-01-01, after Howard Hinnant's
+/// `civil_from_days`
+fn civil_date(days: i64) -> (i64, u32, u32) {
+  let days = days + 719_468;
+  let era = days.div_euclid(146_097);
+  let day_of_era = days.rem_euclid(146_097);
+  let year_of_era =
+    (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let shifted_month = (5 * day_of_year + 2) / 153;
+  let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+  let month = if shifted_month < 10 {
+    shifted_month + 3
+  } else {
+    shifted_month - 9
+  } as u32;
+  let year = year_of_era + era * 400 + (month <= 2) as i64;
+  (year, month, day)
+}
+
+/// Branch checked out in the repository around `start`, or the short commit
+/// hash when detached; `None` outside a repository
+fn git_branch(start: &Path) -> Option<String> {
+  let dot_git = start
+    .ancestors()
+    .map(|dir| dir.join(".git"))
+    .find(|path| path.exists())?;
+  // Worktrees and submodules have a file pointing at the real git dir
+  let git_dir = match dot_git.is_file() {
+    true => {
+      let pointer = fs::read_to_string(&dot_git).ok()?;
+      let target = PathBuf::from(pointer.strip_prefix("gitdir:")?.trim());
+      dot_git.parent()?.join(target)
+    }
+    false => dot_git,
+  };
+  let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+  let head = head.trim();
+  match head.strip_prefix("ref: ") {
+    Some(reference) => Some(
+      reference
+        .strip_prefix("refs/heads/")
+        .unwrap_or(reference)
+        .to_string(),
+    ),
+    None => Some(head.chars().take(7).collect()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::RenderConfig;
+
+  #[test]
+  fn test_expands_strip_placeholders() {
+    assert_eq!(civil_date(0), (1970, 1, 1));
+    assert_eq!(civil_date(19_782), (2024, 2, 29));
+
+    let dir = std::env::temp_dir().join(format!("shoyu-strips-{}", std::process::id()));
+    fs::create_dir_all(dir.join(".git")).unwrap();
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(dir.join(".git/HEAD"), "ref: refs/heads/fix/overflow\n").unwrap();
+    let config = RenderConfig {
+      source_path: Some(dir.join("src/lib.rs").to_string_lossy().into_owned()),
+      project_root: Some(dir.to_string_lossy().into_owned()),
+      ..RenderConfig::default()
+    };
+    let renderer = SnippetRenderer::new("dracula", config).unwrap();
+    assert_eq!(
+      renderer.expand_strip("{path} ({filename}) on {branch}"),
+      "src/lib.rs (lib.rs) on fix/overflow"
+    );
+    assert_eq!(renderer.expand_strip("{date}").len(), 10);
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
@@ -0,0 +1,107 @@
+/* ~~/src/renderer/substitution.rs */
+
+// local modules
+use crate::config::GlyphSubstitution;
+use crate::renderer::SnippetRenderer;
+use crate::syntax::HighlightedLine;
+
+/// ASCII stand-in for a typographic character
+fn ascii_substitute(ch: char) -> Option<&'static str> {
+  Some(match ch {
+    '→' | '⟶' => "->",
+    '←' | '⟵' => "<-",
+    '↔' => "<->",
+    '⇒' | '⟹' => "=>",
+    '⇐' => "<=",
+    '⇔' => "<=>",
+    '≠' => "!=",
+    '≤' => "<=",
+    '≥' => ">=",
+    '≡' => "===",
+    '≈' => "~=",
+    '×' => "x",
+    '÷' => "/",
+    '−' | '‐' | '‑' | '‒' | '–' | '—' | '―' => "-",
+    '‘' | '’' | '‚' | '‛' | '′' => "'",
+    '“' | '”' | '„' | '‟' | '″' => "\"",
+    '«' => "<<",
+    '»' => ">>",
+    '‹' => "<",
+    '›' => ">",
+    '…' => "...",
+    '•' | '‣' | '∙' => "*",
+    '·' => ".",
+    '©' => "(c)",
+    '®' => "(r)",
+    '™' => "(tm)",
+    '\u{a0}' | '\u{2000}'..='\u{200a}' | '\u{202f}' | '\u{205f}' | '\u{3000}' => " ",
+    '\u{200b}' | '\u{200c}' | '\u{200d}' | '\u{2060}' | '\u{feff}' => "",
+    _ => return None,
+  })
+}
+
+impl SnippetRenderer {
+  /// Replace characters no loaded font can draw according to
+  /// `glyph_substitution`, so they don't render as empty boxes. Byte offsets
+  /// keep pointing into the source; display columns are recounted later.
+  pub(crate) fn substitute_glyphs(&self, lines: &mut [HighlightedLine]) {
+    let custom = match self.config.glyph_substitution {
+      GlyphSubstitution::Off => return,
+      GlyphSubstitution::Ascii => None,
+      GlyphSubstitution::Custom => Some(&self.config.glyph_substitutions),
+    };
+    let substitute = |ch: char| {
+      custom
+        .and_then(|table| table.get(&ch).map(String::as_str))
+        .or_else(|| ascii_substitute(ch))
+    };
+    for token in lines.iter_mut().flat_map(|line| &mut line.tokens) {
+      let missing = |ch: char| substitute(ch).is_some() && !self.font_manager.has_glyph(ch);
+      if !token.text.chars().any(missing) {
+        continue;
+      }
+      token.text = token
+        .text
+        .chars()
+        .map(|ch| match missing(ch) {
+          true => substitute(ch).unwrap_or_default().to_string(),
+          false => ch.to_string(),
+        })
+        .collect();
+    }
+  }
+}
+
+// Coverage is checked against the embedded font alone, not the host's fonts
+#[cfg(all(test, feature = "embedded-font"))]
+mod tests {
+  use super::*;
+  use crate::config::RenderConfig;
+  use crate::font::create_fallback_font;
+
+  /// Text of the first line after highlighting, which applies substitutions
+  fn first_line(renderer: &SnippetRenderer, code: &str) -> String {
+    let lines = renderer.highlight(code, "text");
+    lines[0]
+      .tokens
+      .iter()
+      .map(|token| token.text.as_str())
+      .collect()
+  }
+
+  #[test]
+  fn test_substitutes_only_missing_glyphs() {
+    let mut renderer = SnippetRenderer::new("dracula", RenderConfig::default()).unwrap();
+    // Fira Mono alone, without installed fallbacks, has → but not ⇒ or U+3000
+    renderer.font_manager = create_fallback_font(16.0, &[]).unwrap();
+    let text = first_line(&renderer, "a\u{3000}→ b ⇒ ⇛\n");
+    assert_eq!(text, "a → b => ⇛");
+
+    renderer.config.glyph_substitution = GlyphSubstitution::Custom;
+    renderer.config.glyph_substitutions = [('⇒', "~>".to_string())].into();
+    assert_eq!(first_line(&renderer, "⇒\n"), "~>");
+
+    renderer.config.glyph_substitution = GlyphSubstitution::Off;
+    assert_eq!(first_line(&renderer, "⇒\n"), "⇒");
+  }
+}
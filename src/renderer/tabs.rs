@@ -0,0 +1,218 @@
+/* ~~/src/renderer/tabs.rs */
+
+// third-party crates
+use anyhow::{Result, anyhow};
+use image::{ImageBuffer, RgbaImage, imageops};
+
+// local modules
+use crate::config::{PanelSize, RenderLayer, TabFile, TabLayout, WindowStyle};
+use crate::limits::RenderGuard;
+use crate::renderer::chrome::Chrome;
+use crate::renderer::color::blend_colors;
+use crate::renderer::{PanelRect, SnippetRenderer};
+use crate::syntax::{HighlightedLine, TokenStyle};
+
+/// Space between stacked panels, before export scaling
+const STACK_GAP: f32 = 24.0;
+
+impl SnippetRenderer {
+  /// Render `files` as browser-style tabs: every tab in the title bar over
+  /// the active file's code, or with the "stacked" layout one panel per file
+  /// on a shared backdrop. Returns a data URL.
+  pub fn render_tabs(&mut self, files: &[TabFile]) -> Result<String> {
+    if files.is_empty() {
+      return Err(anyhow!("No files to render as tabs"));
+    }
+    let result = match self.config.tabs.layout {
+      TabLayout::Active => self.render_active_tab(files),
+      TabLayout::Stacked => self.render_stacked_tabs(files),
+    };
+    self.tab_titles.clear();
+    result
+  }
+
+  fn highlight_file(&mut self, file: &TabFile) -> Vec<HighlightedLine> {
+    let language = match file.language.is_empty() {
+      true => &file.title,
+      false => &file.language,
+    };
+    let highlighted_lines = self.highlight_snippet(&file.code, language);
+    self.limit_lines(highlighted_lines)
+  }
+
+  fn render_active_tab(&mut self, files: &[TabFile]) -> Result<String> {
+    let guard = RenderGuard::acquire()?;
+    self.active_tab = self.config.tabs.active.min(files.len() - 1);
+    self.tab_titles = files.iter().map(|file| file.title.clone()).collect();
+    let highlighted_lines = self.highlight_file(&files[self.active_tab]);
+    self.render_highlighted(guard, &highlighted_lines)
+  }
+
+  fn render_stacked_tabs(&mut self, files: &[TabFile]) -> Result<String> {
+    let guard = RenderGuard::acquire()?;
+    let (saved_width, saved_layer, watermark) = (
+      self.config.width,
+      self.config.layer,
+      self.config.watermark.take(),
+    );
+    let result = self.draw_stacked_tabs(&guard, files);
+    (self.config.width, self.config.layer, self.config.watermark) =
+      (saved_width, saved_layer, watermark);
+    let (mut image, panels) = result?;
+    // One credit and QR code for the whole stack rather than one per panel
+    if let Some(&first) = panels.first() {
+      let last = panels[panels.len() - 1];
+      let bounds = PanelRect {
+        height: last.y + last.height - first.y,
+        ..first
+      };
+      self.draw_watermark(&mut image, bounds)?;
+      #[cfg(feature = "qr")]
+      self.draw_qr_code(&mut image, bounds)?;
+    }
+    let image = self.fit_canvas(image);
+    self.encode(guard, &image)
+  }
+
+  /// The stack on its backdrop, and where each panel landed
+  fn draw_stacked_tabs(
+    &mut self,
+    guard: &RenderGuard,
+    files: &[TabFile],
+  ) -> Result<(RgbaImage, Vec<PanelRect>)> {
+    self.active_tab = 0;
+    let mut highlighted = Vec::with_capacity(files.len());
+    for file in files {
+      self.tab_titles = vec![file.title.clone()];
+      highlighted.push(self.highlight_file(file));
+    }
+    // Panels share the widest one's width
+    if self.config.width == PanelSize::Auto {
+      let widest = highlighted
+        .iter()
+        .map(|lines| self.compute_metrics(lines).panel_width)
+        .max()
+        .unwrap_or(0);
+      let unscaled = (widest as f32 / self.config.export_size).ceil() as u32;
+      self.config.width = PanelSize::Fixed(unscaled);
+    }
+    // The canvas preset shapes the whole stack rather than each panel
+    let (layer, canvas) = (self.config.layer, self.config.canvas.take());
+    self.config.layer = RenderLayer::Panel;
+    let panels: Result<Vec<RgbaImage>> = files
+      .iter()
+      .zip(&highlighted)
+      .map(|(file, lines)| {
+        self.tab_titles = vec![file.title.clone()];
+        let metrics = self.compute_metrics(lines);
+        let image = self.rasterize(guard, lines)?;
+        Ok(
+          imageops::crop_imm(
+            &image,
+            metrics.panel_padding.left,
+            metrics.panel_padding.top,
+            metrics.panel_width,
+            metrics.panel_height,
+          )
+          .to_image(),
+        )
+      })
+      .collect();
+    (self.config.layer, self.config.canvas) = (layer, canvas);
+    let panels = panels?;
+
+    let gap = (STACK_GAP * self.config.export_size) as u32;
+    let width = panels.iter().map(|panel| panel.width()).max().unwrap_or(0);
+    let height =
+      panels.iter().map(|panel| panel.height()).sum::<u32>() + gap * (panels.len() as u32 - 1);
+    let padding = self.config.get_scaled_panel_padding();
+    let margin = self.canvas_margin(width + padding.horizontal(), height + padding.vertical());
+    let padding = padding + margin;
+    let (canvas_width, canvas_height) = (width + padding.horizontal(), height + padding.vertical());
+    guard.check_pixels(canvas_width, canvas_height)?;
+    let mut canvas = ImageBuffer::new(canvas_width, canvas_height);
+    if layer == RenderLayer::Full {
+      self.draw_backdrop(&mut canvas)?;
+    }
+    let mut rects = Vec::with_capacity(panels.len());
+    let mut y = padding.top;
+    for panel in &panels {
+      imageops::overlay(&mut canvas, panel, padding.left as i64, y as i64);
+      rects.push(PanelRect {
+        x: padding.left,
+        y,
+        width: panel.width(),
+        height: panel.height(),
+      });
+      y += panel.height() + gap;
+    }
+    Ok((canvas, rects))
+  }
+
+  /// Width the window controls take at the left end of the title bar
+  fn controls_left_width(&self) -> u32 {
+    let scale = self.config.export_size;
+    match (self.config.window_controls, self.config.window_style) {
+      (true, WindowStyle::Macos) => {
+        self.config.get_scaled_padding().left / 2 + (46.0 * scale) as u32
+      }
+      _ => 0,
+    }
+  }
+
+  /// Draw a tab per title along the title bar `bar`, stopping short of
+  /// `right_reserved` at its right end; the active tab takes the panel color
+  /// so it joins the code below
+  pub(super) fn draw_tabs(
+    &self,
+    image: &mut RgbaImage,
+    bar: PanelRect,
+    right_reserved: u32,
+    chrome: &Chrome,
+  ) -> Result<()> {
+    let scale = self.config.export_size;
+    let padding = self.config.get_scaled_padding();
+    let inset = (16.0 * scale) as u32;
+    let top = bar.y + (8.0 * scale) as u32;
+    let tab_height = (bar.y + bar.height).saturating_sub(top);
+    let (ascent, descent) = self.font_manager.get_vertical_metrics();
+    let baseline = (top as f32 + tab_height as f32 / 2.0 + (ascent + descent) / 2.0) as u32;
+    let right = (bar.x + bar.width).saturating_sub(right_reserved + padding.right / 2);
+    let panel_color = self.panel_background()?;
+    let dimmed = blend_colors(chrome.title_text, chrome.title_bar, 0.4);
+    let mut x = bar.x + padding.left / 2 + self.controls_left_width();
+    for (index, title) in self.tab_titles.iter().enumerate() {
+      let width = self
+        .font_manager
+        .measure_text(title, TokenStyle::REGULAR, None)
+        + inset * 2;
+      if x + width > right {
+        break;
+      }
+      let active = index == self.active_tab;
+      if active {
+        self.draw_rounded_rect_top_only(
+          image,
+          x as i32,
+          top as i32,
+          width,
+          tab_height,
+          (8.0 * scale).min(self.config.border_radius.max(4.0 * scale)),
+          panel_color,
+        )?;
+      }
+      self.draw_text(
+        image,
+        title,
+        x + inset,
+        baseline,
+        self.config.get_scaled_font_size(),
+        if active { chrome.title_text } else { dimmed },
+        TokenStyle::REGULAR,
+        None,
+      )?;
+      x += width;
+    }
+    Ok(())
+  }
+}
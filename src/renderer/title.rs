@@ -0,0 +1,140 @@
+/* ~~/src/renderer/title.rs */
+
+// local modules
+use crate::syntax::HighlightedLine;
+
+/// Innermost scopes that name a definition, and whether it is callable
+const DEFINITION_SCOPES: &[(&str, bool)] = &[
+  ("entity.name.function", true),
+  ("entity.name.class", false),
+  ("entity.name.struct", false),
+  ("entity.name.enum", false),
+  ("entity.name.trait", false),
+  ("entity.name.interface", false),
+  ("entity.name.type", false),
+];
+
+/// Keywords that introduce a named definition, and whether it is callable,
+/// for languages highlighted without a grammar
+const DEFINITION_KEYWORDS: &[(&str, bool)] = &[
+  ("fn", true),
+  ("def", true),
+  ("func", true),
+  ("function", true),
+  ("class", false),
+  ("struct", false),
+  ("enum", false),
+  ("trait", false),
+  ("interface", false),
+  ("module", false),
+];
+
+/// Name of the first function, class, or type the code defines, as `name()`
+/// for functions, from the highlighter's scopes or else from keywords
+/// outside comments and strings
+pub(super) fn infer_title(lines: &[HighlightedLine]) -> Option<String> {
+  let title = |name: &str, callable: bool| match callable {
+    true => format!("{}()", name),
+    false => name.to_string(),
+  };
+  let scoped = lines
+    .iter()
+    .flat_map(|line| &line.tokens)
+    .find_map(|token| {
+      let innermost = token.scope.rsplit(' ').next()?;
+      let name = token.text.trim();
+      DEFINITION_SCOPES
+        .iter()
+        .find(|(scope, _)| innermost.starts_with(scope))
+        .filter(|_| is_identifier(name))
+        .map(|(_, callable)| title(name, *callable))
+    });
+  scoped.or_else(|| {
+    lines.iter().find_map(|line| {
+      // Prose such as "// fn stub" or "class Foo" in a string names nothing
+      let text: String = line
+        .tokens
+        .iter()
+        .map(|token| match is_prose(&token.scope) {
+          true => " ",
+          false => token.text.as_str(),
+        })
+        .collect();
+      let words: Vec<&str> = text
+        .split(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+        .filter(|word| !word.is_empty())
+        .collect();
+      words.windows(2).find_map(|pair| {
+        let (_, callable) = DEFINITION_KEYWORDS
+          .iter()
+          .find(|(keyword, _)| *keyword == pair[0])?;
+        is_identifier(pair[1]).then(|| title(pair[1], *callable))
+      })
+    })
+  })
+}
+
+fn is_prose(scope: &str) -> bool {
+  scope
+    .split(' ')
+    .any(|part| part.starts_with("comment") || part.starts_with("string"))
+}
+
+fn is_identifier(word: &str) -> bool {
+  let mut chars = word.chars();
+  chars
+    .next()
+    .is_some_and(|ch| ch.is_alphabetic() || ch == '_')
+    && chars.all(|ch| ch.is_alphanumeric() || ch == '_')
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::RenderConfig;
+  use crate::renderer::SnippetRenderer;
+
+  #[test]
+  fn test_infers_first_definition() {
+    let renderer = SnippetRenderer::new("dracula", RenderConfig::default()).unwrap();
+    let title = |code, language| infer_title(&renderer.highlight(code, language));
+    assert_eq!(
+      title(
+        "use std::io;\n\nstruct Point { x: i32 }\nfn main() {}\n",
+        "rust"
+      ),
+      Some("Point".to_string())
+    );
+    assert_eq!(
+      title("import os\n\ndef greet(name):\n    pass\n", "python"),
+      Some("greet()".to_string())
+    );
+    // Keyword fallback when no grammar scopes the name
+    assert_eq!(
+      title("// notes\nfunction render(x)\n", "nolang"),
+      Some("render()".to_string())
+    );
+    assert_eq!(
+      title(
+        "// fn stub\nlet s = \"class Foo\";\nfunction render(x)\n",
+        "nolang"
+      ),
+      Some("render()".to_string())
+    );
+    assert_eq!(title("x = 1\n", "python"), None);
+  }
+
+  #[test]
+  fn test_inferred_title_lasts_one_render() {
+    let config = RenderConfig {
+      infer_title: true,
+      ..RenderConfig::default()
+    };
+    let mut renderer = SnippetRenderer::new("dracula", config).unwrap();
+    renderer.highlight_snippet("fn first() {}\n", "rust");
+    assert_eq!(renderer.window_title(), Some("first()"));
+    renderer.highlight_snippet("fn second() {}\n", "rust");
+    assert_eq!(renderer.window_title(), Some("second()"));
+    assert_eq!(renderer.config.window_title, None);
+  }
+}
@@ -0,0 +1,132 @@
+/* ~~/src/renderer/tokens.rs */
+
+// third-party crates
+use serde::Serialize;
+
+// local modules
+use crate::detect::resolve_language;
+use crate::renderer::SnippetRenderer;
+use crate::syntax::palette_rule;
+use crate::syntax::stream::StreamToken;
+
+/// Highlighted token stream of a snippet, for seeing why a token got its color
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenDump {
+  /// Language after detection, e.g. `rust` for `auto`
+  pub language: String,
+  /// `tree-sitter`, `syntect`, or `patterns`
+  pub highlighter: &'static str,
+  pub theme: String,
+  pub tokens: Vec<DumpedToken>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DumpedToken {
+  /// Line number, counting from 1
+  pub line: u32,
+  pub text: String,
+  pub scope: String,
+  /// Palette selector matching `scope`, or `None` for the theme foreground
+  pub rule: Option<&'static str>,
+  /// Resolved foreground as `#rrggbb`
+  pub color: String,
+  pub bold: bool,
+  pub italic: bool,
+  pub underline: bool,
+  /// Byte offsets into the source line, end exclusive
+  pub start_byte: usize,
+  pub end_byte: usize,
+  /// Display columns after tab expansion, end exclusive
+  pub start_column: usize,
+  pub end_column: usize,
+}
+
+impl SnippetRenderer {
+  /// Highlight `code` exactly as a render would and list every token
+  pub fn dump_tokens(&self, code: &str, language: &str) -> TokenDump {
+    let highlighted_lines = self.highlight(code, language);
+    let language = resolve_language(language, code).into_owned();
+    let tokens = highlighted_lines
+      .iter()
+      .zip(1..)
+      .flat_map(|(line, number)| {
+        line.tokens.iter().map(move |token| DumpedToken {
+          line: number,
+          text: token.text.clone(),
+          scope: token.scope.clone(),
+          rule: palette_rule(&self.theme, &token.scope),
+          color: {
+            let (r, g, b) = token.color.rgb;
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+          },
+          bold: token.style.is_bold(),
+          italic: token.style.is_italic(),
+          underline: token.style.is_underline(),
+          start_byte: token.start_col.byte,
+          end_byte: token.end_col.byte,
+          start_column: token.start_col.display,
+          end_column: token.end_col.display,
+        })
+      })
+      .collect();
+    TokenDump {
+      highlighter: self
+        .highlighter
+        .backend_name(&language, self.config.highlighter),
+      language,
+      theme: self.theme.name.clone(),
+      tokens,
+    }
+  }
+
+  /// Highlight `code` exactly as a render would, one token list per line in
+  /// the shape `generate_image_from_tokens` reads back
+  pub fn highlight_tokens(&self, code: &str, language: &str) -> Vec<Vec<StreamToken>> {
+    self
+      .highlight(code, language)
+      .iter()
+      .map(Vec::from)
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::RenderConfig;
+
+  #[test]
+  fn test_dump_explains_token_colors() {
+    let renderer = SnippetRenderer::new("dracula", RenderConfig::default()).unwrap();
+    let dump = renderer.dump_tokens("// hi\nlet x = 1;\n", "rust");
+    assert_eq!(dump.highlighter, "syntect");
+    let comment = &dump.tokens[0];
+    assert_eq!(comment.line, 1);
+    assert!(comment.scope.contains("comment"));
+    assert_eq!(
+      comment.rule,
+      Some("comment, punctuation.definition.comment")
+    );
+    let (r, g, b) = renderer.theme.comment.rgb;
+    assert_eq!(comment.color, format!("#{:02x}{:02x}{:02x}", r, g, b));
+    let number = dump.tokens.iter().find(|token| token.text == "1").unwrap();
+    assert_eq!((number.line, number.start_column), (2, 8));
+    assert_eq!(number.rule, Some("constant.numeric"));
+  }
+
+  #[test]
+  fn test_highlighted_tokens_render_back() {
+    let renderer = SnippetRenderer::new("nord", RenderConfig::default()).unwrap();
+    let lines = renderer.highlight_tokens("let x = 1;\n\nfn main() {}", "rust");
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].is_empty());
+    let keyword = &lines[0][0];
+    assert_eq!((keyword.text.as_str(), keyword.start_byte), ("let", 0));
+    assert!(keyword.group.contains("storage"));
+
+    let json = serde_json::to_string(&lines).unwrap();
+    let parsed = crate::syntax::lines_from_json(&json, renderer.theme()).unwrap();
+    assert_eq!(parsed[2].tokens[0].text, "fn");
+    assert_eq!(parsed[0].tokens[0].color.hex, keyword.fg);
+  }
+}
@@ -0,0 +1,78 @@
+/* ~~/src/renderer/truncation.rs */
+
+// local modules
+use crate::renderer::SnippetRenderer;
+use crate::syntax::{Column, HighlightedLine, HighlightedToken, TokenStyle};
+
+impl SnippetRenderer {
+  /// The first `max_lines` of `lines` followed by a row saying how many more
+  /// there are, and that count; unchanged with 0 when they fit
+  pub(super) fn truncate_lines(
+    &self,
+    mut lines: Vec<HighlightedLine>,
+  ) -> (Vec<HighlightedLine>, u32) {
+    let max_lines = self.config.max_lines as usize;
+    if max_lines == 0 || lines.len() <= max_lines {
+      return (lines, 0);
+    }
+    let hidden = lines.len() - max_lines;
+    lines.truncate(max_lines);
+    let text = match hidden {
+      1 => "… 1 more line".to_string(),
+      _ => format!("… {} more lines", hidden),
+    };
+    let end_col = Column {
+      byte: text.len(),
+      display: text.chars().count(),
+    };
+    lines.push(HighlightedLine {
+      tokens: vec![HighlightedToken {
+        text,
+        color: self.theme.comment.clone(),
+        background: None,
+        style: TokenStyle::ITALIC,
+        scope: "meta.truncated".to_string(),
+        start_col: Column::default(),
+        end_col,
+      }],
+    });
+    (lines, hidden as u32)
+  }
+
+  /// Apply `max_lines` to lines about to be drawn, remembering whether the
+  /// last row is the marker
+  pub(super) fn limit_lines(&mut self, lines: Vec<HighlightedLine>) -> Vec<HighlightedLine> {
+    let (lines, hidden) = self.truncate_lines(lines);
+    self.hidden_lines = hidden;
+    lines
+  }
+
+  /// Whether the 0-based row of `line_count` drawn is the marker standing in
+  /// for lines past `max_lines`
+  pub(super) fn is_marker_row(&self, line_index: usize, line_count: u32) -> bool {
+    self.hidden_lines > 0 && line_index + 1 == line_count as usize
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::config::RenderConfig;
+  use crate::renderer::SnippetRenderer;
+
+  #[test]
+  fn test_truncates_past_max_lines() {
+    let config = RenderConfig {
+      max_lines: 2,
+      ..RenderConfig::default()
+    };
+    let renderer = SnippetRenderer::new("dracula", config).unwrap();
+    let lines = renderer.highlight("a = 1\nb = 2\nc = 3\nd = 4\n", "python");
+    let (lines, hidden) = renderer.truncate_lines(lines);
+    assert_eq!(hidden, 2);
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[2].tokens[0].text, "… 2 more lines");
+
+    let short = renderer.highlight("a = 1\n", "python");
+    assert_eq!(renderer.truncate_lines(short).1, 0);
+  }
+}
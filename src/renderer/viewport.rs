@@ -0,0 +1,83 @@
+/* ~~/src/renderer/viewport.rs */
+
+// standard crates
+use std::borrow::Cow;
+
+// local modules
+use crate::renderer::SnippetRenderer;
+use crate::syntax::{HighlightedToken, display_width};
+
+impl SnippetRenderer {
+  /// Display columns that can land inside `width` pixels. Glyphs past this
+  /// are never rasterized, so a pathological line costs at most a panel's
+  /// worth of drawing however long it is.
+  pub(crate) fn visible_columns(&self, width: u32) -> usize {
+    (width / self.font_manager.narrowest_advance()) as usize + 1
+  }
+}
+
+/// Tokens of a line cut off at display column `columns`
+pub(super) fn clip_tokens(
+  tokens: &[HighlightedToken],
+  columns: usize,
+) -> Cow<'_, [HighlightedToken]> {
+  if tokens
+    .last()
+    .is_none_or(|token| token.end_col.display <= columns)
+  {
+    return Cow::Borrowed(tokens);
+  }
+  let mut clipped = Vec::new();
+  for token in tokens {
+    if token.start_col.display >= columns {
+      break;
+    }
+    if token.end_col.display <= columns {
+      clipped.push(token.clone());
+      continue;
+    }
+    let mut column = token.start_col.display;
+    let text: String = token
+      .text
+      .chars()
+      .take_while(|&ch| {
+        let fits = column < columns;
+        column += display_width(ch);
+        fits
+      })
+      .collect();
+    clipped.push(HighlightedToken {
+      end_col: token.start_col.advance(&text),
+      text,
+      ..token.clone()
+    });
+  }
+  Cow::Owned(clipped)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::syntax::SyntaxHighlighter;
+  use crate::themes::get_theme;
+
+  #[test]
+  fn test_clip_tokens_to_window() {
+    let theme = get_theme("dracula").unwrap();
+    let code = format!("let s = \"{}\";", "x".repeat(1 << 20));
+    let lines = SyntaxHighlighter::new(&[])
+      .unwrap()
+      .highlight_code(&code, "text", &theme);
+
+    let clipped = clip_tokens(&lines[0].tokens, 12);
+    let text: String = clipped.iter().map(|token| token.text.as_str()).collect();
+    assert_eq!(text, "let s = \"xxx");
+    assert_eq!(clipped.last().unwrap().end_col.display, 12);
+
+    // Lines inside the window are passed through untouched
+    assert!(matches!(
+      clip_tokens(&lines[0].tokens, usize::MAX),
+      Cow::Borrowed(_)
+    ));
+  }
+}
@@ -0,0 +1,164 @@
+/* ~~/src/renderer/watermark.rs */
+
+// third-party crates
+use anyhow::{Result, anyhow};
+use image::imageops::{self, FilterType};
+use image::{Rgba, RgbaImage};
+
+// local modules
+use crate::config::{RenderConfig, WatermarkPosition};
+use crate::renderer::color::{relative_luminance, rgba_from_hex};
+use crate::renderer::{PanelRect, SnippetRenderer};
+use crate::syntax::TokenStyle;
+use crate::warnings;
+
+/// Backdrop luminance at which black and white text contrast equally
+const LIGHT_BACKDROP: f32 = 0.18;
+
+impl SnippetRenderer {
+  /// Decode the watermark's logo once, so a missing file fails up front
+  pub(super) fn load_watermark_logo(config: &RenderConfig) -> Result<Option<RgbaImage>> {
+    let Some(path) = config
+      .watermark
+      .as_ref()
+      .and_then(|watermark| watermark.image.as_ref())
+    else {
+      return Ok(None);
+    };
+    let image =
+      image::open(path).map_err(|e| anyhow!("Failed to load watermark image '{}': {}", path, e))?;
+    Ok(Some(image.to_rgba8()))
+  }
+
+  /// Draw the watermark's logo and text in its corner of the backdrop, or
+  /// in the panel's bottom padding for `footer` and when the backdrop margin
+  /// is too thin to hold it
+  pub(super) fn draw_watermark(&self, image: &mut RgbaImage, panel: PanelRect) -> Result<()> {
+    let Some(watermark) = &self.config.watermark else {
+      return Ok(());
+    };
+    if watermark.text.is_empty() && self.watermark_logo.is_none() {
+      return Ok(());
+    }
+    let (ascent, descent) = self.font_manager.get_vertical_metrics();
+    let height = (ascent - descent).ceil() as u32;
+    let logo = self.watermark_logo.as_ref().map(|logo| {
+      let width = (logo.width() as f32 * height as f32 / logo.height() as f32).round() as u32;
+      imageops::resize(logo, width.max(1), height, FilterType::Lanczos3)
+    });
+    let logo_width = logo.as_ref().map_or(0, RgbaImage::width);
+    let text_width = match watermark.text.is_empty() {
+      true => 0,
+      false => self
+        .font_manager
+        .measure_text(&watermark.text, TokenStyle::REGULAR, None),
+    };
+    let gap = match logo_width > 0 && text_width > 0 {
+      true => self.badge_padding(),
+      false => 0,
+    };
+    let width = logo_width + gap + text_width;
+
+    // Backdrop margin above or below the panel, whichever the corner is in
+    let margin = match watermark.position {
+      WatermarkPosition::TopLeft | WatermarkPosition::TopRight => panel.y,
+      _ => image.height().saturating_sub(panel.y + panel.height),
+    };
+    let corner = watermark.position != WatermarkPosition::Footer;
+    if corner && margin < height {
+      warnings::push("Watermark doesn't fit in panel_padding; drawing it in the panel footer");
+    }
+    let (x, y, color) = match watermark.position {
+      position if corner && margin >= height => {
+        let inset = (margin - height) / 2;
+        let x = match position {
+          WatermarkPosition::TopLeft | WatermarkPosition::BottomLeft => inset,
+          _ => image.width().saturating_sub(inset + width),
+        };
+        let y = match position {
+          WatermarkPosition::TopLeft | WatermarkPosition::TopRight => inset,
+          _ => image.height().saturating_sub(inset + height),
+        };
+        // White text vanishes on pale gradients and wallpapers
+        let color = match backdrop_luminance(image, x, y, width, height) > LIGHT_BACKDROP {
+          true => Rgba([0, 0, 0, 255]),
+          false => Rgba([255, 255, 255, 255]),
+        };
+        (x, y, color)
+      }
+      _ => {
+        let padding = self.config.get_scaled_padding();
+        let x = (panel.x + panel.width).saturating_sub(padding.right + width);
+        let y = (panel.y + panel.height).saturating_sub((padding.bottom + height) / 2);
+        (x, y, rgba_from_hex(&self.theme.comment.hex)?)
+      }
+    };
+
+    let opacity = watermark.opacity.clamp(0.0, 1.0);
+    if let Some(logo) = &logo {
+      self.draw_image(image, logo, x as i32, y as i32, opacity);
+    }
+    if text_width > 0 {
+      let alpha = (255.0 * opacity).round() as u8;
+      self.draw_text(
+        image,
+        &watermark.text,
+        x + logo_width + gap,
+        y + ascent.ceil() as u32,
+        self.config.get_scaled_font_size(),
+        Rgba([color[0], color[1], color[2], alpha]),
+        TokenStyle::REGULAR,
+        None,
+      )?;
+    }
+    Ok(())
+  }
+}
+
+/// Mean luminance of the pixels in a rectangle of `image`
+fn backdrop_luminance(image: &RgbaImage, x: u32, y: u32, width: u32, height: u32) -> f32 {
+  let columns = x.min(image.width())..(x + width).min(image.width());
+  let rows = y.min(image.height())..(y + height).min(image.height());
+  let count = (columns.len() * rows.len()).max(1);
+  let total: f32 = rows
+    .flat_map(|row| columns.clone().map(move |column| (column, row)))
+    .map(|(column, row)| relative_luminance(*image.get_pixel(column, row)))
+    .sum();
+  total / count as f32
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::config::{RenderConfig, Sides, Watermark};
+  use crate::limits::RenderGuard;
+  use crate::renderer::SnippetRenderer;
+  use crate::renderer::color::relative_luminance;
+
+  #[test]
+  fn test_corner_watermark_reads_on_light_backdrop() {
+    let config = RenderConfig {
+      gradient_backdrop: false,
+      panel_padding: Sides::uniform(40),
+      watermark: Some(Watermark {
+        text: "@shoyu".to_string(),
+        opacity: 1.0,
+        ..Watermark::default()
+      }),
+      ..RenderConfig::default()
+    };
+    let mut renderer = SnippetRenderer::new("github", config).unwrap();
+    let lines = renderer.highlight_snippet("let x = 1;\n", "rust");
+    let guard = RenderGuard::acquire().unwrap();
+    let image = renderer.rasterize(&guard, &lines).unwrap();
+    guard.complete();
+    // The bottom backdrop margin holds only the watermark
+    let margin = (40.0 * renderer.config.export_size) as u32;
+    let darkest = image
+      .rows()
+      .skip((image.height() - margin) as usize)
+      .flatten()
+      .map(|pixel| relative_luminance(*pixel))
+      .fold(1.0, f32::min);
+    assert!(darkest < 0.5);
+  }
+}
@@ -0,0 +1,209 @@
+/* ~~/src/rpc.rs */
+
+// standard crates
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::panic::{self, AssertUnwindSafe};
+
+// third-party crates
+use anyhow::{Result, anyhow};
+use serde_json::Value as Json;
+
+// local modules
+use crate::config::RenderConfig;
+use crate::renderer::SnippetRenderer;
+use crate::syntax::SyntaxHighlighter;
+use crate::themes;
+use crate::warnings;
+
+pub mod jsonrpc;
+pub mod msgpack;
+
+pub use self::jsonrpc::serve_json;
+use self::msgpack::{Undecodable, Value};
+
+/// msgpack-RPC message types
+const REQUEST: i64 = 0;
+const RESPONSE: i64 = 1;
+const NOTIFICATION: i64 = 2;
+
+/// Methods `call` answers
+pub(crate) const METHODS: &[&str] = &["render", "themes", "theme", "languages", "warnings"];
+
+/// Run `method` with positional `params`; the calls every server mode offers
+pub(crate) fn call(method: &str, params: &[Json]) -> Result<Json> {
+  let text = |index: usize| params.get(index).and_then(Json::as_str);
+  match method {
+    // render(code, language?, theme?, config?) -> the metadata FFI render's JSON
+    "render" => {
+      let code = text(0).ok_or_else(|| anyhow!("render needs the code to draw"))?;
+      let language = text(1).unwrap_or("auto");
+      let theme = text(2).unwrap_or("dracula");
+      let config = parse_config(params.get(3))?;
+      let mut renderer = SnippetRenderer::new(theme, config)?;
+      let output = renderer.render_snippet_with_metadata(code, language)?;
+      serde_json::to_value(output).map_err(|e| anyhow!("Failed to serialize render: {}", e))
+    }
+    "themes" => Ok(themes::get_theme_names().into()),
+    // theme(name) -> every color of the theme, as get_theme_details returns it
+    "theme" => {
+      let name = text(0).ok_or_else(|| anyhow!("theme needs a name"))?;
+      let theme = themes::get_theme(name).ok_or_else(|| anyhow!("Unknown theme: {}", name))?;
+      serde_json::to_value(theme).map_err(|e| anyhow!("Failed to serialize theme: {}", e))
+    }
+    "languages" => Ok(SyntaxHighlighter::new(&[])?.language_names().into()),
+    "warnings" => Ok(warnings::drain().into()),
+    _ => Err(anyhow!("Unknown method: {}", method)),
+  }
+}
+
+/// Config sent as an object, as JSON text, or left out for the defaults;
+/// Lua encodes an empty table as an array, so that counts as left out
+fn parse_config(config: Option<&Json>) -> Result<RenderConfig> {
+  match config {
+    None | Some(Json::Null) => Ok(RenderConfig::default()),
+    Some(Json::Array(items)) if items.is_empty() => Ok(RenderConfig::default()),
+    Some(Json::String(json)) => RenderConfig::from_json(json),
+    Some(config @ Json::Object(_)) => RenderConfig::from_json(&config.to_string()),
+    Some(other) => Err(anyhow!("Expected a config object, got {}", other)),
+  }
+}
+
+/// `call`, with a panic reported as an error in builds that unwind. Release
+/// builds abort on panic, which ends the session like any other crash.
+pub(crate) fn call_guarded(method: &str, params: &[Json]) -> Result<Json> {
+  panic::catch_unwind(AssertUnwindSafe(|| call(method, params)))
+    .unwrap_or_else(|_| Err(anyhow!("{} panicked", method)))
+}
+
+/// Answer msgpack-RPC requests from `input` on `output` until input closes.
+/// This is what Neovim speaks to a job started with `rpc = true`, so the
+/// plugin can render without loading the library into the editor.
+pub fn serve_msgpack<R: Read, W: Write>(input: R, output: W) -> Result<()> {
+  let mut input = BufReader::new(input);
+  let mut output = BufWriter::new(output);
+  loop {
+    let message = match msgpack::read_value(&mut input) {
+      Ok(Some(message)) => message,
+      Ok(None) => break,
+      // Read to its end, so the session can go on with the next message
+      Err(e) if e.is::<Undecodable>() => {
+        warnings::push(format!("Skipped a msgpack-RPC message: {}", e));
+        continue;
+      }
+      Err(e) => return Err(e),
+    };
+    let Value::Array(parts) = message else {
+      continue;
+    };
+    // Requests are answered, notifications run silently, anything else is
+    // skipped since there is no id to report an error against
+    let (id, method, params) = match parts.as_slice() {
+      [
+        Value::Int(REQUEST),
+        Value::Int(id),
+        Value::String(method),
+        Value::Array(params),
+      ] => (Some(*id), method, params),
+      [
+        Value::Int(NOTIFICATION),
+        Value::String(method),
+        Value::Array(params),
+      ] => (None, method, params),
+      _ => continue,
+    };
+    let params: Vec<Json> = params.iter().cloned().map(Json::from).collect();
+    let result = call_guarded(method, &params);
+    let Some(id) = id else {
+      continue;
+    };
+    let (error, result) = match result {
+      Ok(result) => (Value::Nil, Value::from(result)),
+      Err(e) => (Value::String(e.to_string()), Value::Nil),
+    };
+    let response = Value::Array(vec![Value::Int(RESPONSE), Value::Int(id), error, result]);
+    msgpack::write_value(&mut output, &response)?;
+    output.flush()?;
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn message(parts: Vec<Value>) -> Value {
+    Value::Array(parts)
+  }
+
+  fn text(text: &str) -> Value {
+    Value::String(text.to_string())
+  }
+
+  #[test]
+  fn test_answers_msgpack_requests() {
+    let config = Value::Map(vec![
+      (text("export_size"), Value::Int(1)),
+      (text("skeleton"), Value::Bool(true)),
+    ]);
+    let messages = [
+      message(vec![
+        Value::Int(REQUEST),
+        Value::Int(1),
+        text("themes"),
+        Value::Array(vec![]),
+      ]),
+      message(vec![
+        Value::Int(NOTIFICATION),
+        text("warnings"),
+        Value::Array(vec![]),
+      ]),
+      message(vec![
+        Value::Int(REQUEST),
+        Value::Int(2),
+        text("render"),
+        Value::Array(vec![text("x = 1"), text("python"), text("nord"), config]),
+      ]),
+      message(vec![
+        Value::Int(REQUEST),
+        Value::Int(3),
+        text("explode"),
+        Value::Array(vec![]),
+      ]),
+    ];
+    // Undecodable messages are skipped, not the end of the session: a
+    // request whose id overflows i64, a reserved marker, and nesting past
+    // the decoder's depth limit
+    let mut input = vec![0x94, 0x00, 0xcf];
+    input.extend([0xff; 8]);
+    input.extend([0xa1, b'x', 0x90, 0xc1]);
+    input.extend([0x91; 100]);
+    input.push(0xc0);
+    for message in &messages {
+      msgpack::write_value(&mut input, message).unwrap();
+    }
+    let mut output = Vec::new();
+    serve_msgpack(input.as_slice(), &mut output).unwrap();
+
+    let mut reader = output.as_slice();
+    let mut responses = Vec::new();
+    while let Some(Value::Array(parts)) = msgpack::read_value(&mut reader).unwrap() {
+      responses.push(parts);
+    }
+    assert_eq!(responses.len(), 3);
+    let themes = Json::from(responses[0][3].clone());
+    assert!(themes.as_array().unwrap().contains(&Json::from("dracula")));
+
+    assert_eq!(responses[1][1], Value::Int(2));
+    assert_eq!(responses[1][2], Value::Nil);
+    let render = Json::from(responses[1][3].clone());
+    assert_eq!(render["line_count"], 1);
+    assert!(
+      render["image"]
+        .as_str()
+        .unwrap()
+        .starts_with("data:image/png")
+    );
+
+    assert_eq!(responses[2][2], text("Unknown method: explode"));
+  }
+}
@@ -0,0 +1,405 @@
+/* ~~/src/rpc/jsonrpc.rs */
+
+// standard crates
+use std::collections::HashMap;
+use std::io::{BufRead, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+// third-party crates
+use anyhow::{Result, anyhow};
+use rayon::prelude::*;
+use serde_json::{Value as Json, json};
+
+// local modules
+use crate::rpc::msgpack::MAX_RESERVE;
+use crate::rpc::{METHODS, call_guarded};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Implementation-defined range; a render or lookup that failed
+const SERVER_ERROR: i64 = -32000;
+/// Code LSP clients expect for a request cancelled with `$/cancelRequest`
+const REQUEST_CANCELLED: i64 = -32800;
+
+const CANCEL_METHOD: &str = "$/cancelRequest";
+
+/// How messages are delimited, matched to the client by its first message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Framing {
+  /// One message per line
+  Lines,
+  /// `Content-Length` headers, as LSP and vscode-jsonrpc send
+  Headers,
+}
+
+/// A message off the input
+enum Incoming {
+  Body(String),
+  /// Framing or text that could not be read, answered with a parse error
+  Unreadable(String),
+}
+
+/// Read through the blank line that ends a message's headers
+fn skip_headers<R: BufRead>(input: &mut R) -> Result<()> {
+  loop {
+    let mut header = Vec::new();
+    if input.read_until(b'\n', &mut header)? == 0 || header.trim_ascii().is_empty() {
+      return Ok(());
+    }
+  }
+}
+
+/// Read the next message, `None` once input closes
+fn read_message<R: BufRead>(
+  input: &mut R,
+  framing: &mut Option<Framing>,
+) -> Result<Option<Incoming>> {
+  loop {
+    let mut bytes = Vec::new();
+    if input.read_until(b'\n', &mut bytes)? == 0 {
+      return Ok(None);
+    }
+    let Ok(line) = String::from_utf8(bytes) else {
+      return Ok(Some(Incoming::Unreadable(
+        "Message is not valid UTF-8".to_string(),
+      )));
+    };
+    let trimmed = line.trim();
+    if trimmed.is_empty() {
+      continue;
+    }
+    let Some((name, value)) = trimmed.split_once(':') else {
+      framing.get_or_insert(Framing::Lines);
+      return Ok(Some(Incoming::Body(line)));
+    };
+    if !name.eq_ignore_ascii_case("content-length") {
+      framing.get_or_insert(Framing::Lines);
+      return Ok(Some(Incoming::Body(line)));
+    }
+    *framing = Some(Framing::Headers);
+    // Skip any other headers up to the blank line before the body
+    skip_headers(input)?;
+    let Ok(length) = value.trim().parse::<usize>() else {
+      return Ok(Some(Incoming::Unreadable(format!(
+        "Invalid Content-Length: {}",
+        value.trim()
+      ))));
+    };
+    // The length is the client's word; the body grows only as it arrives
+    let mut body = Vec::with_capacity(length.min(MAX_RESERVE));
+    input.take(length as u64).read_to_end(&mut body)?;
+    return Ok(Some(Incoming::Body(
+      String::from_utf8_lossy(&body).into_owned(),
+    )));
+  }
+}
+
+fn error(id: &Json, code: i64, message: impl Into<String>) -> Json {
+  json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message.into()}})
+}
+
+/// Positional arguments from `params`, which JSON-RPC allows by position
+/// or by name
+fn positional(method: &str, params: Option<Json>) -> Result<Vec<Json>, String> {
+  let names: &[&str] = match method {
+    "render" => &["code", "language", "theme", "config"],
+    "theme" => &["name"],
+    _ => &[],
+  };
+  match params {
+    None | Some(Json::Null) => Ok(Vec::new()),
+    Some(Json::Array(params)) => Ok(params),
+    Some(Json::Object(mut params)) => Ok(
+      names
+        .iter()
+        .map(|name| params.remove(*name).unwrap_or(Json::Null))
+        .collect(),
+    ),
+    Some(_) => Err("params must be an array or an object".to_string()),
+  }
+}
+
+/// Run one request to its response, `None` for notifications
+fn respond(request: Json) -> Option<Json> {
+  let Json::Object(mut request) = request else {
+    return Some(error(
+      &Json::Null,
+      INVALID_REQUEST,
+      "Expected a request object",
+    ));
+  };
+  let id = request.remove("id");
+  let reply = |response: Json| id.as_ref().map(|_| response);
+  let id_value = id.clone().unwrap_or(Json::Null);
+  let Some(Json::String(method)) = request.remove("method") else {
+    return Some(error(&id_value, INVALID_REQUEST, "Missing method"));
+  };
+  if !METHODS.contains(&method.as_str()) {
+    return reply(error(
+      &id_value,
+      METHOD_NOT_FOUND,
+      format!("Unknown method: {}", method),
+    ));
+  }
+  let params = match positional(&method, request.remove("params")) {
+    Ok(params) => params,
+    Err(message) => return reply(error(&id_value, INVALID_PARAMS, message)),
+  };
+  reply(match call_guarded(&method, &params) {
+    Ok(result) => json!({"jsonrpc": "2.0", "id": id_value, "result": result}),
+    Err(e) => error(&id_value, SERVER_ERROR, e.to_string()),
+  })
+}
+
+/// Requests waiting on a worker, by id, each with a flag set by whichever
+/// of the worker or a cancellation answers first
+type Pending = Mutex<HashMap<String, Arc<AtomicBool>>>;
+
+/// Writes whole messages in the client's framing
+struct Output<W: Write> {
+  writer: Mutex<W>,
+  framing: Mutex<Option<Framing>>,
+}
+
+impl<W: Write> Output<W> {
+  fn send(&self, message: &Json) {
+    let body = message.to_string();
+    let framing = *self.framing.lock().unwrap_or_else(|e| e.into_inner());
+    let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+    // A closed output has no one left to tell
+    let _ = match framing {
+      Some(Framing::Headers) => write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body),
+      _ => writeln!(writer, "{}", body),
+    }
+    .and_then(|_| writer.flush());
+  }
+}
+
+/// Answer JSON-RPC 2.0 requests from `input` on `output` until input closes,
+/// running up to `workers` of them at once (0 for one per core). Responses
+/// arrive as requests finish, so clients match them up by id, and
+/// `$/cancelRequest` answers a request that is queued or still rendering
+/// with an error at once. A render already under way is not interrupted; it
+/// runs to the end on its worker and its result is dropped.
+pub fn serve_json<R: BufRead, W: Write + Send>(
+  mut input: R,
+  output: W,
+  workers: usize,
+) -> Result<()> {
+  let pool = rayon::ThreadPoolBuilder::new()
+    .num_threads(workers)
+    .build()
+    .map_err(|e| anyhow!("Failed to start workers: {}", e))?;
+  let output = Output {
+    writer: Mutex::new(output),
+    framing: Mutex::new(None),
+  };
+  let pending: Pending = Mutex::new(HashMap::new());
+  let mut framing = None;
+
+  // Reading stays on this thread so a blocked read never holds a worker;
+  // the scope waits for outstanding requests once input closes
+  pool.in_place_scope(|scope| {
+    while let Some(incoming) = read_message(&mut input, &mut framing)? {
+      *output.framing.lock().unwrap_or_else(|e| e.into_inner()) = framing;
+      // An unreadable message is answered, not the end of the session
+      let body = match incoming {
+        Incoming::Body(body) => body,
+        Incoming::Unreadable(problem) => {
+          output.send(&error(&Json::Null, PARSE_ERROR, problem));
+          continue;
+        }
+      };
+      let message: Json = match serde_json::from_str(&body) {
+        Ok(message) => message,
+        Err(e) => {
+          output.send(&error(&Json::Null, PARSE_ERROR, e.to_string()));
+          continue;
+        }
+      };
+      match message {
+        Json::Array(batch) if batch.is_empty() => {
+          output.send(&error(&Json::Null, INVALID_REQUEST, "Empty batch"));
+        }
+        // Batches are answered together, with their requests run in parallel
+        Json::Array(batch) => {
+          let output = &output;
+          scope.spawn(move |_| {
+            let responses: Vec<Json> = batch.into_par_iter().filter_map(respond).collect();
+            if !responses.is_empty() {
+              output.send(&Json::Array(responses));
+            }
+          });
+        }
+        message if message["method"] == CANCEL_METHOD => {
+          let key = message["params"]["id"].to_string();
+          let answered = pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&key);
+          if let Some(answered) = answered
+            && !answered.swap(true, Ordering::SeqCst)
+          {
+            let id = &message["params"]["id"];
+            output.send(&error(id, REQUEST_CANCELLED, "Request cancelled"));
+          }
+        }
+        message => {
+          let key = message.get("id").map(Json::to_string);
+          let answered = Arc::new(AtomicBool::new(false));
+          if let Some(key) = &key {
+            pending
+              .lock()
+              .unwrap_or_else(|e| e.into_inner())
+              .insert(key.clone(), answered.clone());
+          }
+          let (output, pending) = (&output, &pending);
+          scope.spawn(move |_| {
+            // Cancelled while queued
+            if answered.load(Ordering::SeqCst) {
+              return;
+            }
+            let response = respond(message);
+            if let Some(key) = &key {
+              pending
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(key);
+            }
+            if let Some(response) = response
+              && !answered.swap(true, Ordering::SeqCst)
+            {
+              output.send(&response);
+            }
+          });
+        }
+      }
+    }
+    Ok(())
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn serve(input: &str) -> Vec<Json> {
+    let mut output = Vec::new();
+    serve_json(input.as_bytes(), &mut output, 2).unwrap();
+    String::from_utf8(output)
+      .unwrap()
+      .lines()
+      .map(|line| serde_json::from_str(line).unwrap())
+      .collect()
+  }
+
+  /// Bodies of `Content-Length` framed output
+  fn framed(mut output: &[u8]) -> Vec<Json> {
+    let mut bodies = Vec::new();
+    while let Some(Incoming::Body(body)) = read_message(&mut output, &mut None).unwrap() {
+      bodies.push(serde_json::from_str(&body).unwrap());
+    }
+    bodies
+  }
+
+  fn by_id(responses: &[Json], id: Json) -> &Json {
+    responses
+      .iter()
+      .find(|response| response["id"] == id)
+      .unwrap()
+  }
+
+  #[test]
+  fn test_answers_requests_by_id() {
+    let input = [
+      r#"{"jsonrpc": "2.0", "id": 1, "method": "themes"}"#,
+      r#"{"jsonrpc": "2.0", "id": "r", "method": "render", "params": {"code": "x = 1", "language": "python", "config": {"export_size": 1, "skeleton": true}}}"#,
+      r#"{"jsonrpc": "2.0", "method": "warnings"}"#,
+      r#"{"jsonrpc": "2.0", "id": 3, "method": "explode"}"#,
+      r#"{"jsonrpc": "2.0", "id": 4, "method": "render", "params": 7}"#,
+      r#"[{"jsonrpc": "2.0", "id": 5, "method": "themes"}, {"jsonrpc": "2.0", "method": "themes"}]"#,
+      "{oops",
+    ]
+    .join("\n");
+    let responses = serve(&input);
+    assert_eq!(responses.len(), 6);
+
+    assert!(
+      by_id(&responses, json!(1))["result"]
+        .as_array()
+        .unwrap()
+        .len()
+        >= 8
+    );
+    let render = &by_id(&responses, json!("r"))["result"];
+    assert_eq!(render["line_count"], 1);
+    assert_eq!(
+      by_id(&responses, json!(3))["error"]["code"],
+      METHOD_NOT_FOUND
+    );
+    assert_eq!(by_id(&responses, json!(4))["error"]["code"], INVALID_PARAMS);
+    let batch = responses.iter().find_map(Json::as_array).unwrap();
+    assert_eq!(batch.len(), 1);
+    assert_eq!(batch[0]["id"], 5);
+    assert_eq!(by_id(&responses, Json::Null)["error"]["code"], PARSE_ERROR);
+  }
+
+  #[test]
+  fn test_cancels_queued_requests() {
+    // One worker busy with the first render leaves the second queued when
+    // the cancellation arrives, so it is answered once, as cancelled
+    let render = |id: u32| {
+      format!(
+        r#"{{"jsonrpc": "2.0", "id": {}, "method": "render", "params": ["fn main() {{}}", "rust", "nord", {{"export_size": 1, "gradient_backdrop": false}}]}}"#,
+        id
+      )
+    };
+    let cancel = r#"{"jsonrpc": "2.0", "method": "$/cancelRequest", "params": {"id": 2}}"#;
+    let body = [render(1), render(2), cancel.to_string()];
+    let input: String = body
+      .iter()
+      .map(|body| format!("Content-Length: {}\r\n\r\n{}", body.len(), body))
+      .collect();
+
+    let mut output = Vec::new();
+    serve_json(input.as_bytes(), &mut output, 1).unwrap();
+    let responses = framed(&output);
+    assert_eq!(responses.len(), 2);
+    assert!(by_id(&responses, json!(1))["result"].is_object());
+    assert_eq!(
+      by_id(&responses, json!(2))["error"]["code"],
+      REQUEST_CANCELLED
+    );
+  }
+
+  #[test]
+  fn test_answers_unreadable_messages() {
+    let themes = |id: u32| format!(r#"{{"jsonrpc": "2.0", "id": {}, "method": "themes"}}"#, id);
+    let mut input = b"Content-Length: lots\r\n\r\n\xff\xfe\n".to_vec();
+    let body = themes(1);
+    input.extend(format!("Content-Length: {}\r\n\r\n{}", body.len(), body).bytes());
+    // A length far past the data reserves no more than what arrives
+    input.extend(format!("Content-Length: {}\r\n\r\n{}", u64::MAX / 2, themes(2)).bytes());
+
+    let mut output = Vec::new();
+    serve_json(input.as_slice(), &mut output, 1).unwrap();
+    let responses = framed(&output);
+    assert_eq!(responses.len(), 4);
+    let problems: Vec<&Json> = responses
+      .iter()
+      .filter(|response| response["id"].is_null())
+      .map(|response| &response["error"])
+      .collect();
+    assert_eq!(problems.len(), 2);
+    assert!(
+      problems
+        .iter()
+        .all(|problem| problem["code"] == PARSE_ERROR)
+    );
+    assert!(by_id(&responses, json!(1))["result"].is_array());
+    assert!(by_id(&responses, json!(2))["result"].is_array());
+  }
+}
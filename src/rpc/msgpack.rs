@@ -0,0 +1,437 @@
+/* ~~/src/rpc/msgpack.rs */
+
+// standard crates
+use std::io::{self, ErrorKind, Read, Write};
+
+// third-party crates
+use anyhow::{Result, anyhow};
+use serde_json::{Map, Number};
+
+/// A decoded MessagePack value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+  Nil,
+  Bool(bool),
+  Int(i64),
+  Float(f64),
+  String(String),
+  Binary(Vec<u8>),
+  Array(Vec<Value>),
+  Map(Vec<(Value, Value)>),
+  /// Application-defined type, such as Neovim's buffer and window handles
+  Ext(i8, Vec<u8>),
+}
+
+/// Cap on elements reserved up front, so a corrupt length cannot allocate
+/// gigabytes before the data runs out
+pub(crate) const MAX_RESERVE: usize = 1024;
+
+/// Deepest nesting of arrays and maps decoded; deeper ones are skipped
+/// without recursing, so a hostile message cannot overflow the stack
+const MAX_DEPTH: usize = 64;
+
+/// A value read to its end but not representable, which leaves the stream in
+/// step for the next one
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct Undecodable(String);
+
+fn read_bytes<R: Read>(reader: &mut R, len: usize) -> Result<Vec<u8>> {
+  let mut bytes = Vec::with_capacity(len.min(MAX_RESERVE));
+  reader.take(len as u64).read_to_end(&mut bytes)?;
+  if bytes.len() < len {
+    return Err(anyhow!("MessagePack data ended early"));
+  }
+  Ok(bytes)
+}
+
+fn read_array<const N: usize, R: Read>(reader: &mut R) -> Result<[u8; N]> {
+  let mut bytes = [0; N];
+  reader.read_exact(&mut bytes)?;
+  Ok(bytes)
+}
+
+fn read_len<R: Read>(reader: &mut R, width: usize) -> Result<usize> {
+  Ok(match width {
+    1 => u8::from_be_bytes(read_array(reader)?) as usize,
+    2 => u16::from_be_bytes(read_array(reader)?) as usize,
+    _ => u32::from_be_bytes(read_array(reader)?) as usize,
+  })
+}
+
+/// A value, or the length of an array or map whose items follow it
+enum Head {
+  Value(Value),
+  Array(usize),
+  Map(usize),
+}
+
+/// Reads one value to its end, noting data it cannot represent rather than
+/// stopping there, so the stream stays in step for the next value
+struct Decoder<'a, R> {
+  reader: &'a mut R,
+  depth: usize,
+  undecodable: Option<String>,
+}
+
+impl<R: Read> Decoder<'_, R> {
+  /// Note the first problem and stand nil in for the value
+  fn reject(&mut self, problem: String) -> Value {
+    self.undecodable.get_or_insert(problem);
+    Value::Nil
+  }
+
+  fn string(&mut self, len: usize) -> Result<Value> {
+    let bytes = read_bytes(self.reader, len)?;
+    Ok(match String::from_utf8(bytes) {
+      Ok(text) => Value::String(text),
+      Err(_) => self.reject("MessagePack string is not valid UTF-8".to_string()),
+    })
+  }
+
+  fn ext(&mut self, len: usize) -> Result<Value> {
+    let [kind] = read_array(self.reader)?;
+    Ok(Value::Ext(kind as i8, read_bytes(self.reader, len)?))
+  }
+
+  /// The next marker and its payload, `None` when the stream ends cleanly
+  /// before one
+  fn head(&mut self) -> Result<Option<Head>> {
+    let mut marker = [0];
+    match self.reader.read_exact(&mut marker) {
+      Ok(()) => {}
+      Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+      Err(e) => return Err(e.into()),
+    }
+    let value = match marker[0] {
+      byte @ 0x00..=0x7f => Value::Int(byte as i64),
+      byte @ 0x80..=0x8f => return Ok(Some(Head::Map((byte & 0x0f) as usize))),
+      byte @ 0x90..=0x9f => return Ok(Some(Head::Array((byte & 0x0f) as usize))),
+      byte @ 0xa0..=0xbf => self.string((byte & 0x1f) as usize)?,
+      0xc0 => Value::Nil,
+      0xc1 => self.reject("0xc1 is not a MessagePack marker".to_string()),
+      0xc2 => Value::Bool(false),
+      0xc3 => Value::Bool(true),
+      byte @ 0xc4..=0xc6 => {
+        let len = read_len(self.reader, 1 << (byte - 0xc4))?;
+        Value::Binary(read_bytes(self.reader, len)?)
+      }
+      byte @ 0xc7..=0xc9 => {
+        let len = read_len(self.reader, 1 << (byte - 0xc7))?;
+        self.ext(len)?
+      }
+      0xca => Value::Float(f32::from_be_bytes(read_array(self.reader)?) as f64),
+      0xcb => Value::Float(f64::from_be_bytes(read_array(self.reader)?)),
+      0xcc => Value::Int(u8::from_be_bytes(read_array(self.reader)?) as i64),
+      0xcd => Value::Int(u16::from_be_bytes(read_array(self.reader)?) as i64),
+      0xce => Value::Int(u32::from_be_bytes(read_array(self.reader)?) as i64),
+      0xcf => {
+        let value = u64::from_be_bytes(read_array(self.reader)?);
+        match i64::try_from(value) {
+          Ok(value) => Value::Int(value),
+          Err(_) => self.reject(format!("Integer {} is out of range", value)),
+        }
+      }
+      0xd0 => Value::Int(i8::from_be_bytes(read_array(self.reader)?) as i64),
+      0xd1 => Value::Int(i16::from_be_bytes(read_array(self.reader)?) as i64),
+      0xd2 => Value::Int(i32::from_be_bytes(read_array(self.reader)?) as i64),
+      0xd3 => Value::Int(i64::from_be_bytes(read_array(self.reader)?)),
+      byte @ 0xd4..=0xd8 => self.ext(1 << (byte - 0xd4))?,
+      byte @ 0xd9..=0xdb => {
+        let len = read_len(self.reader, 1 << (byte - 0xd9))?;
+        self.string(len)?
+      }
+      byte @ 0xdc..=0xdd => {
+        let len = read_len(self.reader, 2 << (byte - 0xdc))?;
+        return Ok(Some(Head::Array(len)));
+      }
+      byte @ 0xde..=0xdf => {
+        let len = read_len(self.reader, 2 << (byte - 0xde))?;
+        return Ok(Some(Head::Map(len)));
+      }
+      byte @ 0xe0..=0xff => Value::Int(byte as i8 as i64),
+    };
+    Ok(Some(Head::Value(value)))
+  }
+
+  fn value(&mut self) -> Result<Option<Value>> {
+    let Some(head) = self.head()? else {
+      return Ok(None);
+    };
+    let (len, items) = match head {
+      Head::Value(value) => return Ok(Some(value)),
+      Head::Array(len) => (len, len as u64),
+      Head::Map(len) => (len, 2 * len as u64),
+    };
+    if self.depth == MAX_DEPTH {
+      self.skip(items)?;
+      let problem = format!("MessagePack nests deeper than {} levels", MAX_DEPTH);
+      return Ok(Some(self.reject(problem)));
+    }
+    self.depth += 1;
+    let value = match head {
+      Head::Map(_) => Value::Map(self.entries(len)?),
+      _ => Value::Array(self.items(len)?),
+    };
+    self.depth -= 1;
+    Ok(Some(value))
+  }
+
+  fn required(&mut self) -> Result<Value> {
+    self
+      .value()?
+      .ok_or_else(|| anyhow!("MessagePack data ended early"))
+  }
+
+  fn items(&mut self, len: usize) -> Result<Vec<Value>> {
+    let mut items = Vec::with_capacity(len.min(MAX_RESERVE));
+    for _ in 0..len {
+      items.push(self.required()?);
+    }
+    Ok(items)
+  }
+
+  fn entries(&mut self, len: usize) -> Result<Vec<(Value, Value)>> {
+    let mut entries = Vec::with_capacity(len.min(MAX_RESERVE));
+    for _ in 0..len {
+      entries.push((self.required()?, self.required()?));
+    }
+    Ok(entries)
+  }
+
+  /// Read past `count` values, counting the items of arrays and maps instead
+  /// of recursing into them
+  fn skip(&mut self, mut count: u64) -> Result<()> {
+    while count > 0 {
+      count -= 1;
+      match self.head()? {
+        Some(Head::Array(len)) => count += len as u64,
+        Some(Head::Map(len)) => count += 2 * len as u64,
+        Some(Head::Value(_)) => {}
+        None => return Err(anyhow!("MessagePack data ended early")),
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Read the next value, `None` when the stream ends cleanly before one. A
+/// value that cannot be represented is still read to its end, then reported
+/// as [`Undecodable`] so the caller can carry on with the next.
+pub fn read_value<R: Read>(reader: &mut R) -> Result<Option<Value>> {
+  let mut decoder = Decoder {
+    reader,
+    depth: 0,
+    undecodable: None,
+  };
+  let value = decoder.value()?;
+  match decoder.undecodable {
+    Some(problem) => Err(Undecodable(problem).into()),
+    None => Ok(value),
+  }
+}
+
+/// Marker followed by a length in the smallest of the three widths
+fn write_len<W: Write>(writer: &mut W, markers: [u8; 3], len: usize) -> io::Result<()> {
+  match len {
+    0..=0xff if markers[0] != 0 => writer.write_all(&[markers[0], len as u8]),
+    0..=0xffff => {
+      writer.write_all(&[markers[1]])?;
+      writer.write_all(&(len as u16).to_be_bytes())
+    }
+    _ => {
+      let len = u32::try_from(len)
+        .map_err(|_| io::Error::new(ErrorKind::InvalidInput, "too long for MessagePack"))?;
+      writer.write_all(&[markers[2]])?;
+      writer.write_all(&len.to_be_bytes())
+    }
+  }
+}
+
+/// Write `value` in its most compact encoding
+pub fn write_value<W: Write>(writer: &mut W, value: &Value) -> io::Result<()> {
+  match value {
+    Value::Nil => writer.write_all(&[0xc0]),
+    Value::Bool(value) => writer.write_all(&[0xc2 + *value as u8]),
+    Value::Int(value) => match *value {
+      // fixints hold -32..=127 in the marker byte itself
+      -32..=0x7f => writer.write_all(&[*value as u8]),
+      0x80..=0xff => writer.write_all(&[0xcc, *value as u8]),
+      0x100..=0xffff => {
+        writer.write_all(&[0xcd])?;
+        writer.write_all(&(*value as u16).to_be_bytes())
+      }
+      0x1_0000..=0xffff_ffff => {
+        writer.write_all(&[0xce])?;
+        writer.write_all(&(*value as u32).to_be_bytes())
+      }
+      -0x80..=-33 => writer.write_all(&[0xd0, *value as i8 as u8]),
+      -0x8000..=-0x81 => {
+        writer.write_all(&[0xd1])?;
+        writer.write_all(&(*value as i16).to_be_bytes())
+      }
+      -0x8000_0000..=-0x8001 => {
+        writer.write_all(&[0xd2])?;
+        writer.write_all(&(*value as i32).to_be_bytes())
+      }
+      value => {
+        writer.write_all(&[0xd3])?;
+        writer.write_all(&value.to_be_bytes())
+      }
+    },
+    Value::Float(value) => {
+      writer.write_all(&[0xcb])?;
+      writer.write_all(&value.to_be_bytes())
+    }
+    Value::String(text) => {
+      match text.len() {
+        len @ 0..=31 => writer.write_all(&[0xa0 | len as u8])?,
+        len => write_len(writer, [0xd9, 0xda, 0xdb], len)?,
+      }
+      writer.write_all(text.as_bytes())
+    }
+    Value::Binary(bytes) => {
+      write_len(writer, [0xc4, 0xc5, 0xc6], bytes.len())?;
+      writer.write_all(bytes)
+    }
+    Value::Array(items) => {
+      match items.len() {
+        len @ 0..=15 => writer.write_all(&[0x90 | len as u8])?,
+        len => write_len(writer, [0, 0xdc, 0xdd], len)?,
+      }
+      items.iter().try_for_each(|item| write_value(writer, item))
+    }
+    Value::Map(entries) => {
+      match entries.len() {
+        len @ 0..=15 => writer.write_all(&[0x80 | len as u8])?,
+        len => write_len(writer, [0, 0xde, 0xdf], len)?,
+      }
+      entries.iter().try_for_each(|(key, value)| {
+        write_value(writer, key)?;
+        write_value(writer, value)
+      })
+    }
+    Value::Ext(kind, bytes) => {
+      write_len(writer, [0xc7, 0xc8, 0xc9], bytes.len())?;
+      writer.write_all(&[*kind as u8])?;
+      writer.write_all(bytes)
+    }
+  }
+}
+
+impl From<serde_json::Value> for Value {
+  fn from(value: serde_json::Value) -> Self {
+    match value {
+      serde_json::Value::Null => Self::Nil,
+      serde_json::Value::Bool(value) => Self::Bool(value),
+      serde_json::Value::Number(number) => match number.as_i64() {
+        Some(value) => Self::Int(value),
+        None => Self::Float(number.as_f64().unwrap_or(0.0)),
+      },
+      serde_json::Value::String(text) => Self::String(text),
+      serde_json::Value::Array(items) => Self::Array(items.into_iter().map(Self::from).collect()),
+      serde_json::Value::Object(entries) => Self::Map(
+        entries
+          .into_iter()
+          .map(|(key, value)| (Self::String(key), Self::from(value)))
+          .collect(),
+      ),
+    }
+  }
+}
+
+impl From<Value> for serde_json::Value {
+  /// Binary reads as text, since editors send strings that way, and maps
+  /// with other keys get them written out as text
+  fn from(value: Value) -> Self {
+    match value {
+      Value::Nil | Value::Ext(..) => Self::Null,
+      Value::Bool(value) => Self::Bool(value),
+      Value::Int(value) => Self::Number(value.into()),
+      Value::Float(value) => Number::from_f64(value).map_or(Self::Null, Self::Number),
+      Value::String(text) => Self::String(text),
+      Value::Binary(bytes) => Self::String(String::from_utf8_lossy(&bytes).into_owned()),
+      Value::Array(items) => Self::Array(items.into_iter().map(Self::from).collect()),
+      Value::Map(entries) => Self::Object(
+        entries
+          .into_iter()
+          .map(|(key, value)| {
+            let key = match Self::from(key) {
+              Self::String(key) => key,
+              key => key.to_string(),
+            };
+            (key, Self::from(value))
+          })
+          .collect::<Map<_, _>>(),
+      ),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_round_trips_every_width() {
+    let long = "x".repeat(300);
+    let values = [
+      Value::Nil,
+      Value::Bool(true),
+      Value::Int(5),
+      Value::Int(-20),
+      Value::Int(200),
+      Value::Int(-200),
+      Value::Int(70_000),
+      Value::Int(-70_000),
+      Value::Int(1 << 40),
+      Value::Int(i64::MIN),
+      Value::Float(0.25),
+      Value::String("醤油".to_string()),
+      Value::String(long.clone()),
+      Value::Binary(vec![0, 255]),
+      Value::Array((0..20).map(Value::Int).collect()),
+      Value::Map(vec![(Value::String("a".to_string()), Value::Nil)]),
+      Value::Ext(1, vec![7]),
+    ];
+    let mut bytes = Vec::new();
+    for value in &values {
+      write_value(&mut bytes, value).unwrap();
+    }
+    // Spot-check the compact encodings against the spec
+    assert_eq!(&bytes[..3], &[0xc0, 0xc3, 0x05]);
+
+    let mut reader = bytes.as_slice();
+    for value in &values {
+      assert_eq!(read_value(&mut reader).unwrap().as_ref(), Some(value));
+    }
+    assert_eq!(read_value(&mut reader).unwrap(), None);
+    // Truncated data is an error, not a clean end
+    assert!(read_value(&mut &[0x92, 0x01][..]).is_err());
+    assert!(read_value(&mut &[0xdb, 0xff, 0xff, 0xff, 0xff][..]).is_err());
+
+    // Values that cannot be represented are read to their end, so the next
+    // value still decodes
+    let mut bytes = vec![0x92, 0xcf];
+    bytes.extend([0xff; 8]);
+    bytes.extend([0xc1, 0x01, 0xa1, 0xff, 0x02]);
+    bytes.extend([0x91; MAX_DEPTH + 1]);
+    bytes.extend([0xc0, 0x03]);
+    let mut reader = bytes.as_slice();
+    for next in 1..=3 {
+      let e = read_value(&mut reader).unwrap_err();
+      assert!(e.is::<Undecodable>(), "{}", e);
+      assert_eq!(read_value(&mut reader).unwrap(), Some(Value::Int(next)));
+    }
+    let mut nested = vec![0x91; MAX_DEPTH];
+    nested.push(0xc0);
+    assert!(read_value(&mut nested.as_slice()).unwrap().is_some());
+
+    let json: serde_json::Value = Value::Map(vec![
+      (Value::Int(1), Value::Binary(b"hi".to_vec())),
+      (Value::String("k".to_string()), Value::Float(1.5)),
+    ])
+    .into();
+    assert_eq!(json, serde_json::json!({"1": "hi", "k": 1.5}));
+  }
+}
@@ -2,16 +2,30 @@
 
 // third-party crates
 use anyhow::{Result, anyhow};
+use std::collections::{BTreeMap, BTreeSet};
 use std::ffi::{CStr, CString};
-use std::os::raw::{c_char, c_int};
+use std::os::raw::{c_char, c_int, c_uint};
 use std::panic;
+use std::path::{Path, PathBuf};
 use std::ptr;
+use std::sync::Mutex;
 
 // local modules
-use crate::config::RenderConfig;
+use crate::animation::{self, AnimationSpec, TransitionSpec, TypingSpec};
+use crate::build_info;
+use crate::config::{GRADIENT_PRESETS, RenderConfig, TabFile};
+use crate::limits;
+use crate::profiles;
 use crate::renderer::SnippetRenderer;
 use crate::syntax;
+use crate::tempfiles;
+use crate::terminal::{self, ImageProtocol};
 use crate::themes;
+use crate::warnings;
+
+/// Addresses of strings handed out and not yet freed, so `free_string` can
+/// ignore double frees and pointers this library never allocated
+static ISSUED: Mutex<BTreeSet<usize>> = Mutex::new(BTreeSet::new());
 
 /// Safe wrapper for converting C string to Rust string
 fn safe_cstr_to_string(ptr: *const c_char) -> Result<String> {
@@ -29,11 +43,35 @@ fn safe_cstr_to_string(ptr: *const c_char) -> Result<String> {
 /// Safe wrapper for converting Rust string to C string
 fn safe_string_to_cstr(s: String) -> *mut c_char {
   match CString::new(s) {
-    Ok(cstring) => cstring.into_raw(),
+    Ok(cstring) => {
+      let raw = cstring.into_raw();
+      ISSUED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(raw as usize);
+      raw
+    }
     Err(_) => ptr::null_mut(),
   }
 }
 
+/// Theme name from `theme`, or the default theme for a null pointer
+fn theme_or_default(theme: *const c_char) -> Result<String> {
+  match theme.is_null() {
+    true => Ok("dracula".to_string()),
+    false => safe_cstr_to_string(theme),
+  }
+}
+
+/// Parse a JSON render config and its preset, falling back to defaults for a null pointer
+fn safe_parse_config(config_json: *const c_char) -> Result<RenderConfig> {
+  if config_json.is_null() {
+    return Ok(RenderConfig::default());
+  }
+  let config_str = safe_cstr_to_string(config_json)?;
+  RenderConfig::from_json(&config_str)
+}
+
 /// Safe wrapper for FFI operations with panic catching
 fn safe_ffi_operation<F, T>(operation: F) -> *mut c_char
 where
@@ -60,26 +98,428 @@ pub fn safe_generate_snippet_image(
     let code_str = safe_cstr_to_string(code)?;
     let language_str = safe_cstr_to_string(language)?;
 
-    let theme_str = if theme.is_null() {
-      "dracula".to_string()
+    let theme_str = theme_or_default(theme)?;
+
+    let config = safe_parse_config(config_json)?;
+
+    let mut renderer = SnippetRenderer::new(&theme_str, config)?;
+    let image_data = renderer.render_snippet(&code_str, &language_str)?;
+
+    Ok(image_data)
+  })
+}
+
+/// Render a snippet and wrap the PNG in a terminal inline image sequence
+pub fn safe_generate_terminal_preview(
+  code: *const c_char,
+  language: *const c_char,
+  theme: *const c_char,
+  config_json: *const c_char,
+  protocol: *const c_char,
+) -> *mut c_char {
+  safe_ffi_operation(|| {
+    let code_str = safe_cstr_to_string(code)?;
+    let language_str = safe_cstr_to_string(language)?;
+
+    let theme_str = theme_or_default(theme)?;
+    let protocol = if protocol.is_null() {
+      ImageProtocol::from_name("auto")?
     } else {
-      safe_cstr_to_string(theme)?
+      ImageProtocol::from_name(&safe_cstr_to_string(protocol)?)?
     };
 
-    let config = if config_json.is_null() {
-      RenderConfig::default()
+    let config = safe_parse_config(config_json)?;
+
+    let mut renderer = SnippetRenderer::new(&theme_str, config)?;
+    let png_data = renderer.render_snippet_png(&code_str, &language_str)?;
+    terminal::inline_image(&png_data, protocol)
+  })
+}
+
+/// Generate a code snippet image as JSON with its size, line count, and timing
+pub fn safe_generate_snippet_image_with_metadata(
+  code: *const c_char,
+  language: *const c_char,
+  theme: *const c_char,
+  config_json: *const c_char,
+) -> *mut c_char {
+  safe_ffi_operation(|| {
+    let code_str = safe_cstr_to_string(code)?;
+    let language_str = safe_cstr_to_string(language)?;
+
+    let theme_str = theme_or_default(theme)?;
+
+    let config = safe_parse_config(config_json)?;
+
+    let mut renderer = SnippetRenderer::new(&theme_str, config)?;
+    let output = renderer.render_snippet_with_metadata(&code_str, &language_str)?;
+    let output_json =
+      serde_json::to_string(&output).map_err(|e| anyhow!("Failed to serialize render: {}", e))?;
+    Ok(output_json)
+  })
+}
+
+/// Render with a stored profile and per-call overrides with safe error handling
+pub fn safe_render_with_profile(
+  code: *const c_char,
+  language: *const c_char,
+  theme: *const c_char,
+  profile: *const c_char,
+  overrides_json: *const c_char,
+) -> *mut c_char {
+  safe_ffi_operation(|| {
+    let code_str = safe_cstr_to_string(code)?;
+    let language_str = safe_cstr_to_string(language)?;
+    let profile_str = safe_cstr_to_string(profile)?;
+
+    let theme_str = theme_or_default(theme)?;
+
+    let overrides = if overrides_json.is_null() {
+      None
     } else {
-      let config_str = safe_cstr_to_string(config_json)?;
-      serde_json::from_str(&config_str).map_err(|e| anyhow!("Invalid JSON config: {}", e))?
+      Some(safe_cstr_to_string(overrides_json)?)
     };
+    let config = profiles::resolve(&profile_str, overrides.as_deref())?;
 
     let mut renderer = SnippetRenderer::new(&theme_str, config)?;
-    let image_data = renderer.render_snippet(&code_str, &language_str)?;
+    renderer.render_snippet(&code_str, &language_str)
+  })
+}
 
-    Ok(image_data)
+/// Render a long snippet as carousel pages, returned as a JSON array of data URLs
+pub fn safe_generate_snippet_carousel(
+  code: *const c_char,
+  language: *const c_char,
+  theme: *const c_char,
+  config_json: *const c_char,
+) -> *mut c_char {
+  safe_ffi_operation(|| {
+    let code_str = safe_cstr_to_string(code)?;
+    let language_str = safe_cstr_to_string(language)?;
+
+    let theme_str = theme_or_default(theme)?;
+
+    let config = safe_parse_config(config_json)?;
+
+    let mut renderer = SnippetRenderer::new(&theme_str, config)?;
+    let images = renderer.render_carousel(&code_str, &language_str)?;
+    let images_json =
+      serde_json::to_string(&images).map_err(|e| anyhow!("Failed to serialize pages: {}", e))?;
+    Ok(images_json)
+  })
+}
+
+/// Render several files as tabs from a JSON array of `{title, code, language}`
+pub fn safe_generate_tabbed_image(
+  files_json: *const c_char,
+  theme: *const c_char,
+  config_json: *const c_char,
+) -> *mut c_char {
+  safe_ffi_operation(|| {
+    let files_str = safe_cstr_to_string(files_json)?;
+    let files: Vec<TabFile> =
+      serde_json::from_str(&files_str).map_err(|e| anyhow!("Invalid tab files: {}", e))?;
+
+    let theme_str = theme_or_default(theme)?;
+
+    let config = safe_parse_config(config_json)?;
+
+    let mut renderer = SnippetRenderer::new(&theme_str, config)?;
+    renderer.render_tabs(&files)
+  })
+}
+
+/// Write a snippet as a one-page PDF to `output_path` with safe error handling
+pub fn safe_export_snippet_pdf(
+  code: *const c_char,
+  language: *const c_char,
+  theme: *const c_char,
+  config_json: *const c_char,
+  output_path: *const c_char,
+) -> *mut c_char {
+  safe_ffi_operation(|| {
+    let code_str = safe_cstr_to_string(code)?;
+    let language_str = safe_cstr_to_string(language)?;
+    let output_str = safe_cstr_to_string(output_path)?;
+
+    let theme_str = theme_or_default(theme)?;
+
+    let config = safe_parse_config(config_json)?;
+
+    let mut renderer = SnippetRenderer::new(&theme_str, config)?;
+    let pdf_data = renderer.render_snippet_pdf(&code_str, &language_str)?;
+    std::fs::write(&output_str, pdf_data)
+      .map_err(|e| anyhow!("Failed to write {}: {}", output_str, e))?;
+    Ok(output_str)
+  })
+}
+
+/// Encode a scrolling video of a snippet to `output_path` with safe error handling
+pub fn safe_export_scrolling_video(
+  code: *const c_char,
+  language: *const c_char,
+  theme: *const c_char,
+  config_json: *const c_char,
+  output_path: *const c_char,
+) -> *mut c_char {
+  safe_ffi_operation(|| {
+    let code_str = safe_cstr_to_string(code)?;
+    let language_str = safe_cstr_to_string(language)?;
+    let output_str = safe_cstr_to_string(output_path)?;
+
+    let theme_str = theme_or_default(theme)?;
+
+    let config = safe_parse_config(config_json)?;
+
+    let mut renderer = SnippetRenderer::new(&theme_str, config)?;
+    animation::export_scrolling_video(
+      &mut renderer,
+      &code_str,
+      &language_str,
+      Path::new(&output_str),
+    )?;
+    Ok(output_str)
+  })
+}
+
+/// Write numbered PNG frames of an animation into `output_dir` with safe error handling
+pub fn safe_export_animation_frames(
+  code: *const c_char,
+  language: *const c_char,
+  theme: *const c_char,
+  config_json: *const c_char,
+  spec_json: *const c_char,
+  output_dir: *const c_char,
+) -> *mut c_char {
+  safe_ffi_operation(|| {
+    let code_str = safe_cstr_to_string(code)?;
+    let language_str = safe_cstr_to_string(language)?;
+    let output_str = safe_cstr_to_string(output_dir)?;
+
+    let theme_str = theme_or_default(theme)?;
+
+    let config = safe_parse_config(config_json)?;
+    let spec = if spec_json.is_null() {
+      AnimationSpec::Typing(TypingSpec::default())
+    } else {
+      let spec_str = safe_cstr_to_string(spec_json)?;
+      serde_json::from_str(&spec_str)
+        .map_err(|e| anyhow!("Failed to parse animation spec: {}", e))?
+    };
+
+    let mut renderer = SnippetRenderer::new(&theme_str, config)?;
+    let frames =
+      animation::render_animation_frames(&mut renderer, &code_str, &language_str, &spec)?;
+    let sequence = animation::write_frames_to_dir(frames, Path::new(&output_str))?;
+    let sequence_json = serde_json::to_string(&sequence)
+      .map_err(|e| anyhow!("Failed to serialize frame sequence: {}", e))?;
+    Ok(sequence_json)
+  })
+}
+
+/// Encode a GIF or APNG of a snippet being typed out with safe error handling
+pub fn safe_export_typing_animation(
+  code: *const c_char,
+  language: *const c_char,
+  theme: *const c_char,
+  config_json: *const c_char,
+  spec_json: *const c_char,
+  output_path: *const c_char,
+) -> *mut c_char {
+  safe_ffi_operation(|| {
+    let code_str = safe_cstr_to_string(code)?;
+    let language_str = safe_cstr_to_string(language)?;
+    let output_str = safe_cstr_to_string(output_path)?;
+
+    let theme_str = theme_or_default(theme)?;
+
+    let config = safe_parse_config(config_json)?;
+    let spec: TypingSpec = if spec_json.is_null() {
+      TypingSpec::default()
+    } else {
+      let spec_str = safe_cstr_to_string(spec_json)?;
+      serde_json::from_str(&spec_str).map_err(|e| anyhow!("Failed to parse typing spec: {}", e))?
+    };
+
+    let fps = spec.fps;
+    let mut renderer = SnippetRenderer::new(&theme_str, config)?;
+    let frames = animation::render_animation_frames(
+      &mut renderer,
+      &code_str,
+      &language_str,
+      &AnimationSpec::Typing(spec),
+    )?;
+    animation::write_animated_image(frames, Path::new(&output_str), fps)?;
+    Ok(output_str)
+  })
+}
+
+/// Encode a GIF morphing a snippet from one theme into another with safe error handling
+pub fn safe_export_theme_transition(
+  code: *const c_char,
+  language: *const c_char,
+  from_theme: *const c_char,
+  to_theme: *const c_char,
+  config_json: *const c_char,
+  output_path: *const c_char,
+) -> *mut c_char {
+  safe_ffi_operation(|| {
+    let code_str = safe_cstr_to_string(code)?;
+    let language_str = safe_cstr_to_string(language)?;
+    let from_str = safe_cstr_to_string(from_theme)?;
+    let to_str = safe_cstr_to_string(to_theme)?;
+    let output_str = safe_cstr_to_string(output_path)?;
+    let config = safe_parse_config(config_json)?;
+
+    let pacing = config.transition;
+    let spec = TransitionSpec {
+      to: to_str,
+      frames: pacing.frames,
+      hold_frames: pacing.hold_frames,
+      fps: pacing.fps,
+    };
+    let fps = spec.fps;
+    let mut renderer = SnippetRenderer::new(&from_str, config)?;
+    let frames = animation::render_animation_frames(
+      &mut renderer,
+      &code_str,
+      &language_str,
+      &AnimationSpec::Transition(spec),
+    )?;
+    animation::write_gif(frames, Path::new(&output_str), fps)?;
+    Ok(output_str)
+  })
+}
+
+/// Measure a snippet's output geometry without rendering it
+pub fn safe_measure_snippet(
+  code: *const c_char,
+  language: *const c_char,
+  config_json: *const c_char,
+) -> *mut c_char {
+  safe_ffi_operation(|| {
+    let code_str = safe_cstr_to_string(code)?;
+    let language_str = safe_cstr_to_string(language)?;
+    let config = safe_parse_config(config_json)?;
+
+    // Geometry only depends on the font, so any theme will do
+    let renderer = SnippetRenderer::new("dracula", config)?;
+    let metrics = renderer.measure_snippet(&code_str, &language_str);
+    let metrics_json =
+      serde_json::to_string(&metrics).map_err(|e| anyhow!("Failed to serialize metrics: {}", e))?;
+    Ok(metrics_json)
+  })
+}
+
+/// List the highlighted tokens of a snippet with their scopes and colors
+pub fn safe_dump_tokens(
+  code: *const c_char,
+  language: *const c_char,
+  theme: *const c_char,
+  config_json: *const c_char,
+) -> *mut c_char {
+  safe_ffi_operation(|| {
+    let code_str = safe_cstr_to_string(code)?;
+    let language_str = safe_cstr_to_string(language)?;
+
+    let theme_str = theme_or_default(theme)?;
+
+    let config = safe_parse_config(config_json)?;
+
+    let renderer = SnippetRenderer::new(&theme_str, config)?;
+    let dump = renderer.dump_tokens(&code_str, &language_str);
+    let dump_json =
+      serde_json::to_string(&dump).map_err(|e| anyhow!("Failed to serialize tokens: {}", e))?;
+    Ok(dump_json)
+  })
+}
+
+/// Highlight code with the default config and return its tokens per line as JSON
+pub fn safe_highlight_code(
+  code: *const c_char,
+  language: *const c_char,
+  theme: *const c_char,
+) -> *mut c_char {
+  safe_ffi_operation(|| {
+    let code_str = safe_cstr_to_string(code)?;
+    let language_str = safe_cstr_to_string(language)?;
+
+    let theme_str = theme_or_default(theme)?;
+
+    let renderer = SnippetRenderer::new(&theme_str, RenderConfig::default())?;
+    let lines = renderer.highlight_tokens(&code_str, &language_str);
+    serde_json::to_string(&lines).map_err(|e| anyhow!("Failed to serialize tokens: {}", e))
+  })
+}
+
+/// Start an incremental highlight of `code`; 0 when it cannot be opened
+pub fn safe_open_highlight_stream(
+  code: *const c_char,
+  language: *const c_char,
+  theme: *const c_char,
+  config_json: *const c_char,
+) -> c_uint {
+  let result = panic::catch_unwind(|| -> Result<u32> {
+    let code_str = safe_cstr_to_string(code)?;
+    let language_str = safe_cstr_to_string(language)?;
+    let theme_str = theme_or_default(theme)?;
+    let config = safe_parse_config(config_json)?;
+    syntax::stream::open(&code_str, &language_str, &theme_str, &config)
+  });
+
+  match result {
+    Ok(Ok(handle)) => handle,
+    Ok(Err(e)) => {
+      warnings::push(format!("Highlight stream not opened: {}", e));
+      0
+    }
+    Err(_) => 0,
+  }
+}
+
+/// Replace lines `start..end` of a stream with `text`, or remove them when it is null
+pub fn safe_edit_highlight_stream(
+  handle: c_uint,
+  start: c_uint,
+  end: c_uint,
+  text: *const c_char,
+) -> c_int {
+  let result = panic::catch_unwind(|| -> Result<()> {
+    let text_str = match text.is_null() {
+      true => None,
+      false => Some(safe_cstr_to_string(text)?),
+    };
+    syntax::stream::edit(handle, start as usize, end as usize, text_str.as_deref())
+  });
+
+  match result {
+    Ok(Ok(())) => 1,
+    Ok(Err(e)) => {
+      warnings::push(format!("Highlight stream edit rejected: {}", e));
+      0
+    }
+    Err(_) => 0,
+  }
+}
+
+/// Highlight up to `max_lines` queued lines of a stream as JSON
+pub fn safe_poll_highlight_stream(handle: c_uint, max_lines: c_uint) -> *mut c_char {
+  safe_ffi_operation(|| {
+    let update = syntax::stream::poll(handle, max_lines as usize)?;
+    let update_json = serde_json::to_string(&update)
+      .map_err(|e| anyhow!("Failed to serialize highlighted lines: {}", e))?;
+    Ok(update_json)
   })
 }
 
+/// Drop a stream; 1 if it was open
+pub fn safe_close_highlight_stream(handle: c_uint) -> c_int {
+  match panic::catch_unwind(|| syntax::stream::close(handle)) {
+    Ok(true) => 1,
+    _ => 0,
+  }
+}
+
 /// Get available themes with safe error handling
 pub fn safe_get_available_themes() -> *mut c_char {
   safe_ffi_operation(|| {
@@ -90,6 +530,245 @@ pub fn safe_get_available_themes() -> *mut c_char {
   })
 }
 
+/// Get every color of one theme with safe error handling
+pub fn safe_get_theme_details(name: *const c_char) -> *mut c_char {
+  safe_ffi_operation(|| {
+    let name_str = safe_cstr_to_string(name)?;
+    let theme =
+      themes::get_theme(&name_str).ok_or_else(|| anyhow!("Unknown theme: {}", name_str))?;
+    let theme_json =
+      serde_json::to_string(&theme).map_err(|e| anyhow!("Failed to serialize theme: {}", e))?;
+    Ok(theme_json)
+  })
+}
+
+/// Check a theme's text contrast on `background`, or its own background
+/// when null, with safe error handling
+pub fn safe_validate_theme(name: *const c_char, background: *const c_char) -> *mut c_char {
+  safe_ffi_operation(|| {
+    let name_str = safe_cstr_to_string(name)?;
+    let background = match background.is_null() {
+      true => None,
+      false => {
+        let hex = safe_cstr_to_string(background)?;
+        Some(
+          themes::ThemeColor::parse(&hex)
+            .map_err(|e| anyhow!("Invalid background '{}': {}", hex, e))?,
+        )
+      }
+    };
+
+    let theme =
+      themes::get_theme(&name_str).ok_or_else(|| anyhow!("Unknown theme: {}", name_str))?;
+    let report = theme.contrast_report(background.as_ref());
+    let report_json =
+      serde_json::to_string(&report).map_err(|e| anyhow!("Failed to serialize report: {}", e))?;
+    Ok(report_json)
+  })
+}
+
+/// Build a theme from Neovim highlight groups, registering it when `name`
+/// is given, with safe error handling
+pub fn safe_theme_from_highlights(groups_json: *const c_char, name: *const c_char) -> *mut c_char {
+  safe_ffi_operation(|| {
+    let groups_str = safe_cstr_to_string(groups_json)?;
+    let groups: BTreeMap<String, themes::HighlightGroup> = serde_json::from_str(&groups_str)
+      .map_err(|e| anyhow!("Failed to parse highlight groups: {}", e))?;
+    let name_str = match name.is_null() {
+      true => None,
+      false => Some(safe_cstr_to_string(name)?),
+    };
+
+    let theme = themes::theme_from_highlights(name_str.as_deref().unwrap_or("Neovim"), &groups)?;
+    if let Some(name) = &name_str {
+      themes::register_theme(name, theme.clone())?;
+    }
+    let theme_json =
+      serde_json::to_string(&theme).map_err(|e| anyhow!("Failed to serialize theme: {}", e))?;
+    Ok(theme_json)
+  })
+}
+
+/// Load a VS Code color theme file, registering it when `name` is given,
+/// with safe error handling
+pub fn safe_import_vscode_theme(path: *const c_char, name: *const c_char) -> *mut c_char {
+  safe_ffi_operation(|| {
+    let path_str = safe_cstr_to_string(path)?;
+    let name_str = match name.is_null() {
+      true => None,
+      false => Some(safe_cstr_to_string(name)?),
+    };
+
+    let theme = themes::load_vscode_theme(Path::new(&path_str))?;
+    if let Some(name) = &name_str {
+      themes::register_theme(name, theme.clone())?;
+    }
+    let theme_json =
+      serde_json::to_string(&theme).map_err(|e| anyhow!("Failed to serialize theme: {}", e))?;
+    Ok(theme_json)
+  })
+}
+
+/// Load a base16 or base24 scheme file, registering it when `name` is
+/// given, with safe error handling
+pub fn safe_import_base16_scheme(path: *const c_char, name: *const c_char) -> *mut c_char {
+  safe_ffi_operation(|| {
+    let path_str = safe_cstr_to_string(path)?;
+    let name_str = match name.is_null() {
+      true => None,
+      false => Some(safe_cstr_to_string(name)?),
+    };
+
+    let theme = themes::load_base16_scheme(Path::new(&path_str))?;
+    if let Some(name) = &name_str {
+      themes::register_theme(name, theme.clone())?;
+    }
+    let theme_json =
+      serde_json::to_string(&theme).map_err(|e| anyhow!("Failed to serialize theme: {}", e))?;
+    Ok(theme_json)
+  })
+}
+
+/// Rescan the themes directory, `dir` when given, with safe error handling
+pub fn safe_reload_themes(dir: *const c_char) -> *mut c_char {
+  safe_ffi_operation(|| {
+    let dir_str = match dir.is_null() {
+      true => None,
+      false => Some(safe_cstr_to_string(dir)?),
+    };
+
+    let names = themes::reload_themes(dir_str.as_deref().map(Path::new))?;
+    let names_json =
+      serde_json::to_string(&names).map_err(|e| anyhow!("Failed to serialize themes: {}", e))?;
+    Ok(names_json)
+  })
+}
+
+/// Get built-in config preset names with safe error handling
+pub fn safe_get_available_presets() -> *mut c_char {
+  safe_ffi_operation(|| {
+    let presets = RenderConfig::preset_names();
+    let presets_json =
+      serde_json::to_string(&presets).map_err(|e| anyhow!("Failed to serialize presets: {}", e))?;
+    Ok(presets_json)
+  })
+}
+
+/// Get named backdrop gradients with safe error handling
+pub fn safe_get_gradient_presets() -> *mut c_char {
+  safe_ffi_operation(|| {
+    let presets_json = serde_json::to_string(GRADIENT_PRESETS)
+      .map_err(|e| anyhow!("Failed to serialize gradient presets: {}", e))?;
+    Ok(presets_json)
+  })
+}
+
+/// Describe the build and its capabilities with safe error handling
+pub fn safe_get_build_info() -> *mut c_char {
+  safe_ffi_operation(|| {
+    let info_json = serde_json::to_string(&build_info::build_info())
+      .map_err(|e| anyhow!("Failed to serialize build info: {}", e))?;
+    Ok(info_json)
+  })
+}
+
+/// Drain queued warnings with safe error handling
+pub fn safe_get_warnings() -> *mut c_char {
+  safe_ffi_operation(|| {
+    let warnings_json = serde_json::to_string(&warnings::drain())
+      .map_err(|e| anyhow!("Failed to serialize warnings: {}", e))?;
+    Ok(warnings_json)
+  })
+}
+
+/// Render tokens captured from the editor with safe error handling
+pub fn safe_generate_image_from_tokens(
+  tokens_json: *const c_char,
+  theme: *const c_char,
+  config_json: *const c_char,
+) -> *mut c_char {
+  safe_ffi_operation(|| {
+    let tokens_str = safe_cstr_to_string(tokens_json)?;
+
+    let theme_str = theme_or_default(theme)?;
+
+    let config = safe_parse_config(config_json)?;
+
+    let mut renderer = SnippetRenderer::new(&theme_str, config)?;
+    let lines = syntax::lines_from_json(&tokens_str, renderer.theme())?;
+    let image_data = renderer.render_tokens(lines)?;
+    Ok(image_data)
+  })
+}
+
+/// Render a backdrop alone with safe error handling
+pub fn safe_generate_backdrop(
+  theme: *const c_char,
+  config_json: *const c_char,
+  width: c_uint,
+  height: c_uint,
+) -> *mut c_char {
+  safe_ffi_operation(|| {
+    let theme_str = theme_or_default(theme)?;
+
+    let config = safe_parse_config(config_json)?;
+
+    let mut renderer = SnippetRenderer::new(&theme_str, config)?;
+    let image_data = renderer.render_backdrop(width, height)?;
+    Ok(image_data)
+  })
+}
+
+/// Store or remove a named profile with safe error handling
+pub fn safe_set_profile(name: *const c_char, config_json: *const c_char) -> c_int {
+  let result = panic::catch_unwind(|| -> Result<bool> {
+    let name_str = safe_cstr_to_string(name)?;
+    if config_json.is_null() {
+      return Ok(profiles::remove(&name_str));
+    }
+    let config_str = safe_cstr_to_string(config_json)?;
+    profiles::set(&name_str, &config_str)?;
+    Ok(true)
+  });
+
+  match result {
+    Ok(Ok(true)) => 1,
+    Ok(Ok(false)) => 0,
+    Ok(Err(e)) => {
+      // The return code can't say why, so leave the reason for get_warnings
+      warnings::push(format!("Profile rejected: {}", e));
+      0
+    }
+    Err(_) => 0,
+  }
+}
+
+/// List stored profile names with safe error handling
+pub fn safe_get_profiles() -> *mut c_char {
+  safe_ffi_operation(|| {
+    let profiles_json = serde_json::to_string(&profiles::names())
+      .map_err(|e| anyhow!("Failed to serialize profiles: {}", e))?;
+    Ok(profiles_json)
+  })
+}
+
+/// Register a syntax directory with safe error handling
+pub fn safe_add_syntax_directory(path: *const c_char) -> c_int {
+  let result = panic::catch_unwind(|| -> Result<()> {
+    let path_str = safe_cstr_to_string(path)?;
+    syntax::add_syntax_directory(&path_str)
+  });
+
+  match result {
+    Ok(Ok(())) => 1,
+    Ok(Err(e)) => {
+      warnings::push(format!("Syntax directory rejected: {}", e));
+      0
+    }
+    Err(_) => 0,
+  }
+}
+
 /// Check if language is supported with safe error handling
 pub fn safe_is_language_supported(language: *const c_char) -> c_int {
   let result = panic::catch_unwind(|| -> Result<bool> {
@@ -110,11 +789,63 @@ pub fn safe_is_language_supported(language: *const c_char) -> c_int {
   }
 }
 
-/// Safe memory deallocation
+/// Update process-wide resource limits
+pub fn safe_set_resource_limits(max_concurrent: c_uint, max_pixels: u64, max_cache_mb: c_uint) {
+  limits::set_limits(max_concurrent as usize, max_pixels, max_cache_mb as u64);
+}
+
+/// Point the temp directory at `dir` and set its cleanup age, then apply it
+pub fn safe_set_temp_policy(dir: *const c_char, cleanup_older_than: c_uint) -> c_int {
+  let result = panic::catch_unwind(|| -> Result<()> {
+    let dir = match dir.is_null() {
+      true => None,
+      false => Some(safe_cstr_to_string(dir)?).filter(|dir| !dir.is_empty()),
+    };
+    tempfiles::set_policy(dir.map(PathBuf::from), cleanup_older_than as u64);
+    tempfiles::cleanup();
+    Ok(())
+  });
+
+  match result {
+    Ok(Ok(())) => 1,
+    Ok(Err(_)) => 0,
+    Err(_) => 0,
+  }
+}
+
+/// Remove staged temp files with safe error handling
+pub fn safe_purge_cache() -> *mut c_char {
+  safe_ffi_operation(|| {
+    let summary = tempfiles::purge();
+    let summary_json = serde_json::to_string(&summary)
+      .map_err(|e| anyhow!("Failed to serialize purge summary: {}", e))?;
+    Ok(summary_json)
+  })
+}
+
+/// Get resource limits and usage counters with safe error handling
+pub fn safe_get_resource_usage() -> *mut c_char {
+  safe_ffi_operation(|| {
+    let usage = limits::usage();
+    let usage_json =
+      serde_json::to_string(&usage).map_err(|e| anyhow!("Failed to serialize usage: {}", e))?;
+    Ok(usage_json)
+  })
+}
+
+/// Safe memory deallocation; unknown and already freed pointers are ignored
 pub fn safe_free_string(s: *mut c_char) {
   if s.is_null() {
     return;
   }
+  let issued = ISSUED
+    .lock()
+    .unwrap_or_else(|e| e.into_inner())
+    .remove(&(s as usize));
+  if !issued {
+    warnings::push("free_string called with a pointer that is not live");
+    return;
+  }
 
   let _ = panic::catch_unwind(|| unsafe {
     let _ = CString::from_raw(s);
@@ -171,4 +902,24 @@ mod tests {
     // Clean up
     safe_free_string(result);
   }
+
+  #[test]
+  fn test_measure_snippet() {
+    let code_cstr = CString::new("fn main() {\n    println!(\"Hello\");\n}").unwrap();
+    let language_cstr = CString::new("rust").unwrap();
+
+    let result = safe_measure_snippet(code_cstr.as_ptr(), language_cstr.as_ptr(), ptr::null());
+    assert!(!result.is_null());
+
+    let json = safe_cstr_to_string(result).unwrap();
+    safe_free_string(result);
+    let metrics: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let config = RenderConfig::default();
+    assert_eq!(metrics["line_count"], 3);
+    assert_eq!(
+      metrics["width"],
+      config.get_actual_width(0) + config.get_scaled_panel_padding().horizontal()
+    );
+    assert_eq!(metrics["overflows"], false);
+  }
 }
@@ -1,19 +1,55 @@
 /* ~~/src/syntax.rs */
 
+// standard crates
+use std::ops::BitOr;
+use std::sync::{Arc, Mutex, OnceLock};
+
 // third-party crates
-use syntect::easy::HighlightLines;
-use syntect::highlighting::{Style, ThemeSet};
-use syntect::parsing::{SyntaxReference, SyntaxSet};
+use anyhow::{Result, anyhow};
+use syntect::easy::ScopeRangeIterator;
+use syntect::highlighting::{
+  Color, FontStyle, Highlighter as SyntectHighlighter, ScopeSelectors, Style, StyleModifier,
+  ThemeItem, ThemeSettings,
+};
+use syntect::parsing::{
+  ParseState, ScopeStack, ScopeStackOp, SyntaxReference, SyntaxSet, SyntaxSetBuilder,
+};
 use syntect::util::LinesWithEndings;
 
 // local modules
-use crate::themes::{Theme, ThemeColor};
+use crate::config::HighlighterBackend;
+use crate::themes::{ScopeStyle, Theme, ThemeColor};
+pub mod ansi;
+mod editor;
+pub mod external;
+pub mod stream;
+#[cfg(feature = "tree-sitter")]
+mod treesitter;
+
+pub use self::editor::lines_from_json;
+pub use self::external::ExternalHighlighter;
+
+/// Directories registered with `add_syntax_directory`, each with the lowercase
+/// names and extensions of the syntaxes it provides
+static SYNTAX_DIRECTORIES: Mutex<Vec<(String, Vec<String>)>> = Mutex::new(Vec::new());
+/// Syntax sets already built, by the directories loaded over the bundled
+/// grammars, so repeated renders in a session skip rebuilding them
+static SYNTAX_SETS: Mutex<Vec<(Vec<String>, Arc<SyntaxSet>)>> = Mutex::new(Vec::new());
 
+/// Turns source into lines of themed tokens
+pub trait Highlighter {
+  fn highlight(&self, code: &str, language: &str, theme: &Theme) -> Vec<HighlightedLine>;
+}
+
+/// TextMate grammars through syntect, falling back to `PatternHighlighter`
+/// for languages without one
 pub struct SyntaxHighlighter {
-  syntax_set: SyntaxSet,
-  theme_set: ThemeSet,
+  syntax_set: Arc<SyntaxSet>,
 }
 
+/// Keyword and punctuation heuristics that work for any language
+pub struct PatternHighlighter;
+
 #[derive(Debug, Clone)]
 pub struct HighlightedLine {
   pub tokens: Vec<HighlightedToken>,
@@ -23,28 +59,231 @@ pub struct HighlightedLine {
 pub struct HighlightedToken {
   pub text: String,
   pub color: ThemeColor,
+  /// Fill behind the text, only set for terminal output with backgrounds
+  pub background: Option<ThemeColor>,
+  pub style: TokenStyle,
+  /// Space-separated scope stack, e.g. `source.rust comment.line.double-slash.rust`
+  pub scope: String,
+  /// Where the token starts within its line
+  pub start_col: Column,
+  /// Where the token ends within its line, exclusive
+  pub end_col: Column,
 }
 
-impl SyntaxHighlighter {
-  pub fn new() -> Self {
-    let syntax_set = SyntaxSet::load_defaults_newlines();
-    let theme_set = ThemeSet::load_defaults();
+/// Position within a line as both a byte offset into the source and a
+/// display column. Display columns count wide glyphs twice and follow tab
+/// expansion; byte offsets always point into the original source line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Column {
+  pub byte: usize,
+  pub display: usize,
+}
+
+impl Column {
+  /// Position just past `text` when it starts here
+  pub fn advance(self, text: &str) -> Self {
     Self {
-      syntax_set,
-      theme_set,
+      byte: self.byte + text.len(),
+      display: self.display + text.chars().map(display_width).sum::<usize>(),
+    }
+  }
+}
+
+/// Font style bitfield carried by each token
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenStyle(u8);
+
+impl TokenStyle {
+  pub const REGULAR: Self = Self(0);
+  pub const BOLD: Self = Self(1 << 0);
+  pub const ITALIC: Self = Self(1 << 1);
+  pub const UNDERLINE: Self = Self(1 << 2);
+
+  /// This style with the flags in `other` cleared
+  pub fn without(self, other: Self) -> Self {
+    Self(self.0 & !other.0)
+  }
+
+  pub fn contains(self, other: Self) -> bool {
+    self.0 & other.0 == other.0
+  }
+
+  pub fn is_bold(self) -> bool {
+    self.contains(Self::BOLD)
+  }
+
+  pub fn is_italic(self) -> bool {
+    self.contains(Self::ITALIC)
+  }
+
+  pub fn is_underline(self) -> bool {
+    self.contains(Self::UNDERLINE)
+  }
+}
+
+impl BitOr for TokenStyle {
+  type Output = Self;
+
+  fn bitor(self, rhs: Self) -> Self {
+    Self(self.0 | rhs.0)
+  }
+}
+
+impl From<FontStyle> for TokenStyle {
+  fn from(font_style: FontStyle) -> Self {
+    let mut style = Self::REGULAR;
+    if font_style.contains(FontStyle::BOLD) {
+      style = style | Self::BOLD;
+    }
+    if font_style.contains(FontStyle::ITALIC) {
+      style = style | Self::ITALIC;
+    }
+    if font_style.contains(FontStyle::UNDERLINE) {
+      style = style | Self::UNDERLINE;
+    }
+    style
+  }
+}
+
+impl From<&ScopeStyle> for TokenStyle {
+  fn from(rule: &ScopeStyle) -> Self {
+    let mut style = Self::REGULAR;
+    if rule.bold {
+      style = style | Self::BOLD;
+    }
+    if rule.italic {
+      style = style | Self::ITALIC;
+    }
+    if rule.underline {
+      style = style | Self::UNDERLINE;
+    }
+    style
+  }
+}
+
+impl From<TokenStyle> for FontStyle {
+  fn from(style: TokenStyle) -> Self {
+    let mut font_style = FontStyle::empty();
+    if style.is_bold() {
+      font_style |= FontStyle::BOLD;
+    }
+    if style.is_italic() {
+      font_style |= FontStyle::ITALIC;
     }
+    if style.is_underline() {
+      font_style |= FontStyle::UNDERLINE;
+    }
+    font_style
+  }
+}
+
+impl SyntaxHighlighter {
+  /// Bundled syntaxes plus `.sublime-syntax` files from every registered
+  /// directory and `extra_dirs`
+  pub fn new(extra_dirs: &[String]) -> Result<Self> {
+    let mut dirs: Vec<String> = SYNTAX_DIRECTORIES
+      .lock()
+      .unwrap_or_else(|e| e.into_inner())
+      .iter()
+      .map(|(dir, _)| dir.clone())
+      .collect();
+    for dir in extra_dirs {
+      if !dirs.contains(dir) {
+        dirs.push(dir.clone());
+      }
+    }
+
+    let cached = SYNTAX_SETS
+      .lock()
+      .unwrap_or_else(|e| e.into_inner())
+      .iter()
+      .find(|(loaded, _)| *loaded == dirs)
+      .map(|(_, syntax_set)| syntax_set.clone());
+    if let Some(syntax_set) = cached {
+      return Ok(Self { syntax_set });
+    }
+
+    let syntax_set = match dirs.is_empty() {
+      true => bundled_syntaxes().clone(),
+      false => {
+        let mut builder = bundled_syntaxes().clone().into_builder();
+        for dir in &dirs {
+          add_syntax_folder(&mut builder, dir)?;
+        }
+        builder.build()
+      }
+    };
+    let syntax_set = Arc::new(syntax_set);
+    SYNTAX_SETS
+      .lock()
+      .unwrap_or_else(|e| e.into_inner())
+      .push((dirs, syntax_set.clone()));
+    Ok(Self { syntax_set })
   }
 
   pub fn highlight_code(&self, code: &str, language: &str, theme: &Theme) -> Vec<HighlightedLine> {
     // Try to use syntect for advanced highlighting
     if let Some(syntax) = self.find_syntax_by_language(language) {
-      if let Some(syntect_theme) = self.theme_set.themes.get("base16-ocean.dark") {
-        return self.highlight_with_syntect(code, syntax, syntect_theme, theme);
-      }
+      return self.highlight_with_syntect(code, syntax, &syntect_theme(theme), theme);
     }
 
     // Fallback to pattern-based highlighting
-    self.highlight_with_patterns(code, theme)
+    PatternHighlighter.highlight(code, language, theme)
+  }
+
+  /// Highlight with `backend`, using syntect for languages it has no grammar for.
+  /// `External` needs a command to run, so it is highlighted with syntect
+  /// here; see `ExternalHighlighter`.
+  pub fn highlight_code_with(
+    &self,
+    code: &str,
+    language: &str,
+    theme: &Theme,
+    backend: HighlighterBackend,
+  ) -> Vec<HighlightedLine> {
+    #[cfg(feature = "tree-sitter")]
+    if backend == HighlighterBackend::TreeSitter
+      && let Some(lines) = treesitter::highlight(code, language, theme)
+    {
+      return lines;
+    }
+    #[cfg(not(feature = "tree-sitter"))]
+    let _ = backend;
+    self.highlight_code(code, language, theme)
+  }
+
+  /// Names of every grammar loaded, sorted
+  #[cfg(feature = "cli")]
+  pub fn language_names(&self) -> Vec<String> {
+    let mut names: Vec<String> = self
+      .syntax_set
+      .syntaxes()
+      .iter()
+      .map(|syntax| syntax.name.clone())
+      .collect();
+    names.sort_by_key(|name| name.to_lowercase());
+    names.dedup();
+    names
+  }
+
+  /// Name of the highlighter `highlight_code_with` uses for `language`
+  pub fn backend_name(&self, language: &str, backend: HighlighterBackend) -> &'static str {
+    if backend == HighlighterBackend::External {
+      return "external";
+    }
+    if language.eq_ignore_ascii_case("ansi") {
+      return "ansi";
+    }
+    #[cfg(feature = "tree-sitter")]
+    if backend == HighlighterBackend::TreeSitter && treesitter::supports(language) {
+      return "tree-sitter";
+    }
+    #[cfg(not(feature = "tree-sitter"))]
+    let _ = backend;
+    match self.find_syntax_by_language(language) {
+      Some(_) => "syntect",
+      None => "patterns",
+    }
   }
 
   fn find_syntax_by_language(&self, language: &str) -> Option<&SyntaxReference> {
@@ -76,7 +315,7 @@ impl SyntaxHighlighter {
       "scala" => "Scala",
       "lua" => "Lua",
       "vim" => "VimL",
-      _ => return self.syntax_set.find_syntax_by_extension(&lang),
+      _ => return self.syntax_set.find_syntax_by_token(&lang),
     };
 
     self
@@ -92,31 +331,81 @@ impl SyntaxHighlighter {
     syntect_theme: &syntect::highlighting::Theme,
     theme: &Theme,
   ) -> Vec<HighlightedLine> {
-    let mut lines = Vec::new();
-    let mut highlighter = HighlightLines::new(syntax, syntect_theme);
-
-    for line in LinesWithEndings::from(code) {
-      let ranges = highlighter
-        .highlight_line(line, &self.syntax_set)
-        .unwrap_or_default();
-      let mut tokens = Vec::new();
-
-      for (style, text) in ranges {
-        let color = self.convert_syntect_style_to_theme_color(style, theme);
-        // Strip newline characters to prevent tofu glyphs
-        let clean_text = text.replace('\n', "").replace('\r', "");
-        if !clean_text.is_empty() {
-          tokens.push(HighlightedToken {
-            text: clean_text,
-            color,
-          });
-        }
-      }
+    let highlighter = SyntectHighlighter::new(syntect_theme);
+    let mut parse_state = ParseState::new(syntax);
+    let mut scope_stack = ScopeStack::new();
+    LinesWithEndings::from(code)
+      .map(|line| {
+        self.highlight_syntect_line(
+          line,
+          &mut parse_state,
+          &mut scope_stack,
+          &highlighter,
+          theme,
+        )
+      })
+      .collect()
+  }
 
-      lines.push(HighlightedLine { tokens });
-    }
+  /// Highlight one line, newline included, carrying the parser state over
+  /// to the next
+  fn highlight_syntect_line(
+    &self,
+    line: &str,
+    parse_state: &mut ParseState,
+    scope_stack: &mut ScopeStack,
+    highlighter: &SyntectHighlighter,
+    theme: &Theme,
+  ) -> HighlightedLine {
+    let ops = parse_state
+      .parse_line(line, &self.syntax_set)
+      .unwrap_or_default();
+    self.tokens_from_ops(line, &ops, scope_stack, highlighter, theme)
+  }
 
-    lines
+  /// Tokens of `line` styled by the scope stack as `ops` change it
+  fn tokens_from_ops(
+    &self,
+    line: &str,
+    ops: &[(usize, ScopeStackOp)],
+    scope_stack: &mut ScopeStack,
+    highlighter: &SyntectHighlighter,
+    theme: &Theme,
+  ) -> HighlightedLine {
+    let mut tokens = Vec::new();
+    let mut display = 0;
+
+    // Each op applies to the stack before the range that follows it. An op
+    // that fails leaves the stack, and so the style, as it was; the text is
+    // drawn regardless so nothing goes missing and columns stay aligned.
+    for (range, op) in ScopeRangeIterator::new(ops, line) {
+      let _ = scope_stack.apply(op);
+      if range.is_empty() {
+        continue;
+      }
+      let style = highlighter.style_for_stack(scope_stack.as_slice());
+      let color = self.convert_syntect_style_to_theme_color(style, theme);
+      // Strip newline characters to prevent tofu glyphs
+      let start_col = Column {
+        byte: range.start,
+        display,
+      };
+      let clean_text = line[range].replace(['\n', '\r'], "");
+      if !clean_text.is_empty() {
+        let end_col = start_col.advance(&clean_text);
+        display = end_col.display;
+        tokens.push(HighlightedToken {
+          text: clean_text,
+          color,
+          background: None,
+          style: style.font_style.into(),
+          scope: scope_stack.to_string().trim_end().to_string(),
+          start_col,
+          end_col,
+        });
+      }
+    }
+    HighlightedLine { tokens }
   }
 
   fn convert_syntect_style_to_theme_color(&self, style: Style, _theme: &Theme) -> ThemeColor {
@@ -124,21 +413,41 @@ impl SyntaxHighlighter {
     let hex = format!("#{:02x}{:02x}{:02x}", fg.r, fg.g, fg.b);
     ThemeColor::new(&hex)
   }
+}
 
-  fn highlight_with_patterns(&self, code: &str, theme: &Theme) -> Vec<HighlightedLine> {
-    let mut lines = Vec::new();
-
-    for line in code.lines() {
-      let highlighted_line = self.highlight_line(line, theme);
-      lines.push(highlighted_line);
-    }
+impl Highlighter for SyntaxHighlighter {
+  fn highlight(&self, code: &str, language: &str, theme: &Theme) -> Vec<HighlightedLine> {
+    self.highlight_code(code, language, theme)
+  }
+}
 
-    lines
+impl Highlighter for PatternHighlighter {
+  fn highlight(&self, code: &str, _language: &str, theme: &Theme) -> Vec<HighlightedLine> {
+    code
+      .lines()
+      .map(|line| self.highlight_line(line, theme))
+      .collect()
   }
+}
 
-  fn highlight_line(&self, line: &str, theme: &Theme) -> HighlightedLine {
+impl PatternHighlighter {
+  pub(crate) fn highlight_line(&self, line: &str, theme: &Theme) -> HighlightedLine {
     let mut tokens = Vec::new();
-    let _current_pos = 0;
+    let mut column = Column::default();
+    // Pattern tokens tile the line, so each one starts where the last ended
+    let mut push = |text: String, color: ThemeColor, scope: &str| {
+      let end_col = column.advance(&text);
+      tokens.push(HighlightedToken {
+        text,
+        color,
+        background: None,
+        style: TokenStyle::REGULAR,
+        scope: scope.to_string(),
+        start_col: column,
+        end_col,
+      });
+      column = end_col;
+    };
 
     // Simple tokenization by splitting on whitespace and common delimiters
     let chars: Vec<char> = line.chars().collect();
@@ -153,10 +462,11 @@ impl SyntaxHighlighter {
       }
 
       if i > start {
-        tokens.push(HighlightedToken {
-          text: chars[start..i].iter().collect(),
-          color: theme.foreground.clone(),
-        });
+        push(
+          chars[start..i].iter().collect(),
+          theme.foreground.clone(),
+          "source",
+        );
         continue;
       }
 
@@ -178,19 +488,21 @@ impl SyntaxHighlighter {
           i += 1; // Include closing quote
         }
 
-        tokens.push(HighlightedToken {
-          text: chars[string_start..i].iter().collect(),
-          color: theme.string.clone(),
-        });
+        push(
+          chars[string_start..i].iter().collect(),
+          theme.string.clone(),
+          "source string",
+        );
         continue;
       }
 
       // Handle comments
       if i + 1 < chars.len() && chars[i] == '/' && chars[i + 1] == '/' {
-        tokens.push(HighlightedToken {
-          text: chars[i..].iter().collect(),
-          color: theme.comment.clone(),
-        });
+        push(
+          chars[i..].iter().collect(),
+          theme.comment.clone(),
+          "source comment",
+        );
         break;
       }
 
@@ -208,18 +520,16 @@ impl SyntaxHighlighter {
 
       if i > token_start {
         let token_text: String = chars[token_start..i].iter().collect();
-        let color = self.determine_color_by_pattern(&token_text, theme);
+        let (color, scope) = self.determine_color_by_pattern(&token_text, theme);
 
-        tokens.push(HighlightedToken {
-          text: token_text,
-          color,
-        });
+        push(token_text, color, scope);
       } else if i < chars.len() {
         // Single character tokens
-        tokens.push(HighlightedToken {
-          text: chars[i].to_string(),
-          color: theme.punctuation.clone(),
-        });
+        push(
+          chars[i].to_string(),
+          theme.punctuation.clone(),
+          "source punctuation",
+        );
         i += 1;
       }
     }
@@ -227,22 +537,197 @@ impl SyntaxHighlighter {
     HighlightedLine { tokens }
   }
 
-  fn determine_color_by_pattern(&self, text: &str, theme: &Theme) -> ThemeColor {
+  /// Pick a color and the closest TextMate scope stack for a bare word
+  fn determine_color_by_pattern(&self, text: &str, theme: &Theme) -> (ThemeColor, &'static str) {
     // Pattern-based highlighting
     if text.parse::<f64>().is_ok() {
-      theme.number.clone()
+      (theme.number.clone(), "source constant.numeric")
     } else if is_keyword(text) {
-      theme.keyword.clone()
+      (theme.keyword.clone(), "source keyword")
     } else if is_type(text) {
-      theme.type_color.clone()
+      (theme.type_color.clone(), "source storage.type")
     } else if text.chars().all(|c| c.is_uppercase() || c == '_') && text.len() > 1 {
-      theme.constant.clone()
+      (theme.constant.clone(), "source constant")
     } else {
-      theme.foreground.clone()
+      (theme.foreground.clone(), "source")
     }
   }
 }
 
+/// TextMate scope selectors for each palette slot; syntect styles a token
+/// from the most specific selector matching its scope stack
+fn theme_scopes(theme: &Theme) -> [(&'static str, &ThemeColor); 14] {
+  [
+    ("comment, punctuation.definition.comment", &theme.comment),
+    ("string, punctuation.definition.string", &theme.string),
+    ("constant.numeric", &theme.number),
+    ("constant, support.constant", &theme.constant),
+    ("keyword, storage, entity.name.tag", &theme.keyword),
+    ("keyword.operator", &theme.operator),
+    (
+      "storage.type, support.type, entity.name.type",
+      &theme.type_color,
+    ),
+    (
+      "entity.name.class, entity.name.struct, entity.other.inherited-class, support.class",
+      &theme.class,
+    ),
+    (
+      "entity.name.function, support.function, meta.function-call, entity.other.attribute-name",
+      &theme.function,
+    ),
+    ("variable, variable.parameter", &theme.variable),
+    ("punctuation", &theme.punctuation),
+    ("markup.heading, entity.name.section", &theme.keyword),
+    ("markup.raw, markup.quote", &theme.string),
+    (
+      "markup.underline.link, markup.bold, markup.italic",
+      &theme.constant,
+    ),
+  ]
+}
+
+/// The palette selector that colors a space-separated scope stack, picked
+/// the way syntect picks it, or `None` when the theme foreground applies
+pub fn palette_rule(theme: &Theme, scope: &str) -> Option<&'static str> {
+  let stack = scope.parse::<ScopeStack>().ok()?;
+  theme_scopes(theme)
+    .into_iter()
+    .filter_map(|(selector, _)| {
+      let selectors = selector.parse::<ScopeSelectors>().ok()?;
+      Some((selectors.does_match(stack.as_slice())?, selector))
+    })
+    .max_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+    .map(|(_, selector)| selector)
+}
+
+/// Font styles for markup emphasis followed by the theme's own, with
+/// unparsable selectors skipped
+fn scope_styles(theme: &Theme) -> Vec<(ScopeSelectors, TokenStyle)> {
+  let markup = [
+    ("markup.bold", TokenStyle::BOLD),
+    ("markup.italic", TokenStyle::ITALIC),
+    ("markup.underline", TokenStyle::UNDERLINE),
+  ];
+  markup
+    .into_iter()
+    .chain(
+      theme
+        .scope_styles
+        .iter()
+        .map(|rule| (rule.scope.as_str(), rule.into())),
+    )
+    .filter_map(|(selector, style)| Some((selector.parse().ok()?, style)))
+    .collect()
+}
+
+/// Every style whose selector matches a space-separated scope stack, combined
+#[cfg(feature = "tree-sitter")]
+fn style_for_scope(styles: &[(ScopeSelectors, TokenStyle)], scope: &str) -> TokenStyle {
+  let Ok(stack) = scope.parse::<ScopeStack>() else {
+    return TokenStyle::REGULAR;
+  };
+  styles
+    .iter()
+    .filter(|(selectors, _)| selectors.does_match(stack.as_slice()).is_some())
+    .fold(TokenStyle::REGULAR, |style, (_, matched)| style | *matched)
+}
+
+/// A syntect theme carrying `theme`'s palette, so token colors follow the
+/// selected shoyu theme
+fn syntect_theme(theme: &Theme) -> syntect::highlighting::Theme {
+  let color = |color: &ThemeColor| {
+    let (r, g, b) = color.rgb;
+    Color { r, g, b, a: 0xff }
+  };
+  let colors = theme_scopes(theme)
+    .into_iter()
+    .filter_map(|(selector, slot)| {
+      Some(ThemeItem {
+        scope: selector.parse::<ScopeSelectors>().ok()?,
+        style: StyleModifier {
+          foreground: Some(color(slot)),
+          background: None,
+          font_style: None,
+        },
+      })
+    });
+  let styles = scope_styles(theme)
+    .into_iter()
+    .map(|(scope, style)| ThemeItem {
+      scope,
+      style: StyleModifier {
+        foreground: None,
+        background: None,
+        font_style: Some(style.into()),
+      },
+    });
+  let scopes = colors.chain(styles).collect();
+  syntect::highlighting::Theme {
+    name: Some(theme.name.clone()),
+    settings: ThemeSettings {
+      foreground: Some(color(&theme.foreground)),
+      background: Some(color(&theme.background)),
+      ..Default::default()
+    },
+    scopes,
+    ..Default::default()
+  }
+}
+
+/// Replace tabs with spaces up to the next multiple of `tab_width`, counting
+/// columns across token boundaries so alignment survives highlighting
+pub fn expand_tabs(lines: &mut [HighlightedLine], tab_width: u32) {
+  let tab_width = tab_width.max(1) as usize;
+  for line in lines {
+    let mut column = 0;
+    for token in &mut line.tokens {
+      token.start_col.display = column;
+      if !token.text.contains('\t') {
+        column += token.text.chars().map(display_width).sum::<usize>();
+        token.end_col.display = column;
+        continue;
+      }
+      let mut expanded = String::with_capacity(token.text.len() + tab_width);
+      for ch in token.text.chars() {
+        if ch == '\t' {
+          let spaces = tab_width - column % tab_width;
+          expanded.extend(std::iter::repeat_n(' ', spaces));
+          column += spaces;
+        } else {
+          expanded.push(ch);
+          column += display_width(ch);
+        }
+      }
+      token.text = expanded;
+      token.end_col.display = column;
+    }
+  }
+}
+
+/// Terminal cells a character occupies: two for East Asian wide and
+/// fullwidth forms, none for combining marks and zero-width joiners
+pub fn display_width(ch: char) -> usize {
+  match ch as u32 {
+    0x0300..=0x036f | 0x200b..=0x200f | 0xfe00..=0xfe0f => 0,
+    0x1100..=0x115f
+    | 0x2e80..=0x303e
+    | 0x3041..=0x33ff
+    | 0x3400..=0x4dbf
+    | 0x4e00..=0x9fff
+    | 0xa000..=0xa4cf
+    | 0xac00..=0xd7a3
+    | 0xf900..=0xfaff
+    | 0xfe30..=0xfe4f
+    | 0xff00..=0xff60
+    | 0xffe0..=0xffe6
+    | 0x1f300..=0x1f64f
+    | 0x1f900..=0x1f9ff
+    | 0x20000..=0x3fffd => 2,
+    _ => 1,
+  }
+}
+
 fn is_keyword(text: &str) -> bool {
   matches!(
     text,
@@ -330,53 +815,269 @@ fn is_type(text: &str) -> bool {
   )
 }
 
+/// Grammars shipped with syntect, or just plain text when built without the
+/// `bundled-syntaxes` feature (nothing without `yaml` either), loaded on first use
+fn bundled_syntaxes() -> &'static SyntaxSet {
+  static BUNDLED: OnceLock<SyntaxSet> = OnceLock::new();
+  BUNDLED.get_or_init(|| {
+    #[cfg(feature = "bundled-syntaxes")]
+    return SyntaxSet::load_defaults_newlines();
+    #[cfg(all(not(feature = "bundled-syntaxes"), feature = "yaml"))]
+    {
+      let mut builder = SyntaxSetBuilder::new();
+      builder.add_plain_text_syntax();
+      builder.build()
+    }
+    // syntect only builds its plain text grammar with YAML loading
+    #[cfg(not(any(feature = "bundled-syntaxes", feature = "yaml")))]
+    SyntaxSet::new()
+  })
+}
+
+/// Add the `.sublime-syntax` files under `dir` to `builder`
+#[cfg(feature = "yaml")]
+fn add_syntax_folder(builder: &mut SyntaxSetBuilder, dir: &str) -> Result<()> {
+  builder
+    .add_from_folder(dir, true)
+    .map_err(|e| anyhow!("Failed to load syntaxes from '{}': {}", dir, e))
+}
+
+/// Stand-in for builds without the `yaml` feature
+#[cfg(not(feature = "yaml"))]
+fn add_syntax_folder(_: &mut SyntaxSetBuilder, dir: &str) -> Result<()> {
+  Err(anyhow!(
+    "Cannot load syntaxes from '{}': built without the yaml feature",
+    dir
+  ))
+}
+
+/// Load `.sublime-syntax` files from `path` into every highlighter created
+/// afterwards, and count their names and extensions as supported languages
+pub fn add_syntax_directory(path: &str) -> Result<()> {
+  let mut builder = SyntaxSetBuilder::new();
+  add_syntax_folder(&mut builder, path)?;
+  if builder.syntaxes().is_empty() {
+    return Err(anyhow!("No .sublime-syntax files in '{}'", path));
+  }
+  let languages = builder
+    .syntaxes()
+    .iter()
+    .flat_map(|syntax| std::iter::once(&syntax.name).chain(&syntax.file_extensions))
+    .map(|token| token.to_lowercase())
+    .collect();
+
+  let mut directories = SYNTAX_DIRECTORIES.lock().unwrap_or_else(|e| e.into_inner());
+  directories.retain(|(dir, _)| dir != path);
+  directories.push((path.to_string(), languages));
+  // Registering a directory again picks up edits to its files
+  SYNTAX_SETS
+    .lock()
+    .unwrap_or_else(|e| e.into_inner())
+    .retain(|(dirs, _)| !dirs.iter().any(|dir| dir == path));
+  Ok(())
+}
+
 pub fn is_language_supported(language: &str) -> bool {
-  matches!(
-    language.to_lowercase().as_str(),
-    "javascript"
-      | "js"
-      | "just"
-      | "justfile"
-      | "toml"
-      | "tomlfile"
-      | "typescript"
-      | "ts"
-      | "python"
-      | "py"
-      | "rust"
-      | "rs"
-      | "go"
-      | "java"
-      | "c"
-      | "cpp"
-      | "c++"
-      | "html"
-      | "css"
-      | "json"
-      | "yaml"
-      | "yml"
-      | "xml"
-      | "markdown"
-      | "md"
-      | "bash"
-      | "shell"
-      | "sh"
-      | "sql"
-      | "php"
-      | "ruby"
-      | "rb"
-      | "swift"
-      | "kotlin"
-      | "kt"
-      | "scala"
-      | "clojure"
-      | "clj"
-      | "haskell"
-      | "hs"
-      | "lua"
-      | "vim"
-      | "dockerfile"
-      | "text"
-      | "plain"
-  )
+  let language = language.to_lowercase();
+  // Detected from the code at render time
+  if language.is_empty() || language == "auto" {
+    return true;
+  }
+  let registered = SYNTAX_DIRECTORIES
+    .lock()
+    .unwrap_or_else(|e| e.into_inner())
+    .iter()
+    .any(|(_, languages)| languages.contains(&language));
+  registered
+    || matches!(
+      language.as_str(),
+      "javascript"
+        | "js"
+        | "just"
+        | "justfile"
+        | "toml"
+        | "tomlfile"
+        | "typescript"
+        | "ts"
+        | "python"
+        | "py"
+        | "rust"
+        | "rs"
+        | "go"
+        | "java"
+        | "c"
+        | "cpp"
+        | "c++"
+        | "html"
+        | "css"
+        | "json"
+        | "yaml"
+        | "yml"
+        | "xml"
+        | "markdown"
+        | "md"
+        | "bash"
+        | "shell"
+        | "sh"
+        | "sql"
+        | "php"
+        | "ruby"
+        | "rb"
+        | "swift"
+        | "kotlin"
+        | "kt"
+        | "scala"
+        | "clojure"
+        | "clj"
+        | "haskell"
+        | "hs"
+        | "lua"
+        | "vim"
+        | "dockerfile"
+        | "text"
+        | "plain"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_expand_tabs_to_stops() {
+    let theme = crate::themes::get_theme("dracula").unwrap();
+    let highlighter = SyntaxHighlighter::new(&[]).unwrap();
+    let mut lines = highlighter.highlight_code("a\tb\n\tab\tc", "text", &theme);
+    expand_tabs(&mut lines, 4);
+    let text = |line: &HighlightedLine| {
+      line
+        .tokens
+        .iter()
+        .map(|t| t.text.as_str())
+        .collect::<String>()
+    };
+    assert_eq!(text(&lines[0]), "a   b");
+    assert_eq!(text(&lines[1]), "    ab  c");
+
+    // Bytes still point into the source; display columns follow the stops
+    let last = lines[1].tokens.last().unwrap();
+    assert_eq!(last.text, "c");
+    assert_eq!(
+      last.start_col,
+      Column {
+        byte: 4,
+        display: 8
+      }
+    );
+  }
+
+  #[test]
+  fn test_token_columns() {
+    let theme = crate::themes::get_theme("dracula").unwrap();
+    let highlighter = SyntaxHighlighter::new(&[]).unwrap();
+    for language in ["rust", "text"] {
+      let lines = highlighter.highlight_code("let s = \"日本\"; // é\n", language, &theme);
+      let source = "let s = \"日本\"; // é";
+      for token in &lines[0].tokens {
+        assert_eq!(
+          &source[token.start_col.byte..token.end_col.byte],
+          token.text
+        );
+      }
+      let last = lines[0].tokens.last().unwrap();
+      assert_eq!(last.end_col.byte, source.len());
+      assert_eq!(last.end_col.display, 20);
+    }
+  }
+
+  #[test]
+  fn test_keeps_text_when_a_scope_op_fails() {
+    let theme = crate::themes::get_theme("dracula").unwrap();
+    let highlighter = SyntaxHighlighter::new(&[]).unwrap();
+    let syntect_theme = syntect_theme(&theme);
+    let scope = syntect::parsing::Scope::new("string.quoted").unwrap();
+    // Restoring with nothing cleared fails partway through the line
+    let ops = [
+      (0, ScopeStackOp::Push(scope)),
+      (2, ScopeStackOp::Restore),
+      (4, ScopeStackOp::Pop(1)),
+    ];
+    let line = highlighter.tokens_from_ops(
+      "ab\"d\"e",
+      &ops,
+      &mut ScopeStack::new(),
+      &SyntectHighlighter::new(&syntect_theme),
+      &theme,
+    );
+    let text: String = line.tokens.iter().map(|t| t.text.as_str()).collect();
+    assert_eq!(text, "ab\"d\"e");
+    assert_eq!(line.tokens[1].color.hex, line.tokens[0].color.hex);
+    assert_eq!(line.tokens.last().unwrap().end_col.byte, 6);
+  }
+
+  #[test]
+  fn test_syntect_colors_follow_theme() {
+    let highlighter = SyntaxHighlighter::new(&[]).unwrap();
+    for name in crate::themes::get_theme_names() {
+      let theme = crate::themes::get_theme(&name).unwrap();
+      let lines = highlighter.highlight_code("let s = \"hi\"; // note", "rust", &theme);
+      let color_of = |text: &str| {
+        let token = lines[0].tokens.iter().find(|t| t.text.contains(text));
+        token.unwrap().color.hex.clone()
+      };
+      assert_eq!(color_of("hi"), theme.string.hex, "{}", name);
+      assert_eq!(color_of("note"), theme.comment.hex, "{}", name);
+    }
+  }
+
+  #[test]
+  fn test_syntect_styles_follow_theme() {
+    let highlighter = SyntaxHighlighter::new(&[]).unwrap();
+    let style_of = |code: &str, language: &str, theme: &str, text: &str| {
+      let theme = crate::themes::get_theme(theme).unwrap();
+      let lines = highlighter.highlight_code(code, language, &theme);
+      let token = lines[0].tokens.iter().find(|t| t.text.contains(text));
+      token.unwrap().style
+    };
+    assert!(style_of("x = 1  # note", "python", "gruvbox", "note").is_italic());
+    assert_eq!(
+      style_of("x = 1  # note", "python", "dracula", "note"),
+      TokenStyle::REGULAR
+    );
+    let inherited = style_of("class A(Base): pass", "python", "monokai", "Base");
+    assert!(inherited.is_italic() && inherited.is_underline());
+    assert!(style_of("a **b** c", "markdown", "github", "b").is_bold());
+  }
+
+  #[test]
+  fn test_loads_syntax_directories() {
+    let dir = std::env::temp_dir().join(format!("shoyu-syntaxes-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+      dir.join("zig.sublime-syntax"),
+      "%YAML 1.2\n---\nname: Zig\nfile_extensions: [zig]\nscope: source.zig\n\
+       contexts:\n  main:\n    - match: \\b(fn|const)\\b\n      scope: keyword.zig\n",
+    )
+    .unwrap();
+    let dir = dir.to_string_lossy().into_owned();
+
+    let theme = crate::themes::get_theme("dracula").unwrap();
+    let highlighter = SyntaxHighlighter::new(std::slice::from_ref(&dir)).unwrap();
+    let lines = highlighter.highlight_code("const x = 1;", "zig", &theme);
+    assert_eq!(lines[0].tokens[0].scope, "source.zig keyword.zig");
+
+    // Built once, then shared by later highlighters
+    let again = SyntaxHighlighter::new(std::slice::from_ref(&dir)).unwrap();
+    assert!(Arc::ptr_eq(&highlighter.syntax_set, &again.syntax_set));
+    assert!(!Arc::ptr_eq(
+      &highlighter.syntax_set,
+      &SyntaxHighlighter::new(&[]).unwrap().syntax_set
+    ));
+
+    assert!(SyntaxHighlighter::new(&["/nonexistent/syntaxes".to_string()]).is_err());
+    assert!(
+      add_syntax_directory(&std::env::temp_dir().join("shoyu-empty").to_string_lossy()).is_err()
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
 }
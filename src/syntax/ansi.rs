@@ -0,0 +1,251 @@
+/* ~~/src/syntax/ansi.rs */
+
+// local modules
+use crate::syntax::{Column, HighlightedLine, HighlightedToken, TokenStyle};
+use crate::themes::{Theme, ThemeColor};
+
+/// The 16 colors of the xterm palette, which the basic SGR codes select
+const ANSI_COLORS: [(u8, u8, u8); 16] = [
+  (0, 0, 0),
+  (205, 0, 0),
+  (0, 205, 0),
+  (205, 205, 0),
+  (0, 0, 238),
+  (205, 0, 205),
+  (0, 205, 205),
+  (229, 229, 229),
+  (127, 127, 127),
+  (255, 0, 0),
+  (0, 255, 0),
+  (255, 255, 0),
+  (92, 92, 255),
+  (255, 0, 255),
+  (0, 255, 255),
+  (255, 255, 255),
+];
+
+/// Graphic rendition in effect at a point in the output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Rendition {
+  /// `None` for the terminal's default, drawn in the theme's foreground
+  foreground: Option<(u8, u8, u8)>,
+  /// `None` for the terminal's default, leaving the panel showing through
+  background: Option<(u8, u8, u8)>,
+  /// Foreground and background swapped, as for reverse video
+  reverse: bool,
+  style: TokenStyle,
+}
+
+impl Rendition {
+  /// Apply the parameters of an SGR sequence
+  fn apply(&mut self, params: &str) {
+    let mut codes = params
+      .split([';', ':'])
+      .map(|code| code.parse::<u16>().unwrap_or(0));
+    while let Some(code) = codes.next() {
+      match code {
+        0 => *self = Self::default(),
+        1 => self.style = self.style | TokenStyle::BOLD,
+        3 => self.style = self.style | TokenStyle::ITALIC,
+        4 => self.style = self.style | TokenStyle::UNDERLINE,
+        7 => self.reverse = true,
+        22 => self.style = self.style.without(TokenStyle::BOLD),
+        23 => self.style = self.style.without(TokenStyle::ITALIC),
+        24 => self.style = self.style.without(TokenStyle::UNDERLINE),
+        27 => self.reverse = false,
+        30..=37 => self.foreground = Some(ANSI_COLORS[code as usize - 30]),
+        90..=97 => self.foreground = Some(ANSI_COLORS[code as usize - 82]),
+        39 => self.foreground = None,
+        40..=47 => self.background = Some(ANSI_COLORS[code as usize - 40]),
+        100..=107 => self.background = Some(ANSI_COLORS[code as usize - 92]),
+        49 => self.background = None,
+        38 | 48 => {
+          let color = match codes.next() {
+            Some(5) => codes.next().map(palette_color),
+            Some(2) => {
+              let mut channel = || codes.next().unwrap_or(0).min(255) as u8;
+              Some((channel(), channel(), channel()))
+            }
+            _ => None,
+          };
+          match code {
+            38 => self.foreground = color,
+            _ => self.background = color,
+          }
+        }
+        _ => {}
+      }
+    }
+  }
+
+  /// Text and background colors to draw with, defaults filled in from
+  /// `theme` only where reverse video needs them
+  fn colors(&self, theme: &Theme) -> (ThemeColor, Option<ThemeColor>) {
+    let color = |(r, g, b): (u8, u8, u8)| ThemeColor::new(&format!("#{:02x}{:02x}{:02x}", r, g, b));
+    match self.reverse {
+      false => (
+        self
+          .foreground
+          .map_or_else(|| theme.foreground.clone(), color),
+        self.background.map(color),
+      ),
+      true => (
+        self
+          .background
+          .map_or_else(|| theme.background.clone(), color),
+        Some(
+          self
+            .foreground
+            .map_or_else(|| theme.foreground.clone(), color),
+        ),
+      ),
+    }
+  }
+}
+
+/// Color `index` of the 256-color palette
+fn palette_color(index: u16) -> (u8, u8, u8) {
+  match index {
+    0..=15 => ANSI_COLORS[index as usize],
+    16..=231 => {
+      let level = |value: u16| match value {
+        0 => 0,
+        value => (55 + value * 40) as u8,
+      };
+      let index = index - 16;
+      (level(index / 36), level(index / 6 % 6), level(index % 6))
+    }
+    _ => {
+      let gray = (8 + (index.min(255) - 232) * 10) as u8;
+      (gray, gray, gray)
+    }
+  }
+}
+
+/// Split colored terminal output into lines of tokens, one token per run of
+/// text printed in the same colors and style
+pub fn parse_ansi(output: &str, theme: &Theme) -> Vec<HighlightedLine> {
+  let output = output.strip_suffix('\n').unwrap_or(output);
+  if output.is_empty() {
+    return Vec::new();
+  }
+  // Colors carry over from one line to the next until reset
+  let mut rendition = Rendition::default();
+  output
+    .split('\n')
+    .map(|line| {
+      parse_line(
+        line.strip_suffix('\r').unwrap_or(line),
+        &mut rendition,
+        theme,
+      )
+    })
+    .collect()
+}
+
+fn parse_line(line: &str, rendition: &mut Rendition, theme: &Theme) -> HighlightedLine {
+  let mut tokens = Vec::new();
+  let mut column = Column::default();
+  let mut text = String::new();
+  let mut flush = |text: &mut String, rendition: Rendition| {
+    if text.is_empty() {
+      return;
+    }
+    let end_col = column.advance(text);
+    let (color, background) = rendition.colors(theme);
+    tokens.push(HighlightedToken {
+      text: std::mem::take(text),
+      color,
+      background,
+      style: rendition.style,
+      scope: String::from("source"),
+      start_col: column,
+      end_col,
+    });
+    column = end_col;
+  };
+
+  let mut chars = line.chars().peekable();
+  while let Some(ch) = chars.next() {
+    if ch != '\u{1b}' {
+      text.push(ch);
+      continue;
+    }
+    match chars.next() {
+      // Control sequence: parameters, then a final byte in @..~
+      Some('[') => {
+        let mut params = String::new();
+        let mut last = None;
+        for ch in chars.by_ref() {
+          if ('@'..='~').contains(&ch) {
+            last = Some(ch);
+            break;
+          }
+          params.push(ch);
+        }
+        if last == Some('m') {
+          let mut next = *rendition;
+          next.apply(&params);
+          if next != *rendition {
+            flush(&mut text, *rendition);
+            *rendition = next;
+          }
+        }
+      }
+      // Operating system command, such as a hyperlink, ended by BEL or ST
+      Some(']') => {
+        while let Some(ch) = chars.next() {
+          if ch == '\u{7}' || (ch == '\u{1b}' && chars.next_if_eq(&'\\').is_some()) {
+            break;
+          }
+        }
+      }
+      _ => {}
+    }
+  }
+  flush(&mut text, *rendition);
+  HighlightedLine { tokens }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::themes::get_theme;
+
+  #[test]
+  fn test_reads_colors_from_ansi_output() {
+    let theme = get_theme("dracula").unwrap();
+    let output = "\u{1b}[1;38;2;255;121;198mfn\u{1b}[0m \u{1b}[32mmain\u{1b}[39m()\r\n\u{1b}]8;;https://x\u{1b}\\\u{1b}[38;5;196m// é\u{1b}[0m\n";
+    let lines = parse_ansi(output, &theme);
+    assert_eq!(lines.len(), 2);
+    let first: Vec<(&str, &str)> = lines[0]
+      .tokens
+      .iter()
+      .map(|token| (token.text.as_str(), token.color.hex.as_str()))
+      .collect();
+    assert_eq!(
+      first,
+      [
+        ("fn", "#ff79c6"),
+        (" ", theme.foreground.hex.as_str()),
+        ("main", "#00cd00"),
+        ("()", theme.foreground.hex.as_str()),
+      ]
+    );
+    assert!(lines[0].tokens[0].style.is_bold());
+    assert!(!lines[0].tokens[1].style.is_bold());
+    assert_eq!(lines[1].tokens[0].color.hex, "#ff0000");
+    assert_eq!(lines[1].tokens[0].end_col.byte, "// é".len());
+
+    // Backgrounds, and reverse video swapping them with the text
+    let lines = parse_ansi("\u{1b}[41m FAIL \u{1b}[0m \u{1b}[7mok\u{1b}[27m", &theme);
+    let tokens = &lines[0].tokens;
+    assert_eq!(tokens[0].background.as_ref().unwrap().hex, "#cd0000");
+    assert!(tokens[1].background.is_none());
+    assert_eq!(tokens[2].color.hex, theme.background.hex);
+    assert_eq!(
+      tokens[2].background.as_ref().unwrap().hex,
+      theme.foreground.hex
+    );
+  }
+}
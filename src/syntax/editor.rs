@@ -0,0 +1,111 @@
+/* ~~/src/syntax/editor.rs */
+
+// third-party crates
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+
+// local modules
+use crate::syntax::{Column, HighlightedLine, HighlightedToken, TokenStyle};
+use crate::themes::{Theme, ThemeColor};
+
+/// A run of text as Neovim drew it, e.g.
+/// `{"text": "local", "fg": "#ff79c6", "bold": true, "group": "@keyword.lua"}`
+#[derive(Debug, Deserialize)]
+struct EditorToken {
+  text: String,
+  /// Foreground as `#rrggbb`; the theme's foreground when missing
+  #[serde(default)]
+  fg: Option<String>,
+  #[serde(default)]
+  bold: bool,
+  #[serde(default)]
+  italic: bool,
+  #[serde(default)]
+  underline: bool,
+  /// Highlight group the color came from, kept as the token's scope
+  #[serde(default)]
+  group: String,
+}
+
+/// Lines of tokens captured from the editor, as a JSON array with one array
+/// of tokens per line
+pub fn lines_from_json(tokens_json: &str, theme: &Theme) -> Result<Vec<HighlightedLine>> {
+  let lines: Vec<Vec<EditorToken>> =
+    serde_json::from_str(tokens_json).map_err(|e| anyhow!("Failed to parse tokens: {}", e))?;
+  lines
+    .into_iter()
+    .map(|tokens| {
+      let mut column = Column::default();
+      let mut highlighted = Vec::with_capacity(tokens.len());
+      for token in tokens.into_iter().filter(|token| !token.text.is_empty()) {
+        if token.text.contains(['\n', '\r']) {
+          return Err(anyhow!("Token spans more than one line: {:?}", token.text));
+        }
+        let color = match &token.fg {
+          Some(hex) => {
+            ThemeColor::parse(hex).map_err(|e| anyhow!("Invalid token color '{}': {}", hex, e))?
+          }
+          None => theme.foreground.clone(),
+        };
+        let mut style = TokenStyle::REGULAR;
+        if token.bold {
+          style = style | TokenStyle::BOLD;
+        }
+        if token.italic {
+          style = style | TokenStyle::ITALIC;
+        }
+        if token.underline {
+          style = style | TokenStyle::UNDERLINE;
+        }
+        let end_col = column.advance(&token.text);
+        highlighted.push(HighlightedToken {
+          text: token.text,
+          color,
+          background: None,
+          style,
+          scope: token.group,
+          start_col: column,
+          end_col,
+        });
+        column = end_col;
+      }
+      Ok(HighlightedLine {
+        tokens: highlighted,
+      })
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::themes::get_theme;
+
+  #[test]
+  fn test_lines_from_editor_tokens() {
+    let theme = get_theme("nord").unwrap();
+    let json = r##"[
+      [{"text": "local", "fg": "#ff79c6", "bold": true, "group": "@keyword.lua"},
+       {"text": " x = "}, {"text": "", "fg": "#000000"},
+       {"text": "1", "fg": "#BD93F9", "underline": true}],
+      []
+    ]"##;
+    let lines = lines_from_json(json, &theme).unwrap();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[1].tokens.is_empty());
+
+    let tokens = &lines[0].tokens;
+    assert_eq!(tokens.len(), 3);
+    assert_eq!(tokens[0].color.rgb, (0xff, 0x79, 0xc6));
+    assert!(tokens[0].style.is_bold());
+    assert_eq!(tokens[0].scope, "@keyword.lua");
+    assert_eq!(tokens[1].color.hex, theme.foreground.hex);
+    assert_eq!(tokens[2].start_col.byte, 10);
+    assert_eq!(tokens[2].color.rgb, (0xbd, 0x93, 0xf9));
+    assert!(tokens[2].style.is_underline() && !tokens[2].style.is_bold());
+
+    assert!(lines_from_json(r#"[[{"text": "a\nb"}]]"#, &theme).is_err());
+    assert!(lines_from_json(r#"[[{"text": "a", "fg": "red"}]]"#, &theme).is_err());
+    assert!(lines_from_json(r#"{"text": "a"}"#, &theme).is_err());
+  }
+}
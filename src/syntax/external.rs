@@ -0,0 +1,223 @@
+/* ~~/src/syntax/external.rs */
+
+// standard crates
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// third-party crates
+use anyhow::{Result, anyhow};
+
+// local modules
+use crate::syntax::ansi::parse_ansi;
+use crate::syntax::{HighlightedLine, Highlighter, PatternHighlighter, SyntaxHighlighter};
+use crate::themes::Theme;
+use crate::warnings;
+
+/// Argument replaced with the snippet's language
+const LANGUAGE_PLACEHOLDER: &str = "{language}";
+
+/// Also set to the snippet's language, for commands that quote it themselves
+const LANGUAGE_VARIABLE: &str = "SHOYU_LANGUAGE";
+
+/// How often a running command is checked for having exited
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Pipes code through a command such as `bat --color=always` and reads the
+/// colors back from its ANSI escapes, so a language no grammar covers can
+/// be highlighted by any tool that already knows it
+pub struct ExternalHighlighter {
+  command: Vec<String>,
+  /// Runs inside the editor's process, so a command that hangs, e.g. waiting
+  /// on a terminal, is killed rather than waited on forever
+  timeout: Duration,
+}
+
+impl ExternalHighlighter {
+  pub fn new(command: &[String], timeout: Duration) -> Result<Self> {
+    if command.is_empty() {
+      return Err(anyhow!(
+        "The external highlighter needs a highlight_command to run"
+      ));
+    }
+    Ok(Self {
+      command: command.to_vec(),
+      timeout,
+    })
+  }
+
+  /// Run the command with `code` on stdin and parse what it prints
+  pub fn run(&self, code: &str, language: &str, theme: &Theme) -> Result<Vec<HighlightedLine>> {
+    let program = &self.command[0];
+    // Spliced into arguments that may reach a shell, so only a plain name
+    if !is_language_token(language) {
+      return Err(anyhow!(
+        "Not passing language {:?} to {}",
+        language,
+        program
+      ));
+    }
+    let mut child = Command::new(program)
+      .args(
+        self.command[1..]
+          .iter()
+          .map(|arg| arg.replace(LANGUAGE_PLACEHOLDER, language)),
+      )
+      .env(LANGUAGE_VARIABLE, language)
+      // Tools like bat only print 24-bit colors when the terminal says it can
+      .env("COLORTERM", "truecolor")
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .spawn()
+      .map_err(|e| anyhow!("Failed to start {}: {}", program, e))?;
+    // Written and read on other threads so a command printing as it reads
+    // cannot fill a pipe while we are blocked on another, and so the wait
+    // below can give up on it
+    let stdin = child.stdin.take();
+    let input = code.to_string();
+    let writer = thread::spawn(move || match stdin {
+      Some(mut stdin) => stdin.write_all(input.as_bytes()),
+      None => Ok(()),
+    });
+    let (stdout, stderr) = (read_all(child.stdout.take()), read_all(child.stderr.take()));
+
+    let deadline = Instant::now() + self.timeout;
+    let status = loop {
+      match child.try_wait() {
+        Ok(Some(status)) => break status,
+        Ok(None) if Instant::now() < deadline => thread::sleep(POLL_INTERVAL),
+        Ok(None) => {
+          // The pipe threads finish once the killed command's pipes close
+          let _ = child.kill();
+          let _ = child.wait();
+          return Err(anyhow!(
+            "{} timed out after {} ms",
+            program,
+            self.timeout.as_millis()
+          ));
+        }
+        Err(e) => return Err(anyhow!("Failed to wait for {}: {}", program, e)),
+      }
+    };
+    // A command that exits without reading everything closes the pipe early,
+    // which only matters if it also failed
+    let _ = writer.join();
+    let stdout = stdout.join().unwrap_or_default();
+    if !status.success() {
+      let stderr = stderr.join().unwrap_or_default();
+      return Err(anyhow!(
+        "{} failed ({}): {}",
+        program,
+        status,
+        String::from_utf8_lossy(&stderr).trim()
+      ));
+    }
+    Ok(parse_ansi(&String::from_utf8_lossy(&stdout), theme))
+  }
+
+  /// Run the command, or highlight with `fallback` when it can't start,
+  /// fails, or times out
+  pub fn highlight_or(
+    &self,
+    code: &str,
+    language: &str,
+    theme: &Theme,
+    fallback: &dyn Highlighter,
+  ) -> Vec<HighlightedLine> {
+    self.run(code, language, theme).unwrap_or_else(|e| {
+      warnings::push(format!("{}; highlighting with syntect", e));
+      fallback.highlight(code, language, theme)
+    })
+  }
+}
+
+/// Whether `language` is a bare name such as `c++`, `c#` or `objective-c`
+fn is_language_token(language: &str) -> bool {
+  !language.is_empty()
+    && !language.starts_with('-')
+    && language
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '#' | '-' | '_' | '.'))
+}
+
+/// Everything `pipe` yields until it closes, read on its own thread
+fn read_all(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<Vec<u8>> {
+  thread::spawn(move || {
+    let mut bytes = Vec::new();
+    if let Some(mut pipe) = pipe {
+      let _ = pipe.read_to_end(&mut bytes);
+    }
+    bytes
+  })
+}
+
+impl Highlighter for ExternalHighlighter {
+  fn highlight(&self, code: &str, language: &str, theme: &Theme) -> Vec<HighlightedLine> {
+    match SyntaxHighlighter::new(&[]) {
+      Ok(syntect) => self.highlight_or(code, language, theme, &syntect),
+      Err(_) => self.highlight_or(code, language, theme, &PatternHighlighter),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::themes::get_theme;
+
+  #[cfg(unix)]
+  #[test]
+  fn test_runs_command_and_falls_back() {
+    let theme = get_theme("nord").unwrap();
+    let command = [
+      "sh",
+      "-c",
+      "printf '\\033[31m%s\\033[0m\\n' {language}; cat",
+    ]
+    .map(String::from);
+    let timeout = Duration::from_secs(5);
+    let highlighter = ExternalHighlighter::new(&command, timeout).unwrap();
+    let lines = highlighter.run("x = 1\n", "lua", &theme).unwrap();
+    assert_eq!(lines[0].tokens[0].text, "lua");
+    assert_eq!(lines[1].tokens[0].text, "x = 1");
+
+    // The language reaches a shell quoted through the environment, and a
+    // name that isn't one never reaches it at all
+    let quoted = ["sh", "-c", "printf '%s\\n' \"$SHOYU_LANGUAGE\""].map(String::from);
+    let quoted = ExternalHighlighter::new(&quoted, timeout).unwrap();
+    assert_eq!(
+      quoted.run("", "c++", &theme).unwrap()[0].tokens[0].text,
+      "c++"
+    );
+    assert!(highlighter.run("x", "lua; touch pwned", &theme).is_err());
+    assert!(highlighter.run("x", "--help", &theme).is_err());
+
+    let failing = ExternalHighlighter::new(&["false".to_string()], timeout).unwrap();
+    assert!(failing.run("x", "lua", &theme).is_err());
+    let fallback = failing.highlight("local x = 1", "lua", &theme);
+    assert!(fallback[0].tokens.len() > 1);
+    assert!(ExternalHighlighter::new(&[], timeout).is_err());
+
+    // A command that never finishes is killed at the deadline
+    let hanging = ["sh", "-c", "exec sleep 30"].map(String::from);
+    let hanging = ExternalHighlighter::new(&hanging, Duration::from_millis(200)).unwrap();
+    let started = Instant::now();
+    let error = hanging.run("x", "lua", &theme).unwrap_err();
+    assert!(error.to_string().contains("timed out"), "{}", error);
+    assert!(started.elapsed() < Duration::from_secs(5));
+    // Timing out falls back just as failing does
+    let token_texts = |lines: Vec<HighlightedLine>| {
+      lines[0]
+        .tokens
+        .iter()
+        .map(|token| token.text.clone())
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(
+      token_texts(hanging.highlight("local x = 1", "lua", &theme)),
+      token_texts(fallback)
+    );
+  }
+}
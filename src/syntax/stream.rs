@@ -0,0 +1,334 @@
+/* ~~/src/syntax/stream.rs */
+
+// standard crates
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+// third-party crates
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use syntect::highlighting::Highlighter;
+use syntect::parsing::{ParseState, ScopeStack};
+
+// local modules
+use crate::config::RenderConfig;
+use crate::detect::resolve_language;
+use crate::syntax::{HighlightedLine, PatternHighlighter, SyntaxHighlighter, syntect_theme};
+use crate::themes::{Theme, get_theme};
+
+/// Open streams by handle
+static STREAMS: Mutex<BTreeMap<u32, HighlightStream>> = Mutex::new(BTreeMap::new());
+static NEXT_HANDLE: AtomicU32 = AtomicU32::new(1);
+
+/// Parser state entering a line
+type LineState = (ParseState, ScopeStack);
+
+/// A buffer highlighted as it is edited. Edits only queue work; `poll`
+/// highlights from the first edited line until the parser reaches a line
+/// it left in the same state before, so a keystroke costs a line or two
+/// rather than the whole buffer, and edits arriving faster than polls
+/// collapse into one pass.
+pub struct HighlightStream {
+  highlighter: SyntaxHighlighter,
+  theme: Theme,
+  syntect_theme: syntect::highlighting::Theme,
+  /// Lines without their endings
+  lines: Vec<String>,
+  /// State entering each line and after the last, `None` where an edit
+  /// replaced it. Always empty without a grammar, since pattern
+  /// highlighting has no state to carry between lines.
+  states: Vec<Option<LineState>>,
+  /// First line waiting to be highlighted; every state up to it is current
+  dirty: Option<usize>,
+  /// First line past every queued edit, where highlighting may stop once a
+  /// line ends in the state it ended in before
+  settle: usize,
+}
+
+/// Lines highlighted by a poll
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamUpdate {
+  pub lines: Vec<StreamLine>,
+  /// Queued lines remain beyond the poll's budget
+  pub pending: bool,
+  pub line_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamLine {
+  /// Line index, counting from 0 like the Neovim API
+  pub line: usize,
+  pub tokens: Vec<StreamToken>,
+}
+
+/// Token in the shape `generate_image_from_tokens` reads, with byte offsets
+/// for placing extmarks
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamToken {
+  pub text: String,
+  /// Foreground as `#rrggbb`
+  pub fg: String,
+  pub bold: bool,
+  pub italic: bool,
+  pub underline: bool,
+  /// Scope stack the color came from
+  pub group: String,
+  /// Byte offsets into the line, end exclusive
+  pub start_byte: usize,
+  pub end_byte: usize,
+}
+
+impl From<&HighlightedLine> for Vec<StreamToken> {
+  fn from(line: &HighlightedLine) -> Self {
+    line
+      .tokens
+      .iter()
+      .map(|token| {
+        let (r, g, b) = token.color.rgb;
+        StreamToken {
+          text: token.text.clone(),
+          fg: format!("#{:02x}{:02x}{:02x}", r, g, b),
+          bold: token.style.is_bold(),
+          italic: token.style.is_italic(),
+          underline: token.style.is_underline(),
+          group: token.scope.clone(),
+          start_byte: token.start_col.byte,
+          end_byte: token.end_col.byte,
+        }
+      })
+      .collect()
+  }
+}
+
+impl HighlightStream {
+  pub fn new(code: &str, language: &str, theme: Theme, config: &RenderConfig) -> Result<Self> {
+    let highlighter = SyntaxHighlighter::new(&config.syntax_dirs)?;
+    let language = resolve_language(language, code);
+    let lines: Vec<String> = code.lines().map(String::from).collect();
+    let states = match highlighter.find_syntax_by_language(&language) {
+      Some(syntax) => std::iter::once(Some((ParseState::new(syntax), ScopeStack::new())))
+        .chain(lines.iter().map(|_| None))
+        .collect(),
+      None => Vec::new(),
+    };
+    Ok(Self {
+      syntect_theme: syntect_theme(&theme),
+      highlighter,
+      theme,
+      dirty: (!lines.is_empty()).then_some(0),
+      settle: lines.len(),
+      lines,
+      states,
+    })
+  }
+
+  /// Replace lines `start..end` with the lines of `text`, or remove them when
+  /// `text` is `None`
+  pub fn edit(&mut self, start: usize, end: usize, text: Option<&str>) -> Result<()> {
+    if start > end || end > self.lines.len() {
+      return Err(anyhow!(
+        "Edit of lines {}..{} is outside the {} line buffer",
+        start,
+        end,
+        self.lines.len()
+      ));
+    }
+    let replacement: Vec<String> = text
+      .map(|text| text.split('\n').map(String::from).collect())
+      .unwrap_or_default();
+    let inserted = replacement.len();
+    self.lines.splice(start..end, replacement);
+
+    // The state entering `start` holds, and the one entering the first line
+    // after the edit is kept to compare against; states between are unknown
+    if !self.states.is_empty() {
+      let kept = self.states[end].clone();
+      let tail: Vec<Option<LineState>> = self.states.drain(start + 1..).collect();
+      if inserted > 0 {
+        self.states.extend(std::iter::repeat_n(None, inserted - 1));
+        self.states.push(kept);
+      }
+      self.states.extend(tail.into_iter().skip(end - start));
+    }
+
+    let settle = match self.dirty {
+      Some(_) if self.settle >= end => self.settle + inserted - (end - start),
+      _ => 0,
+    };
+    self.settle = settle.max(start + inserted);
+    self.dirty = Some(self.dirty.map_or(start, |dirty| dirty.min(start)));
+    Ok(())
+  }
+
+  /// Highlight up to `max_lines` queued lines, or all of them when 0
+  pub fn poll(&mut self, max_lines: usize) -> StreamUpdate {
+    let budget = match max_lines {
+      0 => usize::MAX,
+      max_lines => max_lines,
+    };
+    let highlighter = Highlighter::new(&self.syntect_theme);
+    let mut lines = Vec::new();
+    while let Some(index) = self.dirty
+      && lines.len() < budget
+    {
+      if index >= self.lines.len() {
+        self.dirty = None;
+        break;
+      }
+      let mut unchanged = true;
+      let highlighted = match self.states.get(index).cloned().flatten() {
+        Some((mut parse_state, mut scope_stack)) => {
+          let line = format!("{}\n", self.lines[index]);
+          let highlighted = self.highlighter.highlight_syntect_line(
+            &line,
+            &mut parse_state,
+            &mut scope_stack,
+            &highlighter,
+            &self.theme,
+          );
+          let state = Some((parse_state, scope_stack));
+          unchanged = self.states[index + 1] == state;
+          self.states[index + 1] = state;
+          highlighted
+        }
+        None => PatternHighlighter.highlight_line(&self.lines[index], &self.theme),
+      };
+      lines.push(StreamLine {
+        line: index,
+        tokens: (&highlighted).into(),
+      });
+      self.dirty = match index + 1 >= self.settle && unchanged {
+        true => None,
+        false => Some(index + 1),
+      };
+    }
+    StreamUpdate {
+      lines,
+      pending: self.dirty.is_some_and(|dirty| dirty < self.lines.len()),
+      line_count: self.lines.len(),
+    }
+  }
+}
+
+/// Start streaming `code`, returning the handle later calls take
+pub fn open(code: &str, language: &str, theme: &str, config: &RenderConfig) -> Result<u32> {
+  let theme = get_theme(theme).ok_or_else(|| anyhow!("Unknown theme: {}", theme))?;
+  let stream = HighlightStream::new(code, language, theme, config)?;
+  let handle = NEXT_HANDLE.fetch_add(1, Ordering::SeqCst);
+  STREAMS
+    .lock()
+    .unwrap_or_else(|e| e.into_inner())
+    .insert(handle, stream);
+  Ok(handle)
+}
+
+fn with_stream<T>(handle: u32, f: impl FnOnce(&mut HighlightStream) -> T) -> Result<T> {
+  let mut streams = STREAMS.lock().unwrap_or_else(|e| e.into_inner());
+  let stream = streams
+    .get_mut(&handle)
+    .ok_or_else(|| anyhow!("Unknown highlight stream: {}", handle))?;
+  Ok(f(stream))
+}
+
+pub fn edit(handle: u32, start: usize, end: usize, text: Option<&str>) -> Result<()> {
+  with_stream(handle, |stream| stream.edit(start, end, text))?
+}
+
+pub fn poll(handle: u32, max_lines: usize) -> Result<StreamUpdate> {
+  with_stream(handle, |stream| stream.poll(max_lines))
+}
+
+pub fn close(handle: u32) -> bool {
+  STREAMS
+    .lock()
+    .unwrap_or_else(|e| e.into_inner())
+    .remove(&handle)
+    .is_some()
+}
+
+// Needs a grammar with state that spans lines
+#[cfg(all(test, feature = "bundled-syntaxes"))]
+mod tests {
+  use super::*;
+
+  /// Apply a poll to a copy of the buffer's tokens the way the plugin would
+  fn apply(view: &mut [Vec<StreamToken>], update: StreamUpdate) -> Vec<usize> {
+    update
+      .lines
+      .into_iter()
+      .map(|line| {
+        view[line.line] = line.tokens;
+        line.line
+      })
+      .collect()
+  }
+
+  fn full(code: &str) -> Vec<Vec<(String, String)>> {
+    let highlighter = SyntaxHighlighter::new(&[]).unwrap();
+    let theme = get_theme("dracula").unwrap();
+    highlighter
+      .highlight_code(code, "rust", &theme)
+      .iter()
+      .map(|line| {
+        Vec::<StreamToken>::from(line)
+          .into_iter()
+          .map(|token| (token.text, token.fg))
+          .collect()
+      })
+      .collect()
+  }
+
+  fn simplify(view: &[Vec<StreamToken>]) -> Vec<Vec<(String, String)>> {
+    view
+      .iter()
+      .map(|tokens| {
+        tokens
+          .iter()
+          .map(|token| (token.text.clone(), token.fg.clone()))
+          .collect()
+      })
+      .collect()
+  }
+
+  #[test]
+  fn test_edits_rehighlight_until_state_settles() {
+    let mut lines: Vec<String> = (0..40).map(|i| format!("let x{} = {};", i, i)).collect();
+    let code = lines.join("\n");
+    let theme = get_theme("dracula").unwrap();
+    let mut stream = HighlightStream::new(&code, "rust", theme, &RenderConfig::default()).unwrap();
+    let mut view = vec![Vec::new(); 40];
+
+    // A budget leaves the rest queued
+    let first = stream.poll(10);
+    assert!(first.pending);
+    assert_eq!(apply(&mut view, first), (0..10).collect::<Vec<_>>());
+    apply(&mut view, stream.poll(0));
+    assert_eq!(simplify(&view), full(&code));
+
+    // Changing one statement touches only that line
+    stream.edit(5, 6, Some("let y = \"text\";")).unwrap();
+    lines[5] = "let y = \"text\";".to_string();
+    assert_eq!(apply(&mut view, stream.poll(0)), vec![5]);
+
+    // Opening a block comment carries on to the end of the buffer
+    stream.edit(10, 10, Some("/*")).unwrap();
+    lines.insert(10, "/*".to_string());
+    view.insert(10, Vec::new());
+    assert_eq!(apply(&mut view, stream.poll(0)).len(), 31);
+
+    // Queued edits collapse into one pass; closing the comment settles again
+    stream.edit(12, 13, None).unwrap();
+    lines.remove(12);
+    view.remove(12);
+    stream.edit(11, 11, Some("*/\nfn f() {}")).unwrap();
+    lines.splice(11..11, ["*/".to_string(), "fn f() {}".to_string()]);
+    view.splice(11..11, [Vec::new(), Vec::new()]);
+    let update = stream.poll(0);
+    assert!(!update.pending);
+    assert_eq!(update.line_count, lines.len());
+    apply(&mut view, update);
+    assert_eq!(simplify(&view), full(&lines.join("\n")));
+    assert!(stream.edit(3, 2, None).is_err());
+  }
+}
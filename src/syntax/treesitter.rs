@@ -0,0 +1,203 @@
+/* ~~/src/syntax/treesitter.rs */
+
+// standard crates
+use std::cmp::Reverse;
+
+// third-party crates
+use streaming_iterator::StreamingIterator;
+use tree_sitter::{Language, Parser, Query, QueryCursor};
+
+// local modules
+use crate::syntax::{Column, HighlightedLine, HighlightedToken, scope_styles, style_for_scope};
+use crate::themes::{Theme, ThemeColor};
+
+/// Grammar, highlight query, and scope suffix for a language name
+fn grammar(language: &str) -> Option<(Language, &'static str, &'static str)> {
+  Some(match language.to_lowercase().as_str() {
+    "rs" | "rust" => (
+      tree_sitter_rust::LANGUAGE.into(),
+      tree_sitter_rust::HIGHLIGHTS_QUERY,
+      "rust",
+    ),
+    "py" | "python" => (
+      tree_sitter_python::LANGUAGE.into(),
+      tree_sitter_python::HIGHLIGHTS_QUERY,
+      "python",
+    ),
+    "js" | "javascript" => (
+      tree_sitter_javascript::LANGUAGE.into(),
+      tree_sitter_javascript::HIGHLIGHT_QUERY,
+      "js",
+    ),
+    "go" => (
+      tree_sitter_go::LANGUAGE.into(),
+      tree_sitter_go::HIGHLIGHTS_QUERY,
+      "go",
+    ),
+    _ => return None,
+  })
+}
+
+/// Whether tree-sitter has a grammar for `language`
+pub(super) fn supports(language: &str) -> bool {
+  grammar(language).is_some()
+}
+
+/// Highlight `code` from tree-sitter captures, or `None` when there is no
+/// grammar for `language`
+pub(super) fn highlight(code: &str, language: &str, theme: &Theme) -> Option<Vec<HighlightedLine>> {
+  let (grammar, highlights, suffix) = grammar(language)?;
+  let mut parser = Parser::new();
+  parser.set_language(&grammar).ok()?;
+  let tree = parser.parse(code, None)?;
+  let query = Query::new(&grammar, highlights).ok()?;
+
+  let mut spans = Vec::new();
+  let mut cursor = QueryCursor::new();
+  let mut captures = cursor.captures(&query, tree.root_node(), code.as_bytes());
+  while let Some((found, index)) = captures.next() {
+    let capture = found.captures[*index];
+    let node = capture.node;
+    spans.push((
+      node.start_byte(),
+      node.end_byte(),
+      found.pattern_index,
+      capture.index,
+    ));
+  }
+
+  // Paint outer nodes first so nested ones override them, and let the first
+  // pattern to capture a node win like Neovim does
+  spans.sort_by_key(|&(start, end, pattern, _)| (start, Reverse(end), pattern));
+  let mut painted = vec![None; code.len()];
+  let mut previous = None;
+  for (start, end, _, capture) in spans {
+    if previous != Some((start, end)) {
+      painted[start..end].fill(Some(capture as usize));
+      previous = Some((start, end));
+    }
+  }
+
+  let names = query.capture_names();
+  let colors: Vec<ThemeColor> = names
+    .iter()
+    .map(|name| capture_color(name, theme))
+    .collect();
+  let scopes: Vec<String> = names
+    .iter()
+    .map(|name| format!("source.{} {}", suffix, capture_scope(name)))
+    .collect();
+  let plain_scope = format!("source.{}", suffix);
+  let declared = scope_styles(theme);
+  let styles: Vec<_> = scopes
+    .iter()
+    .map(|scope| style_for_scope(&declared, scope))
+    .collect();
+
+  let mut lines = Vec::new();
+  let mut line_start = 0;
+  for line in code.split_inclusive('\n') {
+    let text = line.trim_end_matches(['\n', '\r']);
+    let mut tokens = Vec::new();
+    let mut run_start = 0;
+    let mut column = Column::default();
+    // Split the line into runs of bytes painted by the same capture
+    let boundaries = text.char_indices().map(|(offset, _)| offset).skip(1);
+    for offset in boundaries.chain((!text.is_empty()).then_some(text.len())) {
+      let capture = painted[line_start + run_start];
+      if offset < text.len() && painted[line_start + offset] == capture {
+        continue;
+      }
+      let run = &text[run_start..offset];
+      let end_col = column.advance(run);
+      tokens.push(HighlightedToken {
+        text: run.to_string(),
+        color: capture.map_or(theme.foreground.clone(), |c| colors[c].clone()),
+        background: None,
+        style: capture.map_or(Default::default(), |c| styles[c]),
+        scope: capture.map_or(plain_scope.clone(), |c| scopes[c].clone()),
+        start_col: column,
+        end_col,
+      });
+      column = end_col;
+      run_start = offset;
+    }
+    lines.push(HighlightedLine { tokens });
+    line_start += line.len();
+  }
+  Some(lines)
+}
+
+/// Theme color for a capture like `function.method`, keyed on its first part
+fn capture_color(name: &str, theme: &Theme) -> ThemeColor {
+  match name.split('.').next().unwrap_or(name) {
+    "comment" => theme.comment.clone(),
+    "keyword" => theme.keyword.clone(),
+    "string" => theme.string.clone(),
+    "number" => theme.number.clone(),
+    "escape" | "constant" | "label" => theme.constant.clone(),
+    "function" | "attribute" => theme.function.clone(),
+    "constructor" => theme.class.clone(),
+    "type" => theme.type_color.clone(),
+    "variable" | "property" => theme.variable.clone(),
+    "operator" => theme.operator.clone(),
+    "punctuation" => theme.punctuation.clone(),
+    _ => theme.foreground.clone(),
+  }
+}
+
+/// Nearest TextMate scope for a capture so `scope_fonts` selectors still match
+fn capture_scope(name: &str) -> &str {
+  match name.split('.').next().unwrap_or(name) {
+    "comment" => "comment",
+    "keyword" => "keyword",
+    "string" => "string",
+    "number" => "constant.numeric",
+    "escape" => "constant.character.escape",
+    "constant" => "constant",
+    "label" => "entity.name.label",
+    "function" => "entity.name.function",
+    "attribute" => "entity.other.attribute-name",
+    "constructor" => "entity.name.type",
+    "type" => "storage.type",
+    "variable" => "variable",
+    "property" => "variable.other.member",
+    "operator" => "keyword.operator",
+    "punctuation" => "punctuation",
+    _ => name,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::themes::get_theme;
+
+  #[test]
+  fn test_tree_sitter_captures() {
+    let theme = get_theme("dracula").unwrap();
+    let source = "fn main() { // hi\n\n    let n = 42;\n}\n";
+    let lines = highlight(source, "rust", &theme).unwrap();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[1].tokens.is_empty());
+
+    for (line, text) in lines.iter().zip(source.lines()) {
+      let joined: String = line.tokens.iter().map(|t| t.text.as_str()).collect();
+      assert_eq!(joined, text);
+      for token in &line.tokens {
+        assert_eq!(&text[token.start_col.byte..token.end_col.byte], token.text);
+      }
+    }
+    let find = |text: &str| {
+      lines
+        .iter()
+        .flat_map(|line| &line.tokens)
+        .find(|token| token.text == text)
+        .unwrap()
+    };
+    assert_eq!(find("fn").color.hex, theme.keyword.hex);
+    assert_eq!(find("// hi").scope, "source.rust comment");
+    assert_eq!(find("42").color.hex, theme.constant.hex);
+    assert!(highlight(source, "cobol", &theme).is_none());
+  }
+}
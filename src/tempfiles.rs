@@ -0,0 +1,239 @@
+/* ~~/src/tempfiles.rs */
+
+// standard crates
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+
+// third-party crates
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+
+// local modules
+use crate::limits;
+
+/// Directory holding the `shoyu` staging directory, the system temp
+/// directory when unset
+static TEMP_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+/// Staged files older than this many seconds are removed; 0 keeps them
+static CLEANUP_OLDER_THAN: AtomicU64 = AtomicU64::new(24 * 60 * 60);
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+/// Files still being written, which cleanup and purges leave alone
+static IN_USE: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Files and bytes removed from the temp directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CleanupSummary {
+  pub files: u64,
+  pub bytes: u64,
+}
+
+/// Stage exports in a `shoyu` directory under `dir`, or under the system temp
+/// directory when `None`, and remove staged
+/// files older than `cleanup_older_than` seconds (0 keeps them)
+pub fn set_policy(dir: Option<PathBuf>, cleanup_older_than: u64) {
+  *TEMP_DIR.lock().unwrap_or_else(|e| e.into_inner()) = dir;
+  CLEANUP_OLDER_THAN.store(cleanup_older_than, Ordering::SeqCst);
+}
+
+/// The staging directory, always a `shoyu` directory of its own so cleanup
+/// never touches files it did not create
+pub fn temp_dir() -> PathBuf {
+  TEMP_DIR
+    .lock()
+    .unwrap_or_else(|e| e.into_inner())
+    .clone()
+    .unwrap_or_else(std::env::temp_dir)
+    .join("shoyu")
+}
+
+/// The process that staged `name`, when it is named `<pid>-<n>.<ext>` the way
+/// `TempFile::create` names files
+fn staging_pid(name: &str) -> Option<u32> {
+  let (stem, extension) = name.split_once('.')?;
+  let (pid, id) = stem.split_once('-')?;
+  id.parse::<u64>().ok()?;
+  match extension.is_empty() {
+    true => None,
+    false => pid.parse().ok(),
+  }
+}
+
+/// Whether `pid` is another process that is still running, and may still be
+/// writing its staged files
+fn other_process_alive(pid: u32) -> bool {
+  if pid == std::process::id() {
+    return false;
+  }
+  #[cfg(unix)]
+  {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+      return false;
+    };
+    // Signal 0 only checks the process exists; EPERM means it does
+    let exists = unsafe { libc::kill(pid, 0) } == 0;
+    exists || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+  }
+  // Without a cheap check elsewhere, assume it is and leave its files
+  #[cfg(not(unix))]
+  true
+}
+
+/// Remove staged files past the TTL, then the oldest ones while the directory
+/// is over the `max_cache_mb` limit
+pub fn cleanup() -> CleanupSummary {
+  let older_than = match CLEANUP_OLDER_THAN.load(Ordering::SeqCst) {
+    0 => None,
+    seconds => Some(Duration::from_secs(seconds)),
+  };
+  cleanup_dir(&temp_dir(), older_than, limits::max_cache_bytes())
+}
+
+/// Remove every staged file that is not being written, here or by another
+/// running process
+pub fn purge() -> CleanupSummary {
+  cleanup_dir(&temp_dir(), Some(Duration::ZERO), 0)
+}
+
+fn cleanup_dir(dir: &Path, older_than: Option<Duration>, max_bytes: u64) -> CleanupSummary {
+  let mut summary = CleanupSummary::default();
+  let Ok(entries) = fs::read_dir(dir) else {
+    return summary;
+  };
+  let in_use = IN_USE.lock().unwrap_or_else(|e| e.into_inner()).clone();
+  let now = SystemTime::now();
+  let mut files: Vec<(PathBuf, SystemTime, u64)> = entries
+    .flatten()
+    .filter(|entry| !in_use.contains(&entry.path()))
+    .filter(|entry| {
+      staging_pid(&entry.file_name().to_string_lossy()).is_some_and(|pid| !other_process_alive(pid))
+    })
+    .filter_map(|entry| {
+      let metadata = entry
+        .metadata()
+        .ok()
+        .filter(|metadata| metadata.is_file())?;
+      Some((entry.path(), metadata.modified().ok()?, metadata.len()))
+    })
+    .collect();
+  // Oldest first, so size trimming drops the stalest files
+  files.sort_by_key(|(_, modified, _)| *modified);
+  let mut total: u64 = files.iter().map(|(_, _, len)| len).sum();
+  for (path, modified, len) in files {
+    let expired =
+      older_than.is_some_and(|ttl| now.duration_since(modified).is_ok_and(|age| age >= ttl));
+    let over_budget = max_bytes > 0 && total > max_bytes;
+    if (expired || over_budget) && fs::remove_file(&path).is_ok() {
+      summary.files += 1;
+      summary.bytes += len;
+      total -= len;
+    }
+  }
+  summary
+}
+
+/// A file in the temp directory that is moved to its destination once
+/// written, or removed if dropped first, so failed exports leave nothing
+/// half-written behind
+pub struct TempFile {
+  path: PathBuf,
+}
+
+impl TempFile {
+  /// Reserve a unique path ending in `extension`, which tools like ffmpeg
+  /// read the container format from
+  pub fn create(extension: &str) -> Result<Self> {
+    cleanup();
+    let dir = temp_dir();
+    fs::create_dir_all(&dir)
+      .map_err(|e| anyhow!("Failed to create temp directory {}: {}", dir.display(), e))?;
+    let path = dir.join(format!(
+      "{}-{}.{}",
+      std::process::id(),
+      NEXT_ID.fetch_add(1, Ordering::SeqCst),
+      extension
+    ));
+    IN_USE
+      .lock()
+      .unwrap_or_else(|e| e.into_inner())
+      .push(path.clone());
+    Ok(Self { path })
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  /// Move the finished file to `destination`, copying across filesystems
+  pub fn persist(self, destination: &Path) -> Result<()> {
+    if fs::rename(&self.path, destination).is_err() {
+      fs::copy(&self.path, destination)
+        .map_err(|e| anyhow!("Failed to write {}: {}", destination.display(), e))?;
+    }
+    Ok(())
+  }
+}
+
+impl Drop for TempFile {
+  fn drop(&mut self) {
+    let _ = fs::remove_file(&self.path);
+    IN_USE
+      .lock()
+      .unwrap_or_else(|e| e.into_inner())
+      .retain(|path| path != &self.path);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_cleanup_removes_stale_and_excess_files() {
+    let dir = std::env::temp_dir().join(format!("shoyu-cleanup-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let pid = std::process::id();
+    let write = |name: &str, age: u64| {
+      let path = dir.join(name);
+      fs::write(&path, [0u8; 100]).unwrap();
+      let file = fs::File::options().write(true).open(&path).unwrap();
+      file
+        .set_modified(SystemTime::now() - Duration::from_secs(age))
+        .unwrap();
+    };
+    let (stale, old, fresh) = (
+      format!("{}-0.png", pid),
+      format!("{}-1.png", pid),
+      format!("{}-2.png", pid),
+    );
+    write(&stale, 7200);
+    write(&old, 600);
+    write(&fresh, 0);
+    // Files it did not stage, and ones a running process staged, are kept
+    write("notes.txt", 7200);
+    write("1-0.png", 7200);
+
+    let hour = Some(Duration::from_secs(3600));
+    assert_eq!(
+      cleanup_dir(&dir, hour, 0),
+      CleanupSummary {
+        files: 1,
+        bytes: 100
+      }
+    );
+    // Over budget, the oldest remaining file goes first
+    assert_eq!(cleanup_dir(&dir, hour, 150).files, 1);
+    assert!(dir.join(&fresh).exists());
+    assert!(!dir.join(&old).exists());
+    assert_eq!(cleanup_dir(&dir, None, 0).files, 0);
+    assert_eq!(cleanup_dir(&dir, Some(Duration::ZERO), 0).files, 1);
+    assert!(dir.join("notes.txt").exists());
+    assert!(dir.join("1-0.png").exists());
+    assert_eq!(staging_pid("42-7.mp4"), Some(42));
+    assert_eq!(staging_pid("42-7"), None);
+    assert_eq!(staging_pid("notes-draft.md"), None);
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
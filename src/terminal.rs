@@ -0,0 +1,201 @@
+/* ~~/src/terminal.rs */
+
+// standard crates
+use std::env;
+use std::io::Write;
+
+// third-party crates
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose;
+use image::ImageFormat;
+
+mod sixel;
+
+/// Base64 bytes per Kitty graphics chunk, the most the protocol accepts
+const KITTY_CHUNK: usize = 4096;
+
+/// Escape sequence family a terminal draws inline images with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageProtocol {
+  /// Kitty graphics protocol, also spoken by Ghostty and Konsole
+  Kitty,
+  /// iTerm2 `OSC 1337` inline files, also spoken by WezTerm
+  Iterm2,
+  /// DEC Sixel graphics, for xterm, mlterm, foot, and other terminals
+  /// without either of the above
+  Sixel,
+}
+
+impl ImageProtocol {
+  /// Protocol by name, `"auto"` or an empty name detecting it from the
+  /// environment
+  pub fn from_name(name: &str) -> Result<Self> {
+    match name.trim().to_lowercase().as_str() {
+      "" | "auto" => Self::detect(|name| env::var(name).ok()).ok_or_else(|| {
+        anyhow!(
+          "Cannot tell which image protocol this terminal speaks; pick kitty, iterm2 or sixel"
+        )
+      }),
+      "kitty" => Ok(Self::Kitty),
+      "iterm" | "iterm2" => Ok(Self::Iterm2),
+      "sixel" => Ok(Self::Sixel),
+      other => Err(anyhow!(
+        "Unknown image protocol: {} (expected kitty, iterm2, sixel or auto)",
+        other
+      )),
+    }
+  }
+
+  /// Guess from the variables terminals set, `None` for terminals that
+  /// can't show images inline or don't say who they are
+  fn detect(var: impl Fn(&str) -> Option<String>) -> Option<Self> {
+    let program = var("TERM_PROGRAM").unwrap_or_default();
+    let term = var("TERM").unwrap_or_default();
+    // LC_TERMINAL survives ssh and tmux, which replace TERM_PROGRAM
+    if program == "iTerm.app"
+      || program == "WezTerm"
+      || var("LC_TERMINAL").as_deref() == Some("iTerm2")
+    {
+      return Some(Self::Iterm2);
+    }
+    if var("KITTY_WINDOW_ID").is_some()
+      || term.contains("kitty")
+      || term.contains("ghostty")
+      || program == "ghostty"
+    {
+      return Some(Self::Kitty);
+    }
+    // Sixel support isn't announced in the environment, so only terminals
+    // known to have it are picked; xterm needs it compiled in and enabled
+    if term.starts_with("foot") || term.starts_with("mlterm") || program == "mintty" {
+      return Some(Self::Sixel);
+    }
+    None
+  }
+}
+
+/// Escape sequence that draws `png_data` at the cursor, wrapped for tmux to
+/// pass through when running inside it
+pub fn inline_image(png_data: &[u8], protocol: ImageProtocol) -> Result<String> {
+  let mut sequence = Vec::new();
+  write_inline_image(&mut sequence, png_data, protocol)?;
+  Ok(String::from_utf8_lossy(&sequence).into_owned())
+}
+
+/// Stream the sequence of [`inline_image`] to `writer`, which for Sixel
+/// starts drawing before the whole image is encoded
+pub fn write_inline_image<W: Write>(
+  writer: &mut W,
+  png_data: &[u8],
+  protocol: ImageProtocol,
+) -> Result<()> {
+  let tmux = env::var_os("TMUX").is_some();
+  let written = match protocol {
+    ImageProtocol::Kitty => writer.write_all(kitty(png_data, tmux).as_bytes()),
+    ImageProtocol::Iterm2 => writer.write_all(iterm2(png_data, tmux).as_bytes()),
+    // tmux 3.4 draws Sixel itself rather than passing it through
+    ImageProtocol::Sixel => {
+      let image = image::load_from_memory_with_format(png_data, ImageFormat::Png)
+        .map_err(|e| anyhow!("Failed to decode the image: {}", e))?
+        .to_rgba8();
+      sixel::write_sixel(&image, writer)
+    }
+  };
+  written.map_err(|e| anyhow!("Failed to write the image: {}", e))
+}
+
+/// Kitty graphics chunks transmitting and placing `png_data`
+fn kitty(png_data: &[u8], tmux: bool) -> String {
+  let base64_data = general_purpose::STANDARD.encode(png_data);
+  // Base64 is ASCII, so splitting the bytes never splits a character
+  let chunks: Vec<&[u8]> = base64_data.as_bytes().chunks(KITTY_CHUNK).collect();
+  let last = chunks.len().saturating_sub(1);
+  chunks
+    .iter()
+    .enumerate()
+    .map(|(index, chunk)| {
+      // Only the first chunk carries the keys; q=2 keeps the terminal
+      // from answering on stdin
+      let keys = match index {
+        0 => "a=T,f=100,q=2,",
+        _ => "",
+      };
+      let more = (index != last) as u8;
+      let payload = String::from_utf8_lossy(chunk);
+      wrap(format!("\x1b_G{}m={};{}\x1b\\", keys, more, payload), tmux)
+    })
+    .collect()
+}
+
+/// iTerm2 inline file holding `png_data`
+fn iterm2(png_data: &[u8], tmux: bool) -> String {
+  let sequence = format!(
+    "\x1b]1337;File=inline=1;size={};preserveAspectRatio=1:{}\x07",
+    png_data.len(),
+    general_purpose::STANDARD.encode(png_data)
+  );
+  wrap(sequence, tmux)
+}
+
+/// `sequence` in tmux's DCS passthrough when `tmux` is set, doubling its
+/// escapes
+fn wrap(sequence: String, tmux: bool) -> String {
+  match tmux {
+    true => format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b")),
+    false => sequence,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_wraps_images_for_each_protocol() {
+    let png_data = vec![7u8; 6000];
+    let kitty = kitty(&png_data, false);
+    let chunks: Vec<&str> = kitty.split("\x1b\\").filter(|c| !c.is_empty()).collect();
+    // 8000 base64 bytes split at 4096
+    assert_eq!(chunks.len(), 2);
+    assert!(chunks[0].starts_with("\x1b_Ga=T,f=100,q=2,m=1;"));
+    assert!(chunks[1].starts_with("\x1b_Gm=0;"));
+    let payload: String = chunks
+      .iter()
+      .map(|chunk| chunk.split_once(';').unwrap().1)
+      .collect();
+    assert_eq!(general_purpose::STANDARD.decode(payload).unwrap(), png_data);
+
+    let iterm = iterm2(&png_data[..3], false);
+    assert_eq!(
+      iterm,
+      "\x1b]1337;File=inline=1;size=3;preserveAspectRatio=1:BwcH\x07"
+    );
+    let tmux = iterm2(&png_data[..3], true);
+    assert!(tmux.starts_with("\x1bPtmux;\x1b\x1b]1337;"));
+    assert!(tmux.ends_with("\x07\x1b\\"));
+
+    let detect = |vars: &[(&str, &str)]| {
+      ImageProtocol::detect(|name| {
+        vars
+          .iter()
+          .find(|(var, _)| *var == name)
+          .map(|(_, value)| value.to_string())
+      })
+    };
+    assert_eq!(
+      detect(&[("TERM", "xterm-kitty")]),
+      Some(ImageProtocol::Kitty)
+    );
+    assert_eq!(
+      detect(&[("TERM_PROGRAM", "tmux"), ("LC_TERMINAL", "iTerm2")]),
+      Some(ImageProtocol::Iterm2)
+    );
+    assert_eq!(
+      detect(&[("TERM", "foot-extra")]),
+      Some(ImageProtocol::Sixel)
+    );
+    assert_eq!(detect(&[("TERM", "xterm-256color")]), None);
+    assert!(ImageProtocol::from_name("ascii").is_err());
+  }
+}
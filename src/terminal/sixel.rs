@@ -0,0 +1,128 @@
+/* ~~/src/terminal/sixel.rs */
+
+// standard crates
+use std::io::{self, Write};
+
+// third-party crates
+use color_quant::NeuQuant;
+use image::RgbaImage;
+use image::imageops::{self, ColorMap};
+
+/// Color registers used, the most xterm and mlterm give an image
+const PALETTE_SIZE: usize = 256;
+/// Pixels NeuQuant skips between samples while learning the palette
+const SAMPLE_FACTOR: i32 = 10;
+/// Pixels at or below this alpha are left unpainted, showing the terminal
+/// background through
+const TRANSPARENT_BELOW: u8 = 128;
+
+/// Stream `image` as a Sixel sequence, quantized and dithered to at most 256
+/// colors, one six-pixel band at a time
+pub(super) fn write_sixel<W: Write>(image: &RgbaImage, writer: &mut W) -> io::Result<()> {
+  let (width, height) = (image.width() as usize, image.height() as usize);
+  let quantizer = NeuQuant::new(SAMPLE_FACTOR, PALETTE_SIZE, image.as_raw());
+  let mut mapped = image.clone();
+  imageops::dither(&mut mapped, &quantizer);
+  let indices: Vec<Option<u8>> = mapped
+    .pixels()
+    .zip(image.pixels())
+    .map(|(pixel, original)| {
+      (original[3] > TRANSPARENT_BELOW).then(|| ColorMap::index_of(&quantizer, pixel) as u8)
+    })
+    .collect();
+
+  // P2=1 keeps unpainted pixels transparent; the raster attributes give a
+  // 1:1 aspect ratio and the size up front
+  write!(writer, "\x1bP0;1;0q\"1;1;{};{}", width, height)?;
+  for (index, color) in quantizer.color_map_rgb().chunks_exact(3).enumerate() {
+    let percent = |channel: u8| channel as u32 * 100 / 255;
+    write!(
+      writer,
+      "#{};2;{};{};{}",
+      index,
+      percent(color[0]),
+      percent(color[1]),
+      percent(color[2])
+    )?;
+  }
+
+  // Per band, a row of six-bit column masks for each color it uses
+  let mut masks = vec![Vec::new(); PALETTE_SIZE];
+  for band in (0..height).step_by(6) {
+    for mask in masks.iter_mut() {
+      mask.clear();
+    }
+    for y in band..(band + 6).min(height) {
+      let bit = 1 << (y - band);
+      for (x, index) in indices[y * width..(y + 1) * width].iter().enumerate() {
+        if let Some(index) = index {
+          let mask = &mut masks[*index as usize];
+          if mask.is_empty() {
+            mask.resize(width, 0);
+          }
+          mask[x] |= bit;
+        }
+      }
+    }
+    let mut first = true;
+    for (index, mask) in masks.iter().enumerate() {
+      if mask.is_empty() {
+        continue;
+      }
+      // `$` returns to the start of the band to overlay the next color
+      if !first {
+        writer.write_all(b"$")?;
+      }
+      first = false;
+      write!(writer, "#{}", index)?;
+      write_runs(writer, mask)?;
+    }
+    writer.write_all(b"-")?;
+  }
+  writer.write_all(b"\x1b\\")
+}
+
+/// Write column masks as sixel characters, run-length encoding repeats
+fn write_runs<W: Write>(writer: &mut W, mask: &[u8]) -> io::Result<()> {
+  // Trailing empty columns draw nothing
+  let end = mask
+    .iter()
+    .rposition(|&bits| bits != 0)
+    .map_or(0, |last| last + 1);
+  let mut columns = mask[..end].iter().peekable();
+  while let Some(&bits) = columns.next() {
+    let mut count = 1;
+    while columns.next_if_eq(&&bits).is_some() {
+      count += 1;
+    }
+    let sixel = b'?' + bits;
+    match count {
+      1..=3 => writer.write_all(&vec![sixel; count])?,
+      _ => write!(writer, "!{}{}", count, sixel as char)?,
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use image::Rgba;
+
+  #[test]
+  fn test_encodes_bands_with_runs() {
+    let mut image = RgbaImage::from_pixel(10, 7, Rgba([40, 42, 54, 255]));
+    image.put_pixel(9, 6, Rgba([0, 0, 0, 0]));
+    let mut sixel = Vec::new();
+    write_sixel(&image, &mut sixel).unwrap();
+    let sixel = String::from_utf8(sixel).unwrap();
+
+    assert!(sixel.starts_with("\x1bP0;1;0q\"1;1;10;7#0;2;"));
+    assert!(sixel.ends_with("-\x1b\\"));
+    // A full band of one color is a single run, and the second band's one
+    // row stops short of the transparent corner
+    assert!(sixel.contains("!10~-"));
+    assert!(sixel.contains("!9@-"));
+    assert_eq!(sixel.matches('-').count(), 2);
+  }
+}
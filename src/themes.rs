@@ -1,13 +1,71 @@
 /* ~~/src/themes.rs */
 
+// standard crates
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// third-party crates
+use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
 
+// local modules
+use crate::warnings;
+
+#[cfg(feature = "yaml")]
+mod base16;
+mod contrast;
+mod highlights;
+mod variant;
+mod vscode;
+
+#[cfg(feature = "yaml")]
+pub use self::base16::load_base16_scheme;
+pub use self::highlights::{HighlightGroup, theme_from_highlights};
+pub use self::variant::ThemeVariant;
+pub use self::vscode::load_vscode_theme;
+
+/// Themes registered at runtime, such as ones built from editor highlights
+static CUSTOM_THEMES: Mutex<BTreeMap<String, Theme>> = Mutex::new(BTreeMap::new());
+
+/// Themes loaded from `themes_dir`, replaced whole by every scan
+static DIRECTORY_THEMES: Mutex<DirectoryThemes> = Mutex::new(DirectoryThemes {
+  dir: None,
+  themes: BTreeMap::new(),
+});
+
+struct DirectoryThemes {
+  /// Directory last scanned, rescanned by `reload_themes`
+  dir: Option<PathBuf>,
+  themes: BTreeMap<String, Theme>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "ThemeColorJson")]
 pub struct ThemeColor {
   pub hex: String,
   pub rgb: (u8, u8, u8),
 }
 
+/// A color in theme JSON, either just its hex or the serialized form, whose
+/// `rgb` is recomputed from the hex
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ThemeColorJson {
+  Hex(String),
+  Serialized { hex: String },
+}
+
+impl TryFrom<ThemeColorJson> for ThemeColor {
+  type Error = String;
+
+  fn try_from(json: ThemeColorJson) -> Result<Self, Self::Error> {
+    let (ThemeColorJson::Hex(hex) | ThemeColorJson::Serialized { hex }) = json;
+    Self::parse(&hex).map_err(|e| format!("{} '{}'", e, hex))
+  }
+}
+
 impl ThemeColor {
   pub fn new(hex: &str) -> Self {
     let rgb = hex_to_rgb(hex).unwrap_or((255, 255, 255));
@@ -16,10 +74,34 @@ impl ThemeColor {
       rgb,
     }
   }
+
+  /// Like `new`, but rejects anything that isn't a `#rrggbb` color
+  pub fn parse(hex: &str) -> Result<Self, &'static str> {
+    Ok(Self {
+      rgb: hex_to_rgb(hex)?,
+      hex: hex.to_string(),
+    })
+  }
+
+  /// Mix toward `other`; `t` of 0.0 keeps this color, 1.0 gives `other`
+  pub fn blend(&self, other: &ThemeColor, t: f32) -> Self {
+    let t = t.clamp(0.0, 1.0);
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    let (r, g, b) = (
+      mix(self.rgb.0, other.rgb.0),
+      mix(self.rgb.1, other.rgb.1),
+      mix(self.rgb.2, other.rgb.2),
+    );
+    Self {
+      hex: format!("#{:02x}{:02x}{:02x}", r, g, b),
+      rgb: (r, g, b),
+    }
+  }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Theme {
+  #[serde(default)]
   pub name: String,
   pub background: ThemeColor,
   pub foreground: ThemeColor,
@@ -34,10 +116,256 @@ pub struct Theme {
   pub punctuation: ThemeColor,
   pub constant: ThemeColor,
   pub class: ThemeColor,
+  /// Alternate faces for matching scopes, e.g. comments in an italic serif
+  #[serde(default)]
+  pub scope_fonts: Vec<ScopeFont>,
+  /// Font styles for matching scopes, e.g. italic comments
+  #[serde(default)]
+  pub scope_styles: Vec<ScopeStyle>,
+  /// Window and gutter colors, derived from the background where unset
+  #[serde(default)]
+  pub chrome: ThemeChrome,
+}
+
+/// Overrides for the colors drawn around the code. Anything left unset is
+/// derived from the panel background, darker or lighter depending on how
+/// light it is, so the same defaults hold up on dark and light themes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ThemeChrome {
+  pub title_bar: Option<ThemeColor>,
+  pub title_text: Option<ThemeColor>,
+  /// Window control circles, left to right
+  pub close: Option<ThemeColor>,
+  pub minimize: Option<ThemeColor>,
+  pub maximize: Option<ThemeColor>,
+  pub line_numbers: Option<ThemeColor>,
+  pub gutter_rule: Option<ThemeColor>,
+}
+
+impl ThemeChrome {
+  fn blend(&self, other: &ThemeChrome, t: f32) -> Self {
+    // A color only one side overrides switches over halfway through
+    let mix = |a: &Option<ThemeColor>, b: &Option<ThemeColor>| match (a, b) {
+      (Some(a), Some(b)) => Some(a.blend(b, t)),
+      _ if t < 0.5 => a.clone(),
+      _ => b.clone(),
+    };
+    Self {
+      title_bar: mix(&self.title_bar, &other.title_bar),
+      title_text: mix(&self.title_text, &other.title_text),
+      close: mix(&self.close, &other.close),
+      minimize: mix(&self.minimize, &other.minimize),
+      maximize: mix(&self.maximize, &other.maximize),
+      line_numbers: mix(&self.line_numbers, &other.line_numbers),
+      gutter_rule: mix(&self.gutter_rule, &other.gutter_rule),
+    }
+  }
+}
+
+/// Route tokens whose scope stack matches `scope` to the face at `font`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScopeFont {
+  /// TextMate scope selector such as `comment` or `string.quoted, constant.numeric`
+  pub scope: String,
+  /// Path to a font file
+  pub font: String,
+}
+
+/// Draw tokens whose scope stack matches `scope` in bold, italic, or underlined
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScopeStyle {
+  /// TextMate scope selector such as `comment` or `storage.type`
+  pub scope: String,
+  #[serde(default)]
+  pub bold: bool,
+  #[serde(default)]
+  pub italic: bool,
+  #[serde(default)]
+  pub underline: bool,
+}
+
+impl ScopeStyle {
+  fn italic(scope: &str) -> Self {
+    Self {
+      scope: scope.to_string(),
+      bold: false,
+      italic: true,
+      underline: false,
+    }
+  }
+}
+
+impl Theme {
+  /// Interpolate every color toward `other`, e.g. for theme transition frames.
+  /// Scope fonts and styles are kept from this theme.
+  pub fn blend(&self, other: &Theme, t: f32) -> Self {
+    Self {
+      name: format!("{}-{}", self.name, other.name),
+      background: self.background.blend(&other.background, t),
+      foreground: self.foreground.blend(&other.foreground, t),
+      comment: self.comment.blend(&other.comment, t),
+      keyword: self.keyword.blend(&other.keyword, t),
+      string: self.string.blend(&other.string, t),
+      number: self.number.blend(&other.number, t),
+      function: self.function.blend(&other.function, t),
+      type_color: self.type_color.blend(&other.type_color, t),
+      variable: self.variable.blend(&other.variable, t),
+      operator: self.operator.blend(&other.operator, t),
+      punctuation: self.punctuation.blend(&other.punctuation, t),
+      constant: self.constant.blend(&other.constant, t),
+      class: self.class.blend(&other.class, t),
+      scope_fonts: self.scope_fonts.clone(),
+      scope_styles: self.scope_styles.clone(),
+      chrome: self.chrome.blend(&other.chrome, t),
+    }
+  }
 }
 
 pub fn get_theme(name: &str) -> Option<Theme> {
-  match name.to_lowercase().as_str() {
+  let name = name.to_lowercase();
+  builtin_theme(&name)
+    .or_else(|| {
+      let themes = CUSTOM_THEMES.lock().unwrap_or_else(|e| e.into_inner());
+      themes.get(&name).cloned()
+    })
+    .or_else(|| {
+      let directory = DIRECTORY_THEMES.lock().unwrap_or_else(|e| e.into_inner());
+      directory.themes.get(&name).cloned()
+    })
+}
+
+/// Make `theme` available as `name` to every later render, replacing a
+/// theme registered under that name before but never a built-in one
+pub fn register_theme(name: &str, theme: Theme) -> Result<()> {
+  let name = name.trim().to_lowercase();
+  if name.is_empty() {
+    return Err(anyhow!("Theme name is empty"));
+  }
+  if builtin_theme(&name).is_some() {
+    return Err(anyhow!("Cannot replace the built-in theme {}", name));
+  }
+  let mut themes = CUSTOM_THEMES.lock().unwrap_or_else(|e| e.into_inner());
+  themes.insert(name, theme);
+  Ok(())
+}
+
+/// Stand-in for builds without the `yaml` feature
+#[cfg(not(feature = "yaml"))]
+pub fn load_base16_scheme(path: &Path) -> Result<Theme> {
+  Err(anyhow!(
+    "Cannot load base16 scheme {}: built without the yaml feature",
+    path.display()
+  ))
+}
+
+/// Load a theme file by its extension: a base16 or base24 scheme for
+/// `.yaml` and `.yml`, otherwise a shoyu theme JSON as the FFI returns
+/// themes, with plain hex colors allowed, or else a VS Code color theme
+pub fn load_theme_file(path: &Path) -> Result<Theme> {
+  let extension = path
+    .extension()
+    .map(|extension| extension.to_string_lossy().to_lowercase());
+  if let Some("yaml" | "yml") = extension.as_deref() {
+    return load_base16_scheme(path);
+  }
+  let text = fs::read_to_string(path)
+    .map_err(|e| anyhow!("Failed to read theme {}: {}", path.display(), e))?;
+  match serde_json::from_str::<Theme>(&text) {
+    Ok(mut theme) => {
+      if theme.name.is_empty() {
+        theme.name = theme_file_name(path);
+      }
+      Ok(theme)
+    }
+    Err(_) => load_vscode_theme(path),
+  }
+}
+
+/// Lowercase file stem a theme file is registered under
+fn theme_file_name(path: &Path) -> String {
+  path
+    .file_stem()
+    .map_or_else(String::new, |stem| stem.to_string_lossy().to_lowercase())
+}
+
+/// Load every `.json`, `.yaml`, and `.yml` theme in `dir`, each named after
+/// its file, in place of the themes an earlier scan found. Files that fail
+/// to load, or would shadow a built-in theme, are skipped with a warning.
+/// Returns the names loaded.
+pub fn scan_themes_dir(dir: &Path) -> Result<Vec<String>> {
+  let entries =
+    fs::read_dir(dir).map_err(|e| anyhow!("Failed to read themes_dir {}: {}", dir.display(), e))?;
+  let mut paths: Vec<PathBuf> = entries
+    .filter_map(|entry| Some(entry.ok()?.path()))
+    .filter(|path| {
+      let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+      path.is_file() && matches!(extension.as_deref(), Some("json" | "yaml" | "yml"))
+    })
+    .collect();
+  paths.sort();
+
+  let mut themes = BTreeMap::new();
+  for path in paths {
+    let name = theme_file_name(&path);
+    if builtin_theme(&name).is_some() {
+      warnings::push(format!(
+        "Skipped {}: {} is a built-in theme",
+        path.display(),
+        name
+      ));
+      continue;
+    }
+    match load_theme_file(&path) {
+      Ok(theme) => {
+        themes.insert(name, theme);
+      }
+      Err(e) => warnings::push(format!("Skipped theme {}: {}", path.display(), e)),
+    }
+  }
+  let names = themes.keys().cloned().collect();
+  let mut directory = DIRECTORY_THEMES.lock().unwrap_or_else(|e| e.into_inner());
+  *directory = DirectoryThemes {
+    dir: Some(dir.to_path_buf()),
+    themes,
+  };
+  Ok(names)
+}
+
+/// Scan `dir` unless it is the directory scanned last, whose themes stay
+/// cached until `reload_themes`
+pub fn load_themes_dir(dir: &Path) -> Result<()> {
+  let scanned = DIRECTORY_THEMES
+    .lock()
+    .unwrap_or_else(|e| e.into_inner())
+    .dir
+    .as_deref()
+    == Some(dir);
+  if !scanned {
+    scan_themes_dir(dir)?;
+  }
+  Ok(())
+}
+
+/// Scan the last scanned `themes_dir` again, or `dir` when given, picking
+/// up edited, added, and removed theme files
+pub fn reload_themes(dir: Option<&Path>) -> Result<Vec<String>> {
+  let dir = match dir {
+    Some(dir) => dir.to_path_buf(),
+    None => {
+      let directory = DIRECTORY_THEMES.lock().unwrap_or_else(|e| e.into_inner());
+      match &directory.dir {
+        Some(dir) => dir.clone(),
+        None => return Ok(Vec::new()),
+      }
+    }
+  };
+  scan_themes_dir(&dir)
+}
+
+fn builtin_theme(name: &str) -> Option<Theme> {
+  match name {
     "dracula" => Some(dracula_theme()),
     "monokai" => Some(monokai_theme()),
     "github" => Some(github_theme()),
@@ -46,11 +374,26 @@ pub fn get_theme(name: &str) -> Option<Theme> {
     "solarized-light" => Some(solarized_light_theme()),
     "one-dark" => Some(one_dark_theme()),
     "gruvbox" => Some(gruvbox_theme()),
+    "catppuccin-latte" => Some(catppuccin_theme(&CATPPUCCIN_LATTE)),
+    "catppuccin-frappe" => Some(catppuccin_theme(&CATPPUCCIN_FRAPPE)),
+    "catppuccin-macchiato" => Some(catppuccin_theme(&CATPPUCCIN_MACCHIATO)),
+    "catppuccin-mocha" | "catppuccin" => Some(catppuccin_theme(&CATPPUCCIN_MOCHA)),
+    "tokyonight-storm" => Some(tokyonight_theme("Tokyo Night Storm", "#24283b", "#1f2335")),
+    "tokyonight-night" | "tokyonight" => {
+      Some(tokyonight_theme("Tokyo Night", "#1a1b26", "#16161e"))
+    }
+    "rose-pine" => Some(rose_pine_theme(&ROSE_PINE)),
+    "rose-pine-moon" => Some(rose_pine_theme(&ROSE_PINE_MOON)),
+    "rose-pine-dawn" => Some(rose_pine_theme(&ROSE_PINE_DAWN)),
+    "kanagawa" => Some(kanagawa_theme()),
+    "everforest" => Some(everforest_theme()),
     _ => None,
   }
 }
 
 pub fn get_theme_names() -> Vec<String> {
+  let custom = CUSTOM_THEMES.lock().unwrap_or_else(|e| e.into_inner());
+  let directory = DIRECTORY_THEMES.lock().unwrap_or_else(|e| e.into_inner());
   vec![
     "dracula".to_string(),
     "monokai".to_string(),
@@ -60,7 +403,29 @@ pub fn get_theme_names() -> Vec<String> {
     "solarized-light".to_string(),
     "one-dark".to_string(),
     "gruvbox".to_string(),
+    "catppuccin-latte".to_string(),
+    "catppuccin-frappe".to_string(),
+    "catppuccin-macchiato".to_string(),
+    "catppuccin-mocha".to_string(),
+    "tokyonight-storm".to_string(),
+    "tokyonight-night".to_string(),
+    "rose-pine".to_string(),
+    "rose-pine-moon".to_string(),
+    "rose-pine-dawn".to_string(),
+    "kanagawa".to_string(),
+    "everforest".to_string(),
   ]
+  .into_iter()
+  .chain(custom.keys().cloned())
+  // A registered theme shadows a directory one of the same name
+  .chain(
+    directory
+      .themes
+      .keys()
+      .filter(|name| !custom.contains_key(*name))
+      .cloned(),
+  )
+  .collect()
 }
 
 fn dracula_theme() -> Theme {
@@ -79,6 +444,9 @@ fn dracula_theme() -> Theme {
     punctuation: ThemeColor::new("#f8f8f2"),
     constant: ThemeColor::new("#bd93f9"),
     class: ThemeColor::new("#8be9fd"),
+    scope_fonts: Vec::new(),
+    scope_styles: Vec::new(),
+    chrome: ThemeChrome::default(),
   }
 }
 
@@ -98,6 +466,15 @@ fn monokai_theme() -> Theme {
     punctuation: ThemeColor::new("#f8f8f2"),
     constant: ThemeColor::new("#ae81ff"),
     class: ThemeColor::new("#a6e22e"),
+    scope_fonts: Vec::new(),
+    scope_styles: vec![
+      ScopeStyle::italic("storage.type, variable.parameter"),
+      ScopeStyle {
+        underline: true,
+        ..ScopeStyle::italic("entity.other.inherited-class")
+      },
+    ],
+    chrome: ThemeChrome::default(),
   }
 }
 
@@ -117,6 +494,9 @@ fn github_theme() -> Theme {
     punctuation: ThemeColor::new("#24292e"),
     constant: ThemeColor::new("#005cc5"),
     class: ThemeColor::new("#6f42c1"),
+    scope_fonts: Vec::new(),
+    scope_styles: Vec::new(),
+    chrome: ThemeChrome::default(),
   }
 }
 
@@ -136,6 +516,9 @@ fn nord_theme() -> Theme {
     punctuation: ThemeColor::new("#eceff4"),
     constant: ThemeColor::new("#b48ead"),
     class: ThemeColor::new("#8fbcbb"),
+    scope_fonts: Vec::new(),
+    scope_styles: Vec::new(),
+    chrome: ThemeChrome::default(),
   }
 }
 
@@ -155,6 +538,9 @@ fn solarized_dark_theme() -> Theme {
     punctuation: ThemeColor::new("#93a1a1"),
     constant: ThemeColor::new("#cb4b16"),
     class: ThemeColor::new("#b58900"),
+    scope_fonts: Vec::new(),
+    scope_styles: Vec::new(),
+    chrome: ThemeChrome::default(),
   }
 }
 
@@ -174,6 +560,13 @@ fn solarized_light_theme() -> Theme {
     punctuation: ThemeColor::new("#586e75"),
     constant: ThemeColor::new("#cb4b16"),
     class: ThemeColor::new("#b58900"),
+    scope_fonts: Vec::new(),
+    scope_styles: Vec::new(),
+    chrome: ThemeChrome {
+      // Solarized base2, the palette's own background highlight
+      title_bar: Some(ThemeColor::new("#eee8d5")),
+      ..ThemeChrome::default()
+    },
   }
 }
 
@@ -193,6 +586,9 @@ fn one_dark_theme() -> Theme {
     punctuation: ThemeColor::new("#abb2bf"),
     constant: ThemeColor::new("#d19a66"),
     class: ThemeColor::new("#e5c07b"),
+    scope_fonts: Vec::new(),
+    scope_styles: Vec::new(),
+    chrome: ThemeChrome::default(),
   }
 }
 
@@ -212,6 +608,269 @@ fn gruvbox_theme() -> Theme {
     punctuation: ThemeColor::new("#ebdbb2"),
     constant: ThemeColor::new("#d3869b"),
     class: ThemeColor::new("#8ec07c"),
+    scope_fonts: Vec::new(),
+    scope_styles: vec![ScopeStyle::italic("comment")],
+    chrome: ThemeChrome::default(),
+  }
+}
+
+/// The Catppuccin colors a flavor's theme is built from
+struct CatppuccinFlavor {
+  name: &'static str,
+  base: &'static str,
+  mantle: &'static str,
+  text: &'static str,
+  overlay2: &'static str,
+  mauve: &'static str,
+  green: &'static str,
+  peach: &'static str,
+  blue: &'static str,
+  yellow: &'static str,
+  sky: &'static str,
+}
+
+const CATPPUCCIN_LATTE: CatppuccinFlavor = CatppuccinFlavor {
+  name: "Catppuccin Latte",
+  base: "#eff1f5",
+  mantle: "#e6e9ef",
+  text: "#4c4f69",
+  overlay2: "#7c7f93",
+  mauve: "#8839ef",
+  green: "#40a02b",
+  peach: "#fe640b",
+  blue: "#1e66f5",
+  yellow: "#df8e1d",
+  sky: "#04a5e5",
+};
+
+const CATPPUCCIN_FRAPPE: CatppuccinFlavor = CatppuccinFlavor {
+  name: "Catppuccin Frappé",
+  base: "#303446",
+  mantle: "#292c3c",
+  text: "#c6d0f5",
+  overlay2: "#949cbb",
+  mauve: "#ca9ee6",
+  green: "#a6d189",
+  peach: "#ef9f76",
+  blue: "#8caaee",
+  yellow: "#e5c890",
+  sky: "#99d1db",
+};
+
+const CATPPUCCIN_MACCHIATO: CatppuccinFlavor = CatppuccinFlavor {
+  name: "Catppuccin Macchiato",
+  base: "#24273a",
+  mantle: "#1e2030",
+  text: "#cad3f5",
+  overlay2: "#939ab7",
+  mauve: "#c6a0f6",
+  green: "#a6da95",
+  peach: "#f5a97f",
+  blue: "#8aadf4",
+  yellow: "#eed49f",
+  sky: "#91d7e3",
+};
+
+const CATPPUCCIN_MOCHA: CatppuccinFlavor = CatppuccinFlavor {
+  name: "Catppuccin Mocha",
+  base: "#1e1e2e",
+  mantle: "#181825",
+  text: "#cdd6f4",
+  overlay2: "#9399b2",
+  mauve: "#cba6f7",
+  green: "#a6e3a1",
+  peach: "#fab387",
+  blue: "#89b4fa",
+  yellow: "#f9e2af",
+  sky: "#89dceb",
+};
+
+/// Every flavor maps its palette onto syntax the same way, after
+/// catppuccin/nvim
+fn catppuccin_theme(flavor: &CatppuccinFlavor) -> Theme {
+  Theme {
+    name: flavor.name.to_string(),
+    background: ThemeColor::new(flavor.base),
+    foreground: ThemeColor::new(flavor.text),
+    comment: ThemeColor::new(flavor.overlay2),
+    keyword: ThemeColor::new(flavor.mauve),
+    string: ThemeColor::new(flavor.green),
+    number: ThemeColor::new(flavor.peach),
+    function: ThemeColor::new(flavor.blue),
+    type_color: ThemeColor::new(flavor.yellow),
+    variable: ThemeColor::new(flavor.text),
+    operator: ThemeColor::new(flavor.sky),
+    punctuation: ThemeColor::new(flavor.overlay2),
+    constant: ThemeColor::new(flavor.peach),
+    class: ThemeColor::new(flavor.yellow),
+    scope_fonts: Vec::new(),
+    scope_styles: vec![ScopeStyle::italic("comment")],
+    chrome: ThemeChrome {
+      title_bar: Some(ThemeColor::new(flavor.mantle)),
+      ..ThemeChrome::default()
+    },
+  }
+}
+
+/// Tokyo Night's storm and night styles differ only in their backgrounds
+fn tokyonight_theme(name: &str, background: &str, title_bar: &str) -> Theme {
+  Theme {
+    name: name.to_string(),
+    background: ThemeColor::new(background),
+    foreground: ThemeColor::new("#c0caf5"),
+    comment: ThemeColor::new("#565f89"),
+    keyword: ThemeColor::new("#bb9af7"),
+    string: ThemeColor::new("#9ece6a"),
+    number: ThemeColor::new("#ff9e64"),
+    function: ThemeColor::new("#7aa2f7"),
+    type_color: ThemeColor::new("#2ac3de"),
+    variable: ThemeColor::new("#c0caf5"),
+    operator: ThemeColor::new("#89ddff"),
+    punctuation: ThemeColor::new("#89ddff"),
+    constant: ThemeColor::new("#ff9e64"),
+    class: ThemeColor::new("#2ac3de"),
+    scope_fonts: Vec::new(),
+    scope_styles: vec![ScopeStyle::italic("comment, keyword")],
+    chrome: ThemeChrome {
+      title_bar: Some(ThemeColor::new(title_bar)),
+      ..ThemeChrome::default()
+    },
+  }
+}
+
+/// The Rosé Pine colors a variant's theme is built from
+struct RosePineVariant {
+  name: &'static str,
+  base: &'static str,
+  surface: &'static str,
+  text: &'static str,
+  muted: &'static str,
+  subtle: &'static str,
+  gold: &'static str,
+  rose: &'static str,
+  pine: &'static str,
+  foam: &'static str,
+  iris: &'static str,
+}
+
+const ROSE_PINE: RosePineVariant = RosePineVariant {
+  name: "Rosé Pine",
+  base: "#191724",
+  surface: "#1f1d2e",
+  text: "#e0def4",
+  muted: "#6e6a86",
+  subtle: "#908caa",
+  gold: "#f6c177",
+  rose: "#ebbcba",
+  pine: "#31748f",
+  foam: "#9ccfd8",
+  iris: "#c4a7e7",
+};
+
+const ROSE_PINE_MOON: RosePineVariant = RosePineVariant {
+  name: "Rosé Pine Moon",
+  base: "#232136",
+  surface: "#2a273f",
+  text: "#e0def4",
+  muted: "#6e6a86",
+  subtle: "#908caa",
+  gold: "#f6c177",
+  rose: "#ea9a97",
+  pine: "#3e8fb0",
+  foam: "#9ccfd8",
+  iris: "#c4a7e7",
+};
+
+const ROSE_PINE_DAWN: RosePineVariant = RosePineVariant {
+  name: "Rosé Pine Dawn",
+  base: "#faf4ed",
+  surface: "#fffaf3",
+  text: "#575279",
+  muted: "#9893a5",
+  subtle: "#797593",
+  gold: "#ea9d34",
+  rose: "#d7827e",
+  pine: "#286983",
+  foam: "#56949f",
+  iris: "#907aa9",
+};
+
+/// Every variant maps its palette onto syntax the same way, after
+/// rose-pine/neovim
+fn rose_pine_theme(variant: &RosePineVariant) -> Theme {
+  Theme {
+    name: variant.name.to_string(),
+    background: ThemeColor::new(variant.base),
+    foreground: ThemeColor::new(variant.text),
+    comment: ThemeColor::new(variant.muted),
+    keyword: ThemeColor::new(variant.pine),
+    string: ThemeColor::new(variant.gold),
+    number: ThemeColor::new(variant.gold),
+    function: ThemeColor::new(variant.rose),
+    type_color: ThemeColor::new(variant.foam),
+    variable: ThemeColor::new(variant.text),
+    operator: ThemeColor::new(variant.subtle),
+    punctuation: ThemeColor::new(variant.subtle),
+    constant: ThemeColor::new(variant.gold),
+    class: ThemeColor::new(variant.iris),
+    scope_fonts: Vec::new(),
+    scope_styles: vec![ScopeStyle::italic("comment")],
+    chrome: ThemeChrome {
+      title_bar: Some(ThemeColor::new(variant.surface)),
+      ..ThemeChrome::default()
+    },
+  }
+}
+
+fn kanagawa_theme() -> Theme {
+  Theme {
+    name: "Kanagawa".to_string(),
+    background: ThemeColor::new("#1f1f28"),
+    foreground: ThemeColor::new("#dcd7ba"),
+    comment: ThemeColor::new("#727169"),
+    keyword: ThemeColor::new("#957fb8"),
+    string: ThemeColor::new("#98bb6c"),
+    number: ThemeColor::new("#d27e99"),
+    function: ThemeColor::new("#7e9cd8"),
+    type_color: ThemeColor::new("#7aa89f"),
+    variable: ThemeColor::new("#dcd7ba"),
+    operator: ThemeColor::new("#c0a36e"),
+    punctuation: ThemeColor::new("#9cabca"),
+    constant: ThemeColor::new("#ffa066"),
+    class: ThemeColor::new("#7aa89f"),
+    scope_fonts: Vec::new(),
+    scope_styles: vec![ScopeStyle::italic("comment")],
+    chrome: ThemeChrome {
+      // sumiInk0, the palette's darkest ink
+      title_bar: Some(ThemeColor::new("#16161d")),
+      ..ThemeChrome::default()
+    },
+  }
+}
+
+fn everforest_theme() -> Theme {
+  Theme {
+    name: "Everforest".to_string(),
+    background: ThemeColor::new("#2d353b"),
+    foreground: ThemeColor::new("#d3c6aa"),
+    comment: ThemeColor::new("#859289"),
+    keyword: ThemeColor::new("#e67e80"),
+    string: ThemeColor::new("#83c092"),
+    number: ThemeColor::new("#d699b6"),
+    function: ThemeColor::new("#a7c080"),
+    type_color: ThemeColor::new("#dbbc7f"),
+    variable: ThemeColor::new("#d3c6aa"),
+    operator: ThemeColor::new("#e69875"),
+    punctuation: ThemeColor::new("#d3c6aa"),
+    constant: ThemeColor::new("#83c092"),
+    class: ThemeColor::new("#dbbc7f"),
+    scope_fonts: Vec::new(),
+    scope_styles: vec![ScopeStyle::italic("comment")],
+    chrome: ThemeChrome {
+      // bg_dim from the medium dark palette
+      title_bar: Some(ThemeColor::new("#232a2e")),
+      ..ThemeChrome::default()
+    },
   }
 }
 
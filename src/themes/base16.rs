@@ -0,0 +1,132 @@
+/* ~~/src/themes/base16.rs */
+
+// standard crates
+use std::fs;
+use std::path::Path;
+
+// third-party crates
+use anyhow::{Result, anyhow};
+use yaml_rust::{Yaml, YamlLoader};
+
+// local modules
+use crate::themes::{Theme, ThemeChrome, ThemeColor};
+
+/// Load a base16 or base24 scheme, either the classic flat layout with
+/// `scheme` and `base00`..`base0F` or the newer one nesting them under
+/// `palette`. Colors follow the base16 styling guidelines.
+pub fn load_base16_scheme(path: &Path) -> Result<Theme> {
+  let text = fs::read_to_string(path)
+    .map_err(|e| anyhow!("Failed to read base16 scheme {}: {}", path.display(), e))?;
+  let documents = YamlLoader::load_from_str(&text)
+    .map_err(|e| anyhow!("Failed to parse base16 scheme {}: {}", path.display(), e))?;
+  let document = documents
+    .first()
+    .ok_or_else(|| anyhow!("Base16 scheme {} is empty", path.display()))?;
+  let palette = match &document["palette"] {
+    Yaml::Hash(_) => &document["palette"],
+    _ => document,
+  };
+
+  let base = |key: &str| -> Result<Option<ThemeColor>> {
+    let hex = match &palette[key] {
+      Yaml::String(hex) => hex.trim_start_matches('#').to_lowercase(),
+      // Unquoted all-digit colors such as 181818 load as integers
+      Yaml::Integer(digits) => format!("{:06}", digits),
+      Yaml::BadValue | Yaml::Null => return Ok(None),
+      other => return Err(anyhow!("Base16 color {} is not a string: {:?}", key, other)),
+    };
+    ThemeColor::parse(&format!("#{}", hex))
+      .map(Some)
+      .map_err(|e| anyhow!("Invalid base16 color {} '{}': {}", key, hex, e))
+  };
+  let required = |key: &str| {
+    base(key)?.ok_or_else(|| anyhow!("Base16 scheme {} has no {}", path.display(), key))
+  };
+  let name = ["name", "scheme"]
+    .iter()
+    .find_map(|key| document[*key].as_str())
+    .map(str::to_string)
+    .unwrap_or_else(|| {
+      path.file_stem().map_or_else(
+        || "base16".to_string(),
+        |stem| stem.to_string_lossy().into_owned(),
+      )
+    });
+
+  Ok(Theme {
+    name,
+    background: required("base00")?,
+    foreground: required("base05")?,
+    comment: required("base03")?,
+    keyword: required("base0E")?,
+    string: required("base0B")?,
+    number: required("base09")?,
+    function: required("base0D")?,
+    type_color: required("base0A")?,
+    variable: required("base08")?,
+    operator: required("base0C")?,
+    punctuation: required("base05")?,
+    constant: required("base09")?,
+    class: required("base0A")?,
+    scope_fonts: Vec::new(),
+    scope_styles: Vec::new(),
+    chrome: ThemeChrome {
+      // base24's darker background suits the bar better than base01
+      title_bar: match base("base10")? {
+        Some(darker) => Some(darker),
+        None => base("base01")?,
+      },
+      title_text: base("base04")?,
+      line_numbers: base("base03")?,
+      gutter_rule: base("base02")?,
+      ..ThemeChrome::default()
+    },
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_loads_base16_layouts() {
+    let dir = std::env::temp_dir().join(format!("shoyu-base16-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let palette: String = [
+      "1d1f21", "282a2e", "373b41", "969896", "b4b7b4", "c5c8c6", "e0e0e0", "ffffff", "cc6666",
+      "de935f", "f0c674", "b5bd68", "8abeb7", "81a2be", "b294bb", "a3685a",
+    ]
+    .iter()
+    .enumerate()
+    .map(|(index, hex)| format!("base{:02X}: \"{}\"\n", index, hex))
+    .collect();
+
+    let classic = dir.join("tomorrow-night.yaml");
+    fs::write(&classic, format!("scheme: \"Tomorrow Night\"\n{}", palette)).unwrap();
+    let theme = load_base16_scheme(&classic).unwrap();
+    assert_eq!(theme.name, "Tomorrow Night");
+    assert_eq!(theme.background.hex, "#1d1f21");
+    assert_eq!(theme.keyword.hex, "#b294bb");
+    assert_eq!(theme.chrome.title_bar.unwrap().hex, "#282a2e");
+
+    let nested: String = palette
+      .replace('"', "\"#")
+      .lines()
+      .map(|line| format!("  {}\n", line))
+      .collect();
+    let base24 = dir.join("night.yml");
+    fs::write(
+      &base24,
+      format!("system: \"base24\"\npalette:\n{}  base10: 161719\n", nested),
+    )
+    .unwrap();
+    let theme = load_base16_scheme(&base24).unwrap();
+    assert_eq!(theme.name, "night");
+    assert_eq!(theme.string.hex, "#b5bd68");
+    assert_eq!(theme.chrome.title_bar.unwrap().hex, "#161719");
+
+    fs::write(&classic, "scheme: \"Broken\"\nbase00: \"1d1f21\"\n").unwrap();
+    assert!(load_base16_scheme(&classic).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
@@ -0,0 +1,219 @@
+/* ~~/src/themes/contrast.rs */
+
+// third-party crates
+use serde::Serialize;
+
+// local modules
+use crate::themes::{Theme, ThemeColor};
+
+/// OKLab lightness past which a background counts as light
+pub(super) const LIGHT_BACKGROUND: f32 = 0.6;
+
+/// WCAG 2 thresholds for normal text, large text, and enhanced contrast
+const AA: f32 = 4.5;
+const AA_LARGE: f32 = 3.0;
+const AAA: f32 = 7.0;
+
+/// How readable a theme's text colors are on a background
+#[derive(Debug, Clone, Serialize)]
+pub struct ContrastReport {
+  pub theme: String,
+  pub background: String,
+  /// Whether every color meets AA for normal text
+  pub passes_aa: bool,
+  /// The least readable color's ratio
+  pub min_ratio: f32,
+  pub colors: Vec<ContrastCheck>,
+}
+
+/// One text color's WCAG contrast against the background
+#[derive(Debug, Clone, Serialize)]
+pub struct ContrastCheck {
+  /// Theme field, e.g. `comment` or `type_color`
+  pub slot: &'static str,
+  pub hex: String,
+  /// From 1.0 for no contrast to 21.0 for black on white
+  pub ratio: f32,
+  pub aa: bool,
+  pub aa_large: bool,
+  pub aaa: bool,
+}
+
+impl Theme {
+  fn text_colors_mut(&mut self) -> [(&'static str, &mut ThemeColor); 12] {
+    [
+      ("foreground", &mut self.foreground),
+      ("comment", &mut self.comment),
+      ("keyword", &mut self.keyword),
+      ("string", &mut self.string),
+      ("number", &mut self.number),
+      ("function", &mut self.function),
+      ("type_color", &mut self.type_color),
+      ("variable", &mut self.variable),
+      ("operator", &mut self.operator),
+      ("punctuation", &mut self.punctuation),
+      ("constant", &mut self.constant),
+      ("class", &mut self.class),
+    ]
+  }
+
+  /// WCAG contrast of every text color on `background`, the theme's own
+  /// background when `None`
+  pub fn contrast_report(&self, background: Option<&ThemeColor>) -> ContrastReport {
+    let background = background.unwrap_or(&self.background).clone();
+    // A copy to borrow the colors from, as the list is kept in one place
+    let colors: Vec<ContrastCheck> = self
+      .clone()
+      .text_colors_mut()
+      .into_iter()
+      .map(|(slot, color)| {
+        let ratio = contrast_ratio(color, &background);
+        ContrastCheck {
+          slot,
+          hex: color.hex.clone(),
+          ratio: (ratio * 100.0).round() / 100.0,
+          aa: ratio >= AA,
+          aa_large: ratio >= AA_LARGE,
+          aaa: ratio >= AAA,
+        }
+      })
+      .collect();
+    ContrastReport {
+      theme: self.name.clone(),
+      background: background.hex,
+      passes_aa: colors.iter().all(|check| check.aa),
+      min_ratio: colors
+        .iter()
+        .map(|check| check.ratio)
+        .fold(f32::INFINITY, f32::min),
+      colors,
+    }
+  }
+
+  /// This theme with every text color under `ratio` against `background`,
+  /// the theme's own when `None`, darkened or lightened until it reaches it
+  pub fn with_min_contrast(mut self, ratio: f32, background: Option<&ThemeColor>) -> Theme {
+    let background = background.unwrap_or(&self.background).clone();
+    for (_, color) in self.text_colors_mut() {
+      if contrast_ratio(color, &background) < ratio {
+        *color = Oklab::from(&*color).readable_on(&background, ratio);
+      }
+    }
+    self
+  }
+}
+
+/// WCAG contrast ratio between two colors, 1.0 to 21.0
+pub fn contrast_ratio(a: &ThemeColor, b: &ThemeColor) -> f32 {
+  let (a, b) = (luminance(a), luminance(b));
+  (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// WCAG relative luminance
+fn luminance(color: &ThemeColor) -> f32 {
+  let (r, g, b) = color.rgb;
+  0.2126 * to_linear(r) + 0.7152 * to_linear(g) + 0.0722 * to_linear(b)
+}
+
+/// A color in OKLab, where changing lightness leaves the perceived hue alone
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Oklab {
+  pub l: f32,
+  pub a: f32,
+  pub b: f32,
+}
+
+impl From<&ThemeColor> for Oklab {
+  fn from(color: &ThemeColor) -> Self {
+    let (r, g, b) = color.rgb;
+    let [r, g, b] = [r, g, b].map(to_linear);
+    let l = (0.412_221_46 * r + 0.536_332_55 * g + 0.051_445_995 * b).cbrt();
+    let m = (0.211_903_5 * r + 0.680_699_5 * g + 0.107_396_96 * b).cbrt();
+    let s = (0.088_302_46 * r + 0.281_718_85 * g + 0.629_978_7 * b).cbrt();
+    Self {
+      l: 0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+      a: 1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+      b: 0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+    }
+  }
+}
+
+impl Oklab {
+  /// The nearest sRGB color, out-of-gamut channels clipped
+  pub fn to_color(self) -> ThemeColor {
+    let l = (self.l + 0.396_337_78 * self.a + 0.215_803_76 * self.b).powi(3);
+    let m = (self.l - 0.105_561_346 * self.a - 0.063_854_17 * self.b).powi(3);
+    let s = (self.l - 0.089_484_18 * self.a - 1.291_485_5 * self.b).powi(3);
+    let [r, g, b] = [
+      4.076_741_7 * l - 3.307_711_6 * m + 0.230_969_94 * s,
+      -1.268_438 * l + 2.609_757_4 * m - 0.341_319_38 * s,
+      -0.004_196_086_3 * l - 0.703_418_6 * m + 1.707_614_7 * s,
+    ]
+    .map(from_linear);
+    ThemeColor::new(&format!("#{:02x}{:02x}{:02x}", r, g, b))
+  }
+
+  /// This color, darkened on a light `background` or lightened on a dark
+  /// one until it has `contrast` against it or can't move further
+  pub fn readable_on(mut self, background: &ThemeColor, contrast: f32) -> ThemeColor {
+    let step = match Oklab::from(background).l > LIGHT_BACKGROUND {
+      true => -0.02,
+      false => 0.02,
+    };
+    let mut color = self.to_color();
+    while contrast_ratio(&color, background) < contrast && (0.0..=1.0).contains(&self.l) {
+      self.l += step;
+      color = self.to_color();
+    }
+    color
+  }
+}
+
+fn to_linear(channel: u8) -> f32 {
+  let value = channel as f32 / 255.0;
+  match value <= 0.04045 {
+    true => value / 12.92,
+    false => ((value + 0.055) / 1.055).powf(2.4),
+  }
+}
+
+fn from_linear(value: f32) -> u8 {
+  let value = value.clamp(0.0, 1.0);
+  let encoded = match value <= 0.003_130_8 {
+    true => value * 12.92,
+    false => 1.055 * value.powf(1.0 / 2.4) - 0.055,
+  };
+  (encoded * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::themes::get_theme;
+
+  #[test]
+  fn test_reports_and_fixes_contrast() {
+    let black = ThemeColor::new("#000000");
+    let white = ThemeColor::new("#ffffff");
+    assert!((contrast_ratio(&black, &white) - 21.0).abs() < 0.01);
+
+    // Dracula's comments are too dim on its own background for AA, and
+    // on a light backdrop the yellow strings all but vanish
+    let dracula = get_theme("dracula").unwrap();
+    let report = dracula.contrast_report(None);
+    assert_eq!(report.colors.len(), 12);
+    let comment = report.colors.iter().find(|c| c.slot == "comment").unwrap();
+    assert!(!comment.aa && comment.aa_large);
+    let light = ThemeColor::new("#fafafa");
+    assert!(!dracula.contrast_report(Some(&light)).passes_aa);
+
+    let fixed = dracula.with_min_contrast(4.5, Some(&light));
+    let report = fixed.contrast_report(Some(&light));
+    assert!(report.passes_aa, "{:?}", report);
+    assert!(report.min_ratio >= 4.5);
+
+    // Converting to OKLab and back is lossless at 8 bits
+    let color = ThemeColor::new("#bd93f9");
+    assert_eq!(Oklab::from(&color).to_color().hex, "#bd93f9");
+  }
+}
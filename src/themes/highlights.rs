@@ -0,0 +1,173 @@
+/* ~~/src/themes/highlights.rs */
+
+// standard crates
+use std::collections::BTreeMap;
+
+// third-party crates
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+
+// local modules
+use crate::themes::{ScopeStyle, Theme, ThemeChrome, ThemeColor};
+
+/// A Neovim highlight group as gathered from `nvim_get_hl`, either just its
+/// foreground or its attributes
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum HighlightGroup {
+  Foreground(String),
+  Attributes {
+    fg: Option<String>,
+    bg: Option<String>,
+    #[serde(default)]
+    bold: bool,
+    #[serde(default)]
+    italic: bool,
+  },
+}
+
+impl HighlightGroup {
+  fn fg(&self) -> Option<&str> {
+    match self {
+      Self::Foreground(hex) => Some(hex),
+      Self::Attributes { fg, .. } => fg.as_deref(),
+    }
+  }
+
+  fn bg(&self) -> Option<&str> {
+    match self {
+      Self::Foreground(_) => None,
+      Self::Attributes { bg, .. } => bg.as_deref(),
+    }
+  }
+}
+
+/// Groups tried in order for each syntax color, treesitter captures first
+/// since colorschemes style those most precisely; the first one set wins
+const COMMENT: &[&str] = &["@comment", "Comment"];
+const KEYWORD: &[&str] = &["@keyword", "Keyword", "Statement"];
+const STRING: &[&str] = &["@string", "String", "Constant"];
+const NUMBER: &[&str] = &["@number", "Number", "Constant"];
+const FUNCTION: &[&str] = &["@function", "Function", "Identifier"];
+const TYPE: &[&str] = &["@type", "Type"];
+const VARIABLE: &[&str] = &["@variable"];
+const OPERATOR: &[&str] = &["@operator", "Operator", "Statement"];
+const PUNCTUATION: &[&str] = &["@punctuation.delimiter", "Delimiter"];
+const CONSTANT: &[&str] = &["@constant", "Constant"];
+const CLASS: &[&str] = &["@type.definition", "Structure", "Type"];
+
+/// TextMate scopes styled like the groups that color them
+const STYLED_SCOPES: &[(&[&str], &str)] = &[
+  (COMMENT, "comment"),
+  (KEYWORD, "keyword, storage"),
+  (STRING, "string"),
+  (FUNCTION, "entity.name.function"),
+  (TYPE, "entity.name.type, storage.type"),
+];
+
+/// Build a theme named `name` from highlight groups, with `Normal` giving
+/// the background and default foreground
+pub fn theme_from_highlights(
+  name: &str,
+  groups: &BTreeMap<String, HighlightGroup>,
+) -> Result<Theme> {
+  let color =
+    |hex: &str| ThemeColor::parse(hex).map_err(|e| anyhow!("Highlight color {}: {}", hex, e));
+  let normal = groups
+    .get("Normal")
+    .ok_or_else(|| anyhow!("Highlight groups need Normal"))?;
+  let background = color(
+    normal
+      .bg()
+      .ok_or_else(|| anyhow!("Normal has no background color"))?,
+  )?;
+  let foreground = color(
+    normal
+      .fg()
+      .ok_or_else(|| anyhow!("Normal has no foreground color"))?,
+  )?;
+  let first = |names: &[&str]| names.iter().find_map(|name| groups.get(*name));
+  let pick = |names: &[&str]| match names
+    .iter()
+    .find_map(|name| groups.get(*name).and_then(HighlightGroup::fg))
+  {
+    Some(hex) => color(hex),
+    None => Ok(foreground.clone()),
+  };
+  let chrome_color = |name: &str, get: fn(&HighlightGroup) -> Option<&str>| {
+    groups.get(name).and_then(get).map(color).transpose()
+  };
+
+  let scope_styles = STYLED_SCOPES
+    .iter()
+    .filter_map(|(names, scope)| match first(names)? {
+      HighlightGroup::Attributes { bold, italic, .. } if *bold || *italic => Some(ScopeStyle {
+        scope: scope.to_string(),
+        bold: *bold,
+        italic: *italic,
+        underline: false,
+      }),
+      _ => None,
+    })
+    .collect();
+  Ok(Theme {
+    name: name.to_string(),
+    comment: pick(COMMENT)?,
+    keyword: pick(KEYWORD)?,
+    string: pick(STRING)?,
+    number: pick(NUMBER)?,
+    function: pick(FUNCTION)?,
+    type_color: pick(TYPE)?,
+    variable: pick(VARIABLE)?,
+    operator: pick(OPERATOR)?,
+    punctuation: pick(PUNCTUATION)?,
+    constant: pick(CONSTANT)?,
+    class: pick(CLASS)?,
+    scope_fonts: Vec::new(),
+    scope_styles,
+    chrome: ThemeChrome {
+      title_bar: chrome_color("StatusLine", HighlightGroup::bg)?,
+      title_text: chrome_color("StatusLine", HighlightGroup::fg)?,
+      line_numbers: chrome_color("LineNr", HighlightGroup::fg)?,
+      gutter_rule: chrome_color("WinSeparator", HighlightGroup::fg)?,
+      ..ThemeChrome::default()
+    },
+    background,
+    foreground,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_builds_theme_from_highlights() {
+    let groups: BTreeMap<String, HighlightGroup> = serde_json::from_str(
+      r##"{
+        "Normal": {"fg": "#c0caf5", "bg": "#1a1b26"},
+        "Comment": {"fg": "#565f89", "italic": true},
+        "@keyword": "#bb9af7",
+        "Statement": "#7dcfff",
+        "Constant": "#ff9e64",
+        "LineNr": {"fg": "#3b4261"}
+      }"##,
+    )
+    .unwrap();
+    let theme = theme_from_highlights("tokyo", &groups).unwrap();
+    assert_eq!(theme.background.hex, "#1a1b26");
+    assert_eq!(theme.keyword.hex, "#bb9af7");
+    // Operators fall back along their chain, strings to Constant
+    assert_eq!(theme.operator.hex, "#7dcfff");
+    assert_eq!(theme.string.hex, "#ff9e64");
+    assert_eq!(theme.variable.hex, "#c0caf5");
+    assert_eq!(theme.scope_styles.len(), 1);
+    assert!(theme.scope_styles[0].italic);
+    assert_eq!(theme.chrome.line_numbers.unwrap().hex, "#3b4261");
+    assert!(theme.chrome.title_bar.is_none());
+
+    let no_background: BTreeMap<String, HighlightGroup> =
+      serde_json::from_str(r##"{"Normal": "#ffffff"}"##).unwrap();
+    assert!(theme_from_highlights("light", &no_background).is_err());
+  }
+}
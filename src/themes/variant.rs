@@ -0,0 +1,144 @@
+/* ~~/src/themes/variant.rs */
+
+// standard crates
+use std::ops::RangeInclusive;
+
+// third-party crates
+use serde::{Deserialize, Serialize};
+
+// local modules
+use crate::themes::contrast::{LIGHT_BACKGROUND, Oklab};
+use crate::themes::{Theme, ThemeChrome, ThemeColor};
+
+/// Palette a theme is drawn with, its own or one generated for the other
+/// kind of background
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeVariant {
+  /// The theme's own colors
+  #[default]
+  Original,
+  /// Light version of a dark theme, e.g. for print; light themes are kept
+  AutoLight,
+  /// Dark version of a light theme; dark themes are kept
+  AutoDark,
+}
+
+/// WCAG contrast against the background for code, and for comments, which
+/// themes deliberately dim
+const TEXT_CONTRAST: f32 = 4.5;
+const COMMENT_CONTRAST: f32 = 3.0;
+
+impl Theme {
+  /// This theme with `variant` applied
+  pub fn with_variant(self, variant: ThemeVariant) -> Theme {
+    match variant {
+      ThemeVariant::Original => self,
+      ThemeVariant::AutoLight => self.to_light(),
+      ThemeVariant::AutoDark => self.to_dark(),
+    }
+  }
+
+  /// Whether the theme draws on a light background
+  pub fn is_light(&self) -> bool {
+    Oklab::from(&self.background).l > LIGHT_BACKGROUND
+  }
+
+  /// A light version of a dark theme, or the theme itself when it's light
+  /// already
+  pub fn to_light(&self) -> Theme {
+    match self.is_light() {
+      true => self.clone(),
+      false => self.inverted("light", 0.95..=0.985, 0.4..=0.65),
+    }
+  }
+
+  /// A dark version of a light theme, or the theme itself when it's dark
+  /// already
+  pub fn to_dark(&self) -> Theme {
+    match self.is_light() {
+      false => self.clone(),
+      true => self.inverted("dark", 0.18..=0.26, 0.65..=0.88),
+    }
+  }
+
+  /// Every color with its lightness mirrored and its hue and chroma kept,
+  /// then held to a range: the background to `background`, faintly tinted,
+  /// and syntax colors to `syntax`, where they keep their color, before
+  /// nudging text away from the background until it reads
+  fn inverted(
+    &self,
+    kind: &str,
+    background: RangeInclusive<f32>,
+    syntax: RangeInclusive<f32>,
+  ) -> Theme {
+    let mirrored = |color: &ThemeColor, range: &RangeInclusive<f32>| {
+      let mut oklab = Oklab::from(color);
+      oklab.l = (1.0 - oklab.l).clamp(*range.start(), *range.end());
+      oklab
+    };
+    let mut panel = mirrored(&self.background, &background);
+    panel.a *= 0.5;
+    panel.b *= 0.5;
+    let panel = panel.to_color();
+    let text =
+      |color: &ThemeColor, contrast: f32| mirrored(color, &syntax).readable_on(&panel, contrast);
+    Theme {
+      name: format!("{} ({})", self.name, kind),
+      foreground: mirrored(&self.foreground, &(0.0..=1.0)).readable_on(&panel, TEXT_CONTRAST),
+      comment: text(&self.comment, COMMENT_CONTRAST),
+      keyword: text(&self.keyword, TEXT_CONTRAST),
+      string: text(&self.string, TEXT_CONTRAST),
+      number: text(&self.number, TEXT_CONTRAST),
+      function: text(&self.function, TEXT_CONTRAST),
+      type_color: text(&self.type_color, TEXT_CONTRAST),
+      variable: text(&self.variable, TEXT_CONTRAST),
+      operator: text(&self.operator, TEXT_CONTRAST),
+      punctuation: text(&self.punctuation, TEXT_CONTRAST),
+      constant: text(&self.constant, TEXT_CONTRAST),
+      class: text(&self.class, TEXT_CONTRAST),
+      scope_fonts: self.scope_fonts.clone(),
+      scope_styles: self.scope_styles.clone(),
+      // Bar, gutter, and title colors are derived from the new background;
+      // window controls keep their colors
+      chrome: ThemeChrome {
+        close: self.chrome.close.clone(),
+        minimize: self.chrome.minimize.clone(),
+        maximize: self.chrome.maximize.clone(),
+        ..ThemeChrome::default()
+      },
+      background: panel,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::themes::contrast::contrast_ratio;
+  use crate::themes::get_theme;
+
+  #[test]
+  fn test_generates_readable_variants() {
+    let dracula = get_theme("dracula").unwrap();
+    let light = dracula.to_light();
+    assert!(light.is_light());
+    assert_eq!(light.name, "Dracula (light)");
+    for color in [
+      &light.foreground,
+      &light.keyword,
+      &light.string,
+      &light.function,
+    ] {
+      assert!(contrast_ratio(color, &light.background) >= TEXT_CONTRAST);
+    }
+    assert!(contrast_ratio(&light.comment, &light.background) >= COMMENT_CONTRAST);
+    // Pink keywords stay pink, red and blue over green
+    let (r, g, b) = light.keyword.rgb;
+    assert!(r > g && b > g);
+
+    let github = get_theme("github").unwrap();
+    assert!(!github.to_dark().is_light());
+    assert_eq!(github.to_light().name, github.name);
+  }
+}
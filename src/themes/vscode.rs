@@ -0,0 +1,341 @@
+/* ~~/src/themes/vscode.rs */
+
+// standard crates
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+// third-party crates
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use syntect::highlighting::ScopeSelectors;
+use syntect::parsing::ScopeStack;
+
+// local modules
+use crate::themes::{ScopeStyle, Theme, ThemeChrome, ThemeColor};
+
+/// Themes extending themes extending themes past this are assumed to loop
+const MAX_INCLUDE_DEPTH: u32 = 8;
+
+/// The parts of a VS Code color theme shoyu draws with
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VscodeTheme {
+  name: Option<String>,
+  #[serde(rename = "type")]
+  kind: Option<String>,
+  /// Theme this one extends, relative to its file
+  include: Option<String>,
+  #[serde(default)]
+  colors: BTreeMap<String, Option<String>>,
+  #[serde(default)]
+  token_colors: Option<TokenColors>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum TokenColors {
+  Rules(Vec<TokenColor>),
+  /// Path to a .tmTheme file
+  File(String),
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenColor {
+  scope: Option<Scopes>,
+  #[serde(default)]
+  settings: TokenSettings,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Scopes {
+  One(String),
+  Many(Vec<String>),
+}
+
+impl Scopes {
+  fn selector(&self) -> String {
+    match self {
+      Self::One(scope) => scope.clone(),
+      Self::Many(scopes) => scopes.join(", "),
+    }
+  }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenSettings {
+  foreground: Option<String>,
+  font_style: Option<String>,
+}
+
+/// Load a VS Code color theme JSON, comments and trailing commas allowed,
+/// following `include` to the themes it extends
+pub fn load_vscode_theme(path: &Path) -> Result<Theme> {
+  let (vscode, rules) = read_theme(path, 0)?;
+  let name = vscode.name.clone().unwrap_or_else(|| {
+    path.file_stem().map_or_else(
+      || "vscode".to_string(),
+      |stem| stem.to_string_lossy().into_owned(),
+    )
+  });
+  let light = vscode
+    .kind
+    .as_deref()
+    .is_some_and(|kind| kind.starts_with("light") || kind == "hcLight");
+
+  // VS Code's own editor defaults where the theme leaves them out
+  let editor = |key: &str| vscode.colors.get(key).cloned().flatten();
+  let background = opaque(
+    &editor("editor.background").unwrap_or_else(|| match light {
+      true => "#ffffff".to_string(),
+      false => "#1e1e1e".to_string(),
+    }),
+    None,
+  )?;
+  let foreground = opaque(
+    &editor("editor.foreground").unwrap_or_else(|| match light {
+      true => "#000000".to_string(),
+      false => "#d4d4d4".to_string(),
+    }),
+    Some(&background),
+  )?;
+  let chrome_color = |keys: &[&str]| {
+    keys
+      .iter()
+      .find_map(|key| editor(key))
+      .map(|hex| opaque(&hex, Some(&background)))
+      .transpose()
+  };
+
+  let selectors: Vec<(ScopeSelectors, &TokenColor)> = rules
+    .iter()
+    .filter_map(|rule| Some((rule.scope.as_ref()?.selector().parse().ok()?, rule)))
+    .collect();
+  // Color of a token scoped `scope`: the most specific matching rule wins,
+  // the later one among equals as in VS Code
+  let slot = |scope: &str| {
+    let hex = scope.parse::<ScopeStack>().ok().and_then(|stack| {
+      selectors
+        .iter()
+        .filter(|(_, rule)| rule.settings.foreground.is_some())
+        .filter_map(|(selectors, rule)| Some((selectors.does_match(stack.as_slice())?, rule)))
+        .fold(None, |best, (power, rule)| match best {
+          Some((best_power, _)) if best_power > power => best,
+          _ => Some((power, rule)),
+        })
+        .and_then(|(_, rule)| rule.settings.foreground.clone())
+    });
+    match hex {
+      Some(hex) => opaque(&hex, Some(&background)),
+      None => Ok(foreground.clone()),
+    }
+  };
+
+  let scope_styles = rules
+    .iter()
+    .filter_map(|rule| {
+      let font_style = rule.settings.font_style.as_deref()?;
+      let has = |style: &str| font_style.split_whitespace().any(|word| word == style);
+      let style = ScopeStyle {
+        scope: rule.scope.as_ref()?.selector(),
+        bold: has("bold"),
+        italic: has("italic"),
+        underline: has("underline"),
+      };
+      (style.bold || style.italic || style.underline).then_some(style)
+    })
+    .collect();
+  Ok(Theme {
+    name,
+    comment: slot("comment.line")?,
+    keyword: slot("keyword.control")?,
+    string: slot("string.quoted.double")?,
+    number: slot("constant.numeric")?,
+    function: slot("entity.name.function")?,
+    type_color: slot("entity.name.type")?,
+    variable: slot("variable")?,
+    operator: slot("keyword.operator")?,
+    punctuation: slot("punctuation.separator")?,
+    constant: slot("constant.language")?,
+    class: slot("entity.name.type.class")?,
+    scope_fonts: Vec::new(),
+    scope_styles,
+    chrome: ThemeChrome {
+      title_bar: chrome_color(&["titleBar.activeBackground"])?,
+      title_text: chrome_color(&["titleBar.activeForeground"])?,
+      line_numbers: chrome_color(&["editorLineNumber.foreground"])?,
+      gutter_rule: chrome_color(&["editorRuler.foreground", "editorIndentGuide.background"])?,
+      ..ThemeChrome::default()
+    },
+    background,
+    foreground,
+  })
+}
+
+/// Parse the theme at `path` over the themes it includes, returning its
+/// merged colors and the token rules of the whole chain in override order
+fn read_theme(path: &Path, depth: u32) -> Result<(VscodeTheme, Vec<TokenColor>)> {
+  if depth > MAX_INCLUDE_DEPTH {
+    return Err(anyhow!(
+      "VS Code theme includes nest too deep at {}",
+      path.display()
+    ));
+  }
+  let text = fs::read_to_string(path)
+    .map_err(|e| anyhow!("Failed to read VS Code theme {}: {}", path.display(), e))?;
+  let mut theme: VscodeTheme = serde_json::from_str(&strip_jsonc(&text))
+    .map_err(|e| anyhow!("Failed to parse VS Code theme {}: {}", path.display(), e))?;
+  let (mut merged, mut rules) = match &theme.include {
+    Some(include) => {
+      let parent = path.parent().unwrap_or(Path::new("")).join(include);
+      read_theme(&parent, depth + 1)?
+    }
+    None => (VscodeTheme::default(), Vec::new()),
+  };
+  match theme.token_colors.take() {
+    Some(TokenColors::Rules(own)) => rules.extend(own),
+    Some(TokenColors::File(file)) => {
+      return Err(anyhow!(
+        "VS Code theme {} keeps its tokenColors in {}, which isn't supported",
+        path.display(),
+        file
+      ));
+    }
+    None => {}
+  }
+  merged.colors.append(&mut theme.colors);
+  merged.name = theme.name.or(merged.name);
+  merged.kind = theme.kind.or(merged.kind);
+  Ok((merged, rules))
+}
+
+/// `hex` as an opaque color: `#rgb` widened, and alpha from `#rrggbbaa` or
+/// `#rgba` applied over `base`, or dropped without one
+fn opaque(hex: &str, base: Option<&ThemeColor>) -> Result<ThemeColor> {
+  let invalid = |e: &str| anyhow!("Invalid VS Code theme color {}: {}", hex, e);
+  let digits = hex.trim_start_matches('#').to_lowercase();
+  let widened: String = match digits.len() {
+    3 | 4 => digits.chars().flat_map(|c| [c, c]).collect(),
+    6 | 8 => digits,
+    _ => return Err(invalid("Expected #rgb, #rgba, #rrggbb or #rrggbbaa")),
+  };
+  if !widened.is_ascii() {
+    return Err(invalid("Invalid hex color format"));
+  }
+  let (rgb, alpha) = widened.split_at(6);
+  let color = ThemeColor::parse(&format!("#{}", rgb)).map_err(invalid)?;
+  match (alpha, base) {
+    ("", _) | (_, None) => Ok(color),
+    (alpha, Some(base)) => {
+      let alpha = u8::from_str_radix(alpha, 16).map_err(|_| invalid("Invalid alpha component"))?;
+      Ok(base.blend(&color, alpha as f32 / 255.0))
+    }
+  }
+}
+
+/// JSON with comments and trailing commas removed, as VS Code writes its
+/// themes; strings are copied untouched
+fn strip_jsonc(text: &str) -> String {
+  let mut output = String::with_capacity(text.len());
+  let mut chars = text.chars().peekable();
+  while let Some(c) = chars.next() {
+    match c {
+      '"' => {
+        output.push(c);
+        while let Some(c) = chars.next() {
+          output.push(c);
+          match c {
+            '\\' => output.extend(chars.next()),
+            '"' => break,
+            _ => {}
+          }
+        }
+      }
+      '/' if chars.peek() == Some(&'/') => while chars.next_if(|&c| c != '\n').is_some() {},
+      '/' if chars.peek() == Some(&'*') => {
+        chars.next();
+        let mut last = ' ';
+        for c in chars.by_ref() {
+          if last == '*' && c == '/' {
+            break;
+          }
+          last = c;
+        }
+      }
+      '}' | ']' => {
+        let trimmed = output.trim_end().len();
+        if output[..trimmed].ends_with(',') {
+          output.truncate(trimmed - 1);
+        }
+        output.push(c);
+      }
+      _ => output.push(c),
+    }
+  }
+  output
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_loads_vscode_theme_with_includes() {
+    let dir = std::env::temp_dir().join(format!("shoyu-vscode-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+      dir.join("base.json"),
+      r##"{
+        "type": "dark",
+        "colors": {"editor.background": "#282a36", "editor.foreground": "#f8f8f2"},
+        "tokenColors": [
+          {"scope": "keyword", "settings": {"foreground": "#ff79c6"}},
+          {"scope": "string", "settings": {"foreground": "#f1fa8c"}},
+        ]
+      }"##,
+    )
+    .unwrap();
+    let path = dir.join("child.json");
+    fs::write(
+      &path,
+      r##"{
+        // Extends the base with a few overrides
+        "name": "Child // Theme",
+        "include": "./base.json",
+        "colors": {"editorLineNumber.foreground": "#ffffff80", "titleBar.activeBackground": null},
+        "tokenColors": [
+          /* Comments in italics */
+          {"scope": ["comment", "punctuation.definition.comment"], "settings": {"foreground": "#6272a4", "fontStyle": "italic"}},
+          {"scope": "keyword.control", "settings": {"foreground": "#8be9fd"}},
+          {"scope": "keyword.operator", "settings": {"foreground": "#abc"}}
+        ]
+      }"##,
+    )
+    .unwrap();
+
+    let theme = load_vscode_theme(&path).unwrap();
+    assert_eq!(theme.name, "Child // Theme");
+    assert_eq!(theme.background.hex, "#282a36");
+    assert_eq!(theme.comment.hex, "#6272a4");
+    // The more specific rule wins over the included one
+    assert_eq!(theme.keyword.hex, "#8be9fd");
+    assert_eq!(theme.operator.hex, "#aabbcc");
+    assert_eq!(theme.string.hex, "#f1fa8c");
+    assert_eq!(theme.number.hex, "#f8f8f2");
+    assert_eq!(theme.scope_styles.len(), 1);
+    assert_eq!(
+      theme.scope_styles[0].scope,
+      "comment, punctuation.definition.comment"
+    );
+    // Half-transparent white over the background
+    assert_eq!(theme.chrome.line_numbers.unwrap().hex, "#94959b");
+    assert!(theme.chrome.title_bar.is_none());
+
+    fs::write(dir.join("loop.json"), r#"{"include": "loop.json"}"#).unwrap();
+    assert!(load_vscode_theme(&dir.join("loop.json")).is_err());
+    assert!(opaque("#12345", None).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
@@ -0,0 +1,21 @@
+/* ~~/src/warnings.rs */
+
+// standard crates
+use std::sync::Mutex;
+
+/// Non-fatal problems waiting to be shown to the user, oldest first
+static WARNINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Queue a warning unless the same message is already waiting
+pub fn push(message: impl Into<String>) {
+  let message = message.into();
+  let mut warnings = WARNINGS.lock().unwrap_or_else(|e| e.into_inner());
+  if !warnings.contains(&message) {
+    warnings.push(message);
+  }
+}
+
+/// Take every queued warning, leaving the queue empty
+pub fn drain() -> Vec<String> {
+  std::mem::take(&mut *WARNINGS.lock().unwrap_or_else(|e| e.into_inner()))
+}
@@ -0,0 +1,869 @@
+/* ~~/tests/ffi_contract.rs */
+
+// standard crates
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_uint};
+use std::ptr;
+use std::sync::OnceLock;
+
+// third-party crates
+use libloading::{Library, Symbol};
+
+type Text = *const c_char;
+type Owned = *mut c_char;
+
+/// Every symbol the Neovim plugin declares in its `ffi.cdef`
+const EXPORTS: &[&str] = &[
+  "generate_snippet_image",
+  "generate_snippet_image_with_metadata",
+  "generate_terminal_preview",
+  "generate_image_from_tokens",
+  "generate_backdrop",
+  "set_profile",
+  "render_with_profile",
+  "get_profiles",
+  "generate_snippet_carousel",
+  "generate_tabbed_image",
+  "export_scrolling_video",
+  "export_snippet_pdf",
+  "export_animation_frames",
+  "export_typing_animation",
+  "export_theme_transition",
+  "measure_snippet",
+  "dump_tokens",
+  "highlight_code",
+  "open_highlight_stream",
+  "edit_highlight_stream",
+  "poll_highlight_stream",
+  "close_highlight_stream",
+  "free_string",
+  "get_theme_details",
+  "validate_theme",
+  "theme_from_highlights",
+  "import_vscode_theme",
+  "import_base16_scheme",
+  "reload_themes",
+  "get_available_themes",
+  "get_available_presets",
+  "get_gradient_presets",
+  "get_build_info",
+  "get_warnings",
+  "add_syntax_directory",
+  "is_language_supported",
+  "set_resource_limits",
+  "get_resource_usage",
+  "set_temp_policy",
+  "purge_cache",
+];
+
+/// The cdylib cargo built alongside this test binary, loaded once like LuaJIT does
+fn library() -> &'static Library {
+  static LIBRARY: OnceLock<Library> = OnceLock::new();
+  LIBRARY.get_or_init(|| {
+    let exe = std::env::current_exe().unwrap();
+    let path = exe
+      .parent()
+      .unwrap()
+      .join(format!("{}shoyu{}", DLL_PREFIX, DLL_SUFFIX));
+    unsafe { Library::new(&path) }.unwrap_or_else(|e| panic!("{}: {}", path.display(), e))
+  })
+}
+
+fn symbol<T>(name: &str) -> Symbol<'static, T> {
+  unsafe { library().get(name.as_bytes()) }.unwrap()
+}
+
+fn text(value: &str) -> CString {
+  CString::new(value).unwrap()
+}
+
+/// Bytes that are not UTF-8, as a stray Lua string might be
+fn garbage() -> CString {
+  CString::new(vec![0xff, 0xfe, b'{', 0xc3]).unwrap()
+}
+
+/// Config for renders that only need to succeed, kept small for debug builds
+fn small() -> CString {
+  text("{\"export_size\": 1}")
+}
+
+/// Copy a returned string out and hand it back to the library
+fn take(owned: Owned) -> Option<String> {
+  if owned.is_null() {
+    return None;
+  }
+  let value = unsafe { CStr::from_ptr(owned) }
+    .to_string_lossy()
+    .into_owned();
+  free(owned);
+  Some(value)
+}
+
+fn free(owned: Owned) {
+  symbol::<extern "C" fn(Owned)>("free_string")(owned);
+}
+
+#[test]
+fn test_every_symbol_is_exported() {
+  for name in EXPORTS {
+    assert!(
+      unsafe { library().get::<*const ()>(name.as_bytes()) }.is_ok(),
+      "missing export {}",
+      name
+    );
+  }
+}
+
+#[test]
+fn test_free_string_guards() {
+  let get_themes = symbol::<extern "C" fn() -> Owned>("get_available_themes");
+  free(ptr::null_mut());
+
+  let owned = get_themes();
+  assert!(!owned.is_null());
+  free(owned);
+  // A second free of the same pointer is ignored
+  free(owned);
+
+  // So are pointers the library never handed out
+  let foreign = CString::new("not ours").unwrap().into_raw();
+  free(foreign);
+  drop(unsafe { CString::from_raw(foreign) });
+  let mut local = [b'x' as c_char, 0];
+  free(local.as_mut_ptr());
+  assert_eq!(local[0], b'x' as c_char);
+}
+
+#[test]
+fn test_listings_return_json() {
+  for name in [
+    "get_available_themes",
+    "get_available_presets",
+    "get_gradient_presets",
+    "get_build_info",
+    "get_profiles",
+    "get_warnings",
+    "get_resource_usage",
+  ] {
+    let json = take(symbol::<extern "C" fn() -> Owned>(name)()).unwrap();
+    assert!(
+      serde_json::from_str::<serde_json::Value>(&json).is_ok(),
+      "{}",
+      name
+    );
+  }
+}
+
+#[test]
+fn test_generate_snippet_image() {
+  let generate = symbol::<extern "C" fn(Text, Text, Text, Text) -> Owned>("generate_snippet_image");
+  let (code, rust, dracula) = (text("let x = 1;"), text("rust"), text("dracula"));
+
+  // A null theme falls back to the default one
+  let image = take(generate(
+    code.as_ptr(),
+    rust.as_ptr(),
+    ptr::null(),
+    small().as_ptr(),
+  ))
+  .unwrap();
+  assert!(image.starts_with("data:image/png;base64,"));
+
+  let (bad_json, bad_theme) = (text("{\"padding\": "), text("no-such-theme"));
+  assert!(
+    take(generate(
+      ptr::null(),
+      rust.as_ptr(),
+      dracula.as_ptr(),
+      ptr::null()
+    ))
+    .is_none()
+  );
+  assert!(
+    take(generate(
+      code.as_ptr(),
+      ptr::null(),
+      dracula.as_ptr(),
+      ptr::null()
+    ))
+    .is_none()
+  );
+  assert!(
+    take(generate(
+      garbage().as_ptr(),
+      rust.as_ptr(),
+      ptr::null(),
+      ptr::null()
+    ))
+    .is_none()
+  );
+  assert!(
+    take(generate(
+      code.as_ptr(),
+      rust.as_ptr(),
+      dracula.as_ptr(),
+      bad_json.as_ptr()
+    ))
+    .is_none()
+  );
+  assert!(
+    take(generate(
+      code.as_ptr(),
+      rust.as_ptr(),
+      bad_theme.as_ptr(),
+      ptr::null()
+    ))
+    .is_none()
+  );
+}
+
+#[test]
+fn test_generate_snippet_image_with_metadata() {
+  let generate = symbol::<extern "C" fn(Text, Text, Text, Text) -> Owned>(
+    "generate_snippet_image_with_metadata",
+  );
+  let (code, rust) = (text("let x = 1;\nlet y = 2;"), text("rust"));
+
+  let json = take(generate(
+    code.as_ptr(),
+    rust.as_ptr(),
+    ptr::null(),
+    small().as_ptr(),
+  ))
+  .unwrap();
+  let output: serde_json::Value = serde_json::from_str(&json).unwrap();
+  assert!(
+    output["image"]
+      .as_str()
+      .unwrap()
+      .starts_with("data:image/png;base64,")
+  );
+  assert!(output["width"].as_u64().unwrap() > 0);
+  assert!(output["height"].as_u64().unwrap() > 0);
+  assert_eq!(output["line_count"], 2);
+  assert!(output["render_ms"].is_u64());
+  assert_eq!(output["truncated"], false);
+
+  // Lines past a short max_height are cut off
+  let short = text("{\"export_size\": 1, \"max_height\": 40}");
+  let json = take(generate(
+    code.as_ptr(),
+    rust.as_ptr(),
+    ptr::null(),
+    short.as_ptr(),
+  ))
+  .unwrap();
+  assert!(json.contains("\"truncated\":true"));
+  assert!(
+    take(generate(
+      ptr::null(),
+      rust.as_ptr(),
+      ptr::null(),
+      ptr::null()
+    ))
+    .is_none()
+  );
+}
+
+#[test]
+fn test_generate_terminal_preview() {
+  let generate =
+    symbol::<extern "C" fn(Text, Text, Text, Text, Text) -> Owned>("generate_terminal_preview");
+  let (code, rust) = (text("let x = 1;"), text("rust"));
+  let (kitty, iterm2, sixel) = (text("kitty"), text("iterm2"), text("sixel"));
+  let ascii = text("ascii");
+
+  let preview = |protocol: &CString| {
+    take(generate(
+      code.as_ptr(),
+      rust.as_ptr(),
+      ptr::null(),
+      small().as_ptr(),
+      protocol.as_ptr(),
+    ))
+  };
+  // Contained rather than leading, as tmux wraps the sequence
+  assert!(preview(&kitty).unwrap().contains("_Ga=T,f=100"));
+  assert!(preview(&iterm2).unwrap().contains("]1337;File=inline=1"));
+  assert!(preview(&sixel).unwrap().starts_with("\x1bP0;1;0q"));
+  assert!(preview(&ascii).is_none());
+}
+
+#[test]
+fn test_generate_image_from_tokens() {
+  let generate = symbol::<extern "C" fn(Text, Text, Text) -> Owned>("generate_image_from_tokens");
+  let tokens = text(r##"[[{"text": "let", "fg": "#ff79c6", "bold": true}, {"text": " x"}]]"##);
+
+  let image = take(generate(tokens.as_ptr(), ptr::null(), small().as_ptr())).unwrap();
+  assert!(image.starts_with("data:image/png;base64,"));
+
+  let unknown_color = text(r#"[[{"text": "x", "fg": "pink"}]]"#);
+  assert!(take(generate(ptr::null(), ptr::null(), ptr::null())).is_none());
+  assert!(take(generate(garbage().as_ptr(), ptr::null(), ptr::null())).is_none());
+  assert!(take(generate(unknown_color.as_ptr(), ptr::null(), ptr::null())).is_none());
+}
+
+#[test]
+fn test_generate_backdrop() {
+  let generate = symbol::<extern "C" fn(Text, Text, c_uint, c_uint) -> Owned>("generate_backdrop");
+
+  let image = take(generate(ptr::null(), ptr::null(), 32, 16)).unwrap();
+  assert!(image.starts_with("data:image/png;base64,"));
+
+  assert!(take(generate(ptr::null(), ptr::null(), 0, 16)).is_none());
+  assert!(take(generate(garbage().as_ptr(), ptr::null(), 32, 16)).is_none());
+  assert!(take(generate(ptr::null(), garbage().as_ptr(), 32, 16)).is_none());
+}
+
+#[test]
+fn test_profiles() {
+  let set_profile = symbol::<extern "C" fn(Text, Text) -> c_int>("set_profile");
+  let render =
+    symbol::<extern "C" fn(Text, Text, Text, Text, Text) -> Owned>("render_with_profile");
+  let (name, config) = (
+    text("ffi-contract"),
+    text("{\"line_numbers\": true, \"export_size\": 1}"),
+  );
+  let (code, rust) = (text("let x = 1;"), text("rust"));
+
+  assert_eq!(set_profile(name.as_ptr(), config.as_ptr()), 1);
+  assert!(
+    take(render(
+      code.as_ptr(),
+      rust.as_ptr(),
+      ptr::null(),
+      name.as_ptr(),
+      ptr::null()
+    ))
+    .is_some()
+  );
+  assert_eq!(set_profile(name.as_ptr(), ptr::null()), 1);
+  assert_eq!(set_profile(name.as_ptr(), ptr::null()), 0);
+
+  assert_eq!(set_profile(ptr::null(), config.as_ptr()), 0);
+  assert_eq!(set_profile(garbage().as_ptr(), config.as_ptr()), 0);
+  assert_eq!(set_profile(name.as_ptr(), garbage().as_ptr()), 0);
+  assert!(
+    take(render(
+      code.as_ptr(),
+      rust.as_ptr(),
+      ptr::null(),
+      garbage().as_ptr(),
+      ptr::null()
+    ))
+    .is_none()
+  );
+}
+
+#[test]
+fn test_theme_from_highlights() {
+  let derive = symbol::<extern "C" fn(Text, Text) -> Owned>("theme_from_highlights");
+  let get_themes = symbol::<extern "C" fn() -> Owned>("get_available_themes");
+  let groups = text(
+    r##"{"Normal": {"fg": "#dcd7ba", "bg": "#1f1f28"}, "Keyword": {"fg": "#957fb8", "italic": true}}"##,
+  );
+  let name = text("ffi-colorscheme");
+
+  // Without a name the theme is only returned
+  let theme = take(derive(groups.as_ptr(), ptr::null())).unwrap();
+  let theme: serde_json::Value = serde_json::from_str(&theme).unwrap();
+  assert_eq!(theme["keyword"]["hex"], "#957fb8");
+  assert!(!take(get_themes()).unwrap().contains("ffi-colorscheme"));
+
+  assert!(take(derive(groups.as_ptr(), name.as_ptr())).is_some());
+  assert!(take(get_themes()).unwrap().contains("ffi-colorscheme"));
+  let generate = symbol::<extern "C" fn(Text, Text, Text, Text) -> Owned>("generate_snippet_image");
+  let (code, rust) = (text("let x = 1;"), text("rust"));
+  assert!(
+    take(generate(
+      code.as_ptr(),
+      rust.as_ptr(),
+      name.as_ptr(),
+      small().as_ptr()
+    ))
+    .is_some()
+  );
+
+  // Built-in themes can't be replaced
+  let dracula = text("dracula");
+  assert!(take(derive(groups.as_ptr(), dracula.as_ptr())).is_none());
+  assert!(take(derive(garbage().as_ptr(), ptr::null())).is_none());
+  assert!(take(derive(ptr::null(), ptr::null())).is_none());
+}
+
+#[test]
+fn test_import_vscode_theme() {
+  let import = symbol::<extern "C" fn(Text, Text) -> Owned>("import_vscode_theme");
+  let dir = std::env::temp_dir().join(format!("shoyu-vscode-contract-{}", std::process::id()));
+  std::fs::create_dir_all(&dir).unwrap();
+  let path = dir.join("ayu.json");
+  std::fs::write(
+    &path,
+    r##"{
+      // Comments are allowed, as in VS Code
+      "name": "Ayu Test",
+      "colors": {"editor.background": "#0b0e14", "editor.foreground": "#bfbdb6"},
+      "tokenColors": [{"scope": "keyword", "settings": {"foreground": "#ff8f40"}},]
+    }"##,
+  )
+  .unwrap();
+  let path_text = text(path.to_str().unwrap());
+
+  let theme = take(import(path_text.as_ptr(), ptr::null())).unwrap();
+  let theme: serde_json::Value = serde_json::from_str(&theme).unwrap();
+  assert_eq!(theme["name"], "Ayu Test");
+  assert_eq!(theme["keyword"]["hex"], "#ff8f40");
+
+  // Also usable directly through `theme_file`
+  let generate = symbol::<extern "C" fn(Text, Text, Text, Text) -> Owned>("generate_snippet_image");
+  let (code, rust, dracula) = (text("let x = 1;"), text("rust"), text("dracula"));
+  let config = text(&format!(
+    r#"{{"width": 400, "export_size": 1.0, "theme_file": {}}}"#,
+    serde_json::to_string(path.to_str().unwrap()).unwrap()
+  ));
+  assert!(
+    take(generate(
+      code.as_ptr(),
+      rust.as_ptr(),
+      dracula.as_ptr(),
+      config.as_ptr()
+    ))
+    .is_some()
+  );
+
+  // base16 schemes register by name like any theme
+  let import_base16 = symbol::<extern "C" fn(Text, Text) -> Owned>("import_base16_scheme");
+  let scheme = dir.join("ocean.yaml");
+  let palette: String = (0..16)
+    .map(|index| format!("base0{:X}: \"{:02x}2b36\"\n", index, index * 16))
+    .collect();
+  std::fs::write(&scheme, format!("scheme: \"Ocean\"\n{}", palette)).unwrap();
+  let (scheme_text, ocean) = (text(scheme.to_str().unwrap()), text("ffi-ocean"));
+  assert!(take(import_base16(scheme_text.as_ptr(), ocean.as_ptr())).is_some());
+  assert!(
+    take(generate(
+      code.as_ptr(),
+      rust.as_ptr(),
+      ocean.as_ptr(),
+      small().as_ptr()
+    ))
+    .is_some()
+  );
+
+  let missing = text(dir.join("missing.json").to_str().unwrap());
+  assert!(take(import(missing.as_ptr(), ptr::null())).is_none());
+  assert!(take(import(path_text.as_ptr(), dracula.as_ptr())).is_none());
+  assert!(take(import(ptr::null(), ptr::null())).is_none());
+  std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_get_theme_details() {
+  let details = symbol::<extern "C" fn(Text) -> Owned>("get_theme_details");
+  let nord = text("Nord");
+  let theme = take(details(nord.as_ptr())).unwrap();
+  let theme: serde_json::Value = serde_json::from_str(&theme).unwrap();
+  assert_eq!(theme["background"]["hex"], "#2e3440");
+  assert_eq!(theme["background"]["rgb"], serde_json::json!([46, 52, 64]));
+  assert!(theme["chrome"].is_object());
+
+  let unknown = text("no-such-theme");
+  assert!(take(details(unknown.as_ptr())).is_none());
+  assert!(take(details(ptr::null())).is_none());
+
+  let validate = symbol::<extern "C" fn(Text, Text) -> Owned>("validate_theme");
+  let report = take(validate(nord.as_ptr(), ptr::null())).unwrap();
+  let report: serde_json::Value = serde_json::from_str(&report).unwrap();
+  assert_eq!(report["background"], "#2e3440");
+  assert_eq!(report["colors"].as_array().unwrap().len(), 12);
+  // Nord's foreground washes out on white
+  let white = text("#ffffff");
+  let report = take(validate(nord.as_ptr(), white.as_ptr())).unwrap();
+  let report: serde_json::Value = serde_json::from_str(&report).unwrap();
+  assert_eq!(report["passes_aa"], false);
+  assert_eq!(report["colors"][0]["slot"], "foreground");
+  assert_eq!(report["colors"][0]["aa"], false);
+
+  let invalid = text("white");
+  assert!(take(validate(nord.as_ptr(), invalid.as_ptr())).is_none());
+  assert!(take(validate(unknown.as_ptr(), ptr::null())).is_none());
+}
+
+#[test]
+fn test_reload_themes() {
+  let reload = symbol::<extern "C" fn(Text) -> Owned>("reload_themes");
+  let get_themes = symbol::<extern "C" fn() -> Owned>("get_available_themes");
+  let dir = std::env::temp_dir().join(format!("shoyu-themes-dir-{}", std::process::id()));
+  std::fs::create_dir_all(&dir).unwrap();
+  // Plain hex colors are enough for a shoyu theme JSON
+  let palette = [
+    "background",
+    "foreground",
+    "comment",
+    "keyword",
+    "string",
+    "number",
+    "function",
+    "type_color",
+    "variable",
+    "operator",
+    "punctuation",
+    "constant",
+    "class",
+  ]
+  .iter()
+  .map(|slot| {
+    format!(
+      r##""{}": "#{}""##,
+      slot,
+      if *slot == "background" {
+        "101820"
+      } else {
+        "f2aa4c"
+      }
+    )
+  })
+  .collect::<Vec<_>>()
+  .join(", ");
+  std::fs::write(dir.join("Harbor.json"), format!("{{{}}}", palette)).unwrap();
+  std::fs::write(dir.join("broken.yaml"), "base00: [").unwrap();
+  std::fs::write(dir.join("dracula.json"), format!("{{{}}}", palette)).unwrap();
+  std::fs::write(dir.join("notes.txt"), "not a theme").unwrap();
+  let dir_text = text(dir.to_str().unwrap());
+
+  // Broken files and built-in names are skipped
+  let names = take(reload(dir_text.as_ptr())).unwrap();
+  assert_eq!(names, r#"["harbor"]"#);
+  assert!(take(get_themes()).unwrap().contains("\"harbor\""));
+  let generate = symbol::<extern "C" fn(Text, Text, Text, Text) -> Owned>("generate_snippet_image");
+  let (code, rust, harbor) = (text("let x = 1;"), text("rust"), text("harbor"));
+  assert!(
+    take(generate(
+      code.as_ptr(),
+      rust.as_ptr(),
+      harbor.as_ptr(),
+      small().as_ptr()
+    ))
+    .is_some()
+  );
+
+  // Renders naming the scanned directory reuse its themes without reading it again
+  std::fs::remove_file(dir.join("Harbor.json")).unwrap();
+  let config = text(&format!(
+    r#"{{"export_size": 1, "themes_dir": {:?}}}"#,
+    dir.to_str().unwrap()
+  ));
+  assert!(
+    take(generate(
+      code.as_ptr(),
+      rust.as_ptr(),
+      harbor.as_ptr(),
+      config.as_ptr()
+    ))
+    .is_some()
+  );
+
+  // A rescan drops themes whose files are gone
+  assert_eq!(take(reload(ptr::null())).unwrap(), "[]");
+  assert!(!take(get_themes()).unwrap().contains("\"harbor\""));
+
+  let missing = text(dir.join("missing").to_str().unwrap());
+  assert!(take(reload(missing.as_ptr())).is_none());
+  std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_highlight_code() {
+  let (code, rust) = (text("fn main() {}\n"), text("rust"));
+  let highlight = symbol::<extern "C" fn(Text, Text, Text) -> Owned>("highlight_code");
+  let lines = take(highlight(code.as_ptr(), rust.as_ptr(), ptr::null())).unwrap();
+  let lines: serde_json::Value = serde_json::from_str(&lines).unwrap();
+  assert_eq!(lines[0][0]["text"], "fn");
+  assert!(lines[0][0]["fg"].as_str().unwrap().starts_with('#'));
+  assert!(take(highlight(garbage().as_ptr(), rust.as_ptr(), ptr::null())).is_none());
+}
+
+#[test]
+fn test_measure_carousel_and_tabs() {
+  let measure = symbol::<extern "C" fn(Text, Text, Text) -> Owned>("measure_snippet");
+  let carousel =
+    symbol::<extern "C" fn(Text, Text, Text, Text) -> Owned>("generate_snippet_carousel");
+  let (code, rust) = (text("fn main() {}\n"), text("rust"));
+
+  let metrics = take(measure(code.as_ptr(), rust.as_ptr(), ptr::null())).unwrap();
+  assert!(metrics.contains("\"line_count\""));
+  assert!(take(measure(ptr::null(), rust.as_ptr(), ptr::null())).is_none());
+  assert!(take(measure(code.as_ptr(), rust.as_ptr(), garbage().as_ptr())).is_none());
+
+  let dump = symbol::<extern "C" fn(Text, Text, Text, Text) -> Owned>("dump_tokens");
+  let tokens = take(dump(code.as_ptr(), rust.as_ptr(), ptr::null(), ptr::null())).unwrap();
+  assert!(tokens.contains("\"scope\""));
+  assert!(
+    take(dump(
+      code.as_ptr(),
+      rust.as_ptr(),
+      ptr::null(),
+      garbage().as_ptr()
+    ))
+    .is_none()
+  );
+
+  let pages = take(carousel(
+    code.as_ptr(),
+    rust.as_ptr(),
+    ptr::null(),
+    small().as_ptr(),
+  ))
+  .unwrap();
+  assert_eq!(
+    serde_json::from_str::<Vec<String>>(&pages).unwrap().len(),
+    1
+  );
+  assert!(
+    take(carousel(
+      garbage().as_ptr(),
+      rust.as_ptr(),
+      ptr::null(),
+      ptr::null()
+    ))
+    .is_none()
+  );
+
+  let tabbed = symbol::<extern "C" fn(Text, Text, Text) -> Owned>("generate_tabbed_image");
+  let files = text(
+    r#"[{"title": "main.rs", "code": "fn main() {}"}, {"title": "Cargo.toml", "code": "[package]"}]"#,
+  );
+  for layout in ["active", "stacked"] {
+    let config = text(&format!(
+      r#"{{"export_size": 1, "tabs": {{"layout": "{}"}}}}"#,
+      layout
+    ));
+    let image = take(tabbed(files.as_ptr(), ptr::null(), config.as_ptr())).unwrap();
+    assert!(image.starts_with("data:image/png;base64,"));
+  }
+  assert!(take(tabbed(text("[]").as_ptr(), ptr::null(), small().as_ptr())).is_none());
+  assert!(take(tabbed(garbage().as_ptr(), ptr::null(), ptr::null())).is_none());
+}
+
+#[test]
+fn test_exports_to_disk() {
+  let frames =
+    symbol::<extern "C" fn(Text, Text, Text, Text, Text, Text) -> Owned>("export_animation_frames");
+  let video =
+    symbol::<extern "C" fn(Text, Text, Text, Text, Text) -> Owned>("export_scrolling_video");
+  let pdf = symbol::<extern "C" fn(Text, Text, Text, Text, Text) -> Owned>("export_snippet_pdf");
+  let transition =
+    symbol::<extern "C" fn(Text, Text, Text, Text, Text, Text) -> Owned>("export_theme_transition");
+  let typing =
+    symbol::<extern "C" fn(Text, Text, Text, Text, Text, Text) -> Owned>("export_typing_animation");
+  let dir = std::env::temp_dir().join(format!("shoyu-ffi-contract-{}", std::process::id()));
+  let output = text(dir.to_str().unwrap());
+  let (code, rust, spec) = (
+    text("a\nb\n"),
+    text("rust"),
+    text("{\"kind\": \"typing\", \"unit\": \"line\", \"hold_frames\": 0}"),
+  );
+
+  let sequence = take(frames(
+    code.as_ptr(),
+    rust.as_ptr(),
+    ptr::null(),
+    small().as_ptr(),
+    spec.as_ptr(),
+    output.as_ptr(),
+  ))
+  .unwrap();
+  let sequence: serde_json::Value = serde_json::from_str(&sequence).unwrap();
+  assert_eq!(sequence["frame_count"], 3);
+  // A .png output is encoded as an APNG with one frame per line and the blank start
+  let apng = dir.join("typing.png");
+  let apng_path = text(apng.to_str().unwrap());
+  take(typing(
+    code.as_ptr(),
+    rust.as_ptr(),
+    ptr::null(),
+    small().as_ptr(),
+    spec.as_ptr(),
+    apng_path.as_ptr(),
+  ))
+  .unwrap();
+  let apng_data = std::fs::read(&apng).unwrap();
+  let actl = apng_data
+    .windows(4)
+    .position(|chunk| chunk == b"acTL")
+    .unwrap();
+  assert_eq!(apng_data[actl + 4..actl + 8], 3u32.to_be_bytes());
+  let document = dir.join("snippet.pdf");
+  let document_path = text(document.to_str().unwrap());
+  take(pdf(
+    code.as_ptr(),
+    rust.as_ptr(),
+    ptr::null(),
+    small().as_ptr(),
+    document_path.as_ptr(),
+  ))
+  .unwrap();
+  assert!(std::fs::read(&document).unwrap().starts_with(b"%PDF-1.4"));
+  std::fs::remove_dir_all(&dir).unwrap();
+  let text_path = text(dir.join("typing.txt").to_str().unwrap());
+  assert!(
+    take(typing(
+      code.as_ptr(),
+      rust.as_ptr(),
+      ptr::null(),
+      ptr::null(),
+      ptr::null(),
+      text_path.as_ptr()
+    ))
+    .is_none()
+  );
+
+  assert!(
+    take(frames(
+      code.as_ptr(),
+      rust.as_ptr(),
+      ptr::null(),
+      ptr::null(),
+      garbage().as_ptr(),
+      output.as_ptr()
+    ))
+    .is_none()
+  );
+  // Empty code has nothing to animate, whichever the kind
+  for spec in ["{\"kind\": \"scroll\"}", "{\"kind\": \"typing\"}"] {
+    assert!(
+      take(frames(
+        text("").as_ptr(),
+        rust.as_ptr(),
+        ptr::null(),
+        ptr::null(),
+        text(spec).as_ptr(),
+        output.as_ptr()
+      ))
+      .is_none()
+    );
+  }
+  assert!(
+    take(frames(
+      code.as_ptr(),
+      rust.as_ptr(),
+      ptr::null(),
+      ptr::null(),
+      spec.as_ptr(),
+      ptr::null()
+    ))
+    .is_none()
+  );
+  assert!(
+    take(video(
+      code.as_ptr(),
+      rust.as_ptr(),
+      ptr::null(),
+      ptr::null(),
+      ptr::null()
+    ))
+    .is_none()
+  );
+  assert!(
+    take(transition(
+      code.as_ptr(),
+      rust.as_ptr(),
+      ptr::null(),
+      ptr::null(),
+      ptr::null(),
+      output.as_ptr()
+    ))
+    .is_none()
+  );
+  assert!(!dir.exists());
+}
+
+#[test]
+fn test_language_and_syntax_checks() {
+  let supported = symbol::<extern "C" fn(Text) -> c_int>("is_language_supported");
+  let add_dir = symbol::<extern "C" fn(Text) -> c_int>("add_syntax_directory");
+
+  assert_eq!(supported(text("rust").as_ptr()), 1);
+  assert_eq!(supported(text("no-such-language").as_ptr()), 0);
+  assert_eq!(supported(ptr::null()), 0);
+  assert_eq!(supported(garbage().as_ptr()), 0);
+
+  assert_eq!(add_dir(ptr::null()), 0);
+  assert_eq!(add_dir(garbage().as_ptr()), 0);
+  assert_eq!(add_dir(text("/no/such/directory").as_ptr()), 0);
+}
+
+#[test]
+fn test_resource_limits_round_trip() {
+  let set_limits = symbol::<extern "C" fn(c_uint, u64, c_uint)>("set_resource_limits");
+  let get_usage = symbol::<extern "C" fn() -> Owned>("get_resource_usage");
+
+  set_limits(0, 0, 64);
+  let usage: serde_json::Value = serde_json::from_str(&take(get_usage()).unwrap()).unwrap();
+  assert_eq!(usage["max_cache_mb"], 64);
+  set_limits(0, 0, 0);
+}
+
+#[test]
+fn test_temp_policy_and_purge() {
+  let set_policy = symbol::<extern "C" fn(Text, c_uint) -> c_int>("set_temp_policy");
+  let purge = symbol::<extern "C" fn() -> Owned>("purge_cache");
+  let dir = std::env::temp_dir().join(format!("shoyu-contract-{}", std::process::id()));
+  let staging = dir.join("shoyu");
+  std::fs::create_dir_all(&staging).unwrap();
+  // Only files named the way shoyu stages them are purged
+  std::fs::write(
+    staging.join(format!("{}-0.png", std::process::id())),
+    [0u8; 10],
+  )
+  .unwrap();
+  std::fs::write(staging.join("notes.png"), [0u8; 10]).unwrap();
+
+  assert_eq!(set_policy(text(&dir.to_string_lossy()).as_ptr(), 3600), 1);
+  let summary: serde_json::Value = serde_json::from_str(&take(purge()).unwrap()).unwrap();
+  assert_eq!(
+    (summary["files"].as_u64(), summary["bytes"].as_u64()),
+    (Some(1), Some(10))
+  );
+  assert!(staging.join("notes.png").exists());
+  assert_eq!(set_policy(garbage().as_ptr(), 0), 0);
+  assert_eq!(set_policy(ptr::null(), 24 * 60 * 60), 1);
+  std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_highlight_stream_lifecycle() {
+  let open = symbol::<extern "C" fn(Text, Text, Text, Text) -> c_uint>("open_highlight_stream");
+  let edit =
+    symbol::<extern "C" fn(c_uint, c_uint, c_uint, Text) -> c_int>("edit_highlight_stream");
+  let poll = symbol::<extern "C" fn(c_uint, c_uint) -> Owned>("poll_highlight_stream");
+  let close = symbol::<extern "C" fn(c_uint) -> c_int>("close_highlight_stream");
+  let rust = text("rust");
+
+  let handle = open(
+    text("fn main() {}\nlet x = 1;").as_ptr(),
+    rust.as_ptr(),
+    ptr::null(),
+    ptr::null(),
+  );
+  assert_ne!(handle, 0);
+  let update: serde_json::Value = serde_json::from_str(&take(poll(handle, 1)).unwrap()).unwrap();
+  assert_eq!(update["lines"][0]["line"], 0);
+  assert_eq!(update["pending"], true);
+  take(poll(handle, 0)).unwrap();
+
+  assert_eq!(edit(handle, 1, 2, text("// done").as_ptr()), 1);
+  let update: serde_json::Value = serde_json::from_str(&take(poll(handle, 0)).unwrap()).unwrap();
+  assert_eq!(update["lines"][0]["tokens"][0]["text"], "//");
+  assert_eq!(edit(handle, 0, 5, ptr::null()), 0);
+  assert_eq!(edit(handle, 0, 1, garbage().as_ptr()), 0);
+
+  assert_eq!(close(handle), 1);
+  assert_eq!(close(handle), 0);
+  assert!(take(poll(handle, 0)).is_none());
+  assert_eq!(
+    open(ptr::null(), rust.as_ptr(), ptr::null(), ptr::null()),
+    0
+  );
+}